@@ -0,0 +1,57 @@
+//! Measures the throughput of creating request contexts, with and without a
+//! context template. Run with `cargo bench --bench context_creation`.
+
+use std::time::{Duration, Instant};
+
+use v8_rs::v8::isolate::V8Isolate;
+use v8_rs::v8::v8_context_template::V8ContextTemplate;
+use v8_rs::v8::v8_init;
+
+const ITERATIONS: u32 = 1000;
+
+/// A setup script of a reasonable size, defining a few hundreds functions.
+fn setup_code() -> String {
+    (0..300)
+        .map(|i| format!("function f{i}(a, b) {{ return a * {i} + b; }}\n"))
+        .collect()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!(
+        "{:<40} {:>10.2?} per context, {:>8.0} contexts/sec",
+        name,
+        elapsed / ITERATIONS,
+        f64::from(ITERATIONS) / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    v8_init();
+    let isolate = V8Isolate::new();
+    let _h_scope = isolate.new_handlers_scope();
+    let i_scope = isolate.enter();
+    let code = setup_code();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let _h_scope = isolate.new_handlers_scope();
+        let _ctx = i_scope.new_context(None);
+    }
+    report("empty context", start.elapsed());
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let _h_scope = isolate.new_handlers_scope();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        ctx_scope.eval(&code).unwrap();
+    }
+    report("context + setup script", start.elapsed());
+
+    let template = V8ContextTemplate::new(&i_scope, None).add_script(&code);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let _ctx = template.instantiate(&i_scope).unwrap();
+    }
+    report("context template", start.elapsed());
+}
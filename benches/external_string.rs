@@ -0,0 +1,49 @@
+//! Compares creating a string by copying its payload into the JS heap (`new_string`)
+//! with handing the payload over as an external string (`new_external_string`), for
+//! payloads of growing size. Run with `cargo bench --bench external_string`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use v8_rs::v8::isolate::V8Isolate;
+use v8_rs::v8::v8_init;
+
+const ITERATIONS: u32 = 100;
+const SIZES: [usize; 4] = [1 << 10, 64 << 10, 1 << 20, 16 << 20];
+
+fn report(name: &str, size: usize, elapsed: Duration) {
+    println!(
+        "{:<20} {:>10} bytes {:>12.2?} per string",
+        name,
+        size,
+        elapsed / ITERATIONS
+    );
+}
+
+/// Create `ITERATIONS` strings of `size` bytes with `f`, each under its own handlers
+/// scope. The payloads are built before the clock starts, so only the string
+/// creation itself is measured.
+fn bench<T, F: Fn(Vec<u8>) -> T>(isolate: &V8Isolate, name: &str, size: usize, f: F) {
+    let payloads: Vec<Vec<u8>> = (0..ITERATIONS).map(|_| vec![b'a'; size]).collect();
+    let start = Instant::now();
+    for payload in payloads {
+        let _h_scope = isolate.new_handlers_scope();
+        black_box(f(payload));
+    }
+    report(name, size, start.elapsed());
+}
+
+fn main() {
+    v8_init();
+    let isolate = V8Isolate::new();
+    let _i_scope = isolate.enter();
+
+    for size in SIZES {
+        bench(&isolate, "new_string", size, |payload| {
+            isolate.new_string(std::str::from_utf8(&payload).unwrap())
+        });
+        bench(&isolate, "new_external_string", size, |payload| {
+            isolate.new_external_string(payload).unwrap()
+        });
+    }
+}
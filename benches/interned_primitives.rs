@@ -0,0 +1,45 @@
+//! Measures getting the primitives that are cached on the isolate (booleans, `null`,
+//! `undefined` and the empty string) compared to creating a number, which allocates
+//! a new local value each time. Run with `cargo bench --bench interned_primitives`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use v8_rs::v8::isolate::V8Isolate;
+use v8_rs::v8::v8_init;
+
+const ITERATIONS: u32 = 1_000_000;
+
+fn report(name: &str, elapsed: Duration, handles: usize) {
+    println!(
+        "{:<20} {:>10.2?} per value, {:>8} handles allocated",
+        name,
+        elapsed / ITERATIONS,
+        handles
+    );
+}
+
+/// Run `f` `ITERATIONS` times under a single handlers scope, the same as a hot
+/// native function that returns many values before its scope is closed.
+fn bench<T, F: Fn(u32) -> T>(isolate: &V8Isolate, name: &str, f: F) {
+    let h_scope = isolate.new_handlers_scope();
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        black_box(f(i));
+    }
+    report(name, start.elapsed(), h_scope.handles_count());
+}
+
+fn main() {
+    v8_init();
+    let isolate = V8Isolate::new();
+    let _i_scope = isolate.enter();
+
+    bench(&isolate, "new_bool", |i| isolate.new_bool(i % 2 == 0));
+    bench(&isolate, "new_null", |_| isolate.new_null());
+    bench(&isolate, "new_undefined", |_| isolate.new_undefined());
+    bench(&isolate, "new_empty_string", |_| isolate.new_empty_string());
+    bench(&isolate, "new_long (uncached)", |i| {
+        isolate.new_long(i64::from(i))
+    });
+}
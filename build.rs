@@ -1,66 +1,361 @@
 extern crate bindgen;
 
 use std::env;
+use std::fs;
+use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
 
-fn main() {
-    println!("cargo:rerun-if-changed=v8_c_api/src/v8_c_api.h");
-    println!("cargo:rerun-if-changed=v8_c_api/src/v8_c_api.cpp");
+const DEFAULT_V8_MONOLITH_URL: &str =
+    "https://s3.eu-west-1.amazonaws.com/dev.cto.redis/libv8_monolith.a";
 
-    if !Command::new("make")
-        .args(&["-C", "v8_c_api/"])
-        .status()
-        .expect("failed to compile v8_c_api")
-        .success()
-    {
-        panic!("failed to compile v8_c_api");
-    }
+/// The target the default prebuilt V8 library is built for.
+const DEFAULT_V8_MONOLITH_TARGET: &str = "x86_64-unknown-linux-gnu";
 
-    let output_dir = env::var("OUT_DIR").expect("Can not find out directory");
+/// The gn args used when building V8 from source, the C wrapper is compiled with
+/// pointer compression and without a custom libc++ so the build must match.
+const DEFAULT_V8_GN_ARGS: &str = "is_debug=false is_component_build=false v8_monolithic=true \
+     v8_use_external_startup_data=false use_custom_libcxx=false v8_enable_pointer_compression=true";
 
-    if !Command::new("cp")
-        .args(&["v8_c_api/src/libv8.a", &output_dir])
-        .status()
-        .expect("failed copy libv8.a to output directory")
-        .success()
-    {
-        panic!("failed copy libv8.a to output directory");
-    }
+/// Where the V8 library the crate links against comes from. Selected by the
+/// `V8_RS_V8_SOURCE` env var (`prebuilt`, `system` or `source`) or, if it is not
+/// set, by the `v8-system` and `v8-source` features. Defaults to `prebuilt`.
+/// Whatever the source is, it must be the V8 version of the headers under
+/// `v8_c_api/src/v8include`, which the C wrapper is compiled with.
+#[derive(Debug, PartialEq, Eq)]
+enum V8Source {
+    /// A prebuilt `libv8_monolith.a` at `V8_MONOLITH_PATH`, downloaded from
+    /// `V8_MONOLITH_URL` if it does not exist.
+    Prebuilt { path: String, url: String },
+    /// A V8 library installed on the system, named `V8_LIB_NAME` (`v8_monolith` by default)
+    /// and searched under `V8_LIB_DIR`, if given, and the linker default paths. The name
+    /// can not be `v8`, which is the name of the C wrapper library.
+    System {
+        lib_dir: Option<String>,
+        lib_name: String,
+    },
+    /// `v8_monolith` built from the V8 checkout at `V8_SOURCE_DIR` using gn and ninja,
+    /// which must be in the path. The gn args can be replaced using `V8_GN_ARGS`, by
+    /// default the target cpu is set according to the cargo target.
+    Source { source_dir: String, gn_args: String },
+}
 
-    let v8_monolith_path = match env::var("V8_MONOLITH_PATH") {
-        Ok(path) => path,
-        Err(_) => "v8_c_api/libv8_monolith.a".to_string(),
+fn v8_source<F: Fn(&str) -> Option<String>>(env: F) -> Result<V8Source, String> {
+    let selected = match env("V8_RS_V8_SOURCE") {
+        Some(source) => source,
+        None => match (
+            env("CARGO_FEATURE_V8_SYSTEM").is_some(),
+            env("CARGO_FEATURE_V8_SOURCE").is_some(),
+        ) {
+            (true, true) => {
+                return Err("The v8-system and v8-source features are exclusive".to_string())
+            }
+            (true, false) => "system".to_string(),
+            (false, true) => "source".to_string(),
+            (false, false) => "prebuilt".to_string(),
+        },
     };
+    match selected.as_str() {
+        "prebuilt" => {
+            let path = env("V8_MONOLITH_PATH");
+            let url = env("V8_MONOLITH_URL");
+            let target = env("TARGET").unwrap_or_else(|| DEFAULT_V8_MONOLITH_TARGET.to_string());
+            if path.is_none() && url.is_none() && target != DEFAULT_V8_MONOLITH_TARGET {
+                return Err(format!(
+                    "The default prebuilt V8 library is built for {}, set V8_MONOLITH_URL \
+                     (or V8_MONOLITH_PATH) to a library built for {} or use the system or source V8",
+                    DEFAULT_V8_MONOLITH_TARGET, target
+                ));
+            }
+            let msvc = env("CARGO_CFG_TARGET_ENV").as_deref() == Some("msvc");
+            Ok(V8Source::Prebuilt {
+                path: path
+                    .unwrap_or_else(|| format!("v8_c_api/{}", lib_file_name("v8_monolith", msvc))),
+                url: url.unwrap_or_else(|| DEFAULT_V8_MONOLITH_URL.to_string()),
+            })
+        }
+        "system" => {
+            let lib_name = env("V8_LIB_NAME").unwrap_or_else(|| "v8_monolith".to_string());
+            if lib_name == "v8" {
+                return Err("V8_LIB_NAME can not be 'v8', rename or link the library".to_string());
+            }
+            Ok(V8Source::System {
+                lib_dir: env("V8_LIB_DIR"),
+                lib_name,
+            })
+        }
+        "source" => Ok(V8Source::Source {
+            source_dir: env("V8_SOURCE_DIR")
+                .ok_or("V8_SOURCE_DIR must be set when building V8 from source")?,
+            gn_args: match env("V8_GN_ARGS") {
+                Some(gn_args) => gn_args,
+                None => match env("CARGO_CFG_TARGET_ARCH").as_deref() {
+                    Some("x86_64") => format!("{} target_cpu=\"x64\"", DEFAULT_V8_GN_ARGS),
+                    Some("aarch64") => format!("{} target_cpu=\"arm64\"", DEFAULT_V8_GN_ARGS),
+                    _ => DEFAULT_V8_GN_ARGS.to_string(),
+                },
+            },
+        }),
+        other => Err(format!(
+            "Unknown V8_RS_V8_SOURCE '{}', expected 'prebuilt', 'system' or 'source'",
+            other
+        )),
+    }
+}
+
+/// The toolchain used to compile the C wrapper, configurable for cross compilation
+/// using the same env vars as the `cc` crate: `CXX`, `AR` and `CXXFLAGS`, optionally
+/// suffixed with the target (for example `CXX_aarch64_unknown_linux_musl`), which
+/// takes precedence. `V8_RS_SYSROOT` sets the sysroot of both the compiler and the
+/// bindings generator. On musl targets the C++ standard library is linked statically,
+/// `V8_RS_STDCXX` replaces the linked library (for example `static=c++`). On MSVC
+/// targets the wrapper is compiled with `cl.exe` and archived with `lib.exe`, and the
+/// C++ standard library is linked by the compiler runtime.
+#[derive(Debug, PartialEq, Eq)]
+struct Toolchain {
+    cxx: String,
+    ar: String,
+    cflags: Vec<String>,
+    stdcxx: Option<String>,
+    msvc: bool,
+}
 
-    let v8_monolith_url = match env::var("V8_MONOLITH_URL") {
-        Ok(path) => path,
-        Err(_) => "https://s3.eu-west-1.amazonaws.com/dev.cto.redis/libv8_monolith.a".to_string(),
+fn toolchain<F: Fn(&str) -> Option<String>>(env: F) -> Toolchain {
+    let target = env("TARGET").unwrap_or_default();
+    let target_var = |name: &str| {
+        env(&format!("{}_{}", name, target.replace('-', "_")))
+            .or_else(|| env(&format!("{}_{}", name, target)))
+            .or_else(|| env(name))
+    };
+    let mut cflags = target_var("CXXFLAGS")
+        .map(|flags| flags.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_else(Vec::new);
+    if let Some(sysroot) = env("V8_RS_SYSROOT") {
+        cflags.push(format!("--sysroot={}", sysroot));
+    }
+    let target_env = env("CARGO_CFG_TARGET_ENV");
+    let msvc = target_env.as_deref() == Some("msvc");
+    let stdcxx = env("V8_RS_STDCXX").or_else(|| match target_env.as_deref() {
+        Some("msvc") => None,
+        Some("musl") => Some("static=stdc++".to_string()),
+        _ => Some("stdc++".to_string()),
+    });
+    let (cxx, ar) = if msvc {
+        ("cl.exe", "lib.exe")
+    } else {
+        ("g++", "ar")
     };
+    Toolchain {
+        cxx: target_var("CXX").unwrap_or_else(|| cxx.to_string()),
+        ar: target_var("AR").unwrap_or_else(|| ar.to_string()),
+        cflags,
+        stdcxx,
+        msvc,
+    }
+}
 
-    if !Path::new(&v8_monolith_path).exists() {
-        // download libv8_monolith.a
-        if !Command::new("wget")
-            .args(&["-O", &v8_monolith_path, &v8_monolith_url])
-            .status()
-            .expect("failed downloading libv8_monolith.a")
-            .success()
-        {
-            panic!("failed downloading libv8_monolith.a");
-        }
+/// The file name of a static library with the given name.
+fn lib_file_name(name: &str, msvc: bool) -> String {
+    if msvc {
+        format!("{}.lib", name)
+    } else {
+        format!("lib{}.a", name)
+    }
+}
+
+fn copy_lib(from: &Path, output_dir: &str, file_name: &str) {
+    fs::copy(from, Path::new(output_dir).join(file_name))
+        .unwrap_or_else(|e| panic!("failed copying {} to output directory: {}", file_name, e));
+}
+
+/// Return true if the file at the given path looks like a static library, a partial
+/// or failed download leaves an empty or truncated file behind. Both the unix
+/// archives and the MSVC libraries start with the same magic.
+fn is_static_lib(path: &Path) -> bool {
+    let mut magic = [0u8; 8];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|()| &magic == b"!<arch>\n")
+}
+
+/// Download the given url to the given path. The file is downloaded next to the path
+/// and only renamed once the download succeeded, so a failed download never leaves
+/// a file at the path that later builds would mistake for the library.
+fn download(url: &str, path: &str, what: &str) {
+    let tmp_path = format!("{}.tmp", path);
+    let _ = fs::remove_file(&tmp_path);
+    let mut download = if cfg!(windows) {
+        let mut curl = Command::new("curl");
+        curl.args(["-L", "-f", "-o", &tmp_path, url]);
+        curl
+    } else {
+        let mut wget = Command::new("wget");
+        wget.args(["-O", &tmp_path, url]);
+        wget
+    };
+    let downloaded = download.status().is_ok_and(|status| status.success());
+    if !downloaded || !is_static_lib(Path::new(&tmp_path)) {
+        let _ = fs::remove_file(&tmp_path);
+        panic!("failed {}", what);
     }
+    fs::rename(&tmp_path, path).unwrap_or_else(|e| panic!("failed {}: {}", what, e));
+}
 
-    if !Command::new("cp")
-        .args(&[&v8_monolith_path, &output_dir])
+fn run(command: &mut Command, what: &str) {
+    if !command
         .status()
-        .expect("failed copy libv8_monolith.a to output directory")
+        .unwrap_or_else(|e| panic!("failed {}: {}", what, e))
         .success()
     {
-        panic!("failed copy libv8_monolith.a to output directory");
+        panic!("failed {}", what);
     }
+}
 
-    let build = bindgen::Builder::default();
+/// Compile the C wrapper into the `v8` static library under the output directory.
+fn compile_wrapper(toolchain: &Toolchain, extra_cflags: &[String], output_dir: &str) {
+    if !toolchain.msvc {
+        run(
+            Command::new("make")
+                .args(["-C", "v8_c_api/"])
+                .env("CXX", &toolchain.cxx)
+                .env("AR", &toolchain.ar)
+                .env("EXTRA_CFLAGS", extra_cflags.join(" ")),
+            "compiling v8_c_api",
+        );
+        copy_lib(
+            Path::new("v8_c_api/src/libv8.a"),
+            output_dir,
+            &lib_file_name("v8", false),
+        );
+        return;
+    }
+    // The wrapper uses designated initializers, which MSVC only supports from C++20.
+    let obj = Path::new(output_dir).join("v8_c_api.obj");
+    run(
+        Command::new(&toolchain.cxx)
+            .args([
+                "/nologo",
+                "/c",
+                "/O2",
+                "/EHsc",
+                "/std:c++20",
+                "/Zc:__cplusplus",
+            ])
+            .arg("/DV8_COMPRESS_POINTERS")
+            .args(extra_cflags)
+            .arg("v8_c_api/src/v8_c_api.cpp")
+            .arg(format!("/Fo{}", obj.display())),
+        "compiling v8_c_api",
+    );
+    run(
+        Command::new(&toolchain.ar)
+            .arg("/nologo")
+            .arg(format!(
+                "/OUT:{}",
+                Path::new(output_dir)
+                    .join(lib_file_name("v8", true))
+                    .display()
+            ))
+            .arg(&obj),
+        "archiving v8_c_api",
+    );
+}
+
+/// Make the V8 library available to the linker and return the name to link with.
+fn prepare_v8(source: &V8Source, output_dir: &str, msvc: bool) -> String {
+    let monolith = lib_file_name("v8_monolith", msvc);
+    match source {
+        V8Source::Prebuilt { path, url } => {
+            // An empty or invalid file is left by an interrupted download, replace it.
+            if !is_static_lib(Path::new(path)) {
+                download(url, path, &format!("downloading {}", monolith));
+            }
+            copy_lib(Path::new(path), output_dir, &monolith);
+            "v8_monolith".to_string()
+        }
+        V8Source::System { lib_dir, lib_name } => {
+            if let Some(lib_dir) = lib_dir {
+                println!("cargo:rustc-link-search=native={}", lib_dir);
+            }
+            lib_name.clone()
+        }
+        V8Source::Source {
+            source_dir,
+            gn_args,
+        } => {
+            let out_dir = "out.gn/v8_rs";
+            run(
+                Command::new("gn").current_dir(source_dir).args([
+                    "gen",
+                    out_dir,
+                    &format!("--args={}", gn_args),
+                ]),
+                "generating the V8 build",
+            );
+            run(
+                Command::new("ninja")
+                    .current_dir(source_dir)
+                    .args(["-C", out_dir, "v8_monolith"]),
+                "building V8",
+            );
+            let lib = Path::new(source_dir)
+                .join(out_dir)
+                .join("obj")
+                .join(&monolith);
+            copy_lib(&lib, output_dir, &monolith);
+            "v8_monolith".to_string()
+        }
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=v8_c_api/src/v8_c_api.h");
+    println!("cargo:rerun-if-changed=v8_c_api/src/v8_c_api.cpp");
+
+    println!("cargo:rerun-if-env-changed=CARGO_FEATURE_I18N");
+    for var in [
+        "V8_RS_V8_SOURCE",
+        "V8_MONOLITH_PATH",
+        "V8_MONOLITH_URL",
+        "V8_LIB_DIR",
+        "V8_LIB_NAME",
+        "V8_SOURCE_DIR",
+        "V8_GN_ARGS",
+        "V8_RS_SYSROOT",
+        "V8_RS_STDCXX",
+    ] {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
+    let target = env::var("TARGET").expect("Can not find the target");
+    for var in ["CXX", "AR", "CXXFLAGS"] {
+        println!("cargo:rerun-if-env-changed={}", var);
+        println!("cargo:rerun-if-env-changed={}_{}", var, target);
+        println!(
+            "cargo:rerun-if-env-changed={}_{}",
+            var,
+            target.replace('-', "_")
+        );
+    }
+
+    let v8_source = v8_source(|name| env::var(name).ok()).unwrap_or_else(|e| panic!("{}", e));
+    let toolchain = toolchain(|name| env::var(name).ok());
+
+    let mut extra_cflags = toolchain.cflags.clone();
+    if env::var("CARGO_FEATURE_I18N").is_ok() {
+        extra_cflags.push("-DV8_RS_I18N".to_string());
+    }
+
+    let output_dir = env::var("OUT_DIR").expect("Can not find out directory");
+
+    compile_wrapper(&toolchain, &extra_cflags, &output_dir);
+
+    let v8_lib = prepare_v8(&v8_source, &output_dir, toolchain.msvc);
+
+    let mut build = bindgen::Builder::default();
+    if let Ok(sysroot) = env::var("V8_RS_SYSROOT") {
+        build = build.clang_arg(format!("--sysroot={}", sysroot));
+    }
 
     let bindings = build
         .header("v8_c_api/src/v8_c_api.h")
@@ -74,8 +369,215 @@ fn main() {
         .write_to_file(out_path.join("v8_c_bindings.rs"))
         .expect("failed to write bindings to file");
 
-    println!(
-        "cargo:rustc-flags=-L{} -lv8 -lv8_monolith -lstdc++",
-        output_dir
-    );
+    // The C wrapper must come before V8 so the linker resolves its V8 symbols.
+    println!("cargo:rustc-link-search=native={}", output_dir);
+    println!("cargo:rustc-link-lib=v8");
+    println!("cargo:rustc-link-lib={}", v8_lib);
+    if let Some(stdcxx) = &toolchain.stdcxx {
+        println!("cargo:rustc-link-lib={}", stdcxx);
+    }
+    if toolchain.msvc {
+        // The Windows libraries V8 depends on, for timers, stack traces and entropy.
+        for lib in ["winmm", "dbghelp", "advapi32"] {
+            println!("cargo:rustc-link-lib={}", lib);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An env lookup that only sees the given variables.
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, val)| val.to_string())
+        }
+    }
+
+    #[test]
+    fn test_v8_source_prebuilt() {
+        assert_eq!(
+            v8_source(env(&[])),
+            Ok(V8Source::Prebuilt {
+                path: "v8_c_api/libv8_monolith.a".to_string(),
+                url: DEFAULT_V8_MONOLITH_URL.to_string(),
+            })
+        );
+        assert_eq!(
+            v8_source(env(&[
+                ("V8_MONOLITH_PATH", "/tmp/v8.a"),
+                ("V8_MONOLITH_URL", "https://example.com/v8.a"),
+            ])),
+            Ok(V8Source::Prebuilt {
+                path: "/tmp/v8.a".to_string(),
+                url: "https://example.com/v8.a".to_string(),
+            })
+        );
+        assert_eq!(
+            v8_source(env(&[("CARGO_CFG_TARGET_ENV", "msvc")])),
+            Ok(V8Source::Prebuilt {
+                path: "v8_c_api/v8_monolith.lib".to_string(),
+                url: DEFAULT_V8_MONOLITH_URL.to_string(),
+            })
+        );
+        // there is no default prebuilt library for other targets
+        assert!(v8_source(env(&[("TARGET", "aarch64-unknown-linux-gnu")])).is_err());
+        assert!(v8_source(env(&[
+            ("TARGET", "aarch64-unknown-linux-gnu"),
+            ("V8_MONOLITH_URL", "https://example.com/v8.a"),
+        ]))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_v8_source_system() {
+        let system = V8Source::System {
+            lib_dir: None,
+            lib_name: "v8_monolith".to_string(),
+        };
+        assert_eq!(v8_source(env(&[("V8_RS_V8_SOURCE", "system")])), Ok(system));
+        assert_eq!(
+            v8_source(env(&[
+                ("CARGO_FEATURE_V8_SYSTEM", "1"),
+                ("V8_LIB_DIR", "/usr/lib"),
+                ("V8_LIB_NAME", "v8_custom"),
+            ])),
+            Ok(V8Source::System {
+                lib_dir: Some("/usr/lib".to_string()),
+                lib_name: "v8_custom".to_string(),
+            })
+        );
+        assert_eq!(
+            v8_source(env(&[("V8_RS_V8_SOURCE", "system"), ("V8_LIB_NAME", "v8")])),
+            Err("V8_LIB_NAME can not be 'v8', rename or link the library".to_string())
+        );
+    }
+
+    #[test]
+    fn test_v8_source_source() {
+        assert_eq!(
+            v8_source(env(&[("CARGO_FEATURE_V8_SOURCE", "1")])),
+            Err("V8_SOURCE_DIR must be set when building V8 from source".to_string())
+        );
+        assert_eq!(
+            v8_source(env(&[
+                ("V8_RS_V8_SOURCE", "source"),
+                ("V8_SOURCE_DIR", "/src/v8"),
+                ("CARGO_CFG_TARGET_ARCH", "aarch64"),
+            ])),
+            Ok(V8Source::Source {
+                source_dir: "/src/v8".to_string(),
+                gn_args: format!("{} target_cpu=\"arm64\"", DEFAULT_V8_GN_ARGS),
+            })
+        );
+        assert_eq!(
+            v8_source(env(&[
+                ("V8_RS_V8_SOURCE", "source"),
+                ("V8_SOURCE_DIR", "/src/v8"),
+                ("V8_GN_ARGS", "is_debug=true"),
+            ])),
+            Ok(V8Source::Source {
+                source_dir: "/src/v8".to_string(),
+                gn_args: "is_debug=true".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_v8_source_selection() {
+        assert_eq!(
+            v8_source(env(&[
+                ("CARGO_FEATURE_V8_SYSTEM", "1"),
+                ("CARGO_FEATURE_V8_SOURCE", "1"),
+            ])),
+            Err("The v8-system and v8-source features are exclusive".to_string())
+        );
+        // the env var takes precedence over the features
+        assert!(matches!(
+            v8_source(env(&[
+                ("CARGO_FEATURE_V8_SYSTEM", "1"),
+                ("CARGO_FEATURE_V8_SOURCE", "1"),
+                ("V8_RS_V8_SOURCE", "prebuilt"),
+            ])),
+            Ok(V8Source::Prebuilt { .. })
+        ));
+        assert_eq!(
+            v8_source(env(&[("V8_RS_V8_SOURCE", "vendored")])),
+            Err(
+                "Unknown V8_RS_V8_SOURCE 'vendored', expected 'prebuilt', 'system' or 'source'"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_toolchain() {
+        assert_eq!(
+            toolchain(env(&[("TARGET", "x86_64-unknown-linux-gnu")])),
+            Toolchain {
+                cxx: "g++".to_string(),
+                ar: "ar".to_string(),
+                cflags: Vec::new(),
+                stdcxx: Some("stdc++".to_string()),
+                msvc: false,
+            }
+        );
+        assert_eq!(
+            toolchain(env(&[
+                ("TARGET", "aarch64-unknown-linux-musl"),
+                ("CARGO_CFG_TARGET_ENV", "musl"),
+                ("CXX", "c++"),
+                ("CXX_aarch64_unknown_linux_musl", "aarch64-linux-musl-g++"),
+                ("AR_aarch64-unknown-linux-musl", "aarch64-linux-musl-ar"),
+                ("CXXFLAGS", "-O2  -g"),
+                ("V8_RS_SYSROOT", "/sysroot"),
+            ])),
+            Toolchain {
+                cxx: "aarch64-linux-musl-g++".to_string(),
+                ar: "aarch64-linux-musl-ar".to_string(),
+                cflags: vec![
+                    "-O2".to_string(),
+                    "-g".to_string(),
+                    "--sysroot=/sysroot".to_string()
+                ],
+                stdcxx: Some("static=stdc++".to_string()),
+                msvc: false,
+            }
+        );
+        assert_eq!(
+            toolchain(env(&[
+                ("TARGET", "x86_64-pc-windows-msvc"),
+                ("CARGO_CFG_TARGET_ENV", "msvc"),
+            ])),
+            Toolchain {
+                cxx: "cl.exe".to_string(),
+                ar: "lib.exe".to_string(),
+                cflags: Vec::new(),
+                stdcxx: None,
+                msvc: true,
+            }
+        );
+        assert_eq!(
+            toolchain(env(&[("V8_RS_STDCXX", "static=c++")])).stdcxx,
+            Some("static=c++".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_static_lib() {
+        let dir = env::temp_dir().join(format!("v8_rs_build_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let lib = dir.join("lib.a");
+        assert!(!is_static_lib(&lib));
+        fs::write(&lib, b"").unwrap();
+        assert!(!is_static_lib(&lib));
+        fs::write(&lib, b"<html>not found</html>").unwrap();
+        assert!(!is_static_lib(&lib));
+        fs::write(&lib, b"!<arch>\nfoo.o/").unwrap();
+        assert!(is_static_lib(&lib));
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
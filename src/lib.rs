@@ -3,17 +3,14 @@ mod v8_c_raw;
 
 #[cfg(test)]
 mod json_path_tests {
-    use crate::v8::{isolate, v8_context_scope, v8_init, v8_native_function_template, v8_value};
-
-    static mut IS_INITIALIZED: bool = false;
+    use crate::v8::{
+        host_function_registry, identity_map, isolate, native_module_registry, sandbox, testing,
+        v8_array_buffer, v8_builder, v8_context_scope, v8_context_template, v8_error, v8_module,
+        v8_native_function_template, v8_object, v8_promise, v8_script, v8_value,
+    };
 
     fn initialize() {
-        unsafe {
-            if !IS_INITIALIZED {
-                v8_init();
-                IS_INITIALIZED = true;
-            }
-        }
+        testing::initialize();
     }
 
     #[test]
@@ -21,6 +18,100 @@ mod json_path_tests {
         initialize();
     }
 
+    #[test]
+    fn test_external_startup_data_invalid_path() {
+        use crate::v8::{v8_init_external_startup_data, V8StartupData};
+        assert_eq!(
+            v8_init_external_startup_data(V8StartupData::File("snapshot\0blob.bin")),
+            Err("The startup data path contains a NUL byte".to_string())
+        );
+    }
+
+    #[cfg(feature = "i18n")]
+    #[test]
+    fn test_icu_invalid_data_file() {
+        assert!(!crate::v8::v8_init_icu(Some("icudtl\0.dat")));
+    }
+
+    #[test]
+    fn test_tracing_invalid_category() {
+        use crate::v8::{v8_init_with_tracing, V8TracingConfig};
+        let config = V8TracingConfig::new(|_| {}).add_category("v8\0gc");
+        assert_eq!(
+            v8_init_with_tracing(config),
+            Err("A trace category contains a NUL byte".to_string())
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_trace_event_emit_to_tracing() {
+        use crate::v8::{V8TraceEvent, V8TraceEventPhase};
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+        use tracing::field::{Field, Visit};
+
+        #[derive(Default)]
+        struct Recorder {
+            fields: Mutex<Vec<(String, String)>>,
+            target: Mutex<Option<String>>,
+        }
+
+        impl Visit for &Recorder {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.fields
+                    .lock()
+                    .unwrap()
+                    .push((field.name().to_string(), format!("{:?}", value)));
+            }
+        }
+
+        struct Subscriber(Arc<Recorder>);
+
+        impl tracing::Subscriber for Subscriber {
+            fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+                tracing::span::Id::from_u64(1)
+            }
+            fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+            fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+            fn event(&self, event: &tracing::Event<'_>) {
+                *self.0.target.lock().unwrap() = Some(event.metadata().target().to_string());
+                event.record(&mut &*self.0);
+            }
+            fn enter(&self, _: &tracing::span::Id) {}
+            fn exit(&self, _: &tracing::span::Id) {}
+        }
+
+        let recorder = Arc::new(Recorder::default());
+        tracing::subscriber::with_default(Subscriber(Arc::clone(&recorder)), || {
+            V8TraceEvent {
+                phase: V8TraceEventPhase::Complete,
+                category: "v8",
+                name: "V8.Execute",
+                timestamp: Duration::from_micros(10),
+                duration: Duration::from_micros(5),
+            }
+            .emit_to_tracing();
+        });
+
+        assert_eq!(recorder.target.lock().unwrap().as_deref(), Some("v8"));
+        let fields = recorder.fields.lock().unwrap();
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+        };
+        assert_eq!(field("category").as_deref(), Some("\"v8\""));
+        assert_eq!(field("name").as_deref(), Some("\"V8.Execute\""));
+        assert_eq!(field("phase").as_deref(), Some("Complete"));
+        assert_eq!(field("timestamp_us").as_deref(), Some("10"));
+        assert_eq!(field("duration_us").as_deref(), Some("5"));
+    }
+
     #[test]
     fn test_simple_isolate_creation() {
         initialize();
@@ -208,13 +299,17 @@ mod json_path_tests {
         let ctx_scope = ctx.enter();
 
         let module = ctx_scope
-            .compile_as_module(&code_name, &code_str, true)
+            .compile_as_module(&code_name, &code_str, &v8_script::V8CompileOptions::new())
             .unwrap();
         module.initialize(
             &ctx_scope,
             |_isolate, ctx_scope, name_to_load, _identity_hash| {
                 let code_str = isolate.new_string("export let msg = \"foo\";");
-                ctx_scope.compile_as_module(name_to_load, &code_str, true)
+                ctx_scope.compile_as_module(
+                    name_to_load,
+                    &code_str,
+                    &v8_script::V8CompileOptions::new(),
+                )
             },
         );
         let res = module.evaluate(&ctx_scope).unwrap();
@@ -315,6 +410,3257 @@ mod json_path_tests {
         );
     }
 
+    #[test]
+    fn test_set_globals_after_context_creation() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        assert!(
+            ctx_scope.set_global_native_function("foo", |args, isolate, _ctx_scope| {
+                let v = args.get(0);
+                let s = v.to_utf8(isolate).unwrap();
+                assert_eq!(s.as_str(), "2");
+                Some(isolate.new_long(3))
+            })
+        );
+        assert!(ctx_scope.set_global_value(
+            "bar",
+            &isolate.new_long(1),
+            v8_object::V8PropertyAttributes::READ_ONLY
+        ));
+        let code_str = isolate.new_string("bar = 5; foo(2) + bar");
+        // In sloppy mode the assignment to the read only global is silently ignored.
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let res = script.run(&ctx_scope).unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "4");
+    }
+
+    #[test]
+    fn test_persisted_templates() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let (persisted_globals, persisted_native) = {
+            let _h_scope = isolate.new_handlers_scope();
+            let mut globals = isolate.new_object_template();
+            globals.add_native_function(&isolate, "foo", |_args, isolate, _ctx_scope| {
+                Some(isolate.new_string("foo").to_value())
+            });
+            let native = isolate.new_native_function_template(|_args, isolate, _ctx_scope| {
+                Some(isolate.new_string("bar").to_value())
+            });
+            (globals.persist(&isolate), native.persist(&isolate))
+        };
+
+        let _h_scope = isolate.new_handlers_scope();
+        let mut globals = persisted_globals.to_local(&isolate);
+        let native = persisted_native.to_local(&isolate);
+        globals.set_native_function(&isolate.new_string("bar"), &native);
+        let code_str = isolate.new_string("foo() + bar()");
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(Some(&globals));
+        let ctx_scope = ctx.enter();
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let res = script.run(&ctx_scope).unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "foobar");
+    }
+
+    #[test]
+    fn test_persisted_templates_wrong_isolate() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let other_isolate = isolate::V8Isolate::new();
+        let (persisted_globals, persisted_native) = {
+            let _h_scope = isolate.new_handlers_scope();
+            let globals = isolate.new_object_template();
+            let native = isolate.new_native_function_template(|_args, _isolate, _ctx_scope| None);
+            (globals.persist(&isolate), native.persist(&isolate))
+        };
+
+        let _h_scope = other_isolate.new_handlers_scope();
+        assert_eq!(
+            persisted_globals.try_to_local(&other_isolate).err(),
+            Some("The persisted template belongs to a different isolate")
+        );
+        assert_eq!(
+            persisted_native.try_to_local(&other_isolate).err(),
+            Some("The persisted template belongs to a different isolate")
+        );
+        drop(isolate);
+        assert_eq!(
+            persisted_globals.try_to_local(&other_isolate).err(),
+            Some("The isolate of the persisted template was already freed")
+        );
+        assert_eq!(
+            persisted_native.try_to_local(&other_isolate).err(),
+            Some("The isolate of the persisted template was already freed")
+        );
+    }
+
+    #[test]
+    fn test_isolate_templates_cache() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        {
+            let _h_scope = isolate.new_handlers_scope();
+            let mut globals = isolate.new_object_template();
+            globals.add_native_function(&isolate, "foo", |_args, isolate, _ctx_scope| {
+                Some(isolate.new_string("foo").to_value())
+            });
+            isolate.set_object_template(0, &globals);
+        }
+
+        let _h_scope = isolate.new_handlers_scope();
+        assert!(isolate.get_object_template(1).is_none());
+        assert!(isolate.get_native_function_template(0).is_none());
+        let globals = isolate.get_object_template(0).unwrap();
+        let code_str = isolate.new_string("foo()");
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(Some(&globals));
+        let ctx_scope = ctx.enter();
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let res = script.run(&ctx_scope).unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "foo");
+    }
+
+    #[test]
+    fn test_persisted_value_after_isolate_free() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let persisted = {
+            let _h_scope = isolate.new_handlers_scope();
+            isolate.new_string("foo").to_value().persist(&isolate)
+        };
+        assert!(persisted.get_isolate_id().is_alive());
+
+        let other_isolate = isolate::V8Isolate::new();
+        {
+            let _h_scope = other_isolate.new_handlers_scope();
+            assert!(persisted.try_as_local(&other_isolate).is_err());
+        }
+        {
+            let _h_scope = isolate.new_handlers_scope();
+            let local = persisted.try_as_local(&isolate).unwrap();
+            assert_eq!(local.to_utf8(&isolate).unwrap().as_str(), "foo");
+        }
+
+        drop(isolate);
+        assert!(!persisted.get_isolate_id().is_alive());
+        let _h_scope = other_isolate.new_handlers_scope();
+        assert!(persisted.try_as_local(&other_isolate).is_err());
+    }
+
+    #[test]
+    fn test_persisted_value_duplicate() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let persisted = {
+            let _h_scope = isolate.new_handlers_scope();
+            isolate.new_string("foo").to_value().persist(&isolate)
+        };
+        let duplicated = persisted.duplicate(&isolate);
+        drop(persisted);
+        {
+            let _h_scope = isolate.new_handlers_scope();
+            let local = duplicated.as_local(&isolate);
+            assert_eq!(local.to_utf8(&isolate).unwrap().as_str(), "foo");
+        }
+        let duplicated_again = duplicated.duplicate(&isolate);
+        drop(duplicated);
+        let _h_scope = isolate.new_handlers_scope();
+        let local = duplicated_again.as_local(&isolate);
+        assert_eq!(local.to_utf8(&isolate).unwrap().as_str(), "foo");
+    }
+
+    #[test]
+    fn test_escapable_handlers_scope() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let res = {
+            let e_scope = isolate.new_escapable_handlers_scope();
+            let _tmp = isolate.new_string("tmp");
+            let res = isolate.new_string("foo").to_value();
+            e_scope.escape(&res)
+        };
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "foo");
+    }
+
+    #[test]
+    fn test_handlers_scope_handles_count() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let h_scope = isolate.new_handlers_scope();
+        assert_eq!(h_scope.handles_count(), 0);
+        {
+            let _inner_h_scope = isolate.new_handlers_scope();
+            let _s1 = isolate.new_string("foo");
+            let _s2 = isolate.new_string("bar");
+            assert!(h_scope.handles_count() >= 2);
+        }
+        assert_eq!(h_scope.handles_count(), 0);
+    }
+
+    #[test]
+    fn test_live_locals_by_type() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let count_of = |local_type| {
+            isolate
+                .live_locals_by_type()
+                .into_iter()
+                .find(|(t, _)| *t == local_type)
+                .map_or(0, |(_, count)| count)
+        };
+        let strings = count_of("V8LocalString");
+        let objects = count_of("V8LocalObject");
+        let s1 = isolate.new_string("foo");
+        let _s2 = isolate.new_string("bar");
+        let _obj = isolate.new_object();
+        assert_eq!(count_of("V8LocalString"), strings + 2);
+        assert_eq!(count_of("V8LocalObject"), objects + 1);
+        drop(s1);
+        assert_eq!(count_of("V8LocalString"), strings + 1);
+    }
+
+    #[test]
+    fn test_isolate_dispose() {
+        initialize();
+        let mut isolate = isolate::V8Isolate::new();
+        let id = isolate.get_id();
+        let persisted = {
+            let _h_scope = isolate.new_handlers_scope();
+            isolate.new_string("foo").to_value().persist(&isolate)
+        };
+        isolate.low_memory_notification();
+        assert!(isolate.dispose().is_err());
+        assert!(!isolate.is_disposed());
+
+        drop(persisted);
+        isolate.dispose().unwrap();
+        assert!(isolate.is_disposed());
+        assert!(!id.is_alive());
+        assert!(isolate.dispose().is_err());
+    }
+
+    #[test]
+    fn test_memory_pressure_notification() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _i_scope = isolate.enter();
+        isolate.memory_pressure_notification(isolate::V8MemoryPressureLevel::Critical);
+        isolate.memory_pressure_notification(isolate::V8MemoryPressureLevel::None);
+        let _h_scope = isolate.new_handlers_scope();
+        let s = isolate.new_string("foo");
+        assert_eq!(s.to_value().to_utf8(&isolate).unwrap().as_str(), "foo");
+    }
+
+    #[test]
+    fn test_context_execution_stats() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let code_str =
+            isolate.new_string("let sum = 0; for (let i = 0; i < 100000; i++) { sum += i; } sum");
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        assert_eq!(ctx.get_execution_stats().enter_count, 0);
+        {
+            let ctx_scope = ctx.enter();
+            let _nested_ctx_scope = ctx.enter();
+            let script = ctx_scope.compile(&code_str).unwrap();
+            script.run(&ctx_scope).unwrap();
+        }
+        let stats = ctx.get_execution_stats();
+        assert_eq!(stats.enter_count, 1);
+        assert!(stats.wall_time > std::time::Duration::ZERO);
+        assert_eq!(ctx.get_execution_stats(), stats);
+    }
+
+    #[test]
+    fn test_context_execution_sampling() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let sampler = isolate.start_execution_sampling(std::time::Duration::from_millis(1));
+        {
+            let ctx_scope = ctx.enter();
+            let code_str =
+                isolate.new_string("const end = Date.now() + 100; while (Date.now() < end) {}");
+            let script = ctx_scope.compile(&code_str).unwrap();
+            script.run(&ctx_scope).unwrap();
+            let stats = ctx.get_execution_stats();
+            assert!(stats.sample_count > 0);
+            assert!(stats.cpu_time > std::time::Duration::ZERO);
+        }
+        drop(sampler);
+        assert!(ctx.get_execution_stats().sample_count > 0);
+    }
+
+    #[test]
+    fn test_host_function_registry() {
+        initialize();
+        let mut registry = host_function_registry::HostFunctionRegistry::new();
+        registry
+            .register_function("foo", "foo() -> string", |_args, isolate, _ctx_scope| {
+                Some(isolate.new_string("foo").to_value())
+            })
+            .register_namespace_function(
+                "ns",
+                "bar",
+                "bar() -> string",
+                |_args, isolate, _ctx_scope| Some(isolate.new_string("bar").to_value()),
+            );
+        assert_eq!(
+            registry.document(),
+            "foo: foo() -> string\nns.bar: bar() -> string\n"
+        );
+
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        for _ in 0..2 {
+            let ctx = i_scope.new_context(None);
+            let ctx_scope = ctx.enter();
+            assert!(registry.install(&ctx_scope));
+            let code_str = isolate.new_string("foo() + ns.bar()");
+            let script = ctx_scope.compile(&code_str).unwrap();
+            let res = script.run(&ctx_scope).unwrap();
+            assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "foobar");
+        }
+    }
+
+    #[test]
+    fn test_external_string() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let s = isolate
+            .new_external_string("external string".as_bytes().to_vec())
+            .unwrap();
+        assert!(s.is_external());
+        assert!(!isolate.new_string("foo").is_external());
+        let globals = ctx_scope.get_globals();
+        globals.set(
+            &ctx_scope,
+            &isolate.new_string("s").to_value(),
+            &s.to_value(),
+        );
+        let code_str = isolate.new_string("s.toUpperCase()");
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let res = script.run(&ctx_scope).unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "EXTERNAL STRING");
+
+        // non ASCII data is rejected instead of being read as Latin-1
+        assert!(isolate
+            .new_external_string("héllo".as_bytes().to_vec())
+            .is_none());
+        let s = isolate
+            .new_external_string_utf16("héllo 😀".encode_utf16().collect())
+            .unwrap();
+        assert!(s.is_external());
+        assert_eq!(s.len(), 8);
+        assert_eq!(s.to_value().to_utf8(&isolate).unwrap().as_str(), "héllo 😀");
+    }
+
+    #[test]
+    fn test_array_buffer_transfer() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let code_str = isolate.new_string("new Uint8Array([1, 2, 3]).buffer");
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let res = script.run(&ctx_scope).unwrap();
+        assert!(res.is_array_buffer());
+        let array_buffer = res.as_array_buffer();
+        let backing_store = array_buffer.transfer(&isolate).unwrap();
+        assert_eq!(backing_store.data(), &[1, 2, 3]);
+        assert!(array_buffer.data().is_empty());
+    }
+
+    #[test]
+    fn test_shared_array_buffer() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let shared = isolate.new_shared_array_buffer(8);
+        let backing_store = shared.get_backing_store(&isolate);
+        let counters = backing_store.as_atomics_u32().unwrap();
+        assert_eq!(counters.len(), 2);
+        counters[0].store(5, std::sync::atomic::Ordering::SeqCst);
+
+        let globals = ctx_scope.get_globals();
+        globals.set(
+            &ctx_scope,
+            &isolate.new_string("shared").to_value(),
+            &shared.to_value(),
+        );
+        let code_str = isolate.new_string(
+            "const view = new Int32Array(shared); Atomics.add(view, 1, Atomics.load(view, 0) + 1); shared",
+        );
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let res = script.run(&ctx_scope).unwrap();
+        assert!(res.is_shared_array_buffer());
+        assert_eq!(counters[1].load(std::sync::atomic::Ordering::SeqCst), 6);
+        assert_eq!(backing_store.as_atomics().len(), 8);
+    }
+
+    #[test]
+    fn test_identity_map() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let code_str = isolate.new_string("[{a: 1}, {a: 1}]");
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let arr = script.run(&ctx_scope).unwrap().as_array();
+        let obj1 = arr.get(&ctx_scope, 0).as_object();
+        let obj2 = arr.get(&ctx_scope, 1).as_object();
+        assert_eq!(
+            obj1.get_identity_hash(),
+            arr.get(&ctx_scope, 0).as_object().get_identity_hash()
+        );
+
+        let mut map = identity_map::IdentityMap::new();
+        assert!(map.insert(&isolate, &obj1, "first").is_none());
+        assert!(map.get(&isolate, &obj2).is_none());
+        assert_eq!(map.insert(&isolate, &obj2, "second"), None);
+        assert_eq!(map.insert(&isolate, &obj1, "first again"), Some("first"));
+        assert_eq!(map.len(), 2);
+        let same_obj1 = arr.get(&ctx_scope, 0).as_object();
+        assert_eq!(map.get(&isolate, &same_obj1), Some(&"first again"));
+        assert_eq!(map.remove(&isolate, &obj2), Some("second"));
+        assert!(map.get(&isolate, &obj2).is_none());
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_integer_conversions() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let code_str = isolate.new_string("[5, -1, 0, 1.5, 4294967296, 'foo']");
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let arr = script.run(&ctx_scope).unwrap().as_array();
+
+        assert_eq!(u32::try_from(&arr.get(&ctx_scope, 0)), Ok(5));
+        assert_eq!(i32::try_from(&arr.get(&ctx_scope, 1)), Ok(-1));
+        assert_eq!(
+            u32::try_from(&arr.get(&ctx_scope, 1)),
+            Err("Value -1 is out of range for u32".to_string())
+        );
+        assert!(std::num::NonZeroU32::try_from(&arr.get(&ctx_scope, 2)).is_err());
+        assert!(usize::try_from(&arr.get(&ctx_scope, 3)).is_err());
+        assert!(u32::try_from(&arr.get(&ctx_scope, 4)).is_err());
+        assert_eq!(u64::try_from(&arr.get(&ctx_scope, 4)), Ok(4294967296));
+        assert!(i64::try_from(&arr.get(&ctx_scope, 5)).is_err());
+    }
+
+    #[test]
+    fn test_big_int_conversions() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let arr = ctx_scope
+            .eval("[2n ** 64n - 1n, 2n ** 64n + 5n, -(2n ** 63n) - 1n, -(2n ** 63n), -5n]")
+            .unwrap()
+            .as_array();
+
+        assert_eq!(u64::try_from(&arr.get(&ctx_scope, 0)), Ok(u64::MAX));
+        assert!(i64::try_from(&arr.get(&ctx_scope, 0)).is_err());
+        assert_eq!(
+            u64::try_from(&arr.get(&ctx_scope, 1)),
+            Err("BigInt value is out of range for u64".to_string())
+        );
+        assert!(usize::try_from(&arr.get(&ctx_scope, 1)).is_err());
+        assert!(std::num::NonZeroU64::try_from(&arr.get(&ctx_scope, 1)).is_err());
+        assert_eq!(
+            i64::try_from(&arr.get(&ctx_scope, 2)),
+            Err("BigInt value is out of range for i64".to_string())
+        );
+        assert_eq!(i64::try_from(&arr.get(&ctx_scope, 3)), Ok(i64::MIN));
+        assert_eq!(i32::try_from(&arr.get(&ctx_scope, 4)), Ok(-5));
+        assert!(u32::try_from(&arr.get(&ctx_scope, 4)).is_err());
+    }
+
+    #[test]
+    fn test_object_path() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let code_str = isolate.new_string("({a: {b: {c: 'foo'}, d: 1}})");
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let obj = script.run(&ctx_scope).unwrap().as_object();
+
+        let res = obj.get_path(&ctx_scope, &["a", "b", "c"]).unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "foo");
+        assert!(obj.get_path(&ctx_scope, &["a", "x", "c"]).is_none());
+        assert!(obj.get_path(&ctx_scope, &["a", "d", "c"]).is_none());
+
+        assert!(obj.set_path(
+            &ctx_scope,
+            &["a", "e", "f"],
+            &isolate.new_string("bar").to_value()
+        ));
+        let res = obj.get_path(&ctx_scope, &["a", "e", "f"]).unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "bar");
+        assert!(!obj.set_path(&ctx_scope, &["a", "d", "f"], &isolate.new_null()));
+    }
+
+    #[test]
+    fn test_unhandled_exception_handler() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let errors = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let errors_clone = std::sync::Arc::clone(&errors);
+        ctx_scope.set_unhandled_exception_handler(move |isolate, _ctx_scope, error| {
+            let msg = error.error.to_utf8(isolate).unwrap().as_str().to_string();
+            errors_clone.lock().unwrap().push((error.kind, msg));
+        });
+
+        let code_str = isolate.new_string("Promise.reject('foo'); 1");
+        let script = ctx_scope.compile(&code_str).unwrap();
+        script.run(&ctx_scope).unwrap();
+        // A handler added after the rejection, even from a microtask, keeps it from being reported.
+        let code_str = isolate.new_string(
+            "const p = Promise.reject('handled'); Promise.resolve().then(() => p.catch(() => {})); 1",
+        );
+        let script = ctx_scope.compile(&code_str).unwrap();
+        script.run(&ctx_scope).unwrap();
+        isolate.perform_microtask_checkpoint();
+        let code_str = isolate.new_string("throw 'bar'");
+        let script = ctx_scope.compile(&code_str).unwrap();
+        assert!(script.run(&ctx_scope).is_none());
+
+        assert_eq!(
+            *errors.lock().unwrap(),
+            vec![
+                (
+                    v8_context_scope::V8UnhandledErrorKind::UnhandledRejection,
+                    "foo".to_string()
+                ),
+                (
+                    v8_context_scope::V8UnhandledErrorKind::UncaughtException,
+                    "bar".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_function_metadata() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let code_str = isolate.new_string("let bar = 1;\nlet foo = function() {};\nfoo");
+        let script = ctx_scope.compile(&code_str).unwrap();
+        let func = script.run(&ctx_scope).unwrap();
+        assert!(func.is_function());
+        assert_eq!(func.get_line_number(), Some(1));
+        assert!(func.get_column().is_some());
+        assert_eq!(func.get_inferred_name(&isolate), Some("foo".to_string()));
+        assert!(func.get_script_id().is_some());
+        assert!(func.get_script_name(&isolate).is_none());
+    }
+
+    #[test]
+    fn test_isolates_registry() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let id = isolate.get_id();
+        assert!(isolate.get_name().is_none());
+        isolate.set_name("foo");
+        assert_eq!(id.get_name(), Some("foo".to_string()));
+        assert!(isolate::V8Isolate::live_isolates().contains(&id));
+
+        // The owner is alive until the end of the test.
+        let same_isolate = unsafe { isolate::V8Isolate::get_by_id(id) }.unwrap();
+        assert_eq!(same_isolate.get_id(), id);
+        assert_eq!(same_isolate.get_name(), Some("foo".to_string()));
+        drop(same_isolate);
+
+        drop(isolate);
+        assert!(unsafe { isolate::V8Isolate::get_by_id(id) }.is_none());
+        assert!(!isolate::V8Isolate::live_isolates().contains(&id));
+        assert!(id.get_name().is_none());
+    }
+
+    #[test]
+    fn test_isolate_tasks() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _i_scope = isolate.enter();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let id = isolate.get_id();
+        isolate.post_task(move |isolate| {
+            sender.send(isolate.get_id()).unwrap();
+        });
+        assert!(receiver.try_recv().is_err());
+        assert!(isolate.pump_message_loop(false));
+        assert_eq!(receiver.try_recv().unwrap(), id);
+        assert!(!isolate.pump_message_loop(false));
+        isolate.run_idle_tasks(std::time::Duration::from_millis(1));
+        isolate.idle_notification_for(std::time::Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_data_modules() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let code_name = isolate.new_string("base_module");
+        let code_str = isolate.new_string(
+            "import config from \"./config.json\"; import data from \"./data.bin\"; globalThis.res = config.foo + new Uint8Array(data)[1];",
+        );
+        let module = ctx_scope
+            .compile_as_module(&code_name, &code_str, &v8_script::V8CompileOptions::new())
+            .unwrap();
+        assert!(module.initialize(
+            &ctx_scope,
+            |isolate, ctx_scope, name_to_load, _identity_hash| {
+                let name = name_to_load.to_value().to_utf8(isolate).unwrap();
+                match name.as_str() {
+                    "./config.json" => ctx_scope
+                        .compile_as_json_module(name_to_load, &isolate.new_string("{\"foo\": 1}")),
+                    "./data.bin" => {
+                        Some(ctx_scope.compile_as_binary_module(name_to_load, &[1, 2, 3]))
+                    }
+                    _ => None,
+                }
+            },
+        ));
+        module.evaluate(&ctx_scope).unwrap();
+        let res = ctx_scope
+            .compile(&isolate.new_string("res"))
+            .unwrap()
+            .run(&ctx_scope)
+            .unwrap();
+        assert_eq!(res.get_long(), 3);
+    }
+
+    #[test]
+    fn test_module_top_level_await() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let code_name = isolate.new_string("tla_module");
+        let code_str = isolate.new_string("await Promise.resolve(); await Promise.resolve();");
+        let module = ctx_scope
+            .compile_as_module(&code_name, &code_str, &v8_script::V8CompileOptions::new())
+            .unwrap();
+        assert_eq!(
+            module.get_status(),
+            v8_module::V8ModuleStatus::Uninstantiated
+        );
+        assert!(module.initialize(&ctx_scope, |_isolate, _ctx_scope, _name, _hash| None));
+        assert!(module.is_graph_async());
+        let res = module
+            .evaluate_and_wait(&ctx_scope, std::time::Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(
+            res.state(),
+            crate::v8::v8_promise::V8PromiseState::Fulfilled
+        );
+        assert_eq!(module.get_status(), v8_module::V8ModuleStatus::Evaluated);
+
+        let code_str = isolate.new_string("await new Promise(() => {});");
+        let module = ctx_scope
+            .compile_as_module(&code_name, &code_str, &v8_script::V8CompileOptions::new())
+            .unwrap();
+        assert!(module.initialize(&ctx_scope, |_isolate, _ctx_scope, _name, _hash| None));
+        let res = module
+            .evaluate_and_wait(&ctx_scope, std::time::Duration::from_millis(10))
+            .unwrap();
+        assert_eq!(res.state(), crate::v8::v8_promise::V8PromiseState::Pending);
+    }
+
+    #[test]
+    fn test_clear_kept_objects() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let code_str = isolate.new_string(
+            "globalThis.obj = {foo: 1}; globalThis.ref = new WeakRef(obj); ref.deref().foo",
+        );
+        let res = ctx_scope
+            .compile(&code_str)
+            .unwrap()
+            .run(&ctx_scope)
+            .unwrap();
+        assert_eq!(res.get_long(), 1);
+        isolate.clear_kept_objects();
+        while isolate.pump_message_loop(false) {}
+        // obj is still strongly referenced so the weak ref is still alive
+        let code_str = isolate.new_string("ref.deref().foo");
+        let res = ctx_scope
+            .compile(&code_str)
+            .unwrap()
+            .run(&ctx_scope)
+            .unwrap();
+        assert_eq!(res.get_long(), 1);
+    }
+
+    #[test]
+    fn test_array_buffer_allocator() {
+        use std::alloc::{alloc, alloc_zeroed, dealloc, Layout};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        struct CountingAllocator {
+            allocated: Arc<AtomicUsize>,
+            limit: usize,
+        }
+
+        impl v8_array_buffer::V8ArrayBufferAllocator for CountingAllocator {
+            fn allocate(&self, len: usize, zero_initialize: bool) -> *mut u8 {
+                if self.allocated.load(Ordering::Relaxed) + len > self.limit {
+                    return std::ptr::null_mut();
+                }
+                self.allocated.fetch_add(len, Ordering::Relaxed);
+                let layout = Layout::from_size_align(len.max(1), 16).unwrap();
+                unsafe {
+                    if zero_initialize {
+                        alloc_zeroed(layout)
+                    } else {
+                        alloc(layout)
+                    }
+                }
+            }
+
+            fn free(&self, data: *mut u8, len: usize) {
+                self.allocated.fetch_sub(len, Ordering::Relaxed);
+                let layout = Layout::from_size_align(len.max(1), 16).unwrap();
+                unsafe { dealloc(data, layout) }
+            }
+        }
+
+        initialize();
+        let allocated = Arc::new(AtomicUsize::new(0));
+        let isolate = isolate::V8Isolate::new_with_allocator(
+            0,
+            1024 * 1024 * 1024,
+            CountingAllocator {
+                allocated: Arc::clone(&allocated),
+                limit: 1024,
+            },
+        );
+        {
+            let _h_scope = isolate.new_handlers_scope();
+            let i_scope = isolate.enter();
+            let ctx = i_scope.new_context(None);
+            let ctx_scope = ctx.enter();
+            let code_str = isolate.new_string("globalThis.buf = new ArrayBuffer(100); 1");
+            ctx_scope
+                .compile(&code_str)
+                .unwrap()
+                .run(&ctx_scope)
+                .unwrap();
+            assert_eq!(allocated.load(Ordering::Relaxed), 100);
+
+            let trycatch = isolate.new_try_catch();
+            let code_str = isolate.new_string("new ArrayBuffer(2000)");
+            assert!(ctx_scope
+                .compile(&code_str)
+                .unwrap()
+                .run(&ctx_scope)
+                .is_none());
+            let trycatch_res = trycatch.get_exception();
+            let error = trycatch_res.to_utf8(&isolate).unwrap();
+            assert!(error.as_str().starts_with("RangeError"));
+        }
+        drop(isolate);
+        assert_eq!(allocated.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_eval() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        assert_eq!(ctx_scope.eval("1 + 2").unwrap().get_long(), 3);
+        assert_eq!(
+            ctx_scope.eval("throw new Error('foo')").err(),
+            Some(v8_context_scope::V8EvalError::Exception(
+                "Error: foo".to_string()
+            ))
+        );
+        assert!(ctx_scope
+            .eval("(")
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("SyntaxError"));
+
+        let a = isolate.new_long(2);
+        let b = isolate.new_long(3);
+        let res = ctx_scope
+            .eval_with(&[("a", &a), ("b", &b)], "var c = a * b; c")
+            .unwrap();
+        assert_eq!(res.get_long(), 6);
+        assert!(ctx_scope
+            .eval("typeof a === 'undefined' && typeof c === 'undefined'")
+            .unwrap()
+            .get_boolean());
+
+        // The binding names are spliced into the wrapper code, so anything that is
+        // not an identifier is refused instead of being evaluated.
+        for name in [
+            "a) { globalThis.x = 1; } (function(b",
+            "1a",
+            "",
+            "arguments",
+            "if",
+        ] {
+            assert_eq!(
+                ctx_scope.eval_with(&[(name, &a)], "1").err(),
+                Some(v8_context_scope::V8EvalError::InvalidBinding(
+                    name.to_string()
+                ))
+            );
+        }
+        assert!(ctx_scope
+            .eval("typeof x === 'undefined'")
+            .unwrap()
+            .get_boolean());
+        assert_eq!(
+            ctx_scope
+                .eval_with(&[("$_é1", &a)], "$_é1")
+                .unwrap()
+                .get_long(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_compile_function() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let name = isolate.new_string("user_code");
+        let code = isolate.new_string("return this.base + a + b;");
+        let func = ctx_scope
+            .compile_function(&name, &code, &["a", "b"])
+            .unwrap();
+        assert!(func.is_function());
+        let receiver = ctx_scope.eval("({base: 1})").unwrap();
+        let a = isolate.new_long(2);
+        let b = isolate.new_long(3);
+        let res = func
+            .call_with_receiver(&ctx_scope, &receiver, Some(&[&a, &b]))
+            .unwrap();
+        assert_eq!(res.get_long(), 6);
+
+        let code = isolate.new_string("\nfunction f() {}\nreturn f;");
+        let res = ctx_scope
+            .compile_function(&name, &code, &[])
+            .unwrap()
+            .call(&ctx_scope, None)
+            .unwrap();
+        assert_eq!(res.get_script_name(&isolate).unwrap(), "user_code");
+        // line numbers are zero based and not shifted by the function wrapping
+        assert_eq!(res.get_line_number(), Some(1));
+    }
+
+    #[test]
+    fn test_cached_primitives() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let handles = isolate.number_of_handles();
+        for _ in 0..1000 {
+            assert!(isolate.new_bool(true).get_boolean());
+            assert!(!isolate.new_bool(false).get_boolean());
+            assert!(isolate.new_null().is_null());
+            assert!(isolate.new_undefined().is_undefined());
+            assert!(isolate.new_empty_string().to_value().is_string());
+        }
+        assert_eq!(isolate.number_of_handles(), handles);
+        let func = ctx_scope.eval("(function(a, b, c, d) { return a === true && b === null && c === undefined && d === ''; })").unwrap();
+        let res = func
+            .call(
+                &ctx_scope,
+                Some(&[
+                    &isolate.new_bool(true),
+                    &isolate.new_null(),
+                    &isolate.new_undefined(),
+                    &isolate.new_empty_string().to_value(),
+                ]),
+            )
+            .unwrap();
+        assert!(res.get_boolean());
+    }
+
+    #[test]
+    fn test_numeric_array() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let values = (0..1000).map(|i| i as f64 / 2.0).collect::<Vec<f64>>();
+        let handles = isolate.number_of_handles();
+        let arr = isolate.new_array_from_f64_slice(&values);
+        assert_eq!(isolate.number_of_handles(), handles + 1);
+        assert_eq!(arr.len(), 1000);
+        assert_eq!(arr.to_f64_vec(&ctx_scope).unwrap(), values);
+
+        let sum = ctx_scope
+            .eval("(function(arr) { return arr.reduce((a, b) => a + b, 0); })")
+            .unwrap()
+            .call(&ctx_scope, Some(&[&arr.to_value()]))
+            .unwrap();
+        assert_eq!(sum.get_number(), values.iter().sum::<f64>());
+
+        let arr = ctx_scope.eval("[1, 'foo', 3]").unwrap().as_array();
+        assert!(arr.to_f64_vec(&ctx_scope).is_none());
+    }
+
+    #[test]
+    fn test_array_string_vec() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let keys = ["foo", "", "b\u{e4}r", "\u{1f600}"];
+        let arr = isolate.new_array_from_str_slice(&keys).unwrap();
+        assert_eq!(arr.len(), keys.len());
+        assert_eq!(arr.to_string_vec(&ctx_scope).unwrap(), keys);
+
+        let joined = ctx_scope
+            .eval("(function(arr) { return arr.join(','); })")
+            .unwrap()
+            .call(&ctx_scope, Some(&[&arr.to_value()]))
+            .unwrap();
+        assert_eq!(joined.to_utf8(&isolate).unwrap().as_str(), keys.join(","));
+
+        let arr = ctx_scope.eval("['foo', 'bar', 3]").unwrap().as_array();
+        assert_eq!(
+            arr.to_string_vec(&ctx_scope).unwrap_err(),
+            "index 2: Value is not a string"
+        );
+
+        let arr = ctx_scope
+            .eval("const a = ['foo']; Object.defineProperty(a, 1, { get() { throw 'bad' } }); a")
+            .unwrap()
+            .as_array();
+        let trycatch = isolate.new_try_catch();
+        assert_eq!(arr.to_string_vec(&ctx_scope).unwrap_err(), "index 1: bad");
+        assert!(!trycatch.has_caught());
+    }
+
+    #[test]
+    fn test_native_function_panic() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        ctx_scope.set_global_native_function("foo", |args, _isolate, _ctx_scope| {
+            panic!("bad argument count {}", args.len());
+        });
+        let res = ctx_scope
+            .eval("try { foo(1, 2); } catch (e) { e }")
+            .unwrap();
+        let res = res.to_utf8(&isolate).unwrap();
+        assert_eq!(
+            res.as_str(),
+            "Native function panicked: bad argument count 2"
+        );
+        // the isolate is still usable after the panic
+        assert_eq!(ctx_scope.eval("1 + 1").unwrap().get_long(), 2);
+    }
+
+    #[test]
+    fn test_current_context() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        assert!(isolate.current_context().is_none());
+        let ctx1 = i_scope.new_context(None);
+        let ctx2 = i_scope.new_context(None);
+        let ctx_scope1 = ctx1.enter();
+        ctx_scope1.eval("globalThis.name = 'ctx1'").unwrap();
+        assert!(ctx_scope1.is_current());
+        {
+            let ctx_scope2 = ctx2.enter();
+            ctx_scope2.eval("globalThis.name = 'ctx2'").unwrap();
+            assert!(ctx_scope2.is_current());
+            assert!(!ctx_scope1.is_current());
+            let current = isolate.current_context().unwrap();
+            let name = current.eval("name").unwrap();
+            assert_eq!(name.to_utf8(&isolate).unwrap().as_str(), "ctx2");
+        }
+        assert!(ctx_scope1.is_current());
+        let current = isolate.current_context().unwrap();
+        let name = current.eval("name").unwrap();
+        assert_eq!(name.to_utf8(&isolate).unwrap().as_str(), "ctx1");
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        use crate::v8::diagnostics::{V8Diagnostics, V8DiagnosticsEvent};
+        use std::sync::{Arc, Mutex};
+
+        struct EventsCollector {
+            events: Arc<Mutex<Vec<String>>>,
+        }
+
+        impl V8Diagnostics for EventsCollector {
+            fn on_event(&self, _isolate: &isolate::V8Isolate, event: &V8DiagnosticsEvent) {
+                let event = match event {
+                    V8DiagnosticsEvent::CompileStarted { name } => {
+                        format!("compile started {}", name.unwrap_or("<anonymous>"))
+                    }
+                    V8DiagnosticsEvent::CompileFinished { name, success, .. } => format!(
+                        "compile finished {} {}",
+                        name.unwrap_or("<anonymous>"),
+                        success
+                    ),
+                    V8DiagnosticsEvent::UncaughtException {
+                        message,
+                        line_number,
+                        ..
+                    } => format!("uncaught {} {:?}", message, line_number),
+                    // GC might happen at any time, not collected to keep the test deterministic
+                    _ => return,
+                };
+                self.events.lock().unwrap().push(event);
+            }
+        }
+
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        isolate.set_diagnostics(EventsCollector {
+            events: Arc::clone(&events),
+        });
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let name = isolate.new_string("foo");
+        let code = isolate.new_string("export let a = 1;");
+        let _module = ctx_scope
+            .compile_as_module(&name, &code, &v8_script::V8CompileOptions::new())
+            .unwrap();
+        assert!(ctx_scope.compile(&isolate.new_string("(")).is_none());
+        let script = ctx_scope
+            .compile(&isolate.new_string("throw new Error('bar')"))
+            .unwrap();
+        assert!(script.run(&ctx_scope).is_none());
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 8);
+        assert_eq!(events[0], "compile started foo");
+        assert_eq!(events[1], "compile finished foo true");
+        assert_eq!(events[2], "compile started <anonymous>");
+        assert!(events[3].starts_with("uncaught") && events[3].contains("SyntaxError"));
+        assert_eq!(events[4], "compile finished <anonymous> false");
+        assert_eq!(events[5], "compile started <anonymous>");
+        assert_eq!(events[6], "compile finished <anonymous> true");
+        assert!(events[7].starts_with("uncaught") && events[7].contains("Error: bar"));
+        assert!(events[7].ends_with("Some(1)"));
+    }
+
+    #[test]
+    fn test_script_timing() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        ctx_scope.eval("function f() { return 1; }").unwrap();
+        assert_eq!(ctx.get_execution_stats().compile_count, 0);
+        assert_eq!(ctx.get_execution_stats().run_count, 0);
+
+        ctx.set_script_timing(true);
+        let code_str =
+            isolate.new_string("let sum = 0; for (let i = 0; i < 100000; i++) { sum += f(); } sum");
+        let script = ctx_scope.compile(&code_str).unwrap();
+        script.run(&ctx_scope).unwrap();
+        let f = ctx_scope.eval("f").unwrap();
+        f.call(&ctx_scope, None).unwrap();
+        let stats = ctx.get_execution_stats();
+        // the eval of "f" is also a compilation and a run
+        assert_eq!(stats.compile_count, 2);
+        assert_eq!(stats.run_count, 3);
+        assert!(stats.run_time > std::time::Duration::ZERO);
+        assert!(stats.max_run_time <= stats.run_time);
+
+        ctx.set_script_timing(false);
+        script.run(&ctx_scope).unwrap();
+        assert_eq!(ctx.get_execution_stats().run_count, 3);
+    }
+
+    #[test]
+    fn test_sandbox() {
+        initialize();
+        let logs = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let logs_clone = std::rc::Rc::clone(&logs);
+        let errors = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let errors_clone = std::rc::Rc::clone(&errors);
+        let sandbox = sandbox::V8Sandbox::builder()
+            .time_limit(std::time::Duration::from_millis(100))
+            .console(move |level, msg| logs_clone.borrow_mut().push((level, msg.to_string())))
+            .error_handler(move |msg| errors_clone.borrow_mut().push(msg.to_string()))
+            .module_loader(|name| (name == "lib").then(|| "export const x = 1;".to_string()))
+            .build();
+
+        assert_eq!(
+            sandbox.eval("console.warn('foo', 1); ({a: [1, 'b'], c: null})"),
+            Ok(sandbox::V8SandboxValue::Object(vec![
+                (
+                    "a".to_string(),
+                    sandbox::V8SandboxValue::Array(vec![
+                        sandbox::V8SandboxValue::Number(1.0),
+                        sandbox::V8SandboxValue::String("b".to_string()),
+                    ])
+                ),
+                ("c".to_string(), sandbox::V8SandboxValue::Null),
+            ]))
+        );
+        assert_eq!(
+            *logs.borrow(),
+            vec![(sandbox::V8ConsoleLevel::Warn, "foo 1".to_string())]
+        );
+
+        sandbox
+            .eval("async function add(a, b) { return a + b; }")
+            .unwrap();
+        assert_eq!(
+            sandbox.call_function(
+                "add",
+                &[
+                    sandbox::V8SandboxValue::Number(1.0),
+                    sandbox::V8SandboxValue::Number(2.0)
+                ]
+            ),
+            Ok(sandbox::V8SandboxValue::Number(3.0))
+        );
+        assert!(sandbox.call_function("foo", &[]).is_err());
+        assert!(sandbox
+            .eval("throw new Error('bar')")
+            .unwrap_err()
+            .contains("bar"));
+
+        assert_eq!(
+            sandbox.eval("while (true) {}"),
+            Err("Execution timed out".to_string())
+        );
+        assert_eq!(sandbox.eval("1"), Ok(sandbox::V8SandboxValue::Number(1.0)));
+
+        sandbox
+            .load_module("main", "import {x} from 'lib'; globalThis.y = x + 1;")
+            .unwrap();
+        assert_eq!(sandbox.eval("y"), Ok(sandbox::V8SandboxValue::Number(2.0)));
+        assert!(sandbox.load_module("other", "import 'foo';").is_err());
+
+        sandbox.eval("Promise.reject('baz'); 1").unwrap();
+        assert_eq!(*errors.borrow(), vec!["baz".to_string()]);
+    }
+
+    #[test]
+    fn test_context_template() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let mut globals = isolate.new_object_template();
+        globals.add_value(&isolate, "start", &isolate.new_long(10));
+        let template = v8_context_template::V8ContextTemplate::new(&i_scope, Some(&globals))
+            .add_script("var counter = start;")
+            .add_script("function inc() { return ++counter; }");
+
+        for _ in 0..3 {
+            let ctx = template.instantiate(&i_scope).unwrap();
+            let ctx_scope = ctx.enter();
+            assert_eq!(ctx_scope.eval("inc()").unwrap().get_long(), 11);
+            assert_eq!(ctx_scope.eval("inc()").unwrap().get_long(), 12);
+        }
+
+        let template = v8_context_template::V8ContextTemplate::new(&i_scope, None)
+            .add_script("throw new Error('setup failed')");
+        assert!(matches!(template.instantiate(&i_scope), Err(e) if e.contains("setup failed")));
+    }
+
+    #[test]
+    fn test_constructor_and_class_names() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let names = |code: &str| {
+            let val = ctx_scope.eval(code).unwrap();
+            (
+                val.get_constructor_name(&ctx_scope),
+                val.is_object()
+                    .then(|| val.as_object().get_internal_class_name(&ctx_scope).unwrap()),
+            )
+        };
+        assert_eq!(
+            names("new Map()"),
+            (Some("Map".to_string()), Some("Map".to_string()))
+        );
+        assert_eq!(
+            names("new Uint8Array(1)"),
+            (
+                Some("Uint8Array".to_string()),
+                Some("Uint8Array".to_string())
+            )
+        );
+        assert_eq!(
+            names("class MyUserClass {}; new MyUserClass()"),
+            (Some("MyUserClass".to_string()), Some("Object".to_string()))
+        );
+        assert_eq!(names("1"), (None, None));
+    }
+
+    #[test]
+    fn test_args_conversion_errors() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        ctx_scope.set_global_native_function("add", |args, isolate, _ctx_scope| {
+            let mut iter = args.iter();
+            let res = iter
+                .next_as::<i64>()
+                .and_then(|a| Ok(a + iter.next_as::<u32>()? as i64));
+            match res {
+                Ok(n) => Some(isolate.new_long(n)),
+                Err(e) => {
+                    isolate.raise_exception_str(&e);
+                    None
+                }
+            }
+        });
+        assert_eq!(ctx_scope.eval("add(1, 2)").unwrap().get_long(), 3);
+        assert_eq!(
+            ctx_scope.eval("add(1, 'foo')").err().map(|e| e.to_string()),
+            Some("argument 2: expected u32, got string 'foo'".to_string())
+        );
+        assert_eq!(
+            ctx_scope
+                .eval("add(new Map(), 1)")
+                .err()
+                .map(|e| e.to_string()),
+            Some("argument 1: expected i64, got Map".to_string())
+        );
+        assert_eq!(
+            ctx_scope.eval("add(1)").err().map(|e| e.to_string()),
+            Some("argument 2: expected u32, got nothing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_object_builder() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let existing = isolate.new_string("existing").to_value();
+        let obj = i_scope
+            .object()
+            .prop("a", 1)
+            .prop("b", "x")
+            .prop("c", &existing)
+            .prop(
+                "d",
+                i_scope
+                    .array()
+                    .push(true)
+                    .push(1.5)
+                    .push(v8_builder::V8BuilderValue::Null),
+            )
+            .prop("e", v8_builder::V8ObjectBuilder::new().prop("f", 2))
+            .build(&ctx_scope);
+        let res = ctx_scope
+            .eval_with(&[("obj", &obj)], "JSON.stringify(obj)")
+            .unwrap();
+        assert_eq!(
+            res.to_utf8(&isolate).unwrap().as_str(),
+            r#"{"a":1,"b":"x","c":"existing","d":[true,1.5,null],"e":{"f":2}}"#
+        );
+    }
+
+    #[test]
+    fn test_array_buffer_from_vec() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        ctx_scope.set_global_native_function("bytes", |_args, isolate, _ctx_scope| {
+            Some(isolate.new_uint8_array_from_vec(vec![1, 2, 3]))
+        });
+        let res = ctx_scope
+            .eval("let b = bytes(); b instanceof Uint8Array && b.length == 3 && b[2] == 3")
+            .unwrap();
+        assert!(res.get_boolean());
+
+        let arr_buff = isolate.new_array_buffer_from_vec(b"foo".to_vec());
+        assert_eq!(arr_buff.data(), b"foo");
+        drop(arr_buff);
+        isolate.low_memory_notification();
+
+        // The data of an array buffer is freed with the same helper once the
+        // array buffer is collected, check that it frees the whole buffer.
+        struct Freed(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+        impl Drop for Freed {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+        let freed = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let data = vec![Freed(freed.clone()), Freed(freed.clone())];
+        isolate::free_boxed_vec::<Freed>(Box::into_raw(Box::new(data)).cast());
+        assert_eq!(freed.load(std::sync::atomic::Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_raise_typed_errors() {
+        struct ValidationError;
+
+        impl v8_error::V8Throwable for ValidationError {
+            fn error_message(&self) -> String {
+                "invalid".to_string()
+            }
+
+            fn error_kind(&self) -> v8_error::V8ErrorKind {
+                v8_error::V8ErrorKind::Custom("ValidationError".to_string())
+            }
+        }
+
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        ctx_scope.set_global_native_function("to_u8", |args, isolate, ctx_scope| {
+            let s = args.get(0).to_utf8(isolate).unwrap();
+            let res = s
+                .as_str()
+                .parse::<i64>()
+                .map_err(|e| ctx_scope.raise_error(&e))
+                .and_then(|n| u8::try_from(n).map_err(|e| ctx_scope.raise_error(&e)));
+            res.ok().map(|n| isolate.new_long(n.into()))
+        });
+        ctx_scope.set_global_native_function("validate", |_args, _isolate, ctx_scope| {
+            ctx_scope.raise_error(&ValidationError);
+            None
+        });
+        let res = ctx_scope
+            .eval(
+                "let errors = [];
+                for (let f of [() => to_u8('foo'), () => to_u8('300'), validate]) {
+                    try { f(); } catch (e) { errors.push(`${e.constructor.name}:${e.name}`); }
+                }
+                errors.join(',')",
+            )
+            .unwrap();
+        assert_eq!(
+            res.to_utf8(&isolate).unwrap().as_str(),
+            "SyntaxError:SyntaxError,RangeError:RangeError,Error:ValidationError"
+        );
+        assert_eq!(ctx_scope.eval("to_u8('7')").unwrap().get_long(), 7);
+    }
+
+    #[test]
+    fn test_object_brands() {
+        struct Point;
+        struct Other;
+
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let mut template = isolate.new_object_template();
+        template.set_brand::<Point>(&isolate);
+        let point = template.to_value(&ctx_scope).as_object();
+        assert!(point.check_brand::<Point>(&ctx_scope));
+        assert!(!point.check_brand::<Other>(&ctx_scope));
+
+        let obj = isolate.new_object();
+        assert!(!obj.check_brand::<Point>(&ctx_scope));
+        assert!(obj.set_brand::<Point>(&ctx_scope));
+        assert!(obj.check_brand::<Point>(&ctx_scope));
+
+        // A type with the same name, declared in a nested block, is a different brand.
+        {
+            struct Point;
+            assert!(!obj.check_brand::<Point>(&ctx_scope));
+        }
+
+        // brands are invisible to JS
+        let res = ctx_scope
+            .eval_with(&[("obj", &obj.to_value())], "Reflect.ownKeys(obj).length")
+            .unwrap();
+        assert_eq!(res.get_long(), 0);
+    }
+
+    #[test]
+    fn test_promise_hook() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        let events = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let events_clone = std::rc::Rc::clone(&events);
+        isolate.set_promise_hook(move |_isolate, hook_type, _promise, _parent| {
+            events_clone.borrow_mut().push(hook_type);
+        });
+        ctx_scope.eval("Promise.resolve(1).then((x) => x)").unwrap();
+        isolate.perform_microtask_checkpoint();
+        {
+            let events = events.borrow();
+            for hook_type in [
+                v8_promise::V8PromiseHookType::Init,
+                v8_promise::V8PromiseHookType::Resolve,
+                v8_promise::V8PromiseHookType::Before,
+                v8_promise::V8PromiseHookType::After,
+            ] {
+                assert!(events.contains(&hook_type));
+            }
+        }
+
+        isolate.clear_promise_hook();
+        let events_count = events.borrow().len();
+        ctx_scope.eval("Promise.resolve(1).then((x) => x)").unwrap();
+        isolate.perform_microtask_checkpoint();
+        assert_eq!(events.borrow().len(), events_count);
+    }
+
+    #[test]
+    fn test_continuation_preserved_data() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        ctx_scope.set_global_native_function("set_request_id", |args, _isolate, ctx_scope| {
+            ctx_scope.set_continuation_preserved_data(&args.get(0));
+            None
+        });
+        ctx_scope.set_global_native_function("get_request_id", |_args, _isolate, ctx_scope| {
+            Some(ctx_scope.get_continuation_preserved_data())
+        });
+        assert!(ctx_scope.get_continuation_preserved_data().is_undefined());
+        ctx_scope
+            .eval(
+                "var results = [];
+                async function handle(id) {
+                    set_request_id(id);
+                    await null;
+                    await null;
+                    results.push(get_request_id());
+                }
+                handle('a');
+                handle('b');",
+            )
+            .unwrap();
+        isolate.perform_microtask_checkpoint();
+        let res = ctx_scope.eval("results.join(',')").unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "a,b");
+    }
+
+    #[test]
+    fn test_weak_values() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let obj = ctx_scope.eval("({a: 1})").unwrap();
+        let weak_ref = ctx_scope.new_weak_ref(&obj).unwrap();
+        let res = ctx_scope
+            .eval_with(&[("w", &weak_ref), ("o", &obj)], "w.deref() === o")
+            .unwrap();
+        assert!(res.get_boolean());
+        {
+            let trycatch = isolate.new_try_catch();
+            assert!(ctx_scope.new_weak_ref(&isolate.new_long(1)).is_none());
+            assert!(trycatch.get_exception().is_object());
+        }
+
+        let collected = Arc::new(AtomicBool::new(false));
+        let weak = {
+            let _h_scope = isolate.new_handlers_scope();
+            let obj = isolate.new_object().to_value();
+            let collected = Arc::clone(&collected);
+            let weak = obj
+                .to_weak_with_finalizer(&isolate, move || {
+                    collected.store(true, Ordering::Relaxed);
+                })
+                .unwrap();
+            assert!(weak.to_local(&isolate).unwrap().strict_equals(&obj));
+            weak
+        };
+        isolate.low_memory_notification();
+        assert!(collected.load(Ordering::Relaxed));
+        assert!(weak.to_local(&isolate).is_none());
+
+        assert!(isolate.new_long(1).to_weak(&isolate).is_none());
+        let kept = obj.to_weak(&isolate).unwrap();
+        isolate.low_memory_notification();
+        assert!(kept.to_local(&isolate).unwrap().strict_equals(&obj));
+    }
+
+    #[test]
+    fn test_nullable_conversion() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let opts = ctx_scope.eval("({a: null, b: 3})").unwrap().as_object();
+        let get = |key: &str| -> v8_value::V8Nullable<i64> {
+            let val = opts
+                .get(&ctx_scope, &isolate.new_string(key).to_value())
+                .unwrap();
+            v8_value::V8Nullable::try_from(&val).unwrap()
+        };
+        assert_eq!(get("a"), v8_value::V8Nullable::Null);
+        assert_eq!(get("b"), v8_value::V8Nullable::Value(3));
+        assert_eq!(get("c"), v8_value::V8Nullable::Undefined);
+        assert_eq!(get("c").into_option(), None);
+
+        let val = isolate.new_string("x").to_value();
+        assert!(v8_value::V8Nullable::<i64>::try_from(&val).is_err());
+    }
+
+    #[test]
+    fn test_array_to_vec() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let arr = ctx_scope.eval("[1, 2, 3]").unwrap().as_array();
+        assert_eq!(arr.try_to_vec::<u32>(&ctx_scope).unwrap(), vec![1, 2, 3]);
+
+        let arr = ctx_scope.eval("[1, 'x']").unwrap().as_array();
+        assert_eq!(
+            arr.try_to_vec::<i64>(&ctx_scope).err().unwrap(),
+            "index 1: Value is not a number"
+        );
+
+        let nested = |val: &v8_value::V8LocalValue| -> Result<Vec<i64>, String> {
+            if !val.is_array() {
+                return Err("Value is not an array".to_string());
+            }
+            val.as_array().try_to_vec(&ctx_scope)
+        };
+        let arr = ctx_scope.eval("[[1], [2, 3]]").unwrap().as_array();
+        assert_eq!(
+            arr.try_to_vec_with(&ctx_scope, nested).unwrap(),
+            vec![vec![1], vec![2, 3]]
+        );
+        let arr = ctx_scope.eval("[[1], [2, -1.5]]").unwrap().as_array();
+        assert_eq!(
+            arr.try_to_vec_with(&ctx_scope, nested).err().unwrap(),
+            "index 1: index 1: Value -1.5 is not an integer"
+        );
+    }
+
+    #[test]
+    fn test_object_to_map() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let obj = ctx_scope.eval("({a: 1, b: 2})").unwrap().as_object();
+        let map = obj.try_to_map::<i64>(&ctx_scope).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["a"], 1);
+        assert_eq!(map["b"], 2);
+
+        let obj = ctx_scope.eval("({a: 1, b: 'x'})").unwrap().as_object();
+        assert_eq!(
+            obj.try_to_map::<i64>(&ctx_scope).err().unwrap(),
+            "property 'b': Value is not a number"
+        );
+
+        let obj = ctx_scope.eval("({a: {x: 1}, b: {}})").unwrap().as_object();
+        let map = obj
+            .try_to_map_with(&ctx_scope, |val| {
+                val.as_object().try_to_map::<u32>(&ctx_scope)
+            })
+            .unwrap();
+        assert_eq!(map["a"]["x"], 1);
+        assert!(map["b"].is_empty());
+    }
+
+    #[test]
+    fn test_native_module_registry() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let created = Arc::new(AtomicUsize::new(0));
+        let mut registry = native_module_registry::NativeModuleRegistry::new();
+        let created_clone = Arc::clone(&created);
+        registry.register_module("math", move |isolate, ctx_scope, exports| {
+            created_clone.fetch_add(1, Ordering::Relaxed);
+            exports.set(
+                ctx_scope,
+                &isolate.new_string("answer").to_value(),
+                &isolate.new_long(42),
+            );
+            Some(exports.to_value())
+        });
+        assert!(registry.install_require(&ctx_scope));
+
+        let res = ctx_scope
+            .eval("require('math').answer + (require('math') === require('math') ? 1 : 0)")
+            .unwrap();
+        assert_eq!(res.get_long(), 43);
+        assert_eq!(
+            ctx_scope.eval("require('fs')").err().unwrap().to_string(),
+            "Error: Cannot find module 'fs'"
+        );
+        assert_eq!(
+            ctx_scope.eval("require(1)").err().unwrap().to_string(),
+            "TypeError: The module name must be a string"
+        );
+
+        // a circular require returns the exports object the module is filling
+        registry.register_module("a", |isolate, ctx_scope, exports| {
+            exports.set(
+                ctx_scope,
+                &isolate.new_string("name").to_value(),
+                &isolate.new_string("a").to_value(),
+            );
+            let b = ctx_scope.eval("require('b')").ok()?;
+            exports.set(ctx_scope, &isolate.new_string("b").to_value(), &b);
+            Some(exports.to_value())
+        });
+        registry.register_module("b", |isolate, ctx_scope, exports| {
+            let a_name = ctx_scope.eval("require('a').name").ok()?;
+            exports.set(ctx_scope, &isolate.new_string("a_name").to_value(), &a_name);
+            Some(exports.to_value())
+        });
+        registry.register_module("broken", |_isolate, _ctx_scope, _exports| None);
+        assert!(registry.install_require(&ctx_scope));
+        testing::assert_eval(&ctx_scope, "require('a').b.a_name", "a");
+        testing::assert_eval_throws(
+            &ctx_scope,
+            "require('broken')",
+            "Module 'broken' failed to load",
+        );
+
+        let code_name = isolate.new_string("base_module");
+        let code_str = isolate
+            .new_string("import math from 'math'; globalThis.same = math === require('math');");
+        let module = ctx_scope
+            .compile_as_module(&code_name, &code_str, &v8_script::V8CompileOptions::new())
+            .unwrap();
+        assert!(
+            module.initialize(&ctx_scope, |_isolate, ctx_scope, name, _identity_hash| {
+                registry.load_module(ctx_scope, name)
+            })
+        );
+        module.evaluate(&ctx_scope).unwrap();
+        assert!(ctx_scope.eval("same").unwrap().get_boolean());
+        assert_eq!(created.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_call_iter() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let func = ctx_scope
+            .eval("(function(...args) { return args.join(','); })")
+            .unwrap();
+        let res = func
+            .call_iter(&ctx_scope, (1..=3).map(|i| isolate.new_long(i)))
+            .unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "1,2,3");
+
+        let a = isolate.new_string("a").to_value();
+        let res = func.call_iter(&ctx_scope, [&a, &a]).unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "a,a");
+
+        let res = v8_builder::V8ArgsBuilder::new()
+            .arg(1)
+            .arg("x")
+            .arg(&a)
+            .arg(v8_builder::V8ArrayBuilder::new().push(true))
+            .call(&ctx_scope, &func)
+            .unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "1,x,a,true");
+    }
+
+    #[test]
+    fn test_object_template_instances() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let mut template = isolate.new_object_template();
+        template.add_value(&isolate, "kind", &isolate.new_string("request").to_value());
+        assert!(!template.set_internal_field_count(usize::MAX));
+        assert!(template.set_internal_field_count(1));
+
+        let first = template
+            .new_instance_with_internal_fields(&ctx_scope, &[&isolate.new_long(1)])
+            .unwrap();
+        let second = template.new_instance(&ctx_scope);
+        assert!(!first.to_value().strict_equals(&second.to_value()));
+        assert_eq!(first.internal_field_count(), 1);
+        assert_eq!(first.get_internal_field(0).unwrap().get_long(), 1);
+        assert!(first.get_internal_field(1).is_none());
+        assert!(second.set_internal_field(0, &isolate.new_long(2)));
+        assert!(!second.set_internal_field(1, &isolate.new_long(2)));
+        assert_eq!(second.get_internal_field(0).unwrap().get_long(), 2);
+
+        let res = ctx_scope
+            .eval_with(
+                &[("o", &first.to_value())],
+                "o.kind + Object.keys(o).length",
+            )
+            .unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "request1");
+
+        let two = isolate.new_long(2);
+        assert!(template
+            .new_instance_with_internal_fields(&ctx_scope, &[&two, &two])
+            .is_none());
+    }
+
+    #[cfg(feature = "raw-handles")]
+    #[test]
+    fn test_raw_handles() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let raw_isolate = unsafe { isolate::V8Isolate::from_raw(isolate.as_raw()) };
+        assert_eq!(raw_isolate.get_id(), isolate.get_id());
+
+        let obj = ctx_scope.eval("({a: 1})").unwrap();
+        let raw_val = unsafe { v8_value::V8LocalValue::from_raw(obj.as_raw()) };
+        assert!(raw_val.strict_equals(&obj));
+
+        let obj = obj.as_object();
+        let raw_obj = unsafe { v8_object::V8LocalObject::from_raw(obj.as_raw()) };
+        assert!(raw_obj.to_value().strict_equals(&obj.to_value()));
+
+        let s = isolate.new_string("foo");
+        let raw_s = unsafe { crate::v8::v8_string::V8LocalString::from_raw(s.as_raw()) };
+        assert_eq!(raw_s.to_value().to_utf8(&isolate).unwrap().as_str(), "foo");
+    }
+
+    #[test]
+    fn test_import_meta_resolve() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        isolate.set_import_meta_resolver(|_isolate, _ctx_scope, _identity_hash, specifier| {
+            specifier
+                .strip_prefix("./")
+                .map(|s| format!("file:///app/{}", s))
+        });
+
+        let code_name = isolate.new_string("base_module");
+        let code_str = isolate.new_string(
+            "globalThis.res = import.meta.resolve('./assets/logo.png');
+             try { import.meta.resolve('fs'); } catch (e) { globalThis.err = e.message; }",
+        );
+        let module = ctx_scope
+            .compile_as_module(&code_name, &code_str, &v8_script::V8CompileOptions::new())
+            .unwrap();
+        assert!(
+            module.initialize(&ctx_scope, |_isolate, _ctx_scope, _name, _identity_hash| {
+                None
+            })
+        );
+        module.evaluate(&ctx_scope).unwrap();
+        let res = ctx_scope.eval("res").unwrap();
+        assert_eq!(
+            res.to_utf8(&isolate).unwrap().as_str(),
+            "file:///app/assets/logo.png"
+        );
+        let err = ctx_scope.eval("err").unwrap();
+        assert_eq!(
+            err.to_utf8(&isolate).unwrap().as_str(),
+            "Failed resolving module specifier 'fs'"
+        );
+        isolate.clear_import_meta_callback();
+    }
+
+    #[test]
+    fn test_string_conversion_modes() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let s = ctx_scope.eval("'a\\u{1F600}b'").unwrap().as_string();
+        assert_eq!(s.len(), 4);
+        assert_eq!(s.to_string_strict(&isolate).unwrap(), "a\u{1F600}b");
+        assert_eq!(s.to_string_lossy(&isolate), "a\u{1F600}b");
+        assert_eq!(s.to_wtf8(&isolate), "a\u{1F600}b".as_bytes());
+
+        let s = ctx_scope.eval("'a\\uD800b'").unwrap().as_string();
+        assert_eq!(s.to_utf16(&isolate), vec![0x61, 0xD800, 0x62]);
+        assert_eq!(
+            s.to_string_strict(&isolate).err().unwrap(),
+            "String contains a lone surrogate 0xD800 at index 1"
+        );
+        assert_eq!(s.to_string_lossy(&isolate), "a\u{FFFD}b");
+        assert_eq!(s.to_wtf8(&isolate), vec![0x61, 0xED, 0xA0, 0x80, 0x62]);
+        assert_eq!(
+            s.to_value().to_utf8(&isolate).unwrap().as_str(),
+            "a\u{FFFD}b"
+        );
+    }
+
+    #[test]
+    fn test_string_from_bytes() {
+        use crate::v8::v8_string::V8StringEncoding;
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let _i_scope = isolate.enter();
+
+        let s = isolate
+            .new_string_from_bytes(&[0x63, 0x61, 0x66, 0xE9], V8StringEncoding::Latin1)
+            .unwrap();
+        assert_eq!(s.to_string_strict(&isolate).unwrap(), "caf\u{E9}");
+
+        let s = isolate
+            .new_string_from_bytes(&[0x61, 0xFF, 0x62], V8StringEncoding::Utf8Lossy)
+            .unwrap();
+        assert_eq!(s.to_string_strict(&isolate).unwrap(), "a\u{FFFD}b");
+
+        let s = isolate
+            .new_string_from_bytes(&[0x61, 0x00, 0x00, 0xD8, 0x62], V8StringEncoding::Utf16)
+            .unwrap();
+        assert_eq!(s.to_utf16(&isolate), vec![0x61, 0xD800, 0xFFFD]);
+    }
+
+    #[test]
+    fn test_iterables() {
+        use crate::v8::v8_iterable::V8Iterable;
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let sum = |val: &v8_value::V8LocalValue| -> Option<i64> {
+            Some(val.values(&ctx_scope)?.map(|v| v.get_long()).sum())
+        };
+        assert_eq!(sum(&ctx_scope.eval("[1, 2, 3]").unwrap()), Some(6));
+        assert_eq!(sum(&ctx_scope.eval("new Set([1, 2, 2])").unwrap()), Some(3));
+        assert_eq!(
+            sum(&ctx_scope
+                .eval("(function*() { yield 4; yield 5; })()")
+                .unwrap()),
+            Some(9)
+        );
+        assert_eq!(sum(&ctx_scope.eval("({a: 1})").unwrap()), None);
+        assert_eq!(sum(&isolate.new_long(1)), None);
+        assert_eq!(sum(&isolate.new_null()), None);
+
+        let chars = isolate
+            .new_string("a😀b")
+            .to_value()
+            .values(&ctx_scope)
+            .unwrap()
+            .map(|c| c.to_utf8(&isolate).unwrap().as_str().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(chars, ["a", "😀", "b"]);
+
+        let map = ctx_scope.eval("new Map([['a', 1], ['b', 2]])").unwrap();
+        let entries = map.values(&ctx_scope).unwrap().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].as_array().get(&ctx_scope, 1).get_long(), 1);
+        let entries = map
+            .map_entries(&ctx_scope)
+            .unwrap()
+            .map(|(key, val)| {
+                (
+                    key.to_utf8(&isolate).unwrap().as_str().to_string(),
+                    val.get_long(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(entries, [("a".to_string(), 1), ("b".to_string(), 2)]);
+        assert!(isolate.new_long(1).map_entries(&ctx_scope).is_none());
+
+        let set = isolate.new_set();
+        set.add(&ctx_scope, &isolate.new_long(7));
+        assert_eq!(set.values(&ctx_scope).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_compile_with_options() {
+        use crate::v8::v8_script::V8CompileOptions;
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let code = isolate.new_string("undeclared = 1");
+        {
+            let trycatch = isolate.new_try_catch();
+            let script = ctx_scope
+                .compile_with_options(&code, &V8CompileOptions::new())
+                .unwrap()
+                .script;
+            assert!(script.run(&ctx_scope).is_none());
+            let err = trycatch.get_exception().to_utf8(&isolate).unwrap();
+            assert!(err.as_str().starts_with("ReferenceError"));
+        }
+        let script = ctx_scope
+            .compile_with_options(&code, &V8CompileOptions::new().strict(false))
+            .unwrap()
+            .script;
+        assert!(script.run(&ctx_scope).is_some());
+        // compile keeps compiling in sloppy mode
+        assert!(ctx_scope.compile(&code).unwrap().run(&ctx_scope).is_some());
+
+        let code = isolate.new_string("new Error('e').stack");
+        let options = V8CompileOptions::new().name("foo.js").offset(10, 4);
+        let stack = ctx_scope
+            .compile_with_options(&code, &options)
+            .unwrap()
+            .script
+            .run(&ctx_scope)
+            .unwrap();
+        assert!(stack
+            .to_utf8(&isolate)
+            .unwrap()
+            .as_str()
+            .contains("at foo.js:11:5"));
+
+        // The strict mode directive does not break a hashbang nor shift the columns.
+        let code = isolate.new_string("#!/usr/bin/env node\nnew Error('e').stack");
+        let options = V8CompileOptions::new().name("bar.js");
+        let stack = ctx_scope
+            .compile_with_options(&code, &options)
+            .unwrap()
+            .script
+            .run(&ctx_scope)
+            .unwrap();
+        assert!(stack
+            .to_utf8(&isolate)
+            .unwrap()
+            .as_str()
+            .contains("at bar.js:2:1"));
+
+        let options = V8CompileOptions::new();
+        let compiled = ctx_scope.compile_with_options(&code, &options).unwrap();
+        assert!(!compiled.cache_rejected);
+        let cache = compiled.script.create_code_cache();
+        let options = V8CompileOptions::new().code_cache(&cache);
+        let compiled = ctx_scope.compile_with_options(&code, &options).unwrap();
+        assert!(!compiled.cache_rejected);
+        let options = V8CompileOptions::new().code_cache(&[1, 2, 3]);
+        let compiled = ctx_scope.compile_with_options(&code, &options).unwrap();
+        assert!(compiled.cache_rejected);
+    }
+
+    #[test]
+    fn test_host_function_registry_merge() {
+        initialize();
+        let mut core = host_function_registry::HostFunctionRegistry::new();
+        core.register_function(
+            "version",
+            "version() -> number",
+            |_args, isolate, _ctx_scope| Some(isolate.new_long(1)),
+        )
+        .register_namespace_function(
+            "db",
+            "get",
+            "get() -> string",
+            |_args, isolate, _ctx_scope| Some(isolate.new_string("core").to_value()),
+        );
+        let mut tenant = host_function_registry::HostFunctionRegistry::new();
+        tenant
+            .register_namespace_function(
+                "db",
+                "get",
+                "get() -> string",
+                |_args, isolate, _ctx_scope| Some(isolate.new_string("tenant").to_value()),
+            )
+            .register_namespace_function("db", "set", "set()", |_args, _isolate, _ctx_scope| None)
+            .register_function("db", "db()", |_args, _isolate, _ctx_scope| None);
+
+        let mut merged = core.clone();
+        assert_eq!(merged.merge(&tenant).err().unwrap(), vec!["db", "db.get"]);
+        assert_eq!(merged.document(), core.document());
+
+        let mut extension = host_function_registry::HostFunctionRegistry::new();
+        extension.register_namespace_function(
+            "db",
+            "set",
+            "set()",
+            |_args, _isolate, _ctx_scope| None,
+        );
+        assert!(merged.merge(&extension).is_ok());
+        assert_eq!(
+            merged.document(),
+            "version: version() -> number\ndb.get: get() -> string\ndb.set: set()\n"
+        );
+
+        let mut tenant = host_function_registry::HostFunctionRegistry::new();
+        tenant.register_namespace_function(
+            "db",
+            "get",
+            "get() -> string",
+            |_args, isolate, _ctx_scope| Some(isolate.new_string("tenant").to_value()),
+        );
+        merged.merge_override(&tenant);
+
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let globals = merged.to_object_template(&isolate);
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(Some(&globals));
+        let ctx_scope = ctx.enter();
+        let res = ctx_scope
+            .eval("version() + db.get() + typeof db.set")
+            .unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "1tenantfunction");
+    }
+
+    #[test]
+    fn test_args_coercion() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let func = ctx_scope.new_native_function(|args, isolate, _ctx_scope| {
+            let mut args = args.iter();
+            let res = (|| {
+                let n = args.next_coerced::<i64>()?;
+                let f = args.next_coerced::<f64>()?;
+                let s = args.next_coerced::<String>()?;
+                let b = args.next_coerced::<bool>()?;
+                Ok::<_, String>(format!("{} {} {} {}", n, f, s, b))
+            })()
+            .unwrap_or_else(|e| e);
+            Some(isolate.new_string(&res).to_value())
+        });
+        let func = func.to_value();
+        let call = |code| {
+            let res = ctx_scope.eval_with(&[("f", &func)], code).unwrap();
+            res.to_utf8(&isolate).unwrap().as_str().to_string()
+        };
+        assert_eq!(call("f('42', '1.5', 7, 'x')"), "42 1.5 7 true");
+        assert_eq!(call("f(-3.7, 1)"), "-3 1 undefined false");
+        assert_eq!(
+            call("f(1e30)"),
+            "argument 1: 1000000000000000000000000000000 is out of range for i64"
+        );
+        // 2 ** 63 does not fit an i64 even though it equals `i64::MAX as f64`.
+        assert_eq!(
+            call("f(2 ** 63)"),
+            "argument 1: 9223372036854776000 is out of range for i64"
+        );
+        assert_eq!(
+            call("f(-(2 ** 63), 1)"),
+            "-9223372036854775808 1 undefined false"
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_native_function() {
+        use crate::v8::v8_async::V8SpawnedFuture;
+        use std::sync::Arc;
+        use std::task::{Context, Wake, Waker};
+        use std::thread;
+
+        struct ThreadWaker(thread::Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        // Run each future to completion on a thread of its own.
+        let spawner = |mut fut: V8SpawnedFuture| {
+            thread::spawn(move || {
+                let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+                let mut cx = Context::from_waker(&waker);
+                while fut.as_mut().poll(&mut cx).is_pending() {
+                    thread::park();
+                }
+            });
+        };
+
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let func = ctx_scope.new_async_native_function(spawner, |args, _isolate, _ctx_scope| {
+            let n = args.iter().next_coerced::<i64>();
+            async move {
+                let n = n?;
+                if n < 0 {
+                    return Err(format!("{} is negative", n));
+                }
+                Ok(n * 2)
+            }
+        });
+        ctx_scope
+            .eval_with(
+                &[("f", &func.to_value())],
+                "f(21).then(v => globalThis.ok = v); f(-1).catch(e => globalThis.err = e.message)",
+            )
+            .unwrap();
+        while !ctx_scope
+            .eval("globalThis.ok !== undefined && globalThis.err !== undefined")
+            .unwrap()
+            .get_boolean()
+        {
+            isolate.pump_message_loop(true);
+        }
+        let res = ctx_scope.eval("`${ok} ${err}`").unwrap();
+        assert_eq!(res.to_utf8(&isolate).unwrap().as_str(), "42 -1 is negative");
+    }
+
+    #[test]
+    fn test_unlocker_relock() {
+        use std::thread;
+
+        initialize();
+        let mut isolate = isolate::V8Isolate::new();
+        let isolate_id = isolate.get_id();
+        {
+            // The owner is only disposed once the handles are no longer used.
+            let handle = unsafe { isolate::V8Isolate::get_by_id(isolate_id) }.unwrap();
+            let _i_scope = handle.enter();
+            let unlocker = handle.new_unlocker();
+            // While unlocked, another thread can use the isolate.
+            thread::spawn(move || {
+                let isolate = unsafe { isolate::V8Isolate::get_by_id(isolate_id) }.unwrap();
+                let _h_scope = isolate.new_handlers_scope();
+                let i_scope = isolate.enter();
+                let ctx = i_scope.new_context(None);
+                let ctx_scope = ctx.enter();
+                assert_eq!(ctx_scope.eval("1 + 1").unwrap().get_long(), 2);
+            })
+            .join()
+            .unwrap();
+            assert_eq!(
+                isolate.dispose().err().unwrap(),
+                "Isolate is unlocked by an active unlocker"
+            );
+            assert!(unlocker.relock().is_ok());
+        }
+        assert!(isolate.dispose().is_ok());
+    }
+
+    #[test]
+    fn test_unlocker_owner_dropped() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let isolate_id = isolate.get_id();
+        let handle = unsafe { isolate::V8Isolate::get_by_id(isolate_id) }.unwrap();
+        let _i_scope = handle.enter();
+        let unlocker = handle.new_unlocker();
+        // The isolate is leaked instead of being freed under the unlocker.
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(isolate)));
+        assert_eq!(res.is_err(), cfg!(debug_assertions));
+        assert!(unsafe { isolate::V8Isolate::get_by_id(isolate_id) }.is_none());
+        assert!(isolate_id.is_alive());
+        assert!(unlocker.relock().is_ok());
+        assert!(!isolate_id.is_alive());
+    }
+
+    #[test]
+    fn test_restore_globals() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        ctx_scope.eval("globalThis.config = 1").unwrap();
+        let baseline = ctx_scope.record_globals().unwrap();
+        ctx_scope
+            .eval("var a = 1; globalThis.b = 2; config = 3; Object = null; Reflect = null")
+            .unwrap();
+        ctx_scope.restore_globals(&baseline).unwrap();
+        let res = ctx_scope
+            .eval("[typeof a, typeof b, config, typeof Object.keys].join()")
+            .unwrap();
+        assert_eq!(
+            res.to_utf8(&isolate).unwrap().as_str(),
+            "undefined,undefined,1,function"
+        );
+
+        ctx_scope
+            .eval("Object.defineProperty(globalThis, 'c', {value: 1})")
+            .unwrap();
+        assert_eq!(
+            ctx_scope.restore_globals(&baseline).err().unwrap(),
+            "Failed restoring the globals: c"
+        );
+    }
+
+    #[test]
+    fn test_json_stringify_stable() {
+        use crate::v8::v8_json::V8JsonStringifyOptions;
+
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let options = V8JsonStringifyOptions::new();
+        let a = ctx_scope
+            .eval("({b: 1, a: {d: [3, {y: 1, x: 2}], c: 2}})")
+            .unwrap();
+        let b = ctx_scope
+            .eval("({a: {c: 2, d: [3, {x: 2, y: 1}]}, b: 1})")
+            .unwrap();
+        let a = ctx_scope.json_stringify_stable(&a, &options).unwrap();
+        assert_eq!(a, r#"{"a":{"c":2,"d":[3,{"x":2,"y":1}]},"b":1}"#);
+        assert_eq!(a, ctx_scope.json_stringify_stable(&b, &options).unwrap());
+
+        let val = ctx_scope.eval("({b: 1, a: 'x'})").unwrap();
+        let replacer = ctx_scope
+            .eval("(k, v) => typeof v === 'number' ? v * 10 : v")
+            .unwrap();
+        let options = V8JsonStringifyOptions::new().replacer(&replacer).pretty();
+        assert_eq!(
+            ctx_scope.json_stringify_stable(&val, &options).unwrap(),
+            "{\n  \"a\": \"x\",\n  \"b\": 10\n}"
+        );
+
+        let val = ctx_scope.eval("const o = {}; o.o = o; o").unwrap();
+        assert!(ctx_scope
+            .json_stringify_stable(&val, &V8JsonStringifyOptions::new())
+            .is_err());
+
+        // The builtins were captured by the first call, replacing them does not matter.
+        let val = ctx_scope
+            .eval("JSON.stringify = () => 'x'; Object.keys = null; Array.prototype.sort = null; ({b: 1, a: 2})")
+            .unwrap();
+        assert_eq!(
+            ctx_scope
+                .json_stringify_stable(&val, &V8JsonStringifyOptions::new())
+                .unwrap(),
+            r#"{"a":2,"b":1}"#
+        );
+    }
+
+    #[test]
+    fn test_promise_settled() {
+        use crate::v8::v8_promise::V8PromiseResultError;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let settled = Rc::new(RefCell::new(Vec::new()));
+        let resolver = ctx_scope.new_resolver();
+        let promise = resolver.get_promise();
+        assert!(matches!(
+            promise.result_checked(),
+            Err(V8PromiseResultError::Pending)
+        ));
+        let settled_clone = Rc::clone(&settled);
+        promise.on_settled(&ctx_scope, move |isolate, _ctx_scope, res| {
+            let res = res
+                .map(|v| v.get_long())
+                .map_err(|e| e.to_utf8(isolate).unwrap().as_str().to_string());
+            settled_clone.borrow_mut().push(res);
+        });
+        resolver.resolve(&ctx_scope, &isolate.new_long(1));
+        assert_eq!(promise.result_checked().ok().unwrap().get_long(), 1);
+
+        let rejected = ctx_scope.eval("Promise.reject('err')").unwrap();
+        let rejected = rejected.as_promise();
+        rejected.on_settled(&ctx_scope, {
+            let settled = Rc::clone(&settled);
+            move |isolate, _ctx_scope, res| {
+                let res = res
+                    .map(|v| v.get_long())
+                    .map_err(|e| e.to_utf8(isolate).unwrap().as_str().to_string());
+                settled.borrow_mut().push(res);
+            }
+        });
+        match rejected.result_checked() {
+            Err(V8PromiseResultError::Rejected(e)) => {
+                assert_eq!(e.to_utf8(&isolate).unwrap().as_str(), "err")
+            }
+            _ => panic!("promise was not rejected"),
+        }
+
+        isolate.perform_microtask_checkpoint();
+        assert_eq!(*settled.borrow(), vec![Ok(1), Err("err".to_string())]);
+    }
+
+    #[test]
+    fn test_thenables() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let thenable = ctx_scope
+            .eval("({then(resolve) { resolve(42); }})")
+            .unwrap();
+        assert!(thenable.is_thenable(&ctx_scope));
+        assert!(ctx_scope
+            .eval("Promise.resolve(1)")
+            .unwrap()
+            .is_thenable(&ctx_scope));
+        assert!(!ctx_scope
+            .eval("({then: 1})")
+            .unwrap()
+            .is_thenable(&ctx_scope));
+        assert!(!ctx_scope
+            .eval("({get then() { throw 'err'; }})")
+            .unwrap()
+            .is_thenable(&ctx_scope));
+
+        let promise = thenable.resolve_thenable(&ctx_scope);
+        isolate.perform_microtask_checkpoint();
+        assert_eq!(promise.state(), v8_promise::V8PromiseState::Fulfilled);
+        assert_eq!(promise.get_result().get_long(), 42);
+
+        let promise = isolate.new_long(1).resolve_thenable(&ctx_scope);
+        assert_eq!(promise.get_result().get_long(), 1);
+    }
+
+    #[test]
+    fn test_local_try_from() {
+        use crate::v8::v8_array::V8LocalArray;
+        use crate::v8::v8_native_function::V8LocalNativeFunction;
+        use crate::v8::v8_object::V8LocalObject;
+        use crate::v8::v8_string::V8LocalString;
+
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+
+        let arr = V8LocalArray::try_from(ctx_scope.eval("[1, 2]").unwrap()).unwrap();
+        assert_eq!(arr.len(), 2);
+        let val: v8_value::V8LocalValue = arr.into();
+        assert!(V8LocalObject::try_from(&val).is_ok());
+        assert_eq!(
+            V8LocalString::try_from(&val).err().unwrap(),
+            "Value is not a string"
+        );
+
+        let func =
+            V8LocalNativeFunction::try_from(ctx_scope.eval("(a) => a * 2").unwrap()).unwrap();
+        let res = func
+            .to_value()
+            .call(&ctx_scope, Some(&[&isolate.new_long(21)]))
+            .unwrap();
+        assert_eq!(res.get_long(), 42);
+        assert_eq!(
+            v8_promise::V8LocalPromise::try_from(res).err().unwrap(),
+            "Value is not a promise"
+        );
+    }
+
+    #[test]
+    fn test_unbound_script_ids() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx1 = i_scope.new_context(None);
+        let ctx2 = i_scope.new_context(None);
+
+        let unbound = {
+            let ctx_scope = ctx1.enter();
+            let script = ctx_scope
+                .compile(&isolate.new_string("function f() {}; f"))
+                .unwrap();
+            let f = script.run(&ctx_scope).unwrap();
+            assert_eq!(f.get_script_id(), Some(script.get_id()));
+            script.to_unbound(&isolate)
+        };
+        let ctx_scope = ctx2.enter();
+        let f = unbound.run(&ctx_scope).unwrap();
+        assert_eq!(f.get_script_id(), Some(unbound.get_id(&isolate)));
+        assert_eq!(unbound.bind(&ctx_scope).get_id(), unbound.get_id(&isolate));
+    }
+
+    #[test]
+    fn test_persisted_module_namespace() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let ctx = {
+            let _h_scope = isolate.new_handlers_scope();
+            let i_scope = isolate.enter();
+            i_scope.new_context(None)
+        };
+
+        let persisted_module = {
+            let _i_scope = isolate.enter();
+            let _h_scope = isolate.new_handlers_scope();
+            let ctx_scope = ctx.enter();
+            let module = ctx_scope
+                .compile_as_module(
+                    &isolate.new_string("lib"),
+                    &isolate.new_string(
+                        "export let calls = 0; export function f() { return ++calls; }",
+                    ),
+                    &v8_script::V8CompileOptions::new(),
+                )
+                .unwrap();
+            assert!(module.get_namespace().is_none());
+            assert!(module.initialize(&ctx_scope, |_, _, _, _| None));
+            module.evaluate(&ctx_scope).unwrap();
+            module.persist(&isolate)
+        };
+
+        let _i_scope = isolate.enter();
+        let ctx_scope = ctx.enter();
+        let module = persisted_module.to_local(&isolate);
+        let namespace = module.get_namespace().unwrap();
+        let res = ctx_scope
+            .eval_with(&[("lib", &namespace)], "lib.f(); lib.f(); lib.calls")
+            .unwrap();
+        assert_eq!(res.get_long(), 2);
+
+        let other = isolate::V8Isolate::new();
+        assert_eq!(
+            persisted_module.try_to_local(&other).err().unwrap(),
+            "The persisted module belongs to a different isolate"
+        );
+    }
+
+    #[test]
+    fn test_testing_fixtures() {
+        use crate::v8::testing::{assert_eval, assert_eval_throws, with_context};
+
+        let res = with_context(|isolate, ctx_scope| {
+            assert_eval(ctx_scope, "[1, 2].map(x => x * 2)", "2,4");
+            assert_eval_throws(ctx_scope, "foo()", "foo is not defined");
+            ctx_scope
+                .eval("'x'")
+                .unwrap()
+                .to_utf8(isolate)
+                .unwrap()
+                .as_str()
+                .to_string()
+        });
+        assert_eq!(res, "x");
+    }
+
+    #[test]
+    fn test_sandbox_eval_expression() {
+        use crate::v8::sandbox::{V8Sandbox, V8SandboxValue};
+
+        initialize();
+        let sandbox = V8Sandbox::builder().build();
+        let scope = [(
+            "limits",
+            V8SandboxValue::Object(vec![("max".to_string(), V8SandboxValue::Number(21.0))]),
+        )];
+        assert_eq!(
+            sandbox.eval_expression("limits.max * 2", &scope).unwrap(),
+            V8SandboxValue::Number(42.0)
+        );
+        assert_eq!(
+            sandbox.eval_expression("typeof Math", &scope).unwrap(),
+            V8SandboxValue::String("undefined".to_string())
+        );
+        assert!(sandbox
+            .eval_expression("limits.max = 1", &scope)
+            .err()
+            .unwrap()
+            .contains("read only"));
+        assert!(sandbox
+            .eval_expression("(() => 0).constructor('return 1')()", &scope)
+            .err()
+            .unwrap()
+            .contains("EvalError"));
+        // Breaking out of the expression to run statements is rejected.
+        for expression in [
+            "1); while (true) {}; (1",
+            "1)]; while (true) {}; [(1",
+            "1\n}\n{",
+        ] {
+            assert!(sandbox
+                .eval_expression(expression, &scope)
+                .err()
+                .unwrap()
+                .contains("SyntaxError"));
+        }
+        assert_eq!(
+            sandbox.eval_expression("{a: 1}", &scope).unwrap(),
+            V8SandboxValue::Object(vec![("a".to_string(), V8SandboxValue::Number(1.0))])
+        );
+        assert_eq!(
+            sandbox
+                .eval_expression("(() => { while (true) {} })()", &scope)
+                .err()
+                .unwrap(),
+            "Execution timed out"
+        );
+        // The sandbox context is not affected.
+        assert_eq!(
+            sandbox.eval("typeof Math").unwrap(),
+            V8SandboxValue::String("object".to_string())
+        );
+    }
+
+    #[test]
+    fn test_error_payload_round_trip() {
+        #[derive(Debug, PartialEq)]
+        struct NativeError(u32);
+
+        testing::with_context(|isolate, ctx_scope| {
+            let native = ctx_scope.new_native_function(|_args, _isolate, ctx_scope| {
+                ctx_scope.raise_error_with_payload("native failure", NativeError(42));
+                None
+            });
+            ctx_scope.get_globals().set(
+                ctx_scope,
+                &isolate.new_string("native").to_value(),
+                &native.to_value(),
+            );
+            let script = ctx_scope
+                .compile(&isolate.new_string(
+                    "try { native() } catch (e) { e.message = 'rethrown: ' + e.message; throw e }",
+                ))
+                .unwrap();
+            let trycatch = isolate.new_try_catch();
+            assert!(script.run(ctx_scope).is_none());
+            let exception = trycatch.get_exception();
+            assert_eq!(
+                exception.get_error_payload::<NativeError>(ctx_scope),
+                Some(&NativeError(42))
+            );
+            assert!(exception.get_error_payload::<String>(ctx_scope).is_none());
+            let message = exception
+                .as_object()
+                .get(ctx_scope, &isolate.new_string("message").to_value())
+                .unwrap();
+            assert_eq!(
+                message.to_utf8(isolate).unwrap().as_str(),
+                "rethrown: native failure"
+            );
+        });
+    }
+
+    #[test]
+    fn test_script_cache() {
+        use crate::v8::isolate::V8MemoryPressureLevel;
+        use crate::v8::script_cache::V8ScriptCache;
+
+        testing::with_context(|isolate, ctx_scope| {
+            let cache = V8ScriptCache::new(isolate, 2);
+            let id = cache.compile(ctx_scope, "1 + 1").unwrap().get_id();
+            assert_eq!(cache.compile(ctx_scope, "1 + 1").unwrap().get_id(), id);
+            let _ = cache.compile(ctx_scope, "2 + 2").unwrap();
+            // "3 + 3" evicts "1 + 1", the least recently used script.
+            let _ = cache.compile(ctx_scope, "3 + 3").unwrap();
+            assert_eq!(cache.len(), 2);
+            assert_ne!(cache.compile(ctx_scope, "1 + 1").unwrap().get_id(), id);
+            assert!(cache.compile(ctx_scope, "1 +").is_none());
+            assert_eq!(cache.len(), 2);
+
+            isolate.memory_pressure_notification(V8MemoryPressureLevel::None);
+            while isolate.pump_message_loop(false) {}
+            assert_eq!(cache.len(), 2);
+
+            isolate.memory_pressure_notification(V8MemoryPressureLevel::Moderate);
+            // the caches are shrunk by a task posted to the isolate
+            assert_eq!(cache.len(), 2);
+            while isolate.pump_message_loop(false) {}
+            assert_eq!(cache.len(), 1);
+            isolate.low_memory_notification();
+            assert!(cache.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_module_error_stages() {
+        use crate::v8::v8_module::V8ModuleErrorStage;
+        use crate::v8::v8_string::V8LocalString;
+
+        testing::with_context(|isolate, ctx_scope| {
+            let load = |isolate: &isolate::V8Isolate,
+                        ctx_scope: &v8_context_scope::V8ContextScope,
+                        name: &V8LocalString,
+                        _identity_hash: i64| {
+                let code = match name.to_value().to_utf8(isolate).unwrap().as_str() {
+                    "./a" => "import './b'; export const a = 1;",
+                    "./b" => "throw new Error('b failed');",
+                    "./c" => "import { missing } from './a';",
+                    "./d" => "import './unknown';",
+                    _ => {
+                        isolate.raise_exception_str("Module not found");
+                        return None;
+                    }
+                };
+                ctx_scope.compile_as_module(
+                    name,
+                    &isolate.new_string(code),
+                    &v8_script::V8CompileOptions::new(),
+                )
+            };
+            let root = |code: &str| {
+                ctx_scope
+                    .compile_as_module_checked(
+                        &isolate.new_string("root"),
+                        &isolate.new_string(code),
+                        &v8_script::V8CompileOptions::new(),
+                    )
+                    .unwrap()
+            };
+
+            let err = ctx_scope
+                .compile_as_module_checked(
+                    &isolate.new_string("root"),
+                    &isolate.new_string("export const;"),
+                    &v8_script::V8CompileOptions::new(),
+                )
+                .err()
+                .unwrap();
+            assert_eq!(err.stage, V8ModuleErrorStage::Compile);
+            assert_eq!(err.module.as_deref(), Some("root"));
+
+            let err = root("import './d';")
+                .initialize_checked(ctx_scope, load)
+                .err()
+                .unwrap();
+            assert_eq!(err.stage, V8ModuleErrorStage::Resolve);
+            assert_eq!(err.module.as_deref(), Some("./unknown"));
+            assert_eq!(err.import_chain, vec!["./d", "./unknown"]);
+            assert!(err.message.contains("Module not found"));
+
+            let err = root("import './c';")
+                .initialize_checked(ctx_scope, load)
+                .err()
+                .unwrap();
+            assert_eq!(err.stage, V8ModuleErrorStage::Instantiate);
+            assert_eq!(err.module.as_deref(), Some("./c"));
+            assert_eq!(err.import_chain, vec!["./c"]);
+
+            let module = root("import { a } from './a';");
+            let graph = module.initialize_checked(ctx_scope, load).unwrap();
+            assert_eq!(graph.import_chain("./b").unwrap(), ["./a", "./b"]);
+            let err = module.evaluate_checked(ctx_scope, &graph).err().unwrap();
+            assert_eq!(err.stage, V8ModuleErrorStage::Evaluate);
+            assert_eq!(err.module.as_deref(), Some("./b"));
+            assert_eq!(err.import_chain, vec!["./a", "./b"]);
+            assert_eq!(err.message, "Error: b failed");
+        });
+    }
+
+    #[test]
+    fn test_import_map() {
+        use crate::v8::import_map::V8ImportMap;
+        use std::cell::RefCell;
+
+        testing::with_context(|isolate, ctx_scope| {
+            let import_map = V8ImportMap::from_json(
+                ctx_scope,
+                r#"{
+                    "imports": {"utils": "/lib/utils.js", "vendor/": "/vendor/"},
+                    "scopes": {"/app/": {"utils": "/app/utils.js"}}
+                }"#,
+            )
+            .unwrap();
+            assert_eq!(
+                import_map.resolve("../x.js", Some("/app/a/main.js")),
+                "/app/x.js"
+            );
+            assert_eq!(import_map.resolve("utils", None), "/lib/utils.js");
+            assert!(
+                V8ImportMap::from_json(ctx_scope, r#"{"imports": {"a": 1}}"#)
+                    .err()
+                    .unwrap()
+                    .contains("property 'a'")
+            );
+            ctx_scope.set_import_map(import_map);
+
+            let loaded = RefCell::new(Vec::new());
+            let module = ctx_scope
+                .compile_as_module(
+                    &isolate.new_string("/app/main.js"),
+                    &isolate
+                        .new_string("import 'utils'; import 'vendor/a.js'; import './local.js';"),
+                    &v8_script::V8CompileOptions::new(),
+                )
+                .unwrap();
+            assert!(
+                module.initialize(ctx_scope, |isolate, ctx_scope, name, _identity_hash| {
+                    let name_utf8 = name.to_value().to_utf8(isolate).unwrap();
+                    loaded.borrow_mut().push(name_utf8.as_str().to_string());
+                    let code = if name_utf8.as_str() == "/vendor/a.js" {
+                        "import 'utils';"
+                    } else {
+                        ""
+                    };
+                    ctx_scope.compile_as_module(
+                        name,
+                        &isolate.new_string(code),
+                        &v8_script::V8CompileOptions::new(),
+                    )
+                })
+            );
+            let mut loaded = loaded.into_inner();
+            loaded.sort();
+            assert_eq!(
+                loaded,
+                vec![
+                    "/app/local.js",
+                    "/app/utils.js",
+                    "/lib/utils.js",
+                    "/vendor/a.js"
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn test_import_map_does_not_hold_modules() {
+        use crate::v8::import_map::V8ImportMap;
+
+        initialize();
+        let mut isolate = isolate::V8Isolate::new();
+        {
+            let _h_scope = isolate.new_handlers_scope();
+            let i_scope = isolate.enter();
+            let ctx = i_scope.new_context(None);
+            let ctx_scope = ctx.enter();
+            ctx_scope.set_import_map(V8ImportMap::new());
+            for i in 0..10 {
+                let _h_scope = isolate.new_handlers_scope();
+                ctx_scope
+                    .compile_as_module(
+                        &isolate.new_string(&format!("/app/{}.js", i)),
+                        &isolate.new_string("export const a = 1;"),
+                        &v8_script::V8CompileOptions::new(),
+                    )
+                    .unwrap();
+            }
+            isolate.low_memory_notification();
+        }
+        // The names of the compiled modules are held by weak handles,
+        // which do not keep the isolate from being disposed.
+        isolate.dispose().unwrap();
+    }
+
+    #[test]
+    fn test_try_call_exception_value() {
+        use crate::v8::v8_value::V8CallError;
+
+        testing::with_context(|isolate, ctx_scope| {
+            let funcs = ctx_scope
+                .eval(
+                    "class AppError extends Error { constructor(code) { super('app'); this.code = code; } };
+                    [(c) => { throw new AppError(c) }, async (c) => { throw new AppError(c) }, (c) => c]",
+                )
+                .unwrap();
+            let funcs = funcs.as_array();
+            let arg = isolate.new_long(7);
+            for i in 0..2 {
+                let exception = match funcs.get(ctx_scope, i).try_call(ctx_scope, &[&arg]) {
+                    Err(V8CallError::Exception(e)) => e,
+                    _ => panic!("expected an exception"),
+                };
+                let code = exception
+                    .as_object()
+                    .get(ctx_scope, &isolate.new_string("code").to_value())
+                    .unwrap();
+                assert_eq!(code.get_long(), 7);
+                assert_eq!(
+                    exception
+                        .as_object()
+                        .get_constructor_name(ctx_scope)
+                        .unwrap(),
+                    "AppError"
+                );
+            }
+            let res = funcs.get(ctx_scope, 2).try_call(ctx_scope, &[&arg]);
+            assert_eq!(res.ok().unwrap().get_long(), 7);
+        });
+    }
+
+    #[test]
+    fn test_try_new_string() {
+        use crate::v8::v8_string::{V8LocalString, V8NewStringOptions};
+
+        testing::with_isolate(|isolate, _i_scope| {
+            let options = V8NewStringOptions::new().max_len(5).internalize(true);
+            let s = isolate.try_new_string("hello", &options).unwrap();
+            assert_eq!(s.to_value().to_utf8(isolate).unwrap().as_str(), "hello");
+            assert_eq!(
+                isolate
+                    .try_new_string("hello world", &options)
+                    .err()
+                    .unwrap(),
+                "String length 11 exceeds the maximum length 5"
+            );
+            let too_long = "a".repeat(V8LocalString::max_length() + 1);
+            assert!(isolate
+                .try_new_string(&too_long, &V8NewStringOptions::new())
+                .is_err());
+        });
+    }
+
+    #[cfg(feature = "raw-handles")]
+    #[test]
+    fn test_isolate_into_raw() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let id = isolate.get_id();
+        let borrowed = unsafe { isolate::V8Isolate::from_raw(isolate.as_raw()) };
+        assert!(borrowed.into_raw().is_none());
+        let raw = isolate.into_raw().unwrap();
+        assert!(id.is_alive());
+
+        let isolate = unsafe { isolate::V8Isolate::from_raw_owned(raw) };
+        assert_eq!(isolate.get_id(), id);
+        drop(isolate);
+        assert!(!id.is_alive());
+    }
+
+    #[test]
+    fn test_private_data_many() {
+        use crate::v8::v8_context_scope::V8PrivateData;
+        testing::with_context(|_isolate, ctx_scope| {
+            let request = 1_i64;
+            let user = "foo".to_string();
+            let outer = 2_i64;
+            ctx_scope.set_private_data(2, Some(&outer));
+            {
+                let _guard = ctx_scope
+                    .set_private_data_many(&[
+                        V8PrivateData::new(0, &request),
+                        V8PrivateData::new(1, &user),
+                        V8PrivateData::new(2, &request),
+                    ])
+                    .unwrap();
+                assert_eq!(ctx_scope.get_private_data::<i64>(0), Some(&1));
+                assert_eq!(ctx_scope.get_private_data::<String>(1).unwrap(), "foo");
+                assert_eq!(ctx_scope.get_private_data::<i64>(2), Some(&1));
+            }
+            assert!(ctx_scope.get_private_data::<i64>(0).is_none());
+            assert!(ctx_scope.get_private_data::<String>(1).is_none());
+            assert_eq!(ctx_scope.get_private_data::<i64>(2), Some(&2));
+            assert_eq!(
+                ctx_scope
+                    .set_private_data_many(&[
+                        V8PrivateData::new(0, &request),
+                        V8PrivateData::new(0, &user),
+                    ])
+                    .err()
+                    .unwrap(),
+                "Private data index 0 is set more than once"
+            );
+            assert!(ctx_scope.get_private_data::<i64>(0).is_none());
+            ctx_scope.set_private_data::<i64>(2, None);
+        });
+    }
+
+    #[test]
+    fn test_host_value() {
+        use crate::v8::host_value::HostValue;
+        testing::with_context(|_isolate, ctx_scope| {
+            let host = HostValue::Array(vec![
+                HostValue::Nil,
+                HostValue::Int(1),
+                HostValue::Double(1.5),
+                HostValue::BulkString(b"foo".to_vec()),
+                HostValue::BulkString(vec![0xff, 0xfe]),
+                HostValue::Map(vec![(
+                    HostValue::Int(2),
+                    HostValue::Error("bar".to_string()),
+                )]),
+                HostValue::Set(vec![HostValue::BulkString(b"baz".to_vec())]),
+            ]);
+            let val = host.to_value(ctx_scope).unwrap();
+            assert_eq!(HostValue::from_value(ctx_scope, &val).unwrap(), host);
+
+            let val = ctx_scope.eval("({a: [true, 2.5], b: null})").unwrap();
+            assert_eq!(
+                HostValue::from_value(ctx_scope, &val).unwrap(),
+                HostValue::Map(vec![
+                    (
+                        HostValue::BulkString(b"a".to_vec()),
+                        HostValue::Array(vec![HostValue::Int(1), HostValue::Double(2.5)])
+                    ),
+                    (HostValue::BulkString(b"b".to_vec()), HostValue::Nil),
+                ])
+            );
+
+            let val = ctx_scope.eval("[() => 1]").unwrap();
+            assert!(HostValue::from_value(ctx_scope, &val).is_err());
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_stream() {
+        use crate::v8::host_value::HostValue;
+        use crate::v8::v8_stream::{V8StreamRecvError, V8StreamSendError};
+        testing::with_context(|isolate, ctx_scope| {
+            let (reader, sender) = ctx_scope.new_stream_reader(1);
+            sender.try_send(HostValue::Int(1)).unwrap();
+            assert_eq!(
+                sender.try_send(HostValue::Int(2)),
+                Err(V8StreamSendError::Full(HostValue::Int(2)))
+            );
+            ctx_scope
+                .eval_with(
+                    &[("reader", &reader)],
+                    "globalThis.items = []; (async () => { \
+                     for (let r = await reader.next(); !r.done; r = await reader.next()) { items.push(r.value); } \
+                     globalThis.done = true; })()",
+                )
+                .unwrap();
+            isolate.perform_microtask_checkpoint();
+            // JS waits for the next item, it is delivered by an isolate task.
+            sender.try_send(HostValue::Int(2)).unwrap();
+            drop(sender);
+            while !ctx_scope
+                .eval("globalThis.done === true")
+                .unwrap()
+                .get_boolean()
+            {
+                isolate.pump_message_loop(false);
+                isolate.perform_microtask_checkpoint();
+            }
+            let res = ctx_scope.eval("items.join()").unwrap();
+            assert_eq!(res.to_utf8(isolate).unwrap().as_str(), "1,2");
+
+            let (writer, receiver) = ctx_scope.new_stream_writer(1);
+            ctx_scope
+                .eval_with(
+                    &[("writer", &writer)],
+                    "writer.write('a'); writer.write('b').then(() => globalThis.written = true); writer.close()",
+                )
+                .unwrap();
+            assert_eq!(
+                receiver.try_recv(),
+                Ok(HostValue::BulkString(b"a".to_vec()))
+            );
+            assert_eq!(
+                receiver.try_recv(),
+                Ok(HostValue::BulkString(b"b".to_vec()))
+            );
+            assert_eq!(receiver.try_recv(), Err(V8StreamRecvError::Closed));
+            while !ctx_scope
+                .eval("globalThis.written === true")
+                .unwrap()
+                .get_boolean()
+            {
+                isolate.pump_message_loop(false);
+                isolate.perform_microtask_checkpoint();
+            }
+        });
+    }
+
+    #[test]
+    fn test_async_iterator() {
+        use std::cell::{Cell, RefCell};
+        use std::rc::Rc;
+        testing::with_context(|isolate, ctx_scope| {
+            let count = Cell::new(0);
+            let iterator = ctx_scope.new_async_iterator(move |isolate, ctx_scope| {
+                count.set(count.get() + 1);
+                let resolver = ctx_scope.new_resolver();
+                let res =
+                    ctx_scope.new_iterator_result(&isolate.new_long(count.get()), count.get() > 3);
+                resolver.resolve(ctx_scope, &res);
+                Some(resolver.get_promise().to_value())
+            });
+            let res = ctx_scope
+                .eval_with(
+                    &[("iterator", &iterator)],
+                    "(async () => { let items = []; for await (const i of iterator) { items.push(i); } return items.join(); })()",
+                )
+                .unwrap();
+            isolate.perform_microtask_checkpoint();
+            let res = res.as_promise().result_checked().ok().unwrap();
+            assert_eq!(res.to_utf8(isolate).unwrap().as_str(), "1,2,3");
+
+            let items = Rc::new(RefCell::new(Vec::new()));
+            let on_item_items = Rc::clone(&items);
+            let generator = ctx_scope
+                .eval("(async function* () { yield 1; yield 2; yield 3; })()")
+                .unwrap();
+            let promise = ctx_scope
+                .for_await(&generator, move |_isolate, _ctx_scope, val| {
+                    on_item_items.borrow_mut().push(val.get_long());
+                    on_item_items.borrow().len() < 2
+                })
+                .unwrap();
+            isolate.perform_microtask_checkpoint();
+            assert!(promise.result_checked().is_ok());
+            assert_eq!(*items.borrow(), vec![1, 2]);
+
+            // A raising `done` getter rejects the iteration and leaves no exception pending.
+            let iterable = ctx_scope
+                .eval(
+                    "({ [Symbol.asyncIterator]() { return { next() { \
+                        return { get done() { throw 'bad done'; } }; } }; } })",
+                )
+                .unwrap();
+            let promise = ctx_scope
+                .for_await(&iterable, |_isolate, _ctx_scope, _val| true)
+                .unwrap();
+            isolate.perform_microtask_checkpoint();
+            match promise.result_checked() {
+                Err(v8_promise::V8PromiseResultError::Rejected(e)) => {
+                    assert_eq!(e.to_utf8(isolate).unwrap().as_str(), "bad done");
+                }
+                _ => panic!("The iteration was not rejected"),
+            }
+            testing::assert_eval(ctx_scope, "1 + 1", "2");
+        });
+    }
+
+    #[test]
+    fn test_external_data_stats() {
+        use std::any::TypeId;
+
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        assert!(isolate.get_external_data_stats().is_empty());
+        {
+            let _h_scope = isolate.new_handlers_scope();
+            let first =
+                v8_value::V8LocalValue::new_external_data_with_size(&isolate, vec![0u8; 100], 124);
+            let _second =
+                v8_value::V8LocalValue::new_external_data_with_size(&isolate, vec![0u8; 10], 34);
+            let _third = v8_value::V8LocalValue::new_external_data(&isolate, 5u64);
+            let stats = isolate.get_external_data_stats();
+            assert_eq!(stats.len(), 2);
+            assert_eq!(
+                stats[&TypeId::of::<Vec<u8>>()],
+                isolate::V8ExternalDataStats {
+                    type_name: std::any::type_name::<Vec<u8>>(),
+                    count: 2,
+                    bytes: 158
+                }
+            );
+            assert_eq!(
+                stats[&TypeId::of::<u64>()],
+                isolate::V8ExternalDataStats {
+                    type_name: "u64",
+                    count: 1,
+                    bytes: 8
+                }
+            );
+
+            let data = first.get_external_data().unwrap();
+            assert_eq!(data.downcast_ref::<Vec<u8>>().unwrap().len(), 100);
+            assert!(isolate.new_long(1).get_external_data().is_none());
+        }
+
+        // The values are no longer referenced, a full garbage collection frees them.
+        isolate.low_memory_notification();
+        while isolate.pump_message_loop(false) {}
+        assert!(isolate.get_external_data_stats().is_empty());
+    }
+
+    #[test]
+    fn test_scopes_drop_order() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        {
+            // a nested handlers scope can be dropped while an outer context scope is alive
+            let _h_scope = isolate.new_handlers_scope();
+            let _ctx_scope = ctx.enter();
+        }
+        assert_eq!(ctx_scope.eval("1 + 1").unwrap().get_long(), 2);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Handlers scope was dropped while a nested handlers scope is alive")]
+    fn test_handlers_scope_drop_order() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let h_scope = isolate.new_handlers_scope();
+        let _nested_h_scope = isolate.new_handlers_scope();
+        drop(h_scope);
+    }
+
+    #[test]
+    fn test_handlers_scopes_of_different_isolates() {
+        initialize();
+        let isolate1 = isolate::V8Isolate::new();
+        let isolate2 = isolate::V8Isolate::new();
+        let h_scope1 = isolate1.new_handlers_scope();
+        let _h_scope2 = isolate2.new_handlers_scope();
+        // the handlers scopes belong to different isolates so they are not nested
+        drop(h_scope1);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(
+        expected = "Handlers scope was dropped while a local created under it is alive (outstanding locals: V8LocalValue: 1)"
+    )]
+    fn test_handlers_scope_local_drop_order() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _local = {
+            let _h_scope = isolate.new_handlers_scope();
+            isolate.new_long(1)
+        };
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Context scope was dropped while a local created under it is alive")]
+    fn test_context_scope_local_drop_order() {
+        initialize();
+        let isolate = isolate::V8Isolate::new();
+        let _h_scope = isolate.new_handlers_scope();
+        let i_scope = isolate.enter();
+        let ctx = i_scope.new_context(None);
+        let _local = {
+            let ctx_scope = ctx.enter();
+            ctx_scope.eval("1").unwrap()
+        };
+    }
+
     fn test_value_is_functions<
         F: Fn(
             &v8_native_function_template::V8LocalNativeFunctionArgs,
@@ -0,0 +1,63 @@
+use crate::v8::isolate::V8Isolate;
+
+use std::time::Duration;
+
+/// The kind of a garbage collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V8GcKind {
+    Scavenge,
+    MinorMarkCompact,
+    MarkSweepCompact,
+    IncrementalMarking,
+    ProcessWeakCallbacks,
+    Other,
+}
+
+impl V8GcKind {
+    pub(crate) const fn from_gc_type(gc_type: i32) -> Self {
+        match gc_type {
+            1 => Self::Scavenge,
+            2 => Self::MinorMarkCompact,
+            4 => Self::MarkSweepCompact,
+            8 => Self::IncrementalMarking,
+            16 => Self::ProcessWeakCallbacks,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A diagnostics event reported to the `V8Diagnostics` registered on an isolate.
+#[derive(Debug)]
+pub enum V8DiagnosticsEvent<'a> {
+    /// Compilation of a script, module or function has started.
+    /// The name is only known for modules and functions.
+    CompileStarted {
+        name: Option<&'a str>,
+    },
+    CompileFinished {
+        name: Option<&'a str>,
+        duration: Duration,
+        success: bool,
+    },
+    GcStarted {
+        kind: V8GcKind,
+    },
+    GcFinished {
+        kind: V8GcKind,
+        duration: Duration,
+    },
+    /// An exception which was not caught by the JS code nor by a try catch object.
+    UncaughtException {
+        message: &'a str,
+        script_name: Option<&'a str>,
+        line_number: Option<usize>,
+    },
+}
+
+/// A receiver of structured diagnostics events, can be registered on an isolate
+/// using `V8Isolate::set_diagnostics`. The GC events are reported while the
+/// garbage collection is in progress, so it is not allowed to use any of the
+/// isolate APIs when handling them.
+pub trait V8Diagnostics: Send + Sync {
+    fn on_event(&self, isolate: &V8Isolate, event: &V8DiagnosticsEvent);
+}
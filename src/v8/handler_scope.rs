@@ -1,24 +1,330 @@
-use crate::v8_c_raw::bindings::{v8_FreeHandlersScope, v8_NewHandlersScope, v8_handlers_scope};
+use crate::v8_c_raw::bindings::{
+    v8_EscapableHandlersScopeEscape, v8_FreeEscapableHandlersScope, v8_FreeHandlersScope,
+    v8_LiveLocalsOfKind, v8_LocalKind, v8_LocalKind_v8_LocalKind_Count,
+    v8_NewEscapableHandlersScope, v8_NewHandlersScope, v8_NumberOfHandles,
+    v8_escapable_handlers_scope, v8_handlers_scope,
+};
 
 use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_value::V8LocalValue;
+
+pub(crate) use scope_order::{
+    check as check_scope_order, context_scope_entered, context_scope_exited,
+};
+
+/// The type of the locals counted by each `v8_LocalKind`, in the order of the kinds.
+const LOCAL_TYPES: [&str; v8_LocalKind_v8_LocalKind_Count as usize] = [
+    "V8LocalString",
+    "V8LocalScript",
+    "V8LocalModule",
+    "V8LocalValue",
+    "V8LocalNativeFunctionTemplate",
+    "V8LocalNativeFunction",
+    "V8LocalObjectTemplate",
+    "V8ContextScope",
+    "V8LocalPromise",
+    "V8LocalResolver",
+    "V8LocalObject",
+    "V8LocalSet",
+    "V8LocalArray",
+    "V8LocalArrayBuffer",
+    "V8LocalSharedArrayBuffer",
+];
+
+/// The number of locals of each type which are alive on the current thread.
+#[derive(Default)]
+pub(crate) struct LiveLocals([usize; LOCAL_TYPES.len()]);
+
+impl LiveLocals {
+    pub(crate) fn current() -> LiveLocals {
+        LiveLocals(std::array::from_fn(|kind| unsafe {
+            v8_LiveLocalsOfKind(kind as v8_LocalKind)
+        }))
+    }
+
+    /// Return the types which have more live locals than on `earlier`, with the number
+    /// of additional locals of each type.
+    pub(crate) fn outstanding_since(&self, earlier: &LiveLocals) -> Vec<(&'static str, usize)> {
+        LOCAL_TYPES
+            .iter()
+            .zip(self.0.iter().zip(earlier.0.iter()))
+            .filter(|(_, (now, before))| now > before)
+            .map(|(local_type, (now, before))| (*local_type, now - before))
+            .collect()
+    }
+}
+
+/// Debug builds track, per isolate, the handlers scopes which are open and the context
+/// scopes which are entered, and check that a scope is not dropped while something that
+/// was created under it is still alive:
+/// * A handlers scope must outlive the handlers scopes and context scopes opened under
+///   it, a context scope holds a local handle of its context so exiting the context
+///   after its handlers scope is gone touches a freed handle.
+/// * A handlers scope or a context scope must outlive the locals created under it. The
+///   C API counts the local wrappers which are alive on the current thread (locals are
+///   not `Send`), so a local created under the scope which is still alive when the scope
+///   is dropped is detected, a local of the same type created before the scope and
+///   dropped under it can hide it. The panic message lists the types of the outstanding
+///   locals.
+///
+/// The scope is released before the check panics. Release builds do not track anything.
+#[cfg(debug_assertions)]
+mod scope_order {
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    use super::LiveLocals;
+    use crate::v8::isolate::IsolateId;
+    use crate::v8::v8_context_scope::V8ContextScope;
+
+    struct EnteredContextScope {
+        ctx_ref: usize,
+        /// The innermost handlers scope which was open when the context scope was entered.
+        handlers_scope: Option<u64>,
+        live_locals: LiveLocals,
+    }
+
+    #[derive(Default)]
+    struct ScopeOrder {
+        /// Ids of the open handlers scopes, the innermost is last. Ids are increasing so
+        /// a handlers scope opened under another one has a greater id.
+        handlers_scopes: Vec<u64>,
+        /// The entered context scopes, the innermost is last.
+        context_scopes: Vec<EnteredContextScope>,
+    }
+
+    impl ScopeOrder {
+        fn is_empty(&self) -> bool {
+            self.handlers_scopes.is_empty() && self.context_scopes.is_empty()
+        }
+    }
+
+    static SCOPE_ORDER: Mutex<BTreeMap<IsolateId, ScopeOrder>> = Mutex::new(BTreeMap::new());
+    static NEXT_HANDLERS_SCOPE_ID: AtomicU64 = AtomicU64::new(1);
+
+    /// Return the given reason followed by the types of the outstanding locals, if any
+    /// local was created since `earlier` and is still alive.
+    fn check_locals(earlier: &LiveLocals, reason: &str) -> Option<String> {
+        let outstanding = LiveLocals::current().outstanding_since(earlier);
+        if outstanding.is_empty() {
+            return None;
+        }
+        let outstanding = outstanding
+            .iter()
+            .map(|(local_type, count)| format!("{local_type}: {count}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Some(format!("{reason} (outstanding locals: {outstanding})"))
+    }
+
+    pub(crate) struct HandlersScopeMark {
+        isolate_id: IsolateId,
+        id: u64,
+        live_locals: LiveLocals,
+    }
+
+    pub(crate) fn handlers_scope_opened(isolate_id: IsolateId) -> HandlersScopeMark {
+        let id = NEXT_HANDLERS_SCOPE_ID.fetch_add(1, Ordering::Relaxed);
+        SCOPE_ORDER
+            .lock()
+            .unwrap()
+            .entry(isolate_id)
+            .or_default()
+            .handlers_scopes
+            .push(id);
+        HandlersScopeMark {
+            isolate_id,
+            id,
+            live_locals: LiveLocals::current(),
+        }
+    }
+
+    /// Forget the handlers scope and return the reason it should not have been dropped
+    /// yet, if any. The locals are not checked if `check_locals` is false.
+    pub(crate) fn handlers_scope_closed(
+        mark: &HandlersScopeMark,
+        check_locals: bool,
+    ) -> Option<String> {
+        let mut scope_order = SCOPE_ORDER.lock().unwrap();
+        let order = scope_order.get_mut(&mark.isolate_id)?;
+        let position = order.handlers_scopes.iter().position(|id| *id == mark.id)?;
+        order.handlers_scopes.remove(position);
+        let nested_scope_alive = position < order.handlers_scopes.len();
+        let context_scope_alive = order
+            .context_scopes
+            .iter()
+            .any(|entered| entered.handlers_scope.is_some_and(|id| id >= mark.id));
+        if order.is_empty() {
+            scope_order.remove(&mark.isolate_id);
+        }
+        drop(scope_order);
+        if nested_scope_alive {
+            Some("Handlers scope was dropped while a nested handlers scope is alive".to_owned())
+        } else if context_scope_alive {
+            Some(
+                "Handlers scope was dropped while a context scope entered under it is alive"
+                    .to_owned(),
+            )
+        } else if check_locals {
+            self::check_locals(
+                &mark.live_locals,
+                "Handlers scope was dropped while a local created under it is alive",
+            )
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn context_scope_entered(ctx_scope: &V8ContextScope) {
+        let isolate_id = ctx_scope.get_isolate().get_id();
+        let mut scope_order = SCOPE_ORDER.lock().unwrap();
+        let order = scope_order.entry(isolate_id).or_default();
+        let handlers_scope = order.handlers_scopes.last().copied();
+        order.context_scopes.push(EnteredContextScope {
+            ctx_ref: ctx_scope.inner_ctx_ref as usize,
+            handlers_scope,
+            live_locals: LiveLocals::current(),
+        });
+    }
+
+    /// Forget the context scope and return the reason it should not have been exited
+    /// yet, if any.
+    pub(crate) fn context_scope_exited(ctx_scope: &V8ContextScope) -> Option<String> {
+        let isolate_id = ctx_scope.get_isolate().get_id();
+        let mut scope_order = SCOPE_ORDER.lock().unwrap();
+        let order = scope_order.get_mut(&isolate_id)?;
+        let position = order
+            .context_scopes
+            .iter()
+            .rposition(|entered| entered.ctx_ref == ctx_scope.inner_ctx_ref as usize)?;
+        let entered = order.context_scopes.remove(position);
+        if order.is_empty() {
+            scope_order.remove(&isolate_id);
+        }
+        drop(scope_order);
+        check_locals(
+            &entered.live_locals,
+            "Context scope was dropped while a local created under it is alive",
+        )
+    }
+
+    /// Panic with the given reason, unless the thread is already panicking. The scopes
+    /// of the panicking frames are dropped anyway so they are not checked.
+    pub(crate) fn check(error: Option<String>) {
+        if let Some(error) = error {
+            if !std::thread::panicking() {
+                panic!("{}", error);
+            }
+        }
+    }
+}
+
+#[cfg(not(debug_assertions))]
+mod scope_order {
+    use crate::v8::isolate::IsolateId;
+    use crate::v8::v8_context_scope::V8ContextScope;
+
+    pub(crate) struct HandlersScopeMark;
+
+    pub(crate) const fn handlers_scope_opened(_isolate_id: IsolateId) -> HandlersScopeMark {
+        HandlersScopeMark
+    }
+
+    pub(crate) const fn handlers_scope_closed(
+        _mark: &HandlersScopeMark,
+        _check_locals: bool,
+    ) -> Option<String> {
+        None
+    }
+
+    pub(crate) const fn context_scope_entered(_ctx_scope: &V8ContextScope) {}
+
+    pub(crate) const fn context_scope_exited(_ctx_scope: &V8ContextScope) -> Option<String> {
+        None
+    }
+
+    pub(crate) fn check(_error: Option<String>) {}
+}
 
 pub struct V8HandlersScope<'a> {
-    _isolate: &'a V8Isolate,
+    isolate: &'a V8Isolate,
     inner_handlers_scope: *mut v8_handlers_scope,
+    handles_on_creation: usize,
+    order: scope_order::HandlersScopeMark,
 }
 
 impl<'a> V8HandlersScope<'a> {
     pub(crate) fn new(isolate: &'a V8Isolate) -> V8HandlersScope<'a> {
+        let handles_on_creation = unsafe { v8_NumberOfHandles(isolate.inner_isolate) };
         let inner_handlers_scope = unsafe { v8_NewHandlersScope(isolate.inner_isolate) };
         V8HandlersScope {
-            _isolate: isolate,
+            isolate,
             inner_handlers_scope,
+            handles_on_creation,
+            order: scope_order::handlers_scope_opened(isolate.get_id()),
         }
     }
+
+    /// Return the number of local handlers that was allocated since the handlers
+    /// scope was created and are still alive (including handlers allocated on
+    /// nested handlers scopes that were not yet freed). All of those handlers will
+    /// be freed when the handlers scope is destroyed. A number that keeps growing
+    /// inside a long running native function usually indicates that a nested handlers
+    /// scope is missing.
+    #[must_use]
+    pub fn handles_count(&self) -> usize {
+        let curr = unsafe { v8_NumberOfHandles(self.isolate.inner_isolate) };
+        curr.saturating_sub(self.handles_on_creation)
+    }
 }
 
 impl<'a> Drop for V8HandlersScope<'a> {
     fn drop(&mut self) {
+        let error = scope_order::handlers_scope_closed(&self.order, true);
         unsafe { v8_FreeHandlersScope(self.inner_handlers_scope) }
+        scope_order::check(error);
+    }
+}
+
+/// A handlers scope that allows a single local value to escape
+/// into the enclosing handlers scope.
+pub struct V8EscapableHandlersScope<'a> {
+    _isolate: &'a V8Isolate,
+    inner_handlers_scope: *mut v8_escapable_handlers_scope,
+    order: scope_order::HandlersScopeMark,
+    escaped: bool,
+}
+
+impl<'a> V8EscapableHandlersScope<'a> {
+    pub(crate) fn new(isolate: &'a V8Isolate) -> V8EscapableHandlersScope<'a> {
+        let inner_handlers_scope = unsafe { v8_NewEscapableHandlersScope(isolate.inner_isolate) };
+        V8EscapableHandlersScope {
+            _isolate: isolate,
+            inner_handlers_scope,
+            order: scope_order::handlers_scope_opened(isolate.get_id()),
+            escaped: false,
+        }
+    }
+
+    /// Escape the given value into the enclosing handlers scope and close this
+    /// handlers scope. All the other local values created after this scope was
+    /// created will be freed, only the returned value remains valid. The locals
+    /// created under this scope are still in use when it is closed this way, so
+    /// debug builds do not check them.
+    #[must_use]
+    pub fn escape(mut self, val: &V8LocalValue) -> V8LocalValue {
+        let inner_val =
+            unsafe { v8_EscapableHandlersScopeEscape(self.inner_handlers_scope, val.inner_val) };
+        self.escaped = true;
+        V8LocalValue { inner_val }
+    }
+}
+
+impl<'a> Drop for V8EscapableHandlersScope<'a> {
+    fn drop(&mut self) {
+        let error = scope_order::handlers_scope_closed(&self.order, !self.escaped);
+        unsafe { v8_FreeEscapableHandlersScope(self.inner_handlers_scope) }
+        scope_order::check(error);
     }
 }
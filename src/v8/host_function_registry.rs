@@ -0,0 +1,260 @@
+use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_native_function_template::V8LocalNativeFunctionArgs;
+use crate::v8::v8_object::V8PropertyAttributes;
+use crate::v8::v8_object_template::V8LocalObjectTemplate;
+use crate::v8::v8_value::V8LocalValue;
+
+use std::sync::Arc;
+
+type HostFunction = Arc<
+    dyn Fn(&V8LocalNativeFunctionArgs, &V8Isolate, &V8ContextScope) -> Option<V8LocalValue>
+        + Send
+        + Sync,
+>;
+
+#[derive(Clone)]
+struct HostFunctionEntry {
+    name: String,
+    signature: String,
+    func: HostFunction,
+}
+
+#[derive(Clone)]
+struct HostNamespace {
+    name: String,
+    functions: Vec<HostFunctionEntry>,
+}
+
+fn add_entry(entries: &mut Vec<HostFunctionEntry>, entry: HostFunctionEntry) {
+    match entries.iter_mut().find(|e| e.name == entry.name) {
+        Some(e) => *e = entry,
+        None => entries.push(entry),
+    }
+}
+
+/// A registry of named native functions (optionally grouped into namespaces)
+/// that are registered once and can then be installed on any context.
+/// Registries can be composed using `merge`, so layered APIs (for example core
+/// functions and tenant specific extensions) can be kept in separate registries.
+#[derive(Default, Clone)]
+pub struct HostFunctionRegistry {
+    functions: Vec<HostFunctionEntry>,
+    namespaces: Vec<HostNamespace>,
+}
+
+impl HostFunctionRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a native function that will be installed on the context globals
+    /// with the given name. `signature` is only used for documentation.
+    /// Registering a function with an existing name replaces the previous one.
+    pub fn register_function<
+        T: Fn(&V8LocalNativeFunctionArgs, &V8Isolate, &V8ContextScope) -> Option<V8LocalValue>
+            + Send
+            + Sync
+            + 'static,
+    >(
+        &mut self,
+        name: &str,
+        signature: &str,
+        func: T,
+    ) -> &mut Self {
+        add_entry(
+            &mut self.functions,
+            HostFunctionEntry {
+                name: name.to_string(),
+                signature: signature.to_string(),
+                func: Arc::new(func),
+            },
+        );
+        self
+    }
+
+    /// Same as `register_function` but the function will be installed as a
+    /// property of a global object named `namespace`.
+    pub fn register_namespace_function<
+        T: Fn(&V8LocalNativeFunctionArgs, &V8Isolate, &V8ContextScope) -> Option<V8LocalValue>
+            + Send
+            + Sync
+            + 'static,
+    >(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        signature: &str,
+        func: T,
+    ) -> &mut Self {
+        let index = match self.namespaces.iter().position(|n| n.name == namespace) {
+            Some(index) => index,
+            None => {
+                self.namespaces.push(HostNamespace {
+                    name: namespace.to_string(),
+                    functions: Vec::new(),
+                });
+                self.namespaces.len() - 1
+            }
+        };
+        add_entry(
+            &mut self.namespaces[index].functions,
+            HostFunctionEntry {
+                name: name.to_string(),
+                signature: signature.to_string(),
+                func: Arc::new(func),
+            },
+        );
+        self
+    }
+
+    /// Install all the registered functions and namespaces on the globals
+    /// of the given context. Return true on success.
+    pub fn install(&self, ctx_scope: &V8ContextScope) -> bool {
+        let isolate = ctx_scope.get_isolate();
+        for entry in &self.functions {
+            let func = Arc::clone(&entry.func);
+            if !ctx_scope
+                .set_global_native_function(&entry.name, move |args, isolate, ctx_scope| {
+                    func(args, isolate, ctx_scope)
+                })
+            {
+                return false;
+            }
+        }
+        for namespace in &self.namespaces {
+            let obj = isolate.new_object();
+            for entry in &namespace.functions {
+                let func = Arc::clone(&entry.func);
+                let native_func = ctx_scope.new_native_function(move |args, isolate, ctx_scope| {
+                    func(args, isolate, ctx_scope)
+                });
+                obj.set(
+                    ctx_scope,
+                    &isolate.new_string(&entry.name).to_value(),
+                    &native_func.to_value(),
+                );
+            }
+            if !ctx_scope.set_global_value(
+                &namespace.name,
+                &obj.to_value(),
+                V8PropertyAttributes::NONE,
+            ) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Return the names of the entries of `other` which already exist on this
+    /// registry. Namespace functions are reported as `<namespace>.<name>`, and a
+    /// function and a namespace with the same name also conflict.
+    #[must_use]
+    pub fn conflicts(&self, other: &Self) -> Vec<String> {
+        let mut res = Vec::new();
+        for entry in &other.functions {
+            if self.functions.iter().any(|e| e.name == entry.name)
+                || self.namespaces.iter().any(|n| n.name == entry.name)
+            {
+                res.push(entry.name.clone());
+            }
+        }
+        for namespace in &other.namespaces {
+            if self.functions.iter().any(|e| e.name == namespace.name) {
+                res.push(namespace.name.clone());
+                continue;
+            }
+            let existing = match self.namespaces.iter().find(|n| n.name == namespace.name) {
+                Some(n) => n,
+                None => continue,
+            };
+            for entry in &namespace.functions {
+                if existing.functions.iter().any(|e| e.name == entry.name) {
+                    res.push(format!("{}.{}", namespace.name, entry.name));
+                }
+            }
+        }
+        res
+    }
+
+    /// Add all the entries of `other` to this registry, namespaces with the same
+    /// name are merged. If any of the entries conflicts with an existing one (see
+    /// `conflicts`) nothing is added and the conflicting names are returned.
+    pub fn merge(&mut self, other: &Self) -> Result<&mut Self, Vec<String>> {
+        let conflicts = self.conflicts(other);
+        if !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+        Ok(self.merge_override(other))
+    }
+
+    /// Same as `merge` but conflicting entries of `other` replace the existing ones.
+    /// A function replaces a namespace with the same name and vice versa.
+    pub fn merge_override(&mut self, other: &Self) -> &mut Self {
+        for entry in &other.functions {
+            self.namespaces.retain(|n| n.name != entry.name);
+            add_entry(&mut self.functions, entry.clone());
+        }
+        for namespace in &other.namespaces {
+            self.functions.retain(|e| e.name != namespace.name);
+            match self
+                .namespaces
+                .iter_mut()
+                .find(|n| n.name == namespace.name)
+            {
+                Some(existing) => {
+                    for entry in &namespace.functions {
+                        add_entry(&mut existing.functions, entry.clone());
+                    }
+                }
+                None => self.namespaces.push(namespace.clone()),
+            }
+        }
+        self
+    }
+
+    /// Create an object template with all the registered functions and namespaces,
+    /// which can be used as the globals of new contexts.
+    #[must_use]
+    pub fn to_object_template(&self, isolate: &V8Isolate) -> V8LocalObjectTemplate {
+        let mut globals = isolate.new_object_template();
+        for entry in &self.functions {
+            let func = Arc::clone(&entry.func);
+            globals.add_native_function(isolate, &entry.name, move |args, isolate, ctx_scope| {
+                func(args, isolate, ctx_scope)
+            });
+        }
+        for namespace in &self.namespaces {
+            let mut obj = isolate.new_object_template();
+            for entry in &namespace.functions {
+                let func = Arc::clone(&entry.func);
+                obj.add_native_function(isolate, &entry.name, move |args, isolate, ctx_scope| {
+                    func(args, isolate, ctx_scope)
+                });
+            }
+            globals.add_object(isolate, &namespace.name, &obj);
+        }
+        globals
+    }
+
+    /// Return a description of all the registered functions, one function per
+    /// line in the form `<name>: <signature>` (namespace functions are
+    /// prefixed with `<namespace>.`).
+    #[must_use]
+    pub fn document(&self) -> String {
+        let mut res = String::new();
+        for entry in &self.functions {
+            res.push_str(&format!("{}: {}\n", entry.name, entry.signature));
+        }
+        for namespace in &self.namespaces {
+            for entry in &namespace.functions {
+                res.push_str(&format!(
+                    "{}.{}: {}\n",
+                    namespace.name, entry.name, entry.signature
+                ));
+            }
+        }
+        res
+    }
+}
@@ -0,0 +1,181 @@
+use crate::v8_c_raw::bindings::{v8_MapSet, v8_NewMap};
+
+use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_error::V8ErrorKind;
+use crate::v8::v8_iterable::V8Iterable;
+use crate::v8::v8_value::V8LocalValue;
+
+/// The maximum nesting of the values that are converted from JS, protects
+/// against cyclic JS values.
+const MAX_DEPTH: usize = 100;
+
+/// A protocol neutral value that embedders can map their host values onto, for
+/// example the Redis replies, and convert to and from JS values.
+///
+/// Converting to JS: `Nil` is `null`, `Int` is a `BigInt`, `Double` is a number,
+/// `BulkString` is a string if it is valid UTF-8 and an `ArrayBuffer` otherwise,
+/// `Array` is an array, `Map` is a `Map`, `Set` is a `Set` and `Error` is an `Error`.
+///
+/// Converting from JS is the other way around, with the following additions:
+/// integers that fit in 32 bits are `Int` even if they are JS numbers, booleans
+/// are `Int` 1 or 0, `undefined` is `Nil` and plain objects are a `Map` with
+/// `BulkString` keys. Other values (functions, promises, ...) can not be converted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostValue {
+    Nil,
+    Int(i64),
+    Double(f64),
+    BulkString(Vec<u8>),
+    Array(Vec<HostValue>),
+    Map(Vec<(HostValue, HostValue)>),
+    Set(Vec<HostValue>),
+    Error(String),
+}
+
+impl HostValue {
+    /// Convert the value into a JS value. Return None if adding an entry
+    /// to a `Map` raised an exception, the exception is left pending.
+    #[must_use]
+    pub fn to_value(&self, ctx_scope: &V8ContextScope) -> Option<V8LocalValue> {
+        self.to_value_with_isolate(&ctx_scope.get_isolate(), ctx_scope)
+    }
+
+    fn to_value_with_isolate(
+        &self,
+        isolate: &V8Isolate,
+        ctx_scope: &V8ContextScope,
+    ) -> Option<V8LocalValue> {
+        Some(match self {
+            Self::Nil => isolate.new_null(),
+            Self::Int(n) => isolate.new_long(*n),
+            Self::Double(n) => isolate.new_double(*n),
+            Self::BulkString(bytes) => match std::str::from_utf8(bytes) {
+                Ok(s) => isolate.new_string(s).to_value(),
+                Err(_) => isolate.new_array_buffer(bytes).to_value(),
+            },
+            Self::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| item.to_value_with_isolate(isolate, ctx_scope))
+                    .collect::<Option<Vec<_>>>()?;
+                let items = items.iter().collect::<Vec<_>>();
+                isolate.new_array(&items).to_value()
+            }
+            Self::Map(entries) => {
+                let map = V8LocalValue {
+                    inner_val: unsafe { v8_NewMap(isolate.inner_isolate) },
+                };
+                for (key, val) in entries {
+                    let key = key.to_value_with_isolate(isolate, ctx_scope)?;
+                    let val = val.to_value_with_isolate(isolate, ctx_scope)?;
+                    let res = unsafe {
+                        v8_MapSet(
+                            ctx_scope.inner_ctx_ref,
+                            map.inner_val,
+                            key.inner_val,
+                            val.inner_val,
+                        )
+                    };
+                    if res == 0 {
+                        return None;
+                    }
+                }
+                map
+            }
+            Self::Set(items) => {
+                let set = isolate.new_set();
+                for item in items {
+                    set.add(ctx_scope, &item.to_value_with_isolate(isolate, ctx_scope)?);
+                }
+                set.to_value()
+            }
+            Self::Error(message) => ctx_scope.new_error(&V8ErrorKind::Error, message),
+        })
+    }
+
+    /// Convert the given JS value, fails if the value or any of its nested
+    /// values can not be converted.
+    pub fn from_value(ctx_scope: &V8ContextScope, val: &V8LocalValue) -> Result<Self, String> {
+        Self::from_value_with_depth(&ctx_scope.get_isolate(), ctx_scope, val, 0)
+    }
+
+    fn from_value_with_depth(
+        isolate: &V8Isolate,
+        ctx_scope: &V8ContextScope,
+        val: &V8LocalValue,
+        depth: usize,
+    ) -> Result<Self, String> {
+        if depth > MAX_DEPTH {
+            return Err("Value is nested too deeply".to_string());
+        }
+        let convert =
+            |val: &V8LocalValue| Self::from_value_with_depth(isolate, ctx_scope, val, depth + 1);
+        let utf8 = |val: &V8LocalValue| {
+            val.to_utf8(isolate)
+                .map(|s| s.as_str().to_string())
+                .ok_or_else(|| "Failed converting value to string".to_string())
+        };
+        let res = if val.is_null() || val.is_undefined() {
+            Self::Nil
+        } else if val.is_boolean() {
+            Self::Int(i64::from(val.get_boolean()))
+        } else if val.is_long() {
+            Self::Int(val.get_long())
+        } else if val.is_number() {
+            Self::Double(val.get_number())
+        } else if val.is_string() {
+            Self::BulkString(utf8(val)?.into_bytes())
+        } else if val.is_array_buffer() {
+            Self::BulkString(val.as_array_buffer().data().to_vec())
+        } else if val.is_array() {
+            let arr = val.as_array();
+            let items = (0..arr.len())
+                .map(|i| convert(&arr.get(ctx_scope, i)))
+                .collect::<Result<_, _>>()?;
+            Self::Array(items)
+        } else if val.is_set() {
+            let items = val
+                .values(ctx_scope)
+                .ok_or_else(|| "Failed iterating the set".to_string())?
+                .map(|item| convert(&item))
+                .collect::<Result<_, _>>()?;
+            Self::Set(items)
+        } else if val.is_map() {
+            let entries = val
+                .map_entries(ctx_scope)
+                .ok_or_else(|| "Failed iterating the map".to_string())?
+                .map(|(key, val)| Ok((convert(&key)?, convert(&val)?)))
+                .collect::<Result<_, String>>()?;
+            Self::Map(entries)
+        } else if val.is_native_error() {
+            let message = val
+                .as_object()
+                .get(ctx_scope, &isolate.new_string("message").to_value())
+                .filter(V8LocalValue::is_string)
+                .map_or_else(|| Ok(String::new()), |message| utf8(&message))?;
+            Self::Error(message)
+        } else if val.is_object() && !val.is_function() && !val.is_promise() {
+            let obj = val.as_object();
+            let names = obj.get_property_names(ctx_scope);
+            let entries = (0..names.len())
+                .map(|i| {
+                    let key = names.get(ctx_scope, i);
+                    let name = utf8(&key)?;
+                    let val = obj
+                        .get(ctx_scope, &key)
+                        .ok_or_else(|| format!("property '{}': failed getting value", name))?;
+                    let val = convert(&val).map_err(|e| format!("property '{}': {}", name, e))?;
+                    Ok((Self::BulkString(name.into_bytes()), val))
+                })
+                .collect::<Result<_, String>>()?;
+            Self::Map(entries)
+        } else {
+            return Err(format!(
+                "Can not convert {} to a host value",
+                val.describe(ctx_scope)
+            ));
+        };
+        Ok(res)
+    }
+}
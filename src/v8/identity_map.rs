@@ -0,0 +1,98 @@
+use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_object::V8LocalObject;
+use crate::v8::v8_value::V8PersistValue;
+
+use std::collections::HashMap;
+
+/// A map from JS objects to native values. Objects are matched by identity
+/// (the same object and not an equal object), which allows associating
+/// native data with a JS object across calls without adding properties to it.
+///
+/// The map holds a persisted handle to each of its keys, so an object is
+/// kept alive at least until it is removed from the map.
+/// All the methods must be called inside a handlers scope of the isolate
+/// that created the objects.
+pub struct IdentityMap<V> {
+    buckets: HashMap<i64, Vec<(V8PersistValue, V)>>,
+    len: usize,
+}
+
+impl<V> Default for IdentityMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> IdentityMap<V> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            buckets: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    fn position(
+        bucket: &[(V8PersistValue, V)],
+        isolate: &V8Isolate,
+        obj: &V8LocalObject,
+    ) -> Option<usize> {
+        let obj_val = obj.to_value();
+        bucket
+            .iter()
+            .position(|(key, _)| key.as_local(isolate).strict_equals(&obj_val))
+    }
+
+    /// Associate the value with the given object. Return the previous
+    /// value associated with the object, if any.
+    pub fn insert(&mut self, isolate: &V8Isolate, obj: &V8LocalObject, val: V) -> Option<V> {
+        let bucket = self.buckets.entry(obj.get_identity_hash()).or_default();
+        match Self::position(bucket, isolate, obj) {
+            Some(i) => Some(std::mem::replace(&mut bucket[i].1, val)),
+            None => {
+                bucket.push((obj.to_value().persist(isolate), val));
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    /// Return the value associated with the given object.
+    #[must_use]
+    pub fn get(&self, isolate: &V8Isolate, obj: &V8LocalObject) -> Option<&V> {
+        let bucket = self.buckets.get(&obj.get_identity_hash())?;
+        Self::position(bucket, isolate, obj).map(|i| &bucket[i].1)
+    }
+
+    /// Same as `get` but returns a mutable reference.
+    #[must_use]
+    pub fn get_mut(&mut self, isolate: &V8Isolate, obj: &V8LocalObject) -> Option<&mut V> {
+        let bucket = self.buckets.get_mut(&obj.get_identity_hash())?;
+        Self::position(bucket, isolate, obj).map(move |i| &mut bucket[i].1)
+    }
+
+    /// Remove the given object from the map and return its associated value.
+    pub fn remove(&mut self, isolate: &V8Isolate, obj: &V8LocalObject) -> Option<V> {
+        let hash = obj.get_identity_hash();
+        let bucket = self.buckets.get_mut(&hash)?;
+        let i = Self::position(bucket, isolate, obj)?;
+        let (_, val) = bucket.swap_remove(i);
+        if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+        self.len -= 1;
+        Some(val)
+    }
+
+    /// Return the number of objects in the map.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return true if the map is empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
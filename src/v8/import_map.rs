@@ -0,0 +1,250 @@
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_module::{V8LocalModule, V8WeakModule};
+use crate::v8::v8_string::V8LocalString;
+use crate::v8::v8_value::V8LocalValue;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// The name of the private value, on the context globals, that holds
+/// the import map of the context.
+const IMPORT_MAP_NAME: &str = "v8_rs::import_map";
+
+/// An import map, maps the module specifiers to the names that are given to the
+/// module loader (see `V8LocalModule::initialize`), for example:
+/// `V8ImportMap::new().import("lodash", "/vendor/lodash.js")`.
+///
+/// A mapping whose specifier ends with `/` maps all the specifiers with this prefix,
+/// keeping the rest of the specifier. Scoped mappings only apply to the imports of
+/// modules whose name starts with the scope, and take precedence over the global
+/// mappings. Relative specifiers (`./`, `../` and `/`) are resolved against the name
+/// of the importing module before they are mapped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct V8ImportMap {
+    imports: HashMap<String, String>,
+    scopes: HashMap<String, HashMap<String, String>>,
+}
+
+/// The import map of a context along with the names of the modules that were
+/// compiled on it, used to resolve relative specifiers. Modules are matched by
+/// identity, bucketed by their identity hash, and held by weak handles so the
+/// names of garbage collected modules are dropped.
+struct ContextImportMap {
+    import_map: V8ImportMap,
+    module_names: RefCell<HashMap<i64, Vec<(V8WeakModule, String)>>>,
+}
+
+impl V8ImportMap {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse an import map from its JSON representation,
+    /// `{"imports": {...}, "scopes": {"/scope/": {...}}}`.
+    pub fn from_json(ctx_scope: &V8ContextScope, json: &str) -> Result<Self, String> {
+        let isolate = ctx_scope.get_isolate();
+        let trycatch = isolate.new_try_catch();
+        let val = ctx_scope
+            .new_object_from_json(&isolate.new_string(json))
+            .ok_or_else(|| V8ContextScope::eval_error(&isolate, &trycatch))?;
+        if !val.is_object() {
+            return Err("Import map must be an object".to_string());
+        }
+        let val = val.as_object();
+        let get = |name: &str| {
+            val.get(ctx_scope, &isolate.new_string(name).to_value())
+                .filter(|v| !v.is_undefined())
+        };
+        let mappings = |val: &V8LocalValue| {
+            if !val.is_object() {
+                return Err("Mappings must be an object".to_string());
+            }
+            val.as_object().try_to_map_with(ctx_scope, |target| {
+                if !target.is_string() {
+                    return Err("Value is not a string".to_string());
+                }
+                target
+                    .to_utf8(&isolate)
+                    .map(|t| t.as_str().to_string())
+                    .ok_or_else(|| "Failed converting value to string".to_string())
+            })
+        };
+        let imports = match get("imports") {
+            Some(imports) => mappings(&imports).map_err(|e| format!("imports: {}", e))?,
+            None => HashMap::new(),
+        };
+        let scopes = match get("scopes") {
+            Some(scopes) if scopes.is_object() => scopes
+                .as_object()
+                .try_to_map_with(ctx_scope, mappings)
+                .map_err(|e| format!("scopes: {}", e))?,
+            Some(_) => return Err("scopes: Scopes must be an object".to_string()),
+            None => HashMap::new(),
+        };
+        Ok(Self { imports, scopes })
+    }
+
+    /// Add a global mapping.
+    #[must_use]
+    pub fn import(mut self, specifier: &str, target: &str) -> Self {
+        self.imports
+            .insert(specifier.to_string(), target.to_string());
+        self
+    }
+
+    /// Add a mapping that only applies to the imports of the modules under the given scope.
+    #[must_use]
+    pub fn scoped_import(mut self, scope: &str, specifier: &str, target: &str) -> Self {
+        self.scopes
+            .entry(scope.to_string())
+            .or_default()
+            .insert(specifier.to_string(), target.to_string());
+        self
+    }
+
+    /// Resolve the specifier imported by the module with the given name (None if
+    /// the name of the importing module is not known). Specifiers that are not
+    /// mapped are returned as is, after resolving them if they are relative.
+    #[must_use]
+    pub fn resolve(&self, specifier: &str, referrer: Option<&str>) -> String {
+        let specifier = match referrer {
+            Some(referrer) if is_relative(specifier) => join(referrer, specifier),
+            _ => specifier.to_string(),
+        };
+        let mut scopes = self
+            .scopes
+            .iter()
+            .filter(|(scope, _)| referrer.is_some_and(|r| r.starts_with(scope.as_str())))
+            .collect::<Vec<_>>();
+        scopes.sort_by_key(|(scope, _)| std::cmp::Reverse(scope.len()));
+        scopes
+            .into_iter()
+            .map(|(_, mappings)| mappings)
+            .chain(std::iter::once(&self.imports))
+            .find_map(|mappings| resolve_mapping(mappings, &specifier))
+            .unwrap_or(specifier)
+    }
+}
+
+fn is_relative(specifier: &str) -> bool {
+    specifier.starts_with("./") || specifier.starts_with("../") || specifier.starts_with('/')
+}
+
+/// Resolve the relative specifier against the name of the importing module.
+fn join(referrer: &str, specifier: &str) -> String {
+    // The part of the name that relative specifiers can not escape, `scheme://host`.
+    let root_len = referrer.find("://").map_or(0, |i| {
+        referrer[i + 3..]
+            .find('/')
+            .map_or(referrer.len(), |j| i + 3 + j)
+    });
+    let (root, path) = referrer.split_at(root_len);
+    let mut segments = path.split('/').collect::<Vec<_>>();
+    // Drop the name of the importing module, keep its directory.
+    segments.pop();
+    if specifier.starts_with('/') {
+        segments.clear();
+    }
+    for segment in specifier.split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                if segments.last().is_some_and(|s| !s.is_empty()) {
+                    segments.pop();
+                }
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let path = segments.join("/");
+    if path.starts_with('/') || root.is_empty() {
+        format!("{}{}", root, path)
+    } else {
+        format!("{}/{}", root, path)
+    }
+}
+
+fn resolve_mapping(mappings: &HashMap<String, String>, specifier: &str) -> Option<String> {
+    if let Some(target) = mappings.get(specifier) {
+        return Some(target.clone());
+    }
+    mappings
+        .iter()
+        .filter(|(prefix, _)| prefix.ends_with('/') && specifier.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(prefix, target)| format!("{}{}", target, &specifier[prefix.len()..]))
+}
+
+fn with_context_import_map<R>(
+    ctx_scope: &V8ContextScope,
+    f: impl FnOnce(&ContextImportMap) -> R,
+) -> Option<R> {
+    let import_map = ctx_scope
+        .get_globals()
+        .get_private(ctx_scope, IMPORT_MAP_NAME)?;
+    let import_map = import_map
+        .get_external_data()?
+        .downcast_ref::<ContextImportMap>()?;
+    Some(f(import_map))
+}
+
+impl V8ContextScope {
+    /// Set the import map that is applied to the imports of the modules of the context,
+    /// the module loader gets the mapped names instead of the imported specifiers.
+    /// Replaces the existing import map, if any.
+    pub fn set_import_map(&self, import_map: V8ImportMap) {
+        let isolate = self.get_isolate();
+        let import_map = V8LocalValue::new_external_data(
+            &isolate,
+            ContextImportMap {
+                import_map,
+                module_names: RefCell::new(HashMap::new()),
+            },
+        );
+        self.get_globals()
+            .set_private(self, IMPORT_MAP_NAME, &import_map);
+    }
+
+    /// Record the name of a module compiled on the context, if the context has
+    /// an import map, so the relative imports of the module can be resolved.
+    pub(crate) fn import_map_module_compiled(&self, name: &V8LocalString, module: &V8LocalModule) {
+        let isolate = self.get_isolate();
+        with_context_import_map(self, |import_map| {
+            if let Some(name) = name.to_value().to_utf8(&isolate) {
+                let mut module_names = import_map.module_names.borrow_mut();
+                module_names.retain(|_, bucket| {
+                    bucket.retain(|(module, _)| !module.is_collected());
+                    !bucket.is_empty()
+                });
+                module_names
+                    .entry(module.get_identity_hash())
+                    .or_default()
+                    .push((module.to_weak(&isolate), name.as_str().to_string()));
+            }
+        });
+    }
+
+    /// Apply the context import map, if any, on the specifier imported by the given module.
+    pub(crate) fn import_map_resolve(
+        &self,
+        specifier: &V8LocalString,
+        referrer: &V8LocalModule,
+    ) -> Option<V8LocalString> {
+        let isolate = self.get_isolate();
+        let resolved = with_context_import_map(self, |import_map| {
+            let specifier = specifier.to_value().to_utf8(&isolate)?;
+            let module_names = import_map.module_names.borrow();
+            let referrer_name = module_names
+                .get(&referrer.get_identity_hash())
+                .and_then(|bucket| bucket.iter().find(|(module, _)| module.is(referrer)))
+                .map(|(_, name)| name.as_str());
+            Some(
+                import_map
+                    .import_map
+                    .resolve(specifier.as_str(), referrer_name),
+            )
+        })??;
+        Some(isolate.new_string(&resolved))
+    }
+}
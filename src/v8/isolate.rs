@@ -1,38 +1,312 @@
 // An isolate rust wrapper to v8 isolate.
 
 use crate::v8_c_raw::bindings::{
-    v8_CancelTerminateExecution, v8_FreeIsolate, v8_IdleNotificationDeadline,
-    v8_IsolateRaiseException, v8_IsolateSetFatalErrorHandler, v8_IsolateSetNearOOMHandler,
-    v8_IsolateSetOOMErrorHandler, v8_NewArray, v8_NewArrayBuffer, v8_NewBool, v8_NewIsolate,
+    v8_CancelTerminateExecution, v8_FreeIsolate, v8_GetCurrentCtxRef, v8_IdleNotificationDeadline,
+    v8_IsolateClearKeptObjects, v8_IsolateGetId, v8_IsolateGetNativeFunctionTemplate,
+    v8_IsolateGetObjectTemplate, v8_IsolateInContext, v8_IsolatePerformMicrotaskCheckpoint,
+    v8_IsolatePostTask, v8_IsolatePumpMessageLoop, v8_IsolateRaiseException,
+    v8_IsolateRestoreHeapLimit, v8_IsolateRunIdleTasks, v8_IsolateSetDiagnosticsCallbacks,
+    v8_IsolateSetFatalErrorHandler, v8_IsolateSetImportMetaCallback,
+    v8_IsolateSetNativeFunctionTemplate, v8_IsolateSetNearOOMHandler, v8_IsolateSetOOMErrorHandler,
+    v8_IsolateSetObjectTemplate, v8_IsolateSetPromiseHook, v8_LowMemoryNotification,
+    v8_MemoryPressureLevel_v8_MemoryPressureLevel_Critical,
+    v8_MemoryPressureLevel_v8_MemoryPressureLevel_Moderate,
+    v8_MemoryPressureLevel_v8_MemoryPressureLevel_None, v8_MemoryPressureNotification, v8_NewArray,
+    v8_NewArrayBuffer, v8_NewArrayFromDoubles, v8_NewArrayFromStrings, v8_NewBool,
+    v8_NewEmptyString, v8_NewExternalArrayBuffer, v8_NewExternalString,
+    v8_NewExternalTwoByteString, v8_NewIsolate, v8_NewIsolateWithAllocator,
     v8_NewNativeFunctionTemplate, v8_NewNull, v8_NewObject, v8_NewObjectTemplate, v8_NewSet,
-    v8_NewString, v8_NewTryCatch, v8_NewUnlocker, v8_RequestInterrupt, v8_StringToValue,
-    v8_TerminateCurrExecution, v8_ValueFromDouble, v8_ValueFromLong, v8_isolate, v8_local_value,
+    v8_NewSharedArrayBuffer, v8_NewString, v8_NewStringFromOneByte, v8_NewStringFromTwoByte,
+    v8_NewTryCatch, v8_NewUndefined, v8_NewUnlocker, v8_NumberOfHandles, v8_PromiseHookType,
+    v8_RequestInterrupt, v8_StringToValue, v8_TerminateCurrExecution, v8_TryNewString,
+    v8_ValueFromDouble, v8_ValueFromLong, v8_context_ref, v8_isolate, v8_local_module,
+    v8_local_object, v8_local_promise, v8_local_value,
 };
 
 use std::os::raw::c_void;
 
-use crate::v8::handler_scope::V8HandlersScope;
+use crate::v8::diagnostics::{V8Diagnostics, V8DiagnosticsEvent, V8GcKind};
+use crate::v8::handler_scope::{LiveLocals, V8EscapableHandlersScope, V8HandlersScope};
 use crate::v8::isolate_scope::V8IsolateScope;
+use crate::v8::script_cache::ScriptCacheEntries;
 use crate::v8::try_catch::V8TryCatch;
 use crate::v8::v8_array::V8LocalArray;
-use crate::v8::v8_array_buffer::V8LocalArrayBuffer;
+use crate::v8::v8_array_buffer::{
+    array_buffer_allocate, array_buffer_allocator_free_pd, array_buffer_free,
+    V8ArrayBufferAllocator, V8LocalArrayBuffer,
+};
+use crate::v8::v8_context::V8ExecutionSampler;
 use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_error::V8ErrorKind;
+use crate::v8::v8_module::V8LocalModule;
 use crate::v8::v8_native_function_template::{
     free_pd, native_basic_function, V8LocalNativeFunctionArgs, V8LocalNativeFunctionTemplate,
 };
 use crate::v8::v8_object::V8LocalObject;
 use crate::v8::v8_object_template::V8LocalObjectTemplate;
+use crate::v8::v8_promise::{V8LocalPromise, V8PromiseHookType};
 use crate::v8::v8_set::V8LocalSet;
-use crate::v8::v8_string::V8LocalString;
+use crate::v8::v8_shared_array_buffer::V8LocalSharedArrayBuffer;
+use crate::v8::v8_string::{V8LocalString, V8NewStringOptions, V8StringEncoding};
 use crate::v8::v8_unlocker::V8Unlocker;
 use crate::v8::v8_value::V8LocalValue;
 
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::CStr;
+use std::marker::PhantomData;
 use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
+
+/// A unique identifier of an isolate. Ids are never reused during the process lifetime
+/// so an id can be used to check if the isolate it refers to is still alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IsolateId(u64);
+
+/// Registry information of a live isolate.
+struct LiveIsolate {
+    inner_isolate: usize,
+    name: Option<String>,
+    /// Number of persisted handles that are still alive on the isolate, updated under
+    /// the registry read lock.
+    persisted_handles: AtomicUsize,
+    /// Number of unlockers that currently keep the isolate unlocked.
+    active_unlockers: usize,
+    /// Set when the owner was dropped while an unlocker was active, the isolate is
+    /// leaked and no longer handed out by `V8Isolate::get_by_id`. The entry is removed
+    /// once the last unlocker re-acquires the lock.
+    leaked: bool,
+    diagnostics: Option<Arc<dyn V8Diagnostics>>,
+    /// Start time of the garbage collections currently in progress.
+    gc_starts: Vec<Instant>,
+    /// The script caches created on the isolate, shrunk on memory pressure.
+    script_caches: Vec<Weak<Mutex<ScriptCacheEntries>>>,
+    /// The external data values alive on the isolate, by their Rust type.
+    external_data: Arc<ExternalDataTable>,
+    /// Signaled when a task is posted to the isolate.
+    task_signal: Arc<TaskSignal>,
+}
+
+/// Counts the tasks posted to an isolate using `post_task`, so a thread that
+/// waits for the isolate tasks can block until a new task is posted.
+#[derive(Default)]
+pub(crate) struct TaskSignal {
+    posted: Mutex<u64>,
+    cond: Condvar,
+}
+
+impl TaskSignal {
+    fn notify(&self) {
+        *self.posted.lock().unwrap() += 1;
+        self.cond.notify_all();
+    }
+
+    /// Return the number of tasks posted so far.
+    pub(crate) fn posted(&self) -> u64 {
+        *self.posted.lock().unwrap()
+    }
+
+    /// Block until a task is posted after `posted` returned `seen`, or until the timeout passes.
+    pub(crate) fn wait(&self, seen: u64, timeout: Duration) {
+        let posted = self.posted.lock().unwrap();
+        let _ = self
+            .cond
+            .wait_timeout_while(posted, timeout, |posted| *posted == seen)
+            .unwrap();
+    }
+}
+
+/// All the isolates that were created and not yet freed.
+/// The registry is read locked by the operations which only update the counters of
+/// an isolate, so they do not serialize with the operations of the other isolates.
+static LIVE_ISOLATES: RwLock<BTreeMap<IsolateId, LiveIsolate>> = RwLock::new(BTreeMap::new());
+
+impl IsolateId {
+    /// Return true if the isolate with this id was not yet freed.
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        LIVE_ISOLATES.read().unwrap().contains_key(self)
+    }
+
+    /// Return the name that was given to the isolate using `V8Isolate::set_name`.
+    /// Return None if the isolate has no name or if it was already freed.
+    #[must_use]
+    pub fn get_name(&self) -> Option<String> {
+        LIVE_ISOLATES.read().unwrap().get(self)?.name.clone()
+    }
+
+    /// Count a new persisted handle that was created on the isolate.
+    pub(crate) fn persisted_handle_created(&self) {
+        if let Some(isolate) = LIVE_ISOLATES.read().unwrap().get(self) {
+            isolate.persisted_handles.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Uncount a persisted handle that is about to be freed. Return false if the
+    /// isolate was already freed, in which case the handle was released with it
+    /// and must not be touched.
+    pub(crate) fn persisted_handle_freed(&self) -> bool {
+        match LIVE_ISOLATES.read().unwrap().get(self) {
+            Some(isolate) => {
+                isolate.persisted_handles.fetch_sub(1, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return the statistics of the external data values of the isolate, None if the
+    /// isolate was already freed. The values keep the table to uncount themselves once
+    /// they are freed, without going through the registry.
+    pub(crate) fn external_data_table(&self) -> Option<Arc<ExternalDataTable>> {
+        LIVE_ISOLATES
+            .read()
+            .unwrap()
+            .get(self)
+            .map(|isolate| Arc::clone(&isolate.external_data))
+    }
+
+    /// Post a task to the isolate, see `V8Isolate::post_task`. The registry is kept
+    /// locked while the task is posted so the isolate can not be freed concurrently.
+    /// Return false if the isolate was already freed, in which case the task is dropped.
+    pub fn post_task<T: FnOnce(&V8Isolate) + Send + 'static>(&self, task: T) -> bool {
+        self.with_live_isolate(|isolate, live_isolate| {
+            isolate.post_delayed_task_unsignaled(task, Duration::ZERO);
+            live_isolate.task_signal.notify();
+        })
+        .is_some()
+    }
+
+    /// Request the isolate to run the given callback, see `V8Isolate::request_interrupt`.
+    /// Can be called from any thread. Return false if the isolate was already freed, in
+    /// which case the callback is dropped.
+    pub fn request_interrupt<T: FnOnce(&V8Isolate) + Send + 'static>(&self, callback: T) -> bool {
+        self.with_live_isolate(|isolate, _| unsafe {
+            v8_RequestInterrupt(
+                isolate.inner_isolate,
+                Some(task_callback::<T>),
+                Box::into_raw(Box::new(callback)).cast::<c_void>(),
+            );
+        })
+        .is_some()
+    }
+
+    /// Terminate the current execution of the isolate, see `V8Isolate::terminate_execution`.
+    /// Can be called from any thread. Return false if the isolate was already freed.
+    pub fn terminate_execution(&self) -> bool {
+        self.with_live_isolate(|isolate, _| isolate.terminate_execution())
+            .is_some()
+    }
+
+    /// Run the given closure with a non owning object of the isolate while the registry
+    /// is locked, so the isolate can not be freed before the closure returns. The closure
+    /// must only use methods that can be called from any thread and must not use the registry.
+    fn with_live_isolate<R, F: FnOnce(&V8Isolate, &LiveIsolate) -> R>(&self, f: F) -> Option<R> {
+        let live_isolates = LIVE_ISOLATES.read().unwrap();
+        let live_isolate = live_isolates.get(self)?;
+        let isolate = V8Isolate {
+            inner_isolate: live_isolate.inner_isolate as *mut v8_isolate,
+            no_release: true,
+        };
+        Some(f(&isolate, live_isolate))
+    }
+
+    /// Return the signal of the tasks posted to the isolate, None if the isolate was already freed.
+    pub(crate) fn task_signal(&self) -> Option<Arc<TaskSignal>> {
+        LIVE_ISOLATES
+            .read()
+            .unwrap()
+            .get(self)
+            .map(|isolate| Arc::clone(&isolate.task_signal))
+    }
+
+    /// Count an unlocker that was created on the isolate.
+    pub(crate) fn unlocker_created(&self) {
+        if let Some(isolate) = LIVE_ISOLATES.write().unwrap().get_mut(self) {
+            isolate.active_unlockers += 1;
+        }
+    }
+
+    /// Re-acquire the isolate lock released by an unlocker using the given function, and
+    /// uncount the unlocker once the lock is re-acquired. The unlocker stays counted while
+    /// the lock is re-acquired, so the owner does not free the isolate meanwhile (see
+    /// `V8Isolate::drop`). The registry is not kept locked while waiting for the isolate
+    /// lock since the threads that currently use the isolate might need the registry.
+    /// Return false if the isolate was already freed, in which case the lock is not
+    /// re-acquired.
+    pub(crate) fn unlocker_relock<F: FnOnce()>(&self, relock: F) -> bool {
+        if !LIVE_ISOLATES.read().unwrap().contains_key(self) {
+            return false;
+        }
+        relock();
+        let mut live_isolates = LIVE_ISOLATES.write().unwrap();
+        if let Some(isolate) = live_isolates.get_mut(self) {
+            isolate.active_unlockers -= 1;
+            if isolate.leaked && isolate.active_unlockers == 0 {
+                live_isolates.remove(self);
+            }
+        }
+        true
+    }
+}
+
+/// The statistics of the external data values alive on an isolate, by their Rust type.
+#[derive(Default)]
+pub(crate) struct ExternalDataTable(Mutex<HashMap<TypeId, V8ExternalDataStats>>);
+
+impl ExternalDataTable {
+    /// Count an external data value that was created on the isolate.
+    pub(crate) fn created(&self, type_id: TypeId, type_name: &'static str, bytes: usize) {
+        let mut table = self.0.lock().unwrap();
+        let stats = table.entry(type_id).or_insert(V8ExternalDataStats {
+            type_name,
+            count: 0,
+            bytes: 0,
+        });
+        stats.count += 1;
+        stats.bytes += bytes;
+    }
+
+    /// Uncount an external data value that was garbage collected.
+    pub(crate) fn freed(&self, type_id: TypeId, bytes: usize) {
+        let mut table = self.0.lock().unwrap();
+        if let Some(stats) = table.get_mut(&type_id) {
+            stats.count -= 1;
+            stats.bytes -= bytes;
+            if stats.count == 0 {
+                table.remove(&type_id);
+            }
+        }
+    }
+}
+
+/// Statistics of the external data values of a single Rust type which are alive
+/// on an isolate, see `V8Isolate::get_external_data_stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct V8ExternalDataStats {
+    /// The name of the Rust type, for display only as type names are not unique.
+    pub type_name: &'static str,
+    /// Number of values that were not yet garbage collected.
+    pub count: usize,
+    /// Sum of the retained sizes declared for the values, see
+    /// `V8LocalValue::new_external_data_with_size`.
+    pub bytes: usize,
+}
+
+/// Memory pressure level, used as a hint to the isolate garbage collector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V8MemoryPressureLevel {
+    None,
+    Moderate,
+    Critical,
+}
 
 /// An isolate rust wrapper object.
-/// The isolate will not be automatically freed.
-/// In order to free an isolate, one must call `free_isolate`.
+/// The isolate is freed when the object is dropped or when `dispose` is called.
 pub struct V8Isolate {
     pub(crate) inner_isolate: *mut v8_isolate,
     pub(crate) no_release: bool,
@@ -52,6 +326,148 @@ pub(crate) extern "C" fn interrupt_callback<T: Fn(&V8Isolate)>(
     });
 }
 
+extern "C" fn task_callback<T: FnOnce(&V8Isolate)>(
+    inner_isolate: *mut v8_isolate,
+    data: *mut ::std::os::raw::c_void,
+) {
+    let func = unsafe { Box::from_raw(data.cast::<T>()) };
+    func(&V8Isolate {
+        inner_isolate,
+        no_release: true,
+    });
+}
+
+/// Free a `Box<Vec<T>>` that was given to the C API as private data, used to free
+/// the data of the external strings and of the array buffers created from a `Vec`.
+pub(crate) extern "C" fn free_boxed_vec<T>(pd: *mut c_void) {
+    unsafe { drop(Box::from_raw(pd.cast::<Vec<T>>())) }
+}
+
+extern "C" fn task_free_pd<T: FnOnce(&V8Isolate)>(data: *mut ::std::os::raw::c_void) {
+    unsafe { drop(Box::from_raw(data.cast::<T>())) }
+}
+
+extern "C" fn promise_hook_callback<
+    T: Fn(&V8Isolate, V8PromiseHookType, &V8LocalPromise, &V8LocalValue),
+>(
+    inner_isolate: *mut v8_isolate,
+    hook_type: v8_PromiseHookType,
+    promise: *mut v8_local_promise,
+    parent: *mut v8_local_value,
+    pd: *mut c_void,
+) {
+    let isolate = V8Isolate {
+        inner_isolate,
+        no_release: true,
+    };
+    let promise = V8LocalPromise {
+        inner_promise: promise,
+    };
+    let parent = V8LocalValue { inner_val: parent };
+    let hook = unsafe { &*pd.cast::<T>() };
+    hook(
+        &isolate,
+        V8PromiseHookType::from_inner(hook_type),
+        &promise,
+        &parent,
+    );
+}
+
+extern "C" fn promise_hook_free_pd<T>(pd: *mut c_void) {
+    unsafe { drop(Box::from_raw(pd.cast::<T>())) }
+}
+
+extern "C" fn import_meta_callback<
+    T: Fn(&V8Isolate, &V8ContextScope, &V8LocalModule, &V8LocalObject),
+>(
+    ctx_ref: *mut v8_context_ref,
+    module: *mut v8_local_module,
+    meta: *mut v8_local_object,
+    pd: *mut c_void,
+) {
+    let ctx_scope = V8ContextScope {
+        inner_ctx_ref: ctx_ref,
+        exit_on_drop: false,
+        execution_tracker: None,
+    };
+    let isolate = ctx_scope.get_isolate();
+    let module = V8LocalModule {
+        inner_module: module,
+    };
+    let meta = V8LocalObject { inner_obj: meta };
+    let callback = unsafe { &*pd.cast::<T>() };
+    callback(&isolate, &ctx_scope, &module, &meta);
+}
+
+extern "C" fn import_meta_free_pd<T>(pd: *mut c_void) {
+    unsafe { drop(Box::from_raw(pd.cast::<T>())) }
+}
+
+extern "C" fn diagnostics_gc_callback(
+    inner_isolate: *mut v8_isolate,
+    is_prologue: c_int,
+    gc_type: c_int,
+) {
+    let isolate = V8Isolate {
+        inner_isolate,
+        no_release: true,
+    };
+    let kind = V8GcKind::from_gc_type(gc_type);
+    let (diagnostics, event) = {
+        let mut live_isolates = LIVE_ISOLATES.write().unwrap();
+        let live_isolate = match live_isolates.get_mut(&isolate.get_id()) {
+            Some(i) => i,
+            None => return,
+        };
+        let event = if is_prologue != 0 {
+            live_isolate.gc_starts.push(Instant::now());
+            V8DiagnosticsEvent::GcStarted { kind }
+        } else {
+            let duration = live_isolate
+                .gc_starts
+                .pop()
+                .map_or(Duration::ZERO, |start| start.elapsed());
+            V8DiagnosticsEvent::GcFinished { kind, duration }
+        };
+        (live_isolate.diagnostics.clone(), event)
+    };
+    if let Some(diagnostics) = diagnostics {
+        diagnostics.on_event(&isolate, &event);
+    }
+}
+
+extern "C" fn diagnostics_message_callback(
+    inner_isolate: *mut v8_isolate,
+    message: *const c_char,
+    message_len: usize,
+    script_name: *const c_char,
+    line_number: c_int,
+) {
+    let isolate = V8Isolate {
+        inner_isolate,
+        no_release: true,
+    };
+    let diagnostics = match isolate.get_diagnostics() {
+        Some(d) => d,
+        None => return,
+    };
+    let message = unsafe { std::slice::from_raw_parts(message.cast::<u8>(), message_len) };
+    let message = String::from_utf8_lossy(message);
+    let script_name = if script_name.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(script_name) }.to_string_lossy())
+    };
+    diagnostics.on_event(
+        &isolate,
+        &V8DiagnosticsEvent::UncaughtException {
+            message: &message,
+            script_name: script_name.as_deref(),
+            line_number: usize::try_from(line_number).ok(),
+        },
+    );
+}
+
 impl Default for V8Isolate {
     fn default() -> Self {
         Self::new()
@@ -103,24 +519,239 @@ impl V8Isolate {
     pub fn new_with_limits(
         initial_heap_size_in_bytes: usize,
         maximum_heap_size_in_bytes: usize,
+    ) -> Self {
+        let inner_isolate =
+            unsafe { v8_NewIsolate(initial_heap_size_in_bytes, maximum_heap_size_in_bytes) };
+        Self::from_new_inner_isolate(inner_isolate)
+    }
+
+    /// Same as `new_with_limits` but the memory of the isolate array buffers
+    /// will be allocated using the given allocator.
+    #[must_use]
+    pub fn new_with_allocator<T: V8ArrayBufferAllocator + 'static>(
+        initial_heap_size_in_bytes: usize,
+        maximum_heap_size_in_bytes: usize,
+        allocator: T,
     ) -> Self {
         let inner_isolate = unsafe {
-            let res = v8_NewIsolate(initial_heap_size_in_bytes, maximum_heap_size_in_bytes);
+            v8_NewIsolateWithAllocator(
+                initial_heap_size_in_bytes,
+                maximum_heap_size_in_bytes,
+                Some(array_buffer_allocate::<T>),
+                Some(array_buffer_free::<T>),
+                Box::into_raw(Box::new(allocator)).cast::<c_void>(),
+                Some(array_buffer_allocator_free_pd::<T>),
+            )
+        };
+        Self::from_new_inner_isolate(inner_isolate)
+    }
+
+    fn from_new_inner_isolate(inner_isolate: *mut v8_isolate) -> Self {
+        unsafe {
             if crate::v8::FATAL_ERROR_CALLBACK.is_some() {
-                v8_IsolateSetFatalErrorHandler(res, Some(fatal_error_callback))
+                v8_IsolateSetFatalErrorHandler(inner_isolate, Some(fatal_error_callback))
             }
             if crate::v8::FATAL_ERROR_CALLBACK.is_some() {
-                v8_IsolateSetOOMErrorHandler(res, Some(oom_error_callback))
+                v8_IsolateSetOOMErrorHandler(inner_isolate, Some(oom_error_callback))
             }
-            res
-        };
+        }
 
-        Self {
+        let isolate = Self {
             inner_isolate: inner_isolate,
             no_release: false,
+        };
+        isolate.register_live();
+        isolate
+    }
+
+    /// Add the isolate to the registry of live isolates.
+    pub(crate) fn register_live(&self) {
+        LIVE_ISOLATES.write().unwrap().insert(
+            self.get_id(),
+            LiveIsolate {
+                inner_isolate: self.inner_isolate as usize,
+                name: None,
+                persisted_handles: AtomicUsize::new(0),
+                active_unlockers: 0,
+                leaked: false,
+                diagnostics: None,
+                gc_starts: Vec::new(),
+                script_caches: Vec::new(),
+                external_data: Arc::default(),
+                task_signal: Arc::default(),
+            },
+        );
+    }
+
+    /// Return the ids of all the isolates that were created and not yet freed.
+    #[must_use]
+    pub fn live_isolates() -> Vec<IsolateId> {
+        LIVE_ISOLATES.read().unwrap().keys().copied().collect()
+    }
+
+    /// Return the isolate with the given id, or None if the isolate was already freed.
+    /// The returned object does not own the isolate, the isolate will not be freed
+    /// when it is dropped. To post a task to the isolate or terminate its execution
+    /// from another thread, use `IsolateId::post_task` or `IsolateId::terminate_execution`
+    /// which do not race with the owner freeing the isolate.
+    ///
+    /// # Safety
+    ///
+    /// The owner may free the isolate at any time, including right after this
+    /// function returns. The caller must make sure the isolate is not freed while
+    /// the returned object is used, for example by only using it on the thread that
+    /// owns the isolate while the owner is known to be alive.
+    #[must_use]
+    pub unsafe fn get_by_id(id: IsolateId) -> Option<Self> {
+        let inner_isolate = LIVE_ISOLATES
+            .read()
+            .unwrap()
+            .get(&id)
+            .filter(|isolate| !isolate.leaked)?
+            .inner_isolate;
+        Some(Self {
+            inner_isolate: inner_isolate as *mut v8_isolate,
+            no_release: true,
+        })
+    }
+
+    /// Return the count and the declared retained size of the external data values
+    /// alive on the isolate, by the id of their Rust type. This makes the memory
+    /// held by wrapped native objects observable without taking a heap snapshot.
+    #[must_use]
+    pub fn get_external_data_stats(&self) -> HashMap<TypeId, V8ExternalDataStats> {
+        self.get_id()
+            .external_data_table()
+            .map(|table| table.0.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// Give the isolate a human readable name, the name can be retrieved
+    /// using `get_name` (or `IsolateId::get_name`) as long as the isolate is alive.
+    pub fn set_name(&self, name: &str) {
+        if let Some(isolate) = LIVE_ISOLATES.write().unwrap().get_mut(&self.get_id()) {
+            isolate.name = Some(name.to_string());
+        }
+    }
+
+    /// Register a diagnostics receiver on the isolate, the receiver will get structured
+    /// events about compilations, garbage collections and uncaught exceptions.
+    /// Replaces the existing receiver, if any.
+    pub fn set_diagnostics<T: V8Diagnostics + 'static>(&self, diagnostics: T) {
+        let installed = {
+            let mut live_isolates = LIVE_ISOLATES.write().unwrap();
+            let live_isolate = match live_isolates.get_mut(&self.get_id()) {
+                Some(i) => i,
+                None => return,
+            };
+            live_isolate
+                .diagnostics
+                .replace(Arc::new(diagnostics))
+                .is_some()
+        };
+        if !installed {
+            unsafe {
+                v8_IsolateSetDiagnosticsCallbacks(
+                    self.inner_isolate,
+                    Some(diagnostics_gc_callback),
+                    Some(diagnostics_message_callback),
+                )
+            };
         }
     }
 
+    pub(crate) fn register_script_cache(&self, entries: &Arc<Mutex<ScriptCacheEntries>>) {
+        if let Some(isolate) = LIVE_ISOLATES.write().unwrap().get_mut(&self.get_id()) {
+            isolate.script_caches.retain(|c| c.strong_count() > 0);
+            isolate.script_caches.push(Arc::downgrade(entries));
+        }
+    }
+
+    /// Release cached scripts of the isolate script caches according to the given level.
+    fn shrink_script_caches(&self, level: V8MemoryPressureLevel) {
+        let caches = match LIVE_ISOLATES.read().unwrap().get(&self.get_id()) {
+            Some(isolate) => isolate
+                .script_caches
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>(),
+            None => return,
+        };
+        for cache in caches {
+            cache.lock().unwrap().on_memory_pressure(level);
+        }
+    }
+
+    pub(crate) fn get_diagnostics(&self) -> Option<Arc<dyn V8Diagnostics>> {
+        LIVE_ISOLATES
+            .read()
+            .unwrap()
+            .get(&self.get_id())?
+            .diagnostics
+            .clone()
+    }
+
+    /// Return the name that was given to the isolate using `set_name`.
+    #[must_use]
+    pub fn get_name(&self) -> Option<String> {
+        self.get_id().get_name()
+    }
+
+    /// Free the isolate without waiting for the object to be dropped.
+    /// Fails if the isolate is not owned by this object (for example, an isolate
+    /// given to a native function callback), if it was already disposed, or if
+    /// there are still persisted handles (values, scripts, modules, templates,
+    /// contexts or array buffer backing stores) or unlockers alive on it. Any use
+    /// of the isolate after a successful `dispose` other than `is_disposed` is
+    /// undefined behavior.
+    pub fn dispose(&mut self) -> Result<(), &'static str> {
+        if self.no_release {
+            return Err("Isolate is not owned by this object and can not be disposed");
+        }
+        if self.is_disposed() {
+            return Err("Isolate was already disposed");
+        }
+        // freeing the isolate might invoke callbacks that use the registry,
+        // the registry is not locked while freeing it
+        self.unregister_live()?;
+        unsafe { v8_FreeIsolate(self.inner_isolate) };
+        self.inner_isolate = ptr::null_mut();
+        Ok(())
+    }
+
+    /// Remove the isolate from the registry of live isolates. Fails if there are
+    /// still persisted handles or unlockers alive on it.
+    pub(crate) fn unregister_live(&self) -> Result<(), &'static str> {
+        let mut live_isolates = LIVE_ISOLATES.write().unwrap();
+        if live_isolates.get(&self.get_id()).map_or(0, |isolate| {
+            isolate.persisted_handles.load(Ordering::Relaxed)
+        }) > 0
+        {
+            return Err("Isolate still has persisted handles alive");
+        }
+        if live_isolates
+            .get(&self.get_id())
+            .map_or(0, |isolate| isolate.active_unlockers)
+            > 0
+        {
+            return Err("Isolate is unlocked by an active unlocker");
+        }
+        live_isolates.remove(&self.get_id());
+        Ok(())
+    }
+
+    /// Return true if the isolate was disposed using `dispose`.
+    #[must_use]
+    pub fn is_disposed(&self) -> bool {
+        self.inner_isolate.is_null()
+    }
+
+    /// Return the unique id of the isolate.
+    #[must_use]
+    pub fn get_id(&self) -> IsolateId {
+        IsolateId(unsafe { v8_IsolateGetId(self.inner_isolate) } as u64)
+    }
+
     /// Enter the isolate for code invocation.
     /// Return an `V8IsolateScope` object, when the returned
     /// object is destroy the code will exit the isolate.
@@ -139,6 +770,31 @@ impl V8Isolate {
         V8HandlersScope::new(self)
     }
 
+    /// Return the number of local handlers currently allocated on the isolate
+    /// (on all the handlers scopes).
+    #[must_use]
+    pub fn number_of_handles(&self) -> usize {
+        unsafe { v8_NumberOfHandles(self.inner_isolate) }
+    }
+
+    /// Return, per type, the number of locals which are alive on the current thread
+    /// (on all the isolates), types without live locals are omitted. Unlike
+    /// `number_of_handles`, locals that are owned by the isolate are not counted.
+    /// Comparing the breakdown taken before and after some code runs tells which
+    /// type of locals it leaks.
+    #[must_use]
+    pub fn live_locals_by_type(&self) -> Vec<(&'static str, usize)> {
+        LiveLocals::current().outstanding_since(&LiveLocals::default())
+    }
+
+    /// Create a new escapable handlers scope. Same as `new_handlers_scope` but
+    /// allows a single local value to escape into the enclosing handlers scope
+    /// using `V8EscapableHandlersScope::escape`.
+    #[must_use]
+    pub fn new_escapable_handlers_scope(&self) -> V8EscapableHandlersScope<'_> {
+        V8EscapableHandlersScope::new(self)
+    }
+
     /// Raise an exception with the given local generic value.
     pub fn raise_exception(&self, exception: V8LocalValue) {
         unsafe { v8_IsolateRaiseException(self.inner_isolate, exception.inner_val) };
@@ -147,7 +803,7 @@ impl V8Isolate {
     /// Same as `raise_exception` but raise exception with the given massage.
     pub fn raise_exception_str(&self, msg: &str) {
         let inner_string =
-            unsafe { v8_NewString(self.inner_isolate, msg.as_ptr().cast::<i8>(), msg.len()) };
+            unsafe { v8_NewString(self.inner_isolate, msg.as_ptr().cast::<c_char>(), msg.len()) };
         let inner_val = unsafe { v8_StringToValue(inner_string) };
         unsafe { v8_IsolateRaiseException(self.inner_isolate, inner_val) };
     }
@@ -160,8 +816,50 @@ impl V8Isolate {
         V8TryCatch { inner_trycatch }
     }
 
+    /// Notify the isolate that the embedder is idle for one second.
+    #[deprecated(note = "use `idle_notification_for`, which takes the idle time")]
     pub fn idle_notification_deadline(&self) {
-        unsafe { v8_IdleNotificationDeadline(self.inner_isolate, 1.0) };
+        self.idle_notification_for(Duration::from_secs(1));
+    }
+
+    /// Notify the isolate that the embedder is idle for the given amount of time,
+    /// the isolate uses it to perform garbage collection. The deadline is computed
+    /// on the platform monotonic clock. Return true if the isolate has done all the
+    /// cleanup it can and should not be notified again until real work has been done.
+    pub fn idle_notification_for(&self, idle_time: Duration) -> bool {
+        unsafe { v8_IdleNotificationDeadline(self.inner_isolate, idle_time.as_secs_f64()) != 0 }
+    }
+
+    /// Notify the isolate that the embedder is under memory pressure. The isolate
+    /// will perform a full garbage collection and release as much memory as possible,
+    /// including the scripts of its script caches (see `V8ScriptCache`).
+    pub fn low_memory_notification(&self) {
+        self.shrink_script_caches(V8MemoryPressureLevel::Critical);
+        unsafe { v8_LowMemoryNotification(self.inner_isolate) };
+    }
+
+    /// Notify the isolate about the current memory pressure level so it can
+    /// adjust its garbage collection heuristics accordingly. The isolate script
+    /// caches (see `V8ScriptCache`) release half of their scripts on moderate
+    /// pressure and all of them on critical pressure. Can be called from any thread,
+    /// the script caches are shrunk by a task posted to the isolate (see `post_task`)
+    /// as their scripts must be released on the thread that uses the isolate, so they
+    /// are only shrunk once the embedder runs the isolate task loop (see
+    /// `pump_message_loop`). No task is posted for `V8MemoryPressureLevel::None`.
+    pub fn memory_pressure_notification(&self, level: V8MemoryPressureLevel) {
+        if level != V8MemoryPressureLevel::None {
+            self.post_task(move |isolate| isolate.shrink_script_caches(level));
+        }
+        let inner_level = match level {
+            V8MemoryPressureLevel::None => v8_MemoryPressureLevel_v8_MemoryPressureLevel_None,
+            V8MemoryPressureLevel::Moderate => {
+                v8_MemoryPressureLevel_v8_MemoryPressureLevel_Moderate
+            }
+            V8MemoryPressureLevel::Critical => {
+                v8_MemoryPressureLevel_v8_MemoryPressureLevel_Critical
+            }
+        };
+        unsafe { v8_MemoryPressureNotification(self.inner_isolate, inner_level) };
     }
 
     pub fn request_interrupt<T: Fn(&Self)>(&self, callback: T) {
@@ -174,14 +872,329 @@ impl V8Isolate {
         };
     }
 
+    /// Start sampling the contexts executing on the isolate at the given interval, until
+    /// the returned sampler is dropped. Each sample interrupts the running JS code and
+    /// accounts the CPU time consumed so far by the entered contexts, so the execution
+    /// stats of a context (see `V8Context::get_execution_stats`) stay up to date during
+    /// a long execution. Samples are only taken while the isolate executes JS code.
+    #[must_use]
+    pub fn start_execution_sampling(&self, interval: Duration) -> V8ExecutionSampler {
+        V8ExecutionSampler::new(self.get_id(), interval)
+    }
+
+    /// Clear the objects that were kept alive by `WeakRef.prototype.deref`, allowing
+    /// them to be collected. Should be called by embedders that control the event loop
+    /// once the current job (including its microtasks) is done, it is also called as
+    /// part of `perform_microtask_checkpoint`. `FinalizationRegistry` cleanup callbacks
+    /// run as foreground tasks, so they run when the message loop is pumped
+    /// (see `pump_message_loop`).
+    pub fn clear_kept_objects(&self) {
+        unsafe { v8_IsolateClearKeptObjects(self.inner_isolate) };
+    }
+
+    /// Return a scope of the current context of the isolate (the last context that was
+    /// entered and not yet exited), or None if no context is entered. Dropping the returned
+    /// scope does not exit the context. Must be called inside a handlers scope.
+    #[must_use]
+    pub fn current_context(&self) -> Option<V8ContextScope> {
+        if unsafe { v8_IsolateInContext(self.inner_isolate) } == 0 {
+            return None;
+        }
+        let inner_ctx_ref = unsafe { v8_GetCurrentCtxRef(self.inner_isolate) };
+        Some(V8ContextScope {
+            inner_ctx_ref,
+            exit_on_drop: false,
+            execution_tracker: None,
+        })
+    }
+
+    /// Run all the pending microtasks (for example, promises callbacks).
+    pub fn perform_microtask_checkpoint(&self) {
+        unsafe { v8_IsolatePerformMicrotaskCheckpoint(self.inner_isolate) };
+    }
+
+    /// Set a hook that is called on every promise lifecycle event (creation, resolution
+    /// and before and after each of its reactions run), replacing the existing hook, if any.
+    /// Can be used to track async context across awaits or to detect orphaned promises.
+    /// The hook is called while JS code runs so it must not run JS code itself.
+    pub fn set_promise_hook<
+        T: Fn(&Self, V8PromiseHookType, &V8LocalPromise, &V8LocalValue) + 'static,
+    >(
+        &self,
+        hook: T,
+    ) {
+        unsafe {
+            v8_IsolateSetPromiseHook(
+                self.inner_isolate,
+                Some(promise_hook_callback::<T>),
+                Box::into_raw(Box::new(hook)).cast::<c_void>(),
+                Some(promise_hook_free_pd::<T>),
+            )
+        }
+    }
+
+    /// Remove the promise hook set using `set_promise_hook`.
+    pub fn clear_promise_hook(&self) {
+        unsafe { v8_IsolateSetPromiseHook(self.inner_isolate, None, ptr::null_mut(), None) }
+    }
+
+    /// Set a callback that is called the first time a module accesses `import.meta`,
+    /// so the callback can populate the `import.meta` object (for example set its `url`),
+    /// replacing the existing callback, if any. The module identity hash can be used to
+    /// find the module name given to the module loader.
+    pub fn set_import_meta_callback<
+        T: Fn(&Self, &V8ContextScope, &V8LocalModule, &V8LocalObject) + 'static,
+    >(
+        &self,
+        callback: T,
+    ) {
+        unsafe {
+            v8_IsolateSetImportMetaCallback(
+                self.inner_isolate,
+                Some(import_meta_callback::<T>),
+                Box::into_raw(Box::new(callback)).cast::<c_void>(),
+                Some(import_meta_free_pd::<T>),
+            )
+        }
+    }
+
+    /// Implement `import.meta.resolve(specifier)` using the given resolver, which gets
+    /// the identity hash of the module that calls `resolve` (the same hash the module
+    /// loader gets) and the specifier, and returns the resolved URL or None if the
+    /// specifier can not be resolved, in which case `resolve` throws a `TypeError`.
+    /// Sharing the logic with the module loader lets the JS code compute URLs the same
+    /// way imports are resolved. Replaces the callback set using `set_import_meta_callback`.
+    pub fn set_import_meta_resolver<
+        T: Fn(&Self, &V8ContextScope, i64, &str) -> Option<String> + 'static,
+    >(
+        &self,
+        resolver: T,
+    ) {
+        let resolver = Rc::new(resolver);
+        self.set_import_meta_callback(move |isolate, ctx_scope, module, meta| {
+            let resolver = Rc::clone(&resolver);
+            let identity_hash = module.get_identity_hash();
+            let resolve = ctx_scope.new_native_function(move |args, isolate, ctx_scope| {
+                let specifier = match args.iter().next() {
+                    Some(s) if s.is_string() => s.to_utf8(isolate)?,
+                    _ => {
+                        let error = ctx_scope
+                            .new_error(&V8ErrorKind::TypeError, "The specifier must be a string");
+                        isolate.raise_exception(error);
+                        return None;
+                    }
+                };
+                match resolver(isolate, ctx_scope, identity_hash, specifier.as_str()) {
+                    Some(url) => Some(isolate.new_string(&url).to_value()),
+                    None => {
+                        let error = ctx_scope.new_error(
+                            &V8ErrorKind::TypeError,
+                            &format!("Failed resolving module specifier '{}'", specifier.as_str()),
+                        );
+                        isolate.raise_exception(error);
+                        None
+                    }
+                }
+            });
+            meta.set(
+                ctx_scope,
+                &isolate.new_string("resolve").to_value(),
+                &resolve.to_value(),
+            );
+        });
+    }
+
+    /// Remove the callback set using `set_import_meta_callback` or `set_import_meta_resolver`.
+    pub fn clear_import_meta_callback(&self) {
+        unsafe { v8_IsolateSetImportMetaCallback(self.inner_isolate, None, ptr::null_mut(), None) }
+    }
+
+    /// Post a task to the isolate foreground task runner. The task will run on the
+    /// thread that pumps the isolate message loop using `pump_message_loop`.
+    /// Can be called from any thread.
+    pub fn post_task<T: FnOnce(&Self) + Send + 'static>(&self, task: T) {
+        self.post_delayed_task(task, Duration::ZERO);
+    }
+
+    /// Same as `post_task` but the task will not run before the given delay passed.
+    pub fn post_delayed_task<T: FnOnce(&Self) + Send + 'static>(&self, task: T, delay: Duration) {
+        self.post_delayed_task_unsignaled(task, delay);
+        if let Some(task_signal) = self.get_id().task_signal() {
+            task_signal.notify();
+        }
+    }
+
+    /// Same as `post_delayed_task` but does not signal the threads that wait for tasks,
+    /// does not use the registry.
+    fn post_delayed_task_unsignaled<T: FnOnce(&Self) + Send + 'static>(
+        &self,
+        task: T,
+        delay: Duration,
+    ) {
+        unsafe {
+            v8_IsolatePostTask(
+                self.inner_isolate,
+                Some(task_callback::<T>),
+                Box::into_raw(Box::new(task)).cast::<c_void>(),
+                Some(task_free_pd::<T>),
+                delay.as_secs_f64(),
+            );
+        };
+    }
+
+    /// Run a single pending foreground task, either a task posted using `post_task`
+    /// or a task posted by v8 itself (for example, wasm async compilation).
+    /// If `wait_for_work` is true, block until a task is available.
+    /// Return true if a task was executed. Must be called while the isolate is entered.
+    pub fn pump_message_loop(&self, wait_for_work: bool) -> bool {
+        unsafe { v8_IsolatePumpMessageLoop(self.inner_isolate, wait_for_work as c_int) != 0 }
+    }
+
+    /// Run the pending idle tasks (for example, idle time garbage collection)
+    /// for up to the given amount of time.
+    pub fn run_idle_tasks(&self, idle_time: Duration) {
+        unsafe { v8_IsolateRunIdleTasks(self.inner_isolate, idle_time.as_secs_f64()) };
+    }
+
     /// Create a new string object.
     #[must_use]
     pub fn new_string(&self, s: &str) -> V8LocalString {
         let inner_string =
-            unsafe { v8_NewString(self.inner_isolate, s.as_ptr().cast::<i8>(), s.len()) };
+            unsafe { v8_NewString(self.inner_isolate, s.as_ptr().cast::<c_char>(), s.len()) };
         V8LocalString { inner_string }
     }
 
+    /// Same as `new_string` but fail, instead of aborting the process, if the string
+    /// is longer than the limit given in the options or than `V8LocalString::max_length`.
+    /// Should be used for data that comes from untrusted sources and might be arbitrarily
+    /// long. Running out of heap memory while creating the string is not caught, it still
+    /// goes through the OOM handling (see `set_near_oom_callback`); only the array buffer
+    /// allocations (see `V8ArrayBufferAllocator`) report allocation failures as exceptions.
+    pub fn try_new_string(
+        &self,
+        s: &str,
+        options: &V8NewStringOptions,
+    ) -> Result<V8LocalString, String> {
+        let max_len = options
+            .max_len
+            .map_or(V8LocalString::max_length(), |max_len| {
+                max_len.min(V8LocalString::max_length())
+            });
+        if s.len() > max_len {
+            return Err(format!(
+                "String length {} exceeds the maximum length {}",
+                s.len(),
+                max_len
+            ));
+        }
+        let inner_string = unsafe {
+            v8_TryNewString(
+                self.inner_isolate,
+                s.as_ptr().cast::<c_char>(),
+                s.len(),
+                c_int::from(options.internalize),
+            )
+        };
+        if inner_string.is_null() {
+            return Err("Failed creating the string".to_string());
+        }
+        Ok(V8LocalString { inner_string })
+    }
+
+    /// Create a new string object from data which is not necessarily valid UTF-8,
+    /// see `V8StringEncoding`. Return None if the string could not be created
+    /// (the data is too long).
+    #[must_use]
+    pub fn new_string_from_bytes(
+        &self,
+        bytes: &[u8],
+        encoding: V8StringEncoding,
+    ) -> Option<V8LocalString> {
+        let inner_string = match encoding {
+            V8StringEncoding::Latin1 => unsafe {
+                v8_NewStringFromOneByte(self.inner_isolate, bytes.as_ptr(), bytes.len())
+            },
+            V8StringEncoding::Utf8Lossy => {
+                let s = String::from_utf8_lossy(bytes);
+                return Some(self.new_string(&s));
+            }
+            V8StringEncoding::Utf16 => {
+                let chunks = bytes.chunks_exact(2);
+                let has_odd_byte = !chunks.remainder().is_empty();
+                let mut code_units = chunks
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .collect::<Vec<u16>>();
+                if has_odd_byte {
+                    code_units.push(0xFFFD);
+                }
+                unsafe {
+                    v8_NewStringFromTwoByte(
+                        self.inner_isolate,
+                        code_units.as_ptr(),
+                        code_units.len(),
+                    )
+                }
+            }
+        };
+        if inner_string.is_null() {
+            None
+        } else {
+            Some(V8LocalString { inner_string })
+        }
+    }
+
+    /// Create a new string object that takes ownership on the given buffer instead
+    /// of copying it into the JS heap. The buffer is freed when the string is
+    /// garbage collected. The buffer must be ASCII, non ASCII UTF-8 data would be
+    /// read as Latin-1 and garbled, so it is rejected, use `new_external_string_utf16`
+    /// for such data. Return None if the buffer is not ASCII or if the string could
+    /// not be created.
+    #[must_use]
+    pub fn new_external_string(&self, data: Vec<u8>) -> Option<V8LocalString> {
+        if !data.is_ascii() {
+            return None;
+        }
+        let ptr = data.as_ptr();
+        let len = data.len();
+        let inner_string = unsafe {
+            v8_NewExternalString(
+                self.inner_isolate,
+                ptr.cast::<c_char>(),
+                len,
+                Box::into_raw(Box::new(data)).cast::<c_void>(),
+                Some(free_boxed_vec::<u8>),
+            )
+        };
+        if inner_string.is_null() {
+            None
+        } else {
+            Some(V8LocalString { inner_string })
+        }
+    }
+
+    /// Same as `new_external_string` but takes a buffer of UTF-16 code units, so
+    /// any text can be held outside of the JS heap, for example the result of
+    /// `str::encode_utf16`. Return None if the string could not be created.
+    #[must_use]
+    pub fn new_external_string_utf16(&self, data: Vec<u16>) -> Option<V8LocalString> {
+        let ptr = data.as_ptr();
+        let len = data.len();
+        let inner_string = unsafe {
+            v8_NewExternalTwoByteString(
+                self.inner_isolate,
+                ptr,
+                len,
+                Box::into_raw(Box::new(data)).cast::<c_void>(),
+                Some(free_boxed_vec::<u16>),
+            )
+        };
+        if inner_string.is_null() {
+            None
+        } else {
+            Some(V8LocalString { inner_string })
+        }
+    }
+
     /// Create a new string object.
     #[must_use]
     pub fn new_array(&self, values: &[&V8LocalValue]) -> V8LocalArray {
@@ -194,6 +1207,42 @@ impl V8Isolate {
         V8LocalArray { inner_array }
     }
 
+    /// Create a new JS array of numbers from the given slice using a single call into
+    /// v8, without creating a `V8LocalValue` per element. v8 still allocates a number
+    /// per element, the numbers are stored on the returned array.
+    #[must_use]
+    pub fn new_array_from_f64_slice(&self, values: &[f64]) -> V8LocalArray {
+        let inner_array =
+            unsafe { v8_NewArrayFromDoubles(self.inner_isolate, values.as_ptr(), values.len()) };
+        V8LocalArray { inner_array }
+    }
+
+    /// Create a new JS array of strings from the given slice using a single call into
+    /// v8, without creating a `V8LocalValue` per element. v8 still allocates a string
+    /// per element. Return None if any of the strings is longer than the maximal v8
+    /// string length.
+    #[must_use]
+    pub fn new_array_from_str_slice(&self, values: &[&str]) -> Option<V8LocalArray> {
+        let mut strs = values
+            .iter()
+            .map(|s| s.as_ptr().cast::<c_char>())
+            .collect::<Vec<_>>();
+        let lens = values.iter().map(|s| s.len()).collect::<Vec<_>>();
+        let inner_array = unsafe {
+            v8_NewArrayFromStrings(
+                self.inner_isolate,
+                strs.as_mut_ptr(),
+                lens.as_ptr(),
+                values.len(),
+            )
+        };
+        if inner_array.is_null() {
+            None
+        } else {
+            Some(V8LocalArray { inner_array })
+        }
+    }
+
     #[must_use]
     pub fn new_array_buffer(&self, buff: &[u8]) -> V8LocalArrayBuffer {
         let inner_array_buffer = unsafe {
@@ -206,6 +1255,39 @@ impl V8Isolate {
         V8LocalArrayBuffer { inner_array_buffer }
     }
 
+    /// Create a new array buffer on top of the given data without copying it.
+    /// The data is freed once the array buffer is garbage collected.
+    #[must_use]
+    pub fn new_array_buffer_from_vec(&self, mut data: Vec<u8>) -> V8LocalArrayBuffer {
+        let ptr = data.as_mut_ptr();
+        let len = data.len();
+        let inner_array_buffer = unsafe {
+            v8_NewExternalArrayBuffer(
+                self.inner_isolate,
+                ptr.cast::<c_void>(),
+                len,
+                Box::into_raw(Box::new(data)).cast::<c_void>(),
+                Some(free_boxed_vec::<u8>),
+            )
+        };
+        V8LocalArrayBuffer { inner_array_buffer }
+    }
+
+    /// Same as `new_array_buffer_from_vec` but returns a `Uint8Array` view of the array buffer.
+    #[must_use]
+    pub fn new_uint8_array_from_vec(&self, data: Vec<u8>) -> V8LocalValue {
+        self.new_array_buffer_from_vec(data).to_uint8_array()
+    }
+
+    /// Create a new shared array buffer of the given length, the memory is zero initialized.
+    #[must_use]
+    pub fn new_shared_array_buffer(&self, len: usize) -> V8LocalSharedArrayBuffer {
+        let inner_shared_array_buffer = unsafe { v8_NewSharedArrayBuffer(self.inner_isolate, len) };
+        V8LocalSharedArrayBuffer {
+            inner_shared_array_buffer,
+        }
+    }
+
     #[must_use]
     pub fn new_object(&self) -> V8LocalObject {
         let inner_obj = unsafe { v8_NewObject(self.inner_isolate) };
@@ -218,6 +1300,8 @@ impl V8Isolate {
         V8LocalSet { inner_set }
     }
 
+    /// Return a JS boolean. Booleans, `null`, `undefined` and the empty string are
+    /// interned by v8 and cached on the isolate, getting them performs no allocation.
     #[must_use]
     pub fn new_bool(&self, val: bool) -> V8LocalValue {
         let inner_val = unsafe { v8_NewBool(self.inner_isolate, val as i32) };
@@ -239,6 +1323,18 @@ impl V8Isolate {
         V8LocalValue { inner_val }
     }
 
+    #[must_use]
+    pub fn new_undefined(&self) -> V8LocalValue {
+        let inner_val = unsafe { v8_NewUndefined(self.inner_isolate) };
+        V8LocalValue { inner_val }
+    }
+
+    #[must_use]
+    pub fn new_empty_string(&self) -> V8LocalString {
+        let inner_string = unsafe { v8_NewEmptyString(self.inner_isolate) };
+        V8LocalString { inner_string }
+    }
+
     /// Create a new JS object template.
     #[must_use]
     pub fn new_object_template(&self) -> V8LocalObjectTemplate {
@@ -264,12 +1360,58 @@ impl V8Isolate {
         V8LocalNativeFunctionTemplate { inner_func }
     }
 
+    /// Cache the object template on the isolate at the given index. The template
+    /// is kept alive as long as the isolate is alive (or until replaced) and can be
+    /// retrieved using `get_object_template` from any handlers scope.
+    /// Object templates and native function templates share the same indexes.
+    pub fn set_object_template(&self, index: usize, obj: &V8LocalObjectTemplate) {
+        unsafe { v8_IsolateSetObjectTemplate(self.inner_isolate, index, obj.inner_obj) };
+    }
+
+    /// Return the object template that was cached using `set_object_template`, or None
+    /// if no object template is cached on the given index.
+    #[must_use]
+    pub fn get_object_template(&self, index: usize) -> Option<V8LocalObjectTemplate> {
+        let inner_obj = unsafe { v8_IsolateGetObjectTemplate(self.inner_isolate, index) };
+        if inner_obj.is_null() {
+            None
+        } else {
+            Some(V8LocalObjectTemplate { inner_obj })
+        }
+    }
+
+    /// Same as `set_object_template` but caches a native function template.
+    pub fn set_native_function_template(&self, index: usize, func: &V8LocalNativeFunctionTemplate) {
+        unsafe { v8_IsolateSetNativeFunctionTemplate(self.inner_isolate, index, func.inner_func) };
+    }
+
+    /// Same as `get_object_template` but returns a native function template.
+    #[must_use]
+    pub fn get_native_function_template(
+        &self,
+        index: usize,
+    ) -> Option<V8LocalNativeFunctionTemplate> {
+        let inner_func = unsafe { v8_IsolateGetNativeFunctionTemplate(self.inner_isolate, index) };
+        if inner_func.is_null() {
+            None
+        } else {
+            Some(V8LocalNativeFunctionTemplate { inner_func })
+        }
+    }
+
     /// Create a new unlocker object that releases the isolate global lock.
-    /// The lock will be re-aquire when the unlocker will be released.
+    /// The lock will be re-aquire when the unlocker is relocked or released,
+    /// see `V8Unlocker` for the valid usage.
     #[must_use]
-    pub fn new_unlocker(&self) -> V8Unlocker {
+    pub fn new_unlocker(&self) -> V8Unlocker<'_> {
         let inner_unlocker = unsafe { v8_NewUnlocker(self.inner_isolate) };
-        V8Unlocker { inner_unlocker }
+        let isolate_id = self.get_id();
+        isolate_id.unlocker_created();
+        V8Unlocker {
+            inner_unlocker,
+            isolate_id,
+            _isolate: PhantomData,
+        }
     }
 
     pub fn set_near_oom_callback<F: Fn(usize, usize) -> usize>(&self, callback: F) {
@@ -283,6 +1425,13 @@ impl V8Isolate {
         }
     }
 
+    /// Restore the heap limit raised by the near OOM callback (see `set_near_oom_callback`)
+    /// to the given limit, or to the minimal limit possible for the current heap size if
+    /// the heap is larger. Must not be called from the near OOM callback itself.
+    pub fn restore_heap_limit(&self, heap_limit: usize) {
+        unsafe { v8_IsolateRestoreHeapLimit(self.inner_isolate, heap_limit) }
+    }
+
     pub fn terminate_execution(&self) {
         unsafe { v8_TerminateCurrExecution(self.inner_isolate) }
     }
@@ -294,7 +1443,26 @@ impl V8Isolate {
 
 impl Drop for V8Isolate {
     fn drop(&mut self) {
-        if !self.no_release {
+        if !self.no_release && !self.is_disposed() {
+            let mut live_isolates = LIVE_ISOLATES.write().unwrap();
+            if let Some(isolate) = live_isolates
+                .get_mut(&self.get_id())
+                .filter(|isolate| isolate.active_unlockers > 0)
+            {
+                // An active unlocker will re-acquire the isolate lock, freeing the
+                // isolate would make it touch freed memory so the isolate is leaked.
+                // This can only happen if the unlocker was created from another
+                // object of the isolate (see `get_by_id`) which outlived the owner.
+                isolate.leaked = true;
+                drop(live_isolates);
+                debug_assert!(
+                    false,
+                    "Isolate dropped while unlocked by an active unlocker, the isolate is leaked"
+                );
+                return;
+            }
+            live_isolates.remove(&self.get_id());
+            drop(live_isolates);
             unsafe { v8_FreeIsolate(self.inner_isolate) }
         }
     }
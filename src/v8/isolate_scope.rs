@@ -1,11 +1,12 @@
 use crate::v8_c_raw::bindings::{v8_IsolateEnter, v8_IsolateExit, v8_isolate_scope};
 
 use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_builder::{V8ArrayBuilder, V8ObjectBuilder};
 use crate::v8::v8_context::V8Context;
 use crate::v8::v8_object_template::V8LocalObjectTemplate;
 
 pub struct V8IsolateScope<'a> {
-    isolate: &'a V8Isolate,
+    pub(crate) isolate: &'a V8Isolate,
     inner_isolate_scope: *mut v8_isolate_scope,
 }
 
@@ -23,6 +24,18 @@ impl<'a> V8IsolateScope<'a> {
     pub fn new_context(&self, globals: Option<&V8LocalObjectTemplate>) -> V8Context {
         V8Context::new(self.isolate, globals)
     }
+
+    /// Start building a JS object, see `V8ObjectBuilder`.
+    #[must_use]
+    pub fn object<'b>(&self) -> V8ObjectBuilder<'b> {
+        V8ObjectBuilder::new()
+    }
+
+    /// Start building a JS array, see `V8ArrayBuilder`.
+    #[must_use]
+    pub fn array<'b>(&self) -> V8ArrayBuilder<'b> {
+        V8ArrayBuilder::new()
+    }
 }
 
 impl<'a> Drop for V8IsolateScope<'a> {
@@ -1,16 +1,43 @@
-use crate::v8_c_raw::bindings::{v8_Dispose, v8_Initialize, v8_Version};
+use crate::v8_c_raw::bindings::{
+    v8_Dispose, v8_Initialize, v8_InitializeExternalStartupDataFromBytes,
+    v8_InitializeExternalStartupDataFromFile, v8_InitializeICU, v8_InitializeWithTracing,
+    v8_Version, v8_alloctor,
+};
 
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_longlong, c_void};
 use std::ptr;
+use std::time::Duration;
 
+pub mod diagnostics;
 pub mod handler_scope;
+pub mod host_function_registry;
+pub mod host_value;
+pub mod identity_map;
+pub mod import_map;
 pub mod isolate;
 pub mod isolate_scope;
+pub mod native_module_registry;
+#[cfg(feature = "raw-handles")]
+pub mod raw;
+pub mod sandbox;
+pub mod script_cache;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod try_catch;
 pub mod v8_array;
 pub mod v8_array_buffer;
+#[cfg(feature = "async")]
+pub mod v8_async;
+pub mod v8_async_iterator;
+pub mod v8_builder;
 pub mod v8_context;
 pub mod v8_context_scope;
+pub mod v8_context_template;
+pub mod v8_error;
+pub mod v8_globals_baseline;
+pub mod v8_iterable;
+pub mod v8_json;
 pub mod v8_module;
 pub mod v8_native_function;
 pub mod v8_native_function_template;
@@ -20,6 +47,9 @@ pub mod v8_promise;
 pub mod v8_resolver;
 pub mod v8_script;
 pub mod v8_set;
+pub mod v8_shared_array_buffer;
+#[cfg(feature = "async")]
+pub mod v8_stream;
 pub mod v8_string;
 pub mod v8_unlocker;
 pub mod v8_utf8;
@@ -33,6 +63,37 @@ pub fn v8_init() {
     unsafe { v8_Initialize(ptr::null_mut()) }
 }
 
+/// Allocation hooks for the memory allocated by the C wrapper layer (the native
+/// data of functions, modules and isolates, not the v8 heap), for example to
+/// attribute it to the accounting of the host application. The hooks are called
+/// from any thread and must behave like their libc counterparts.
+#[derive(Debug, Clone, Copy)]
+pub struct V8Allocator {
+    pub alloc: unsafe extern "C" fn(bytes: usize) -> *mut c_void,
+    pub realloc: unsafe extern "C" fn(ptr: *mut c_void, bytes: usize) -> *mut c_void,
+    pub free: unsafe extern "C" fn(ptr: *mut c_void),
+    pub calloc: unsafe extern "C" fn(nmemb: usize, size: usize) -> *mut c_void,
+    pub strdup: unsafe extern "C" fn(s: *const c_char) -> *mut c_char,
+}
+
+impl V8Allocator {
+    /// The C wrapper keeps using the allocator until the process exits.
+    fn into_raw(self) -> *mut v8_alloctor {
+        Box::leak(Box::new(v8_alloctor {
+            v8_Alloc: Some(self.alloc),
+            v8_Realloc: Some(self.realloc),
+            v8_Free: Some(self.free),
+            v8_Calloc: Some(self.calloc),
+            v8_Strdup: Some(self.strdup),
+        }))
+    }
+}
+
+/// Same as `v8_init` but the C wrapper layer allocates its memory using the given allocator.
+pub fn v8_init_with_allocator(allocator: V8Allocator) {
+    unsafe { v8_Initialize(allocator.into_raw()) }
+}
+
 pub fn v8_init_with_error_handlers(
     fatal_error_hanlder: Box<dyn Fn(&str, &str)>,
     oom_error_handler: Box<dyn Fn(&str, bool)>,
@@ -44,6 +105,203 @@ pub fn v8_init_with_error_handlers(
     }
 }
 
+/// The phase of a trace event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V8TraceEventPhase {
+    Begin,
+    End,
+    /// An event with a duration, reported once the event ends.
+    Complete,
+    Instant,
+    Counter,
+    Other(char),
+}
+
+/// A trace event reported by v8 (GC, compilation, execution, ...).
+pub struct V8TraceEvent<'a> {
+    pub phase: V8TraceEventPhase,
+    pub category: &'a str,
+    pub name: &'a str,
+    /// The event start time according to a monotonic clock, only meaningful
+    /// when compared with the timestamps of other events.
+    pub timestamp: Duration,
+    /// The event duration, zero for events that are not `Complete`.
+    pub duration: Duration,
+}
+
+impl<'a> V8TraceEvent<'a> {
+    /// Emit the trace event as an event of the `tracing` crate, at the trace level
+    /// with the `v8` target. The v8 category, name and phase are given as the
+    /// `category`, `name` and `phase` fields, and the v8 timestamp and duration as
+    /// the `timestamp_us` and `duration_us` fields, in microseconds. `Begin` and `End`
+    /// events are emitted as separate events, they are not turned into spans.
+    #[cfg(feature = "tracing")]
+    pub fn emit_to_tracing(&self) {
+        tracing::trace!(
+            target: "v8",
+            category = self.category,
+            name = self.name,
+            phase = ?self.phase,
+            timestamp_us = self.timestamp.as_micros() as u64,
+            duration_us = self.duration.as_micros() as u64,
+        );
+    }
+}
+
+/// Tracing configuration, gives the trace categories to enable and the
+/// callback that will be called with the trace events of those categories.
+/// The callback can be used to forward the events to any tracing system,
+/// `V8TracingConfig::tracing` forwards them to the Rust `tracing` crate.
+pub struct V8TracingConfig {
+    categories: Vec<String>,
+    callback: Box<dyn Fn(&V8TraceEvent) + Send + Sync>,
+}
+
+impl V8TracingConfig {
+    pub fn new<T: Fn(&V8TraceEvent) + Send + Sync + 'static>(callback: T) -> Self {
+        Self {
+            categories: Vec::new(),
+            callback: Box::new(callback),
+        }
+    }
+
+    /// A configuration that emits the trace events to the subscriber of the Rust
+    /// `tracing` crate, see `V8TraceEvent::emit_to_tracing`.
+    #[cfg(feature = "tracing")]
+    #[must_use]
+    pub fn tracing() -> Self {
+        Self::new(|event| event.emit_to_tracing())
+    }
+
+    /// Enable the given trace category, for example `v8`, `v8.execute`
+    /// or `disabled-by-default-v8.gc`.
+    #[must_use]
+    pub fn add_category(mut self, category: &str) -> Self {
+        self.categories.push(category.to_string());
+        self
+    }
+}
+
+extern "C" fn trace_event_callback(
+    phase: c_char,
+    category: *const c_char,
+    name: *const c_char,
+    timestamp: c_longlong,
+    duration: c_longlong,
+    pd: *mut c_void,
+) {
+    let callback = unsafe { &*(pd as *const Box<dyn Fn(&V8TraceEvent) + Send + Sync>) };
+    let phase = match phase as u8 {
+        b'B' => V8TraceEventPhase::Begin,
+        b'E' => V8TraceEventPhase::End,
+        b'X' => V8TraceEventPhase::Complete,
+        b'I' => V8TraceEventPhase::Instant,
+        b'C' => V8TraceEventPhase::Counter,
+        c => V8TraceEventPhase::Other(c as char),
+    };
+    let category = unsafe { CStr::from_ptr(category) }.to_string_lossy();
+    let name = unsafe { CStr::from_ptr(name) }.to_string_lossy();
+    callback(&V8TraceEvent {
+        phase,
+        category: &category,
+        name: &name,
+        timestamp: Duration::from_micros(timestamp.max(0) as u64),
+        duration: Duration::from_micros(duration.max(0) as u64),
+    });
+}
+
+extern "C" fn free_trace_event_callback(pd: *mut c_void) {
+    unsafe {
+        drop(Box::from_raw(
+            pd as *mut Box<dyn Fn(&V8TraceEvent) + Send + Sync>,
+        ))
+    }
+}
+
+/// Same as `v8_init` but also report the trace events of the categories
+/// enabled on the given configuration to the configuration callback.
+/// Fails, without initializing v8, if a category contains a NUL byte.
+pub fn v8_init_with_tracing(config: V8TracingConfig) -> Result<(), String> {
+    v8_init_with_tracing_and_allocator(config, None)
+}
+
+/// Same as `v8_init_with_tracing` but the C wrapper layer allocates its memory
+/// using the given allocator, if any (see `v8_init_with_allocator`).
+pub fn v8_init_with_tracing_and_allocator(
+    config: V8TracingConfig,
+    allocator: Option<V8Allocator>,
+) -> Result<(), String> {
+    let categories = CString::new(config.categories.join(","))
+        .map_err(|_| "A trace category contains a NUL byte".to_string())?;
+    let callback = Box::into_raw(Box::new(config.callback));
+    let allocator = allocator.map_or(ptr::null_mut(), V8Allocator::into_raw);
+    unsafe {
+        v8_InitializeWithTracing(
+            allocator,
+            categories.as_ptr(),
+            Some(trace_event_callback),
+            callback as *mut c_void,
+            Some(free_trace_event_callback),
+        );
+    }
+    Ok(())
+}
+
+/// Initialize ICU, which is required for `Intl` and the locale dependent
+/// APIs (such as `String.prototype.localeCompare`) to behave the same on
+/// all builds. Must be called before `v8_init`. If `icu_data_file` is None,
+/// the ICU data embedded in v8 is used. Return true on success, false
+/// if the ICU data could not be loaded or the path contains a NUL byte.
+#[cfg(feature = "i18n")]
+pub fn v8_init_icu(icu_data_file: Option<&str>) -> bool {
+    let icu_data_file = match icu_data_file.map(CString::new).transpose() {
+        Ok(f) => f,
+        Err(_) => return false,
+    };
+    let icu_data_file_ptr = icu_data_file.as_ref().map_or(ptr::null(), |f| f.as_ptr());
+    unsafe { v8_InitializeICU(icu_data_file_ptr) != 0 }
+}
+
+/// The v8 startup data (the snapshot blob), for v8 builds that load it at
+/// runtime instead of embedding it in the binary.
+pub enum V8StartupData<'a> {
+    /// Path of a `snapshot_blob.bin` file.
+    File(&'a str),
+    /// The content of a `snapshot_blob.bin` file, it is copied.
+    Bytes(&'a [u8]),
+}
+
+/// Set the startup data v8 will be initialized with, this allows shipping the
+/// startup data separately from the binary and selecting it at runtime. Must be
+/// called before `v8_init`. Has no effect if v8 was built with the startup data
+/// embedded in it. Fails if the path contains a NUL byte or if the data is larger
+/// than `i32::MAX` bytes, the largest size v8 accepts.
+pub fn v8_init_external_startup_data(startup_data: V8StartupData) -> Result<(), String> {
+    match startup_data {
+        V8StartupData::File(path) => {
+            let path = CString::new(path)
+                .map_err(|_| "The startup data path contains a NUL byte".to_string())?;
+            unsafe { v8_InitializeExternalStartupDataFromFile(path.as_ptr()) };
+            Ok(())
+        }
+        V8StartupData::Bytes(data) => {
+            let res = unsafe {
+                v8_InitializeExternalStartupDataFromBytes(
+                    data.as_ptr().cast::<c_char>(),
+                    data.len(),
+                )
+            };
+            if res == 0 {
+                return Err(format!(
+                    "The startup data is larger than {} bytes",
+                    i32::MAX
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
 /// Destroy v8, after called it is not allowed to use any v8 API anymore.
 pub fn v8_destroy() {
     unsafe { v8_Dispose() }
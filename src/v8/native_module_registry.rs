@@ -0,0 +1,182 @@
+use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_error::V8ErrorKind;
+use crate::v8::v8_module::V8LocalModule;
+use crate::v8::v8_object::V8LocalObject;
+use crate::v8::v8_string::V8LocalString;
+use crate::v8::v8_value::V8LocalValue;
+
+use std::sync::Arc;
+
+type ModuleFactory =
+    Arc<dyn Fn(&V8Isolate, &V8ContextScope, &V8LocalObject) -> Option<V8LocalValue> + Send + Sync>;
+
+/// The name of the private value, on the context globals, that holds
+/// the exports of the modules that were already loaded on the context.
+const EXPORTS_CACHE_NAME: &str = "v8_rs::native_modules";
+
+/// A registry of named native modules, each module is created by a factory that
+/// returns the module exports. The modules can be loaded by CommonJS code using
+/// `require(name)` (see `install_require`) and by ES modules using `import`
+/// (see `load_module`). A module is created at most once per context and the
+/// same exports are shared by `require` and `import`. Like CommonJS, a module
+/// which is required while it is being created (a circular `require`) gets the
+/// exports object given to the factory, which might not be filled yet.
+#[derive(Default)]
+pub struct NativeModuleRegistry {
+    modules: Vec<(String, ModuleFactory)>,
+}
+
+impl NativeModuleRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a module factory with the given name. The factory is given an empty
+    /// exports object, which is what a circular `require` of the module returns, and
+    /// returns the module exports (usually the given object, once it is filled) or None
+    /// if it raised an exception. Registering a module with an existing name replaces
+    /// the previous one.
+    pub fn register_module<
+        T: Fn(&V8Isolate, &V8ContextScope, &V8LocalObject) -> Option<V8LocalValue>
+            + Send
+            + Sync
+            + 'static,
+    >(
+        &mut self,
+        name: &str,
+        factory: T,
+    ) -> &mut Self {
+        let factory: ModuleFactory = Arc::new(factory);
+        match self.modules.iter_mut().find(|(n, _)| n == name) {
+            Some((_, f)) => *f = factory,
+            None => self.modules.push((name.to_string(), factory)),
+        }
+        self
+    }
+
+    /// Return the exports of the module with the given name, the module is
+    /// created on the first call for each context. Return None if there is no
+    /// such module, or if the module factory failed in which case an exception
+    /// is raised.
+    #[must_use]
+    pub fn get_exports(&self, ctx_scope: &V8ContextScope, name: &str) -> Option<V8LocalValue> {
+        get_exports(&self.modules, ctx_scope, name)
+    }
+
+    /// Install a global `require` function on the given context which loads the
+    /// registered modules. Requiring an unknown module raises an error.
+    /// Return true on success.
+    pub fn install_require(&self, ctx_scope: &V8ContextScope) -> bool {
+        let modules = self.modules.clone();
+        ctx_scope.set_global_native_function("require", move |args, isolate, ctx_scope| {
+            let name = match args.iter().next() {
+                Some(name) if name.is_string() => name.to_utf8(isolate),
+                _ => None,
+            };
+            let name = match name {
+                Some(name) => name,
+                None => {
+                    let error = ctx_scope
+                        .new_error(&V8ErrorKind::TypeError, "The module name must be a string");
+                    isolate.raise_exception(error);
+                    return None;
+                }
+            };
+            require(&modules, isolate, ctx_scope, name.as_str())
+        })
+    }
+
+    /// Create an ES module, with the module exports as its default export,
+    /// for the registered module with the given name. Can be called from the
+    /// module loader given to `V8LocalModule::initialize`. Return None, with an
+    /// exception raised, if there is no such module or if the module factory failed.
+    #[must_use]
+    pub fn load_module(
+        &self,
+        ctx_scope: &V8ContextScope,
+        name: &V8LocalString,
+    ) -> Option<V8LocalModule> {
+        let isolate = ctx_scope.get_isolate();
+        let name_utf8 = match name.to_value().to_utf8(&isolate) {
+            Some(name_utf8) => name_utf8,
+            None => {
+                let error = ctx_scope
+                    .new_error(&V8ErrorKind::TypeError, "The module name must be a string");
+                isolate.raise_exception(error);
+                return None;
+            }
+        };
+        let exports = require(&self.modules, &isolate, ctx_scope, name_utf8.as_str())?;
+        Some(ctx_scope.new_synthetic_module(name, &exports))
+    }
+}
+
+/// Same as `get_exports` but raise an error if there is no such module.
+fn require(
+    modules: &[(String, ModuleFactory)],
+    isolate: &V8Isolate,
+    ctx_scope: &V8ContextScope,
+    name: &str,
+) -> Option<V8LocalValue> {
+    if !modules.iter().any(|(n, _)| n == name) {
+        let error = ctx_scope.new_error(
+            &V8ErrorKind::Error,
+            &format!("Cannot find module '{}'", name),
+        );
+        isolate.raise_exception(error);
+        return None;
+    }
+    get_exports(modules, ctx_scope, name)
+}
+
+fn get_exports(
+    modules: &[(String, ModuleFactory)],
+    ctx_scope: &V8ContextScope,
+    name: &str,
+) -> Option<V8LocalValue> {
+    let (_, factory) = modules.iter().find(|(n, _)| n == name)?;
+    let isolate = ctx_scope.get_isolate();
+    let globals = ctx_scope.get_globals();
+    let cache = match globals.get_private(ctx_scope, EXPORTS_CACHE_NAME) {
+        Some(cache) => cache.as_object(),
+        None => {
+            let cache = isolate.new_object();
+            globals.set_private(ctx_scope, EXPORTS_CACHE_NAME, &cache.to_value());
+            cache
+        }
+    };
+    let key = isolate.new_string(name).to_value();
+    if let Some(exports) = cache.get(ctx_scope, &key) {
+        if !exports.is_undefined() {
+            return Some(exports);
+        }
+    }
+    // Cache the exports object before creating the module, so a circular require
+    // of the module returns it instead of creating the module again.
+    let exports = isolate.new_object();
+    cache.set(ctx_scope, &key, &exports.to_value());
+    let trycatch = isolate.new_try_catch();
+    if let Some(exports) = factory(&isolate, ctx_scope, &exports) {
+        cache.set(ctx_scope, &key, &exports);
+        return Some(exports);
+    }
+    // A failed module is created again by the next require, like CommonJS.
+    let error = if trycatch.has_terminated() {
+        None
+    } else if trycatch.has_caught() {
+        Some(trycatch.get_exception())
+    } else {
+        Some(ctx_scope.new_error(
+            &V8ErrorKind::Error,
+            &format!("Module '{}' failed to load", name),
+        ))
+    };
+    drop(trycatch);
+    cache.set(ctx_scope, &key, &isolate.new_undefined());
+    if let Some(error) = error {
+        isolate.raise_exception(error);
+    }
+    None
+}
@@ -0,0 +1,166 @@
+//! Escape hatches for projects that also use the v8 C++ API, or need v8
+//! features that are not wrapped by this crate.
+//!
+//! The raw pointers returned by `as_raw` have the same bits as the matching
+//! v8 C++ handle and can be converted back on the C++ side, for example
+//! `v8::Local<v8::Value> val; memcpy(&val, &raw, sizeof(raw));`
+//! (`v8::Isolate*` for isolates). The C++ code must be built against the same
+//! v8 version this crate was built with.
+//!
+//! Safety contract:
+//! * A raw local handle is only valid while the handlers scope that was active
+//!   when the handle was created is alive, exactly like a `v8::Local` in C++.
+//!   Using it after the scope is gone is a use after free.
+//! * `from_raw` must be given a handle of the matching type (for example a
+//!   `v8::Local<v8::Object>` for `V8LocalObject::from_raw`) which belongs to the
+//!   isolate the returned wrapper is used with.
+//! * The returned wrapper must not outlive the handlers scope of the raw handle.
+//!   Dropping the wrapper does not release the handle, it is released with its scope.
+//! * `V8Isolate::from_raw` does not take ownership of the isolate, the isolate is
+//!   not disposed when the returned wrapper is dropped and must outlive it.
+//!
+//! Isolates can also be shared with other components of the process, which allows
+//! migrating C++ glue code to this crate gradually:
+//! * An isolate created by another component (using `v8::Isolate::New`) is attached
+//!   to this crate by the first `V8Isolate::from_raw`, which stores the crate data in
+//!   the isolate data slot 0, so the other component must not use this slot. Before the
+//!   owner disposes the isolate, the crate data must be released using
+//!   `V8Isolate::detach_raw`. Near OOM handlers can not be used on such isolates.
+//! * `V8Isolate::into_raw` hands an isolate created by this crate to another component,
+//!   the isolate is not disposed until it is given back using `V8Isolate::from_raw_owned`.
+
+use crate::v8_c_raw::bindings::{
+    v8_IsolateAttach, v8_IsolateDetach, v8_ObjectFromRaw, v8_ObjectToRaw, v8_StringFromRaw,
+    v8_StringToRaw, v8_ValueFromRaw, v8_ValueToRaw,
+};
+
+use std::mem;
+use std::os::raw::c_void;
+
+use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_object::V8LocalObject;
+use crate::v8::v8_string::V8LocalString;
+use crate::v8::v8_value::V8LocalValue;
+
+impl V8Isolate {
+    /// Return the underlying `v8::Isolate*`.
+    #[must_use]
+    pub fn as_raw(&self) -> *mut c_void {
+        self.inner_isolate.cast::<c_void>()
+    }
+
+    /// Wrap the given `v8::Isolate*`, the isolate is not disposed when the
+    /// returned wrapper is dropped. Isolates that were not created by this
+    /// crate are attached to it, see the module documentation.
+    /// # Safety
+    /// See the module documentation.
+    #[must_use]
+    pub unsafe fn from_raw(raw: *mut c_void) -> Self {
+        let isolate = Self {
+            inner_isolate: raw.cast(),
+            no_release: true,
+        };
+        if v8_IsolateAttach(raw) != 0 {
+            isolate.register_live();
+        }
+        isolate
+    }
+
+    /// Give up the ownership of the isolate and return the underlying `v8::Isolate*`.
+    /// The isolate is not disposed until it is given back using `from_raw_owned`.
+    /// Return None if the wrapper does not own the isolate (for example, it was
+    /// returned by `from_raw`), there is no ownership to give up in that case.
+    #[must_use]
+    pub fn into_raw(self) -> Option<*mut c_void> {
+        if self.no_release {
+            return None;
+        }
+        let raw = self.as_raw();
+        mem::forget(self);
+        Some(raw)
+    }
+
+    /// Take the ownership of an isolate returned by `into_raw`, the isolate
+    /// is disposed when the returned wrapper is dropped.
+    /// # Safety
+    /// The isolate must have been returned by `into_raw` and not already given back.
+    #[must_use]
+    pub unsafe fn from_raw_owned(raw: *mut c_void) -> Self {
+        Self {
+            inner_isolate: raw.cast(),
+            no_release: false,
+        }
+    }
+
+    /// Release the crate data of an isolate that was attached by `from_raw`, without
+    /// disposing it. Fails if there are still persisted handles or unlockers alive on it.
+    /// # Safety
+    /// The isolate must have been attached by `from_raw` and must not be used by this
+    /// crate afterwards, the isolate must not be entered while detaching.
+    pub unsafe fn detach_raw(raw: *mut c_void) -> Result<(), &'static str> {
+        let isolate = Self::from_raw(raw);
+        isolate.unregister_live()?;
+        v8_IsolateDetach(isolate.inner_isolate);
+        Ok(())
+    }
+}
+
+impl V8LocalValue {
+    /// Return the underlying `v8::Local<v8::Value>`.
+    /// # Safety
+    /// See the module documentation.
+    #[must_use]
+    pub unsafe fn as_raw(&self) -> *mut c_void {
+        v8_ValueToRaw(self.inner_val)
+    }
+
+    /// Wrap the given `v8::Local<v8::Value>`.
+    /// # Safety
+    /// See the module documentation.
+    #[must_use]
+    pub unsafe fn from_raw(raw: *mut c_void) -> Self {
+        Self {
+            inner_val: v8_ValueFromRaw(raw),
+        }
+    }
+}
+
+impl V8LocalObject {
+    /// Return the underlying `v8::Local<v8::Object>`.
+    /// # Safety
+    /// See the module documentation.
+    #[must_use]
+    pub unsafe fn as_raw(&self) -> *mut c_void {
+        v8_ObjectToRaw(self.inner_obj)
+    }
+
+    /// Wrap the given `v8::Local<v8::Object>`.
+    /// # Safety
+    /// See the module documentation.
+    #[must_use]
+    pub unsafe fn from_raw(raw: *mut c_void) -> Self {
+        Self {
+            inner_obj: v8_ObjectFromRaw(raw),
+        }
+    }
+}
+
+impl V8LocalString {
+    /// Return the underlying `v8::Local<v8::String>`.
+    /// # Safety
+    /// See the module documentation.
+    #[must_use]
+    pub unsafe fn as_raw(&self) -> *mut c_void {
+        v8_StringToRaw(self.inner_string)
+    }
+
+    /// Wrap the given `v8::Local<v8::String>`.
+    /// # Safety
+    /// See the module documentation.
+    #[must_use]
+    pub unsafe fn from_raw(raw: *mut c_void) -> Self {
+        Self {
+            inner_string: v8_StringFromRaw(raw),
+        }
+    }
+}
@@ -0,0 +1,616 @@
+use crate::v8::isolate::{IsolateId, V8Isolate};
+use crate::v8::v8_context::V8Context;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_promise::V8PromiseState;
+use crate::v8::v8_script::V8CompileOptions;
+use crate::v8::v8_value::V8LocalValue;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The maximum nesting of arrays and objects that can be converted into a `V8SandboxValue`.
+const MAX_VALUE_DEPTH: usize = 64;
+
+/// The time limit of `V8Sandbox::eval_expression` when the sandbox has no time limit.
+const DEFAULT_EXPRESSION_TIME_LIMIT: Duration = Duration::from_millis(100);
+
+/// Removes all the globals except the given names and deeply freezes the kept ones
+/// and the global object, the builtins it needs are captured before they are removed.
+const RESTRICT_GLOBALS: &str = r#"(() => {
+    const { freeze, isFrozen } = Object;
+    const { ownKeys, deleteProperty } = Reflect;
+    const deepFreeze = (val) => {
+        if (val === null || (typeof val !== 'object' && typeof val !== 'function') || isFrozen(val)) {
+            return;
+        }
+        freeze(val);
+        const keys = ownKeys(val);
+        for (let i = 0; i < keys.length; i++) {
+            deepFreeze(val[keys[i]]);
+        }
+    };
+    return (names) => {
+        const global = globalThis;
+        const keys = ownKeys(global);
+        for (let i = 0; i < keys.length; i++) {
+            if (names.indexOf(keys[i]) < 0) {
+                deleteProperty(global, keys[i]);
+            }
+        }
+        for (let i = 0; i < names.length; i++) {
+            deepFreeze(global[names[i]]);
+        }
+        freeze(global);
+    };
+})()"#;
+
+type ConsoleSink = Rc<dyn Fn(V8ConsoleLevel, &str)>;
+type ModuleLoader = Box<dyn Fn(&str) -> Option<String>>;
+type ErrorHandler = Box<dyn Fn(&str)>;
+type SetupCallback = Box<dyn FnOnce(&V8Isolate, &V8ContextScope)>;
+
+/// The level of a message written to the sandbox console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V8ConsoleLevel {
+    Log,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// A plain Rust representation of a JS value, used to pass values in and out
+/// of a `V8Sandbox` without dealing with handles and scopes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum V8SandboxValue {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<V8SandboxValue>),
+    /// An object, as a list of its enumerable properties names and values.
+    Object(Vec<(String, V8SandboxValue)>),
+}
+
+impl V8SandboxValue {
+    fn from_local(
+        isolate: &V8Isolate,
+        ctx_scope: &V8ContextScope,
+        val: &V8LocalValue,
+        depth: usize,
+    ) -> Result<Self, String> {
+        if depth > MAX_VALUE_DEPTH {
+            return Err("Value is too deeply nested or cyclic".to_string());
+        }
+        if val.is_undefined() {
+            Ok(Self::Undefined)
+        } else if val.is_null() {
+            Ok(Self::Null)
+        } else if val.is_boolean() {
+            Ok(Self::Bool(val.get_boolean()))
+        } else if val.is_number() {
+            Ok(Self::Number(val.get_number()))
+        } else if val.is_string() {
+            Ok(Self::String(to_string(isolate, val)?))
+        } else if val.is_array() {
+            let arr = val.as_array();
+            (0..arr.len())
+                .map(|i| Self::from_local(isolate, ctx_scope, &arr.get(ctx_scope, i), depth + 1))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Self::Array)
+        } else if val.is_function() || val.is_promise() {
+            Err("Functions and promises can not be converted into a sandbox value".to_string())
+        } else if val.is_object() {
+            let obj = val.as_object();
+            let names = obj.get_property_names(ctx_scope);
+            (0..names.len())
+                .map(|i| {
+                    let name = names.get(ctx_scope, i);
+                    let prop = obj
+                        .get(ctx_scope, &name)
+                        .ok_or_else(|| "Failed getting object property".to_string())?;
+                    Ok((
+                        to_string(isolate, &name)?,
+                        Self::from_local(isolate, ctx_scope, &prop, depth + 1)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, String>>()
+                .map(Self::Object)
+        } else {
+            Err("Value can not be converted into a sandbox value".to_string())
+        }
+    }
+
+    fn to_local(&self, isolate: &V8Isolate, ctx_scope: &V8ContextScope) -> V8LocalValue {
+        match self {
+            Self::Undefined => isolate.new_undefined(),
+            Self::Null => isolate.new_null(),
+            Self::Bool(b) => isolate.new_bool(*b),
+            Self::Number(n) => isolate.new_double(*n),
+            Self::String(s) => isolate.new_string(s).to_value(),
+            Self::Array(vals) => {
+                let vals = vals
+                    .iter()
+                    .map(|v| v.to_local(isolate, ctx_scope))
+                    .collect::<Vec<_>>();
+                isolate
+                    .new_array(&vals.iter().collect::<Vec<_>>())
+                    .to_value()
+            }
+            Self::Object(props) => {
+                let obj = isolate.new_object();
+                for (name, val) in props {
+                    obj.set(
+                        ctx_scope,
+                        &isolate.new_string(name).to_value(),
+                        &val.to_local(isolate, ctx_scope),
+                    );
+                }
+                obj.to_value()
+            }
+        }
+    }
+}
+
+fn to_string(isolate: &V8Isolate, val: &V8LocalValue) -> Result<String, String> {
+    val.to_utf8(isolate)
+        .map(|s| s.as_str().to_string())
+        .ok_or_else(|| "Failed converting value to string".to_string())
+}
+
+/// Builder for a `V8Sandbox`, see `V8Sandbox::builder`.
+pub struct V8SandboxBuilder {
+    max_heap_size: usize,
+    time_limit: Option<Duration>,
+    console: Option<ConsoleSink>,
+    error_handler: Option<ErrorHandler>,
+    module_loader: Option<ModuleLoader>,
+    setup: Option<SetupCallback>,
+}
+
+impl V8SandboxBuilder {
+    /// The maximum heap size of the sandbox isolate, 1G by default. Code that
+    /// reaches the limit is terminated instead of crashing the process.
+    #[must_use]
+    pub const fn max_heap_size(mut self, max_heap_size: usize) -> Self {
+        self.max_heap_size = max_heap_size;
+        self
+    }
+
+    /// The maximum time a single `eval`, `call_function` or `load_module`
+    /// invocation is allowed to run, unlimited by default.
+    #[must_use]
+    pub const fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Add a `console` object to the sandbox globals, messages written to
+    /// it are passed to the given sink.
+    #[must_use]
+    pub fn console<F: Fn(V8ConsoleLevel, &str) + 'static>(mut self, sink: F) -> Self {
+        self.console = Some(Rc::new(sink));
+        self
+    }
+
+    /// Set a handler for the errors that were not handled by the sandbox code,
+    /// like promises that were rejected without a rejection handler.
+    #[must_use]
+    pub fn error_handler<F: Fn(&str) + 'static>(mut self, handler: F) -> Self {
+        self.error_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Set the loader used to resolve the imports of the modules loaded with
+    /// `V8Sandbox::load_module`. The loader gets the import specifier and returns
+    /// the module code, or None if the module does not exist. Without a loader
+    /// all imports fail.
+    #[must_use]
+    pub fn module_loader<F: Fn(&str) -> Option<String> + 'static>(mut self, loader: F) -> Self {
+        self.module_loader = Some(Box::new(loader));
+        self
+    }
+
+    /// Set a callback that is called once the sandbox context is created,
+    /// can be used to add globals to the context.
+    #[must_use]
+    pub fn setup<F: FnOnce(&V8Isolate, &V8ContextScope) + 'static>(mut self, setup: F) -> Self {
+        self.setup = Some(Box::new(setup));
+        self
+    }
+
+    /// Create the sandbox.
+    #[must_use]
+    pub fn build(self) -> V8Sandbox {
+        let isolate = V8Isolate::new_with_limits(0, self.max_heap_size);
+        let out_of_memory = Rc::new(Cell::new(None));
+        let isolate_id = isolate.get_id();
+        let out_of_memory_clone = Rc::clone(&out_of_memory);
+        isolate.set_near_oom_callback(move |current_heap_limit, initial_heap_limit| {
+            out_of_memory_clone.set(Some(initial_heap_limit));
+            isolate_id.terminate_execution();
+            // Give the isolate room to unwind the terminated execution,
+            // the initial limit is restored once the execution returns.
+            current_heap_limit * 2
+        });
+
+        let ctx = {
+            let _h_scope = isolate.new_handlers_scope();
+            let i_scope = isolate.enter();
+            let ctx = i_scope.new_context(None);
+            let ctx_scope = ctx.enter();
+            if let Some(console) = self.console {
+                install_console(&isolate, &ctx_scope, console);
+            }
+            if let Some(error_handler) = self.error_handler {
+                ctx_scope.set_unhandled_exception_handler(move |isolate, _ctx_scope, error| {
+                    let msg = to_string(isolate, &error.error)
+                        .unwrap_or_else(|_| "Unknown error".to_string());
+                    error_handler(&msg);
+                });
+            }
+            if let Some(setup) = self.setup {
+                setup(&isolate, &ctx_scope);
+            }
+            ctx
+        };
+
+        V8Sandbox {
+            ctx,
+            isolate,
+            time_limit: self.time_limit,
+            module_loader: self.module_loader,
+            out_of_memory,
+            watchdog: Watchdog::new(isolate_id),
+        }
+    }
+}
+
+fn install_console(isolate: &V8Isolate, ctx_scope: &V8ContextScope, sink: ConsoleSink) {
+    let console = isolate.new_object();
+    for (name, level) in [
+        ("log", V8ConsoleLevel::Log),
+        ("debug", V8ConsoleLevel::Debug),
+        ("info", V8ConsoleLevel::Info),
+        ("warn", V8ConsoleLevel::Warn),
+        ("error", V8ConsoleLevel::Error),
+    ] {
+        let sink = Rc::clone(&sink);
+        let func = ctx_scope.new_native_function(move |args, isolate, _ctx_scope| {
+            let msg = args
+                .iter()
+                .map(|arg| to_string(isolate, &arg).unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join(" ");
+            sink(level, &msg);
+            None
+        });
+        console.set(
+            ctx_scope,
+            &isolate.new_string(name).to_value(),
+            &func.to_value(),
+        );
+    }
+    ctx_scope.get_globals().set(
+        ctx_scope,
+        &isolate.new_string("console").to_value(),
+        &console.to_value(),
+    );
+}
+
+/// A batteries included JS sandbox: an isolate with a single context, optional
+/// memory and time limits, a console and an error handler. All the methods take
+/// care of the isolate, handlers and context scopes and return plain Rust values.
+/// Use the lower level types directly when more control is needed.
+pub struct V8Sandbox {
+    // The context must be freed before the isolate.
+    ctx: V8Context,
+    isolate: V8Isolate,
+    time_limit: Option<Duration>,
+    module_loader: Option<ModuleLoader>,
+    /// The initial heap limit, set when the near heap limit callback terminates an execution.
+    out_of_memory: Rc<Cell<Option<usize>>>,
+    watchdog: Watchdog,
+}
+
+impl V8Sandbox {
+    /// Return a builder for a new sandbox.
+    #[must_use]
+    pub fn builder() -> V8SandboxBuilder {
+        V8SandboxBuilder {
+            max_heap_size: 1024 * 1024 * 1024,
+            time_limit: None,
+            console: None,
+            error_handler: None,
+            module_loader: None,
+            setup: None,
+        }
+    }
+
+    /// Return the sandbox isolate.
+    #[must_use]
+    pub const fn get_isolate(&self) -> &V8Isolate {
+        &self.isolate
+    }
+
+    /// Return the sandbox context.
+    #[must_use]
+    pub const fn get_context(&self) -> &V8Context {
+        &self.ctx
+    }
+
+    /// Run the given closure inside the sandbox context. The memory and
+    /// time limits are not applied to code invoked by the closure.
+    pub fn with_scope<R, F: FnOnce(&V8Isolate, &V8ContextScope) -> R>(&self, f: F) -> R {
+        let _h_scope = self.isolate.new_handlers_scope();
+        let _i_scope = self.isolate.enter();
+        let ctx_scope = self.ctx.enter();
+        f(&self.isolate, &ctx_scope)
+    }
+
+    /// Compile and run the given code and return its result. A returned promise
+    /// is awaited and its fulfillment value is returned.
+    pub fn eval(&self, code: &str) -> Result<V8SandboxValue, String> {
+        self.execute(|isolate, ctx_scope| {
+            let res = ctx_scope.eval(code)?;
+            Self::settle(isolate, ctx_scope, res)
+        })
+    }
+
+    /// Call the global function with the given name and return its result.
+    /// A returned promise is awaited and its fulfillment value is returned.
+    pub fn call_function(
+        &self,
+        name: &str,
+        args: &[V8SandboxValue],
+    ) -> Result<V8SandboxValue, String> {
+        self.execute(|isolate, ctx_scope| {
+            let func = ctx_scope
+                .get_globals()
+                .get(ctx_scope, &isolate.new_string(name).to_value())
+                .filter(V8LocalValue::is_function)
+                .ok_or_else(|| format!("Function '{}' does not exist", name))?;
+            let args = args
+                .iter()
+                .map(|arg| arg.to_local(isolate, ctx_scope))
+                .collect::<Vec<_>>();
+            let trycatch = isolate.new_try_catch();
+            let res = func
+                .call(ctx_scope, Some(&args.iter().collect::<Vec<_>>()))
+                .ok_or_else(|| V8ContextScope::eval_error(isolate, &trycatch))?;
+            Self::settle(isolate, ctx_scope, res)
+        })
+    }
+
+    /// Compile, initialize and evaluate the given code as a module, waiting for the module
+    /// top level await (if any) to finish, up to the sandbox time limit if it has one.
+    /// The module imports are resolved using the sandbox module loader.
+    pub fn load_module(&self, name: &str, code: &str) -> Result<(), String> {
+        self.execute(|isolate, ctx_scope| {
+            let trycatch = isolate.new_try_catch();
+            let module = ctx_scope
+                .compile_as_module(
+                    &isolate.new_string(name),
+                    &isolate.new_string(code),
+                    &V8CompileOptions::new(),
+                )
+                .ok_or_else(|| V8ContextScope::eval_error(isolate, &trycatch))?;
+            let initialized = module.initialize(ctx_scope, |isolate, ctx_scope, specifier, _| {
+                let specifier = to_string(isolate, &specifier.to_value()).ok()?;
+                let code = self.module_loader.as_ref().and_then(|l| l(&specifier));
+                if let Some(code) = code {
+                    ctx_scope.compile_as_module(
+                        &isolate.new_string(&specifier),
+                        &isolate.new_string(&code),
+                        &V8CompileOptions::new(),
+                    )
+                } else {
+                    isolate.raise_exception_str(&format!("Module '{}' was not found", specifier));
+                    None
+                }
+            });
+            if !initialized {
+                return Err(V8ContextScope::eval_error(isolate, &trycatch));
+            }
+            let promise = module
+                .evaluate_and_wait(ctx_scope, self.time_limit.unwrap_or(Duration::MAX))
+                .ok_or_else(|| V8ContextScope::eval_error(isolate, &trycatch))?;
+            Self::settle(isolate, ctx_scope, promise.to_value()).map(|_| ())
+        })
+    }
+
+    /// Evaluate an untrusted expression, such as a user supplied config value
+    /// (`limits.max * 2`), hermetically: the expression runs in strict mode on a new
+    /// context whose only globals are the given (deeply frozen) scope values, with
+    /// `eval` and the `Function` constructor disabled, under the sandbox limits.
+    /// Without a sandbox time limit, the expression is given 100 milliseconds.
+    /// Code which is not a single expression is rejected with a `SyntaxError`.
+    pub fn eval_expression(
+        &self,
+        expression: &str,
+        scope: &[(&str, V8SandboxValue)],
+    ) -> Result<V8SandboxValue, String> {
+        let ctx = {
+            let _h_scope = self.isolate.new_handlers_scope();
+            let i_scope = self.isolate.enter();
+            i_scope.new_context(None)
+        };
+        let time_limit = self.time_limit.unwrap_or(DEFAULT_EXPRESSION_TIME_LIMIT);
+        self.execute_in(&ctx, Some(time_limit), |isolate, ctx_scope| {
+            let globals = ctx_scope.get_globals();
+            for (name, val) in scope {
+                globals.set(
+                    ctx_scope,
+                    &isolate.new_string(name).to_value(),
+                    &val.to_local(isolate, ctx_scope),
+                );
+            }
+            let names = scope
+                .iter()
+                .map(|(name, _)| isolate.new_string(name).to_value())
+                .collect::<Vec<_>>();
+            let names = isolate.new_array(&names.iter().collect::<Vec<_>>());
+            let trycatch = isolate.new_try_catch();
+            ctx_scope
+                .eval(RESTRICT_GLOBALS)?
+                .call(ctx_scope, Some(&[&names.to_value()]))
+                .ok_or_else(|| V8ContextScope::eval_error(isolate, &trycatch))?;
+            ctx_scope.set_allow_code_generation_from_strings(false);
+
+            // The expression is compiled as a function body, which it can not escape, and
+            // must also compile inside an array literal, where closing the parentheses to
+            // add statements is a syntax error. Parentheses keep object literals from being
+            // parsed as blocks.
+            let name = isolate.new_string("expression");
+            let compile =
+                |code: String| ctx_scope.compile_function(&name, &isolate.new_string(&code), &[]);
+            let res = compile(format!("'use strict'; return [(\n{}\n)];", expression))
+                .and_then(|_| compile(format!("'use strict'; return (\n{}\n);", expression)))
+                .and_then(|func| func.call(ctx_scope, None))
+                .ok_or_else(|| V8ContextScope::eval_error(isolate, &trycatch))?;
+            V8SandboxValue::from_local(isolate, ctx_scope, &res, 0)
+        })
+    }
+
+    /// Run the microtasks if the given value is a promise and return its
+    /// fulfillment value, or the value itself if it is not a promise.
+    fn settle(
+        isolate: &V8Isolate,
+        ctx_scope: &V8ContextScope,
+        val: V8LocalValue,
+    ) -> Result<V8SandboxValue, String> {
+        if !val.is_promise() {
+            return V8SandboxValue::from_local(isolate, ctx_scope, &val, 0);
+        }
+        let promise = val.as_promise();
+        if promise.state() == V8PromiseState::Pending {
+            isolate.perform_microtask_checkpoint();
+        }
+        match promise.state() {
+            V8PromiseState::Fulfilled => {
+                V8SandboxValue::from_local(isolate, ctx_scope, &promise.get_result(), 0)
+            }
+            V8PromiseState::Rejected => Err(to_string(isolate, &promise.get_result())?),
+            V8PromiseState::Pending | V8PromiseState::Unknown => {
+                Err("Promise was not settled".to_string())
+            }
+        }
+    }
+
+    /// Run the given closure inside the sandbox context while enforcing the sandbox limits.
+    fn execute<R, F: FnOnce(&V8Isolate, &V8ContextScope) -> Result<R, String>>(
+        &self,
+        f: F,
+    ) -> Result<R, String> {
+        self.execute_in(&self.ctx, self.time_limit, f)
+    }
+
+    /// Same as `execute` but runs the closure inside the given context with the given time limit.
+    fn execute_in<R, F: FnOnce(&V8Isolate, &V8ContextScope) -> Result<R, String>>(
+        &self,
+        ctx: &V8Context,
+        time_limit: Option<Duration>,
+        f: F,
+    ) -> Result<R, String> {
+        let isolate = &self.isolate;
+        let _h_scope = isolate.new_handlers_scope();
+        let _i_scope = isolate.enter();
+        let ctx_scope = ctx.enter();
+        let (res, timed_out) = match time_limit {
+            Some(time_limit) => self.watchdog.run(time_limit, || f(isolate, &ctx_scope)),
+            None => (f(isolate, &ctx_scope), false),
+        };
+        let out_of_memory = self.out_of_memory.take();
+        if timed_out || out_of_memory.is_some() {
+            isolate.cancel_terminate_execution();
+        }
+        if let Some(initial_heap_limit) = out_of_memory {
+            // The near heap limit callback raised the limit, lower it back so
+            // every runaway execution does not grow the heap further.
+            isolate.restore_heap_limit(initial_heap_limit);
+        }
+        if out_of_memory.is_some() {
+            Err("Out of memory".to_string())
+        } else if timed_out {
+            Err("Execution timed out".to_string())
+        } else {
+            res
+        }
+    }
+}
+
+#[derive(Default)]
+struct WatchdogState {
+    /// The deadline of the execution which is currently watched, if any.
+    deadline: Option<Instant>,
+    timed_out: bool,
+    shutdown: bool,
+}
+
+/// Terminates the sandbox executions that do not return in time. A single
+/// thread watches all the executions of the sandbox.
+struct Watchdog {
+    state: Arc<(Mutex<WatchdogState>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    fn new(isolate_id: IsolateId) -> Self {
+        let state = Arc::new((Mutex::new(WatchdogState::default()), Condvar::new()));
+        let thread_state = Arc::clone(&state);
+        let thread = thread::spawn(move || {
+            let (state, cond) = &*thread_state;
+            let mut state = state.lock().unwrap();
+            while !state.shutdown {
+                state = match state.deadline {
+                    Some(deadline) if deadline <= Instant::now() => {
+                        state.deadline = None;
+                        state.timed_out = true;
+                        isolate_id.terminate_execution();
+                        state
+                    }
+                    Some(deadline) => {
+                        let timeout = deadline.saturating_duration_since(Instant::now());
+                        cond.wait_timeout(state, timeout).unwrap().0
+                    }
+                    None => cond.wait(state).unwrap(),
+                };
+            }
+        });
+        Self {
+            state,
+            thread: Some(thread),
+        }
+    }
+
+    /// Run the given closure and terminate the isolate execution if it does not return
+    /// in the given time. Return the closure result and whether or not the time passed.
+    fn run<R, F: FnOnce() -> R>(&self, time_limit: Duration, f: F) -> (R, bool) {
+        let (state, cond) = &*self.state;
+        {
+            let mut state = state.lock().unwrap();
+            state.deadline = Instant::now().checked_add(time_limit);
+            state.timed_out = false;
+        }
+        cond.notify_one();
+        let res = f();
+        let mut state = state.lock().unwrap();
+        state.deadline = None;
+        (res, state.timed_out)
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        let (state, cond) = &*self.state;
+        state.lock().unwrap().shutdown = true;
+        cond.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
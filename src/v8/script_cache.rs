@@ -0,0 +1,151 @@
+use crate::v8::isolate::{IsolateId, V8Isolate, V8MemoryPressureLevel};
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_script::{V8LocalScript, V8PersistedUnboundScript};
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+struct ScriptCacheEntry {
+    code: String,
+    script: V8PersistedUnboundScript,
+    last_used: u64,
+}
+
+/// The cached scripts, shared with the isolate registry so the cache can be
+/// shrunk on memory pressure.
+#[derive(Default)]
+pub(crate) struct ScriptCacheEntries {
+    capacity: usize,
+    entries: HashMap<u64, ScriptCacheEntry>,
+    /// The keys of the entries by the order they were last used.
+    lru: BTreeMap<u64, u64>,
+    clock: u64,
+}
+
+impl ScriptCacheEntries {
+    fn touch(&mut self, key: u64) {
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.lru.remove(&entry.last_used);
+            entry.last_used = self.clock;
+            self.lru.insert(self.clock, key);
+        }
+    }
+
+    fn shrink_to(&mut self, len: usize) {
+        while self.entries.len() > len {
+            let (&last_used, &key) = self.lru.iter().next().unwrap();
+            self.lru.remove(&last_used);
+            self.entries.remove(&key);
+        }
+    }
+
+    /// Release cached scripts according to the memory pressure level, half of
+    /// the scripts are released on moderate pressure and all of them on critical.
+    pub(crate) fn on_memory_pressure(&mut self, level: V8MemoryPressureLevel) {
+        match level {
+            V8MemoryPressureLevel::None => {}
+            V8MemoryPressureLevel::Moderate => self.shrink_to(self.entries.len() / 2),
+            V8MemoryPressureLevel::Critical => self.shrink_to(0),
+        }
+    }
+}
+
+/// A per isolate LRU cache of compiled scripts, keyed by the script source.
+/// Compiling a cached source only binds the already compiled script to the
+/// current context. The cache is shrunk when the isolate is notified about
+/// memory pressure (see `V8Isolate::memory_pressure_notification` and
+/// `V8Isolate::low_memory_notification`).
+///
+/// The cached scripts are persisted handles, so the cache must be dropped
+/// before the isolate can be disposed.
+pub struct V8ScriptCache {
+    isolate_id: IsolateId,
+    entries: Arc<Mutex<ScriptCacheEntries>>,
+}
+
+impl V8ScriptCache {
+    /// Create a new cache on the given isolate that holds up to `capacity` scripts.
+    #[must_use]
+    pub fn new(isolate: &V8Isolate, capacity: usize) -> Self {
+        let entries = Arc::new(Mutex::new(ScriptCacheEntries {
+            capacity,
+            ..ScriptCacheEntries::default()
+        }));
+        isolate.register_script_cache(&entries);
+        Self {
+            isolate_id: isolate.get_id(),
+            entries,
+        }
+    }
+
+    /// Return the compiled script of the given code, bound to the context of the
+    /// given scope. The code is compiled only if it is not already cached, in which
+    /// case the least recently used script is evicted if the cache is full.
+    /// Return None if the compilation failed, failures are not cached. Like
+    /// `V8ContextScope::eval`, the code runs in sloppy mode.
+    #[must_use]
+    pub fn compile(&self, ctx_scope: &V8ContextScope, code: &str) -> Option<V8LocalScript> {
+        let isolate = ctx_scope.get_isolate();
+        assert_eq!(
+            isolate.get_id(),
+            self.isolate_id,
+            "Script cache belongs to another isolate"
+        );
+        let mut hasher = DefaultHasher::new();
+        code.hash(&mut hasher);
+        let key = hasher.finish();
+
+        {
+            let mut entries = self.entries.lock().unwrap();
+            if let Some(entry) = entries.entries.get(&key) {
+                if entry.code == code {
+                    let script = entry.script.bind(ctx_scope);
+                    entries.touch(key);
+                    return Some(script);
+                }
+            }
+        }
+        // The lock is not held while compiling, the compilation might call back
+        // into the embedder.
+        let script = ctx_scope.compile(&isolate.new_string(code))?;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.capacity == 0 {
+            return Some(script);
+        }
+        if let Some(entry) = entries.entries.remove(&key) {
+            // A hash collision, replace the existing entry.
+            entries.lru.remove(&entry.last_used);
+        }
+        let capacity = entries.capacity;
+        entries.shrink_to(capacity - 1);
+        entries.entries.insert(
+            key,
+            ScriptCacheEntry {
+                code: code.to_string(),
+                script: script.to_unbound(&isolate),
+                last_used: 0,
+            },
+        );
+        entries.touch(key);
+        Some(script)
+    }
+
+    /// Return the number of cached scripts.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Release all the cached scripts.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().shrink_to(0);
+    }
+}
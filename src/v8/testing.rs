@@ -0,0 +1,77 @@
+//! Helpers for writing unit tests against v8-rs, enabled by the `testing` feature:
+//!
+//! ```ignore
+//! use v8_rs::v8::testing::{assert_eval, with_context};
+//!
+//! #[test]
+//! fn test_sum() {
+//!     with_context(|_isolate, ctx_scope| assert_eval(ctx_scope, "1 + 1", "2"));
+//! }
+//! ```
+
+use crate::v8::isolate::V8Isolate;
+use crate::v8::isolate_scope::V8IsolateScope;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_init;
+
+use std::sync::Once;
+
+static INIT: Once = Once::new();
+
+/// Initialize v8 once per process, can be called by any number of tests concurrently.
+pub fn initialize() {
+    INIT.call_once(v8_init);
+}
+
+/// Run the given function with a new isolate which is entered and has an open handlers scope.
+pub fn with_isolate<R, F: FnOnce(&V8Isolate, &V8IsolateScope) -> R>(f: F) -> R {
+    initialize();
+    let isolate = V8Isolate::new();
+    let i_scope = isolate.enter();
+    let _h_scope = isolate.new_handlers_scope();
+    f(&isolate, &i_scope)
+}
+
+/// Run the given function with a new context (on a new isolate) which is entered.
+pub fn with_context<R, F: FnOnce(&V8Isolate, &V8ContextScope) -> R>(f: F) -> R {
+    with_isolate(|isolate, i_scope| {
+        let ctx = i_scope.new_context(None);
+        let ctx_scope = ctx.enter();
+        f(isolate, &ctx_scope)
+    })
+}
+
+/// Evaluate the code and assert that the string representation of its result is `expected`.
+/// # Panics
+/// If the evaluation failed or the result is different than expected.
+#[track_caller]
+pub fn assert_eval(ctx_scope: &V8ContextScope, code: &str, expected: &str) {
+    let res = match ctx_scope.eval(code) {
+        Ok(res) => res,
+        Err(e) => panic!("Evaluating `{}` failed: {}", code, e),
+    };
+    let res = res.to_utf8(&ctx_scope.get_isolate());
+    assert_eq!(
+        res.as_ref().map(|r| r.as_str()),
+        Some(expected),
+        "Evaluating `{}` gave an unexpected result",
+        code
+    );
+}
+
+/// Evaluate the code and assert that it raised an error which contains `expected_message`.
+/// # Panics
+/// If the evaluation succeeded or failed with a different error.
+#[track_caller]
+pub fn assert_eval_throws(ctx_scope: &V8ContextScope, code: &str, expected_message: &str) {
+    match ctx_scope.eval(code) {
+        Ok(_) => panic!("Evaluating `{}` was expected to fail", code),
+        Err(e) => assert!(
+            e.to_string().contains(expected_message),
+            "Evaluating `{}` failed with `{}`, expected `{}`",
+            code,
+            e,
+            expected_message
+        ),
+    }
+}
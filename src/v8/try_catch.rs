@@ -1,5 +1,6 @@
 use crate::v8_c_raw::bindings::{
-    v8_FreeTryCatch, v8_TryCatchGetException, v8_TryCatchHasTerminated, v8_trycatch,
+    v8_FreeTryCatch, v8_TryCatchGetException, v8_TryCatchHasCaught, v8_TryCatchHasTerminated,
+    v8_trycatch,
 };
 
 use crate::v8::v8_value::V8LocalValue;
@@ -18,6 +19,14 @@ impl V8TryCatch {
         V8LocalValue { inner_val }
     }
 
+    /// Return true if an exception was raised, `get_exception` must only
+    /// be called if an exception was raised.
+    #[must_use]
+    pub fn has_caught(&self) -> bool {
+        let res = unsafe { v8_TryCatchHasCaught(self.inner_trycatch) };
+        res > 0
+    }
+
     #[must_use]
     pub fn has_terminated(&self) -> bool {
         let res = unsafe { v8_TryCatchHasTerminated(self.inner_trycatch) };
@@ -1,10 +1,14 @@
 use crate::v8_c_raw::bindings::{
-    v8_ArrayGet, v8_ArrayLen, v8_ArrayToValue, v8_FreeArray, v8_local_array,
+    v8_ArrayGet, v8_ArrayLen, v8_ArrayToDoubles, v8_ArrayToStrings, v8_ArrayToValue, v8_FreeArray,
+    v8_local_array,
 };
 
 use crate::v8::v8_context_scope::V8ContextScope;
 use crate::v8::v8_value::V8LocalValue;
 
+use std::os::raw::{c_char, c_void};
+use std::slice;
+
 /// JS object
 pub struct V8LocalArray {
     pub(crate) inner_array: *mut v8_local_array,
@@ -20,12 +24,91 @@ impl V8LocalArray {
         V8LocalValue { inner_val }
     }
 
+    /// Copy the array elements into a vector of numbers using a single call into v8,
+    /// without creating a `V8LocalValue` per element. Each element is still read with
+    /// a regular property get, so getters run. Return None if any of the elements is
+    /// not a number or reading it raised an exception.
+    #[must_use]
+    pub fn to_f64_vec(&self, ctx_scope: &V8ContextScope) -> Option<Vec<f64>> {
+        let mut res = vec![0.0; self.len()];
+        let ok = unsafe {
+            v8_ArrayToDoubles(ctx_scope.inner_ctx_ref, self.inner_array, res.as_mut_ptr())
+        };
+        if ok != 0 {
+            Some(res)
+        } else {
+            None
+        }
+    }
+
+    /// Copy the array elements into a vector of strings using a single call into v8,
+    /// without creating a `V8LocalValue` per element. Each element is still read with
+    /// a regular property get, so getters run. On failure, the error includes the index
+    /// of the first element that is not a string, for example: "index 2: Value is not a string",
+    /// or the exception raised while reading it, which is not left pending.
+    pub fn to_string_vec(&self, ctx_scope: &V8ContextScope) -> Result<Vec<String>, String> {
+        let isolate = ctx_scope.get_isolate();
+        let trycatch = isolate.new_try_catch();
+        let mut res: Vec<String> = Vec::with_capacity(self.len());
+        let failed = unsafe {
+            v8_ArrayToStrings(
+                ctx_scope.inner_ctx_ref,
+                self.inner_array,
+                Some(array_string_callback),
+                (&mut res as *mut Vec<String>).cast::<c_void>(),
+            )
+        };
+        if failed < 0 {
+            Ok(res)
+        } else if trycatch.has_caught() || trycatch.has_terminated() {
+            Err(format!(
+                "index {}: {}",
+                failed,
+                V8ContextScope::eval_error(&isolate, &trycatch)
+            ))
+        } else {
+            Err(format!("index {}: Value is not a string", failed))
+        }
+    }
+
+    /// Convert the array elements into a vector of `T`. On failure, the error
+    /// includes the index of the element that failed to convert, for example:
+    /// "index 2: Value is not a number".
+    pub fn try_to_vec<T>(&self, ctx_scope: &V8ContextScope) -> Result<Vec<T>, String>
+    where
+        T: for<'v> TryFrom<&'v V8LocalValue, Error = String>,
+    {
+        self.try_to_vec_with(ctx_scope, |val| T::try_from(val))
+    }
+
+    /// Same as `try_to_vec` but uses the given function to convert the elements,
+    /// useful for elements that are themselves objects or arrays.
+    pub fn try_to_vec_with<T, F>(
+        &self,
+        ctx_scope: &V8ContextScope,
+        mut f: F,
+    ) -> Result<Vec<T>, String>
+    where
+        F: FnMut(&V8LocalValue) -> Result<T, String>,
+    {
+        (0..self.len())
+            .map(|i| f(&self.get(ctx_scope, i)).map_err(|e| format!("index {}: {}", i, e)))
+            .collect()
+    }
+
     pub fn to_value(&self) -> V8LocalValue {
         let inner_val = unsafe { v8_ArrayToValue(self.inner_array) };
         V8LocalValue { inner_val }
     }
 }
 
+extern "C" fn array_string_callback(str: *const c_char, len: usize, pd: *mut c_void) {
+    let res = unsafe { &mut *pd.cast::<Vec<String>>() };
+    let bytes = unsafe { slice::from_raw_parts(str.cast::<u8>(), len) };
+    // V8 replaces invalid UTF-8 sequences, so the conversion does not fail in practice.
+    res.push(String::from_utf8_lossy(bytes).into_owned());
+}
+
 impl Drop for V8LocalArray {
     fn drop(&mut self) {
         unsafe { v8_FreeArray(self.inner_array) }
@@ -1,19 +1,76 @@
 use crate::v8_c_raw::bindings::{
-    v8_ArrayBufferGetData, v8_ArrayBufferToValue, v8_FreeArrayBuffer, v8_local_array_buff,
+    v8_ArrayBufferDetach, v8_ArrayBufferGetBackingStore, v8_ArrayBufferGetData,
+    v8_ArrayBufferToValue, v8_BackingStoreGetData, v8_FreeArrayBuffer, v8_FreeBackingStore,
+    v8_NewUint8Array, v8_backing_store, v8_local_array_buff,
 };
 
+use crate::v8::isolate::{IsolateId, V8Isolate};
+
 use crate::v8::v8_value::V8LocalValue;
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::sync::atomic::{AtomicU32, AtomicU8};
+
+/// A custom allocator for the memory of the array buffers of an isolate
+/// (see `V8Isolate::new_with_allocator`), can be used to track or limit
+/// the memory used by JS array buffers or to route it to a custom memory pool.
+/// The allocator might be called from any thread.
+pub trait V8ArrayBufferAllocator: Send + Sync {
+    /// Allocate `len` bytes, the memory must be zero initialized if `zero_initialize`
+    /// is true. Return null on failure (which will be reported to JS as a `RangeError`).
+    fn allocate(&self, len: usize, zero_initialize: bool) -> *mut u8;
+
+    /// Free memory of the given length that was allocated using `allocate`.
+    fn free(&self, data: *mut u8, len: usize);
+}
+
+pub(crate) extern "C" fn array_buffer_allocate<T: V8ArrayBufferAllocator>(
+    len: usize,
+    zero_initialize: c_int,
+    pd: *mut c_void,
+) -> *mut c_void {
+    let allocator = unsafe { &*pd.cast::<T>() };
+    allocator
+        .allocate(len, zero_initialize != 0)
+        .cast::<c_void>()
+}
+
+pub(crate) extern "C" fn array_buffer_free<T: V8ArrayBufferAllocator>(
+    data: *mut c_void,
+    len: usize,
+    pd: *mut c_void,
+) {
+    let allocator = unsafe { &*pd.cast::<T>() };
+    allocator.free(data.cast::<u8>(), len);
+}
+
+pub(crate) extern "C" fn array_buffer_allocator_free_pd<T: V8ArrayBufferAllocator>(
+    pd: *mut c_void,
+) {
+    unsafe { drop(Box::from_raw(pd.cast::<T>())) }
+}
 
 /// JS object
 pub struct V8LocalArrayBuffer {
     pub(crate) inner_array_buffer: *mut v8_local_array_buff,
 }
 
+/// The memory of an array buffer. The memory stays valid as long as the
+/// backing store is alive, even if the array buffer was detached.
+pub struct V8BackingStore {
+    pub(crate) inner_backing_store: *mut v8_backing_store,
+    pub(crate) isolate_id: IsolateId,
+}
+
 impl V8LocalArrayBuffer {
     pub fn data(&self) -> &[u8] {
         let mut size = 0;
         let data =
             unsafe { v8_ArrayBufferGetData(self.inner_array_buffer, &mut size as *mut usize) };
+        if size == 0 {
+            // a detached array buffer has no data pointer
+            return &[];
+        }
         unsafe { std::slice::from_raw_parts(data.cast::<u8>(), size) }
     }
 
@@ -21,10 +78,116 @@ impl V8LocalArrayBuffer {
         let inner_val = unsafe { v8_ArrayBufferToValue(self.inner_array_buffer) };
         V8LocalValue { inner_val }
     }
+
+    /// Return a `Uint8Array` view of the whole array buffer.
+    #[must_use]
+    pub fn to_uint8_array(&self) -> V8LocalValue {
+        let len = self.data().len();
+        let inner_val = unsafe { v8_NewUint8Array(self.inner_array_buffer, 0, len) };
+        V8LocalValue { inner_val }
+    }
+
+    /// Detach the array buffer from its memory, after detached the array
+    /// buffer is empty. Return false if the array buffer is not detachable.
+    pub fn detach(&self) -> bool {
+        unsafe { v8_ArrayBufferDetach(self.inner_array_buffer) != 0 }
+    }
+
+    /// Return the backing store of the array buffer.
+    #[must_use]
+    pub fn get_backing_store(&self, isolate: &V8Isolate) -> V8BackingStore {
+        let inner_backing_store = unsafe { v8_ArrayBufferGetBackingStore(self.inner_array_buffer) };
+        V8BackingStore::new(inner_backing_store, isolate)
+    }
+
+    /// Move the memory of the array buffer out of JS without copying it.
+    /// Return the backing store and detach the array buffer so it can no
+    /// longer be accessed from JS. Return None if the array buffer is not detachable.
+    #[must_use]
+    pub fn transfer(&self, isolate: &V8Isolate) -> Option<V8BackingStore> {
+        let backing_store = self.get_backing_store(isolate);
+        if self.detach() {
+            Some(backing_store)
+        } else {
+            None
+        }
+    }
+}
+
+impl V8BackingStore {
+    pub(crate) fn new(inner_backing_store: *mut v8_backing_store, isolate: &V8Isolate) -> Self {
+        let isolate_id = isolate.get_id();
+        isolate_id.persisted_handle_created();
+        Self {
+            inner_backing_store,
+            isolate_id,
+        }
+    }
+
+    fn raw_parts(&self) -> (*const u8, usize) {
+        let mut size = 0;
+        let data =
+            unsafe { v8_BackingStoreGetData(self.inner_backing_store, &mut size as *mut usize) };
+        (data.cast::<u8>(), size)
+    }
+
+    /// Return the data of the backing store.
+    /// Should not be used on the backing store of a shared array buffer
+    /// which might be modified by JS at the same time, use `as_atomics` instead.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        let (data, size) = self.raw_parts();
+        if size == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(data, size) }
+    }
+
+    /// Return a view of the data as atomic bytes. The view can be safely read
+    /// and written while JS accesses the same memory (for example, through a
+    /// shared array buffer).
+    #[must_use]
+    pub fn as_atomics(&self) -> &[AtomicU8] {
+        let (data, size) = self.raw_parts();
+        if size == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(data.cast::<AtomicU8>(), size) }
+    }
+
+    /// Same as `as_atomics` but returns a view of 32 bit integers, compatible
+    /// with the JS `Atomics` operations on an `Int32Array`/`Uint32Array`. Trailing
+    /// bytes that do not fill an entire integer are not part of the view.
+    /// Return None if the data is not aligned to 4 bytes.
+    #[must_use]
+    pub fn as_atomics_u32(&self) -> Option<&[AtomicU32]> {
+        let (data, size) = self.raw_parts();
+        if size < mem::size_of::<AtomicU32>() {
+            return Some(&[]);
+        }
+        if data.align_offset(mem::align_of::<AtomicU32>()) != 0 {
+            return None;
+        }
+        Some(unsafe {
+            std::slice::from_raw_parts(data.cast::<AtomicU32>(), size / mem::size_of::<AtomicU32>())
+        })
+    }
 }
 
+unsafe impl Sync for V8BackingStore {}
+unsafe impl Send for V8BackingStore {}
+
 impl Drop for V8LocalArrayBuffer {
     fn drop(&mut self) {
         unsafe { v8_FreeArrayBuffer(self.inner_array_buffer) }
     }
 }
+
+impl Drop for V8BackingStore {
+    fn drop(&mut self) {
+        // The backing store memory is released using the isolate allocator.
+        if self.isolate_id.persisted_handle_freed() {
+            unsafe { v8_FreeBackingStore(self.inner_backing_store) }
+        }
+    }
+}
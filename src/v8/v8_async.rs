@@ -0,0 +1,91 @@
+use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_builder::V8BuilderValue;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_error::V8Throwable;
+use crate::v8::v8_native_function::V8LocalNativeFunction;
+use crate::v8::v8_native_function_template::V8LocalNativeFunctionArgs;
+use crate::v8::v8_value::V8PersistValue;
+
+use std::future::Future;
+use std::pin::Pin;
+
+/// A future handed to a `V8Spawner`.
+pub type V8SpawnedFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Runs the futures of async native functions, usually by spawning them on the
+/// embedder async runtime. The futures never touch the isolate, their output is
+/// posted back to the isolate using `V8Isolate::post_task`, so the promises
+/// returned by async native functions only settle while the isolate message
+/// loop is pumped (see `V8Isolate::pump_message_loop`).
+pub trait V8Spawner {
+    fn spawn(&self, fut: V8SpawnedFuture);
+}
+
+impl<T: Fn(V8SpawnedFuture)> V8Spawner for T {
+    fn spawn(&self, fut: V8SpawnedFuture) {
+        self(fut);
+    }
+}
+
+impl V8ContextScope {
+    /// Create a native function that returns a promise which settles with the output
+    /// of the future returned by `func`: resolved with the value on `Ok` and rejected
+    /// with a JS error created from the `V8Throwable` on `Err`. `func` itself runs on
+    /// the isolate thread, it should take what it needs from the arguments and return
+    /// a future which owns that data. If the isolate is freed before the future
+    /// completes, the output is dropped.
+    #[must_use]
+    pub fn new_async_native_function<S, F, Fut, T, E>(
+        &self,
+        spawner: S,
+        func: F,
+    ) -> V8LocalNativeFunction
+    where
+        S: V8Spawner,
+        F: Fn(&V8LocalNativeFunctionArgs, &V8Isolate, &Self) -> Fut,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        T: Into<V8BuilderValue<'static>> + Send + 'static,
+        E: V8Throwable + Send + 'static,
+    {
+        self.new_native_function(move |args, isolate, ctx_scope| {
+            let resolver = ctx_scope.new_resolver();
+            let promise = resolver.get_promise();
+            let persisted_resolver = resolver.to_value().persist(isolate);
+            let fut = func(args, isolate, ctx_scope);
+            let isolate_id = isolate.get_id();
+            spawner.spawn(Box::pin(async move {
+                let res = fut.await;
+                isolate_id.post_task(move |isolate| settle(isolate, &persisted_resolver, res));
+            }));
+            Some(promise.to_value())
+        })
+    }
+}
+
+fn settle<T: Into<V8BuilderValue<'static>>, E: V8Throwable>(
+    isolate: &V8Isolate,
+    resolver: &V8PersistValue,
+    res: Result<T, E>,
+) {
+    let _h_scope = isolate.new_handlers_scope();
+    let resolver = resolver.as_local(isolate);
+    let object = resolver.as_object();
+    let resolver = resolver.as_resolver();
+    // The task runs outside of any context, settle the promise on the context it was created in.
+    let ctx_scope = match object.enter_creation_context() {
+        Some(ctx_scope) => ctx_scope,
+        None => return,
+    };
+    match res {
+        Ok(val) => {
+            let val = val.into().build(isolate, &ctx_scope);
+            resolver.resolve(&ctx_scope, val.as_value());
+        }
+        Err(e) => {
+            let error = ctx_scope.new_error(&e.error_kind(), &e.error_message());
+            resolver.reject(&ctx_scope, &error);
+        }
+    }
+    drop(ctx_scope);
+    isolate.perform_microtask_checkpoint();
+}
@@ -0,0 +1,205 @@
+use crate::v8_c_raw::bindings::{v8_SymbolAsyncIterator, v8_ValueGetAsyncIterator};
+
+use crate::v8::isolate::V8Isolate;
+use crate::v8::try_catch::V8TryCatch;
+use crate::v8::v8_builder::V8ObjectBuilder;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_error::V8ErrorKind;
+use crate::v8::v8_object::{V8LocalObject, V8PropertyAttributes};
+use crate::v8::v8_promise::V8LocalPromise;
+use crate::v8::v8_value::{V8LocalValue, V8PersistValue};
+
+use std::rc::Rc;
+
+/// Make the object an async iterable whose `Symbol.asyncIterator` method returns
+/// the object itself, the object must implement `next()`.
+pub(crate) fn set_async_iterable(ctx_scope: &V8ContextScope, obj: &V8LocalObject) {
+    let isolate = ctx_scope.get_isolate();
+    let symbol = V8LocalValue {
+        inner_val: unsafe { v8_SymbolAsyncIterator(isolate.inner_isolate) },
+    };
+    let iterator =
+        ctx_scope.new_native_function(|args, _isolate, _ctx_scope| Some(args.get_self()));
+    obj.define_property(
+        ctx_scope,
+        &symbol,
+        &iterator.to_value(),
+        V8PropertyAttributes::DONT_ENUM,
+    );
+}
+
+/// Drives a JS async iterator, each step waits for the promise returned by `next()`.
+struct AsyncIteratorDriver<F> {
+    iterator: V8PersistValue,
+    next: V8PersistValue,
+    resolver: V8PersistValue,
+    on_item: F,
+}
+
+impl<F> AsyncIteratorDriver<F>
+where
+    F: Fn(&V8Isolate, &V8ContextScope, &V8LocalValue) -> bool + 'static,
+{
+    fn step(self: Rc<Self>, isolate: &V8Isolate, ctx_scope: &V8ContextScope) {
+        loop {
+            let iterator = self.iterator.as_local(isolate);
+            let next = self.next.as_local(isolate);
+            let trycatch = isolate.new_try_catch();
+            let res = match next.call_with_receiver(ctx_scope, &iterator, None) {
+                Some(res) => res,
+                None => {
+                    self.reject_caught(isolate, ctx_scope, &trycatch);
+                    return;
+                }
+            };
+            if res.is_promise() {
+                let driver = Rc::clone(&self);
+                res.as_promise()
+                    .on_settled(ctx_scope, move |isolate, ctx_scope, res| match res {
+                        Ok(res) => {
+                            if driver.handle(isolate, ctx_scope, res) {
+                                Rc::clone(&driver).step(isolate, ctx_scope);
+                            }
+                        }
+                        Err(e) => driver.reject(isolate, ctx_scope, e),
+                    });
+                return;
+            }
+            if !self.handle(isolate, ctx_scope, &res) {
+                return;
+            }
+        }
+    }
+
+    /// Handle an iterator result, return true if the iteration should continue.
+    fn handle(&self, isolate: &V8Isolate, ctx_scope: &V8ContextScope, res: &V8LocalValue) -> bool {
+        if !res.is_object() {
+            let error =
+                ctx_scope.new_error(&V8ErrorKind::TypeError, "Iterator result is not an object");
+            self.reject(isolate, ctx_scope, &error);
+            return false;
+        }
+        let res = res.as_object();
+        let value = {
+            // The `done` and `value` getters might raise, which rejects the iteration.
+            let trycatch = isolate.new_try_catch();
+            let get = |name: &str| res.get(ctx_scope, &isolate.new_string(name).to_value());
+            let done = match get("done") {
+                Some(done) => done,
+                None => {
+                    self.reject_caught(isolate, ctx_scope, &trycatch);
+                    return false;
+                }
+            };
+            if done.coerce_to_boolean(isolate) {
+                self.resolve(isolate, ctx_scope);
+                return false;
+            }
+            match get("value") {
+                Some(value) => value,
+                None => {
+                    self.reject_caught(isolate, ctx_scope, &trycatch);
+                    return false;
+                }
+            }
+        };
+        if !(self.on_item)(isolate, ctx_scope, &value) {
+            // Give the iterator a chance to release its resources, like `break` in `for await`.
+            let iterator = self.iterator.as_local(isolate);
+            let ret = iterator
+                .as_object()
+                .get(ctx_scope, &isolate.new_string("return").to_value());
+            if let Some(ret) = ret.filter(V8LocalValue::is_function) {
+                let _trycatch = isolate.new_try_catch();
+                let _ = ret.call_with_receiver(ctx_scope, &iterator, None);
+            }
+            self.resolve(isolate, ctx_scope);
+            return false;
+        }
+        true
+    }
+
+    fn resolve(&self, isolate: &V8Isolate, ctx_scope: &V8ContextScope) {
+        let resolver = self.resolver.as_local(isolate).as_resolver();
+        resolver.resolve(ctx_scope, &isolate.new_undefined());
+    }
+
+    fn reject(&self, isolate: &V8Isolate, ctx_scope: &V8ContextScope, error: &V8LocalValue) {
+        let resolver = self.resolver.as_local(isolate).as_resolver();
+        resolver.reject(ctx_scope, error);
+    }
+
+    /// Reject with the exception caught by the given try catch object, unless the
+    /// execution was terminated.
+    fn reject_caught(
+        &self,
+        isolate: &V8Isolate,
+        ctx_scope: &V8ContextScope,
+        trycatch: &V8TryCatch,
+    ) {
+        if !trycatch.has_terminated() {
+            self.reject(isolate, ctx_scope, &trycatch.get_exception());
+        }
+    }
+}
+
+impl V8ContextScope {
+    /// Create an iterator result object, `{value, done}`.
+    #[must_use]
+    pub fn new_iterator_result(&self, value: &V8LocalValue, done: bool) -> V8LocalValue {
+        V8ObjectBuilder::new()
+            .prop("value", value)
+            .prop("done", done)
+            .build(self)
+    }
+
+    /// Create an async iterable object, usable with `for await`, whose `next()` calls
+    /// the given callback. The callback should return a promise of an iterator result
+    /// (see `new_iterator_result`), or None if it raised an exception.
+    #[must_use]
+    pub fn new_async_iterator<F>(&self, next: F) -> V8LocalValue
+    where
+        F: Fn(&V8Isolate, &Self) -> Option<V8LocalValue> + 'static,
+    {
+        let next =
+            self.new_native_function(move |_args, isolate, ctx_scope| next(isolate, ctx_scope));
+        let iterator = V8ObjectBuilder::new()
+            .prop("next", &next.to_value())
+            .build(self);
+        set_async_iterable(self, &iterator.as_object());
+        iterator
+    }
+
+    /// Iterate the given async iterable (or sync iterable) like `for await` does, calling
+    /// `on_item` with each value. The iteration stops once `on_item` returns false, in which
+    /// case the iterator `return()` method is called. Return a promise that resolves once the
+    /// iteration is done and is rejected with the error raised by the iterator, or None if the
+    /// value is not iterable. The iteration continues as the microtasks are processed. Unlike
+    /// `for await`, the values of sync iterables are given as is, even if they are promises.
+    #[must_use]
+    pub fn for_await<F>(&self, iterable: &V8LocalValue, on_item: F) -> Option<V8LocalPromise>
+    where
+        F: Fn(&V8Isolate, &Self, &V8LocalValue) -> bool + 'static,
+    {
+        let isolate = self.get_isolate();
+        let inner_val = unsafe { v8_ValueGetAsyncIterator(self.inner_ctx_ref, iterable.inner_val) };
+        if inner_val.is_null() {
+            return None;
+        }
+        let iterator = V8LocalValue { inner_val };
+        let next = iterator
+            .as_object()
+            .get(self, &isolate.new_string("next").to_value())
+            .filter(V8LocalValue::is_function)?;
+        let resolver = self.new_resolver();
+        let promise = resolver.get_promise();
+        let driver = Rc::new(AsyncIteratorDriver {
+            iterator: iterator.persist(&isolate),
+            next: next.persist(&isolate),
+            resolver: resolver.to_value().persist(&isolate),
+            on_item,
+        });
+        driver.step(&isolate, self);
+        Some(promise)
+    }
+}
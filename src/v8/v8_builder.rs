@@ -0,0 +1,190 @@
+use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_value::V8LocalValue;
+
+/// A value that can be added to a `V8ObjectBuilder` or a `V8ArrayBuilder`.
+/// The JS values are only created when the builder is built.
+pub enum V8BuilderValue<'a> {
+    Undefined,
+    Null,
+    Bool(bool),
+    Long(i64),
+    Double(f64),
+    String(&'a str),
+    OwnedString(String),
+    Value(&'a V8LocalValue),
+    Object(V8ObjectBuilder<'a>),
+    Array(V8ArrayBuilder<'a>),
+}
+
+/// A built value, existing values are used as is.
+pub(crate) enum V8BuiltValue<'a> {
+    Owned(V8LocalValue),
+    Borrowed(&'a V8LocalValue),
+}
+
+impl<'a> V8BuiltValue<'a> {
+    pub(crate) const fn as_value(&self) -> &V8LocalValue {
+        match self {
+            Self::Owned(v) => v,
+            Self::Borrowed(v) => v,
+        }
+    }
+}
+
+impl<'a> V8BuilderValue<'a> {
+    pub(crate) fn build(
+        &self,
+        isolate: &V8Isolate,
+        ctx_scope: &V8ContextScope,
+    ) -> V8BuiltValue<'a> {
+        let val = match self {
+            Self::Undefined => isolate.new_undefined(),
+            Self::Null => isolate.new_null(),
+            Self::Bool(b) => isolate.new_bool(*b),
+            Self::Long(n) => isolate.new_long(*n),
+            Self::Double(n) => isolate.new_double(*n),
+            Self::String(s) => isolate.new_string(s).to_value(),
+            Self::OwnedString(s) => isolate.new_string(s).to_value(),
+            Self::Value(v) => return V8BuiltValue::Borrowed(v),
+            Self::Object(o) => o.build_with_isolate(isolate, ctx_scope),
+            Self::Array(a) => a.build_with_isolate(isolate, ctx_scope),
+        };
+        V8BuiltValue::Owned(val)
+    }
+}
+
+macro_rules! builder_value_from {
+    ($($t:ty => $variant:ident),*) => {
+        $(
+            impl<'a> From<$t> for V8BuilderValue<'a> {
+                fn from(val: $t) -> Self {
+                    Self::$variant(val.into())
+                }
+            }
+        )*
+    };
+}
+
+builder_value_from!(
+    bool => Bool,
+    i64 => Long,
+    i32 => Long,
+    u32 => Long,
+    f64 => Double,
+    &'a str => String,
+    String => OwnedString,
+    &'a V8LocalValue => Value,
+    V8ObjectBuilder<'a> => Object,
+    V8ArrayBuilder<'a> => Array
+);
+
+/// A fluent builder of JS objects, for example:
+/// `V8ObjectBuilder::new().prop("a", 1).prop("b", "x").build(&ctx_scope)`.
+#[derive(Default)]
+pub struct V8ObjectBuilder<'a> {
+    props: Vec<(&'a str, V8BuilderValue<'a>)>,
+}
+
+impl<'a> V8ObjectBuilder<'a> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { props: Vec::new() }
+    }
+
+    /// Add a property to the object, properties are set by the order they were added.
+    #[must_use]
+    pub fn prop<T: Into<V8BuilderValue<'a>>>(mut self, name: &'a str, val: T) -> Self {
+        self.props.push((name, val.into()));
+        self
+    }
+
+    /// Create the object.
+    #[must_use]
+    pub fn build(&self, ctx_scope: &V8ContextScope) -> V8LocalValue {
+        self.build_with_isolate(&ctx_scope.get_isolate(), ctx_scope)
+    }
+
+    fn build_with_isolate(&self, isolate: &V8Isolate, ctx_scope: &V8ContextScope) -> V8LocalValue {
+        let obj = isolate.new_object();
+        for (name, val) in &self.props {
+            let val = val.build(isolate, ctx_scope);
+            obj.set(
+                ctx_scope,
+                &isolate.new_string(name).to_value(),
+                val.as_value(),
+            );
+        }
+        obj.to_value()
+    }
+}
+
+/// A fluent builder of function arguments, for example:
+/// `V8ArgsBuilder::new().arg(1).arg("x").call(&ctx_scope, &func)`.
+#[derive(Default)]
+pub struct V8ArgsBuilder<'a> {
+    args: Vec<V8BuilderValue<'a>>,
+}
+
+impl<'a> V8ArgsBuilder<'a> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { args: Vec::new() }
+    }
+
+    /// Add an argument after the previously added ones.
+    #[must_use]
+    pub fn arg<T: Into<V8BuilderValue<'a>>>(mut self, val: T) -> Self {
+        self.args.push(val.into());
+        self
+    }
+
+    /// Call the given function with the arguments, see `V8LocalValue::call`.
+    #[must_use]
+    pub fn call(&self, ctx_scope: &V8ContextScope, func: &V8LocalValue) -> Option<V8LocalValue> {
+        let isolate = ctx_scope.get_isolate();
+        let args = self
+            .args
+            .iter()
+            .map(|val| val.build(&isolate, ctx_scope))
+            .collect::<Vec<_>>();
+        func.call_args(ctx_scope, args.iter().map(V8BuiltValue::as_value))
+    }
+}
+
+/// A fluent builder of JS arrays, for example:
+/// `V8ArrayBuilder::new().push(1).push("x").build(&ctx_scope)`.
+#[derive(Default)]
+pub struct V8ArrayBuilder<'a> {
+    items: Vec<V8BuilderValue<'a>>,
+}
+
+impl<'a> V8ArrayBuilder<'a> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    /// Add an item to the end of the array.
+    #[must_use]
+    pub fn push<T: Into<V8BuilderValue<'a>>>(mut self, val: T) -> Self {
+        self.items.push(val.into());
+        self
+    }
+
+    /// Create the array.
+    #[must_use]
+    pub fn build(&self, ctx_scope: &V8ContextScope) -> V8LocalValue {
+        self.build_with_isolate(&ctx_scope.get_isolate(), ctx_scope)
+    }
+
+    fn build_with_isolate(&self, isolate: &V8Isolate, ctx_scope: &V8ContextScope) -> V8LocalValue {
+        let items = self
+            .items
+            .iter()
+            .map(|val| val.build(isolate, ctx_scope))
+            .collect::<Vec<_>>();
+        let items = items.iter().map(V8BuiltValue::as_value).collect::<Vec<_>>();
+        isolate.new_array(&items).to_value()
+    }
+}
@@ -1,17 +1,210 @@
 use crate::v8_c_raw::bindings::{
-    v8_ContextEnter, v8_FreeContext, v8_GetPrivateData, v8_NewContext, v8_SetPrivateData,
-    v8_context,
+    v8_ContextEnter, v8_FreeContext, v8_GetCurrentThreadCPUTime, v8_GetPrivateData, v8_NewContext,
+    v8_SetPrivateData, v8_context,
 };
 
+use std::cell::RefCell;
 use std::os::raw::c_void;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use crate::v8::isolate::V8Isolate;
+use crate::v8::handler_scope::context_scope_entered;
+use crate::v8::isolate::{IsolateId, V8Isolate};
 use crate::v8::v8_context_scope::V8ContextScope;
 use crate::v8::v8_object_template::V8LocalObjectTemplate;
 
+/// Execution statistics accumulated on a context, see `V8Context::get_execution_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct V8ContextExecutionStats {
+    /// Wall clock time spent inside the context.
+    pub wall_time: Duration,
+    /// CPU time consumed by the threads that entered the context.
+    pub cpu_time: Duration,
+    /// Number of times the context was entered (nested enters are not counted).
+    pub enter_count: u64,
+    /// Number of scripts, modules and functions compiled on the context.
+    /// Only recorded when script timing is enabled, see `V8Context::set_script_timing`.
+    pub compile_count: u64,
+    /// Total time spent compiling, only recorded when script timing is enabled.
+    pub compile_time: Duration,
+    /// Number of script runs, module evaluations and function calls (nested calls
+    /// are not counted). Only recorded when script timing is enabled.
+    pub run_count: u64,
+    /// Total time spent running code, only recorded when script timing is enabled.
+    pub run_time: Duration,
+    /// The duration of the slowest run, only recorded when script timing is enabled.
+    pub max_run_time: Duration,
+    /// Number of times the context was sampled while it was entered, see
+    /// `V8Isolate::start_execution_sampling`.
+    pub sample_count: u64,
+}
+
+/// Accumulates the execution statistics of a context. Only the outer most
+/// `V8ContextScope` is measured so nested enters are not counted twice.
+#[derive(Default)]
+pub(crate) struct V8ContextExecutionTracker {
+    stats: V8ContextExecutionStats,
+    depth: usize,
+    wall_start: Option<Instant>,
+    cpu_start: Duration,
+    script_timing: bool,
+    run_depth: usize,
+}
+
+fn current_thread_cpu_time() -> Duration {
+    Duration::from_nanos(unsafe { v8_GetCurrentThreadCPUTime() } as u64)
+}
+
+impl V8ContextExecutionTracker {
+    pub(crate) fn enter(&mut self) {
+        self.depth += 1;
+        if self.depth == 1 {
+            self.stats.enter_count += 1;
+            self.wall_start = Some(Instant::now());
+            self.cpu_start = current_thread_cpu_time();
+        }
+    }
+
+    pub(crate) fn exit(&mut self) {
+        self.depth -= 1;
+        if self.depth == 0 {
+            if let Some(wall_start) = self.wall_start.take() {
+                self.stats.wall_time += wall_start.elapsed();
+            }
+            self.stats.cpu_time += current_thread_cpu_time().saturating_sub(self.cpu_start);
+        }
+    }
+
+    /// Account the CPU time consumed since the context was entered or last sampled,
+    /// must be called on the thread that entered the context.
+    fn sample(&mut self) {
+        if self.depth == 0 {
+            return;
+        }
+        let now = current_thread_cpu_time();
+        self.stats.cpu_time += now.saturating_sub(self.cpu_start);
+        self.cpu_start = now;
+        self.stats.sample_count += 1;
+    }
+
+    pub(crate) const fn script_timing(&self) -> bool {
+        self.script_timing
+    }
+
+    pub(crate) fn record_compile(&mut self, duration: Duration) {
+        self.stats.compile_count += 1;
+        self.stats.compile_time += duration;
+    }
+
+    /// Return true if the run should be measured, only the outer most run is measured.
+    pub(crate) fn run_started(&mut self) -> bool {
+        self.run_depth += 1;
+        self.run_depth == 1
+    }
+
+    pub(crate) fn run_finished(&mut self, duration: Option<Duration>) {
+        self.run_depth -= 1;
+        if let Some(duration) = duration {
+            self.stats.run_count += 1;
+            self.stats.run_time += duration;
+            self.stats.max_run_time = self.stats.max_run_time.max(duration);
+        }
+    }
+}
+
+thread_local! {
+    /// The trackers of the contexts entered on the current thread, with the isolate of
+    /// each context, the innermost is last.
+    static ENTERED_TRACKERS: RefCell<Vec<(IsolateId, Arc<Mutex<V8ContextExecutionTracker>>)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Forget the context scope of the given tracker which was entered last on the current thread.
+pub(crate) fn context_tracker_exited(tracker: &Arc<Mutex<V8ContextExecutionTracker>>) {
+    ENTERED_TRACKERS.with(|trackers| {
+        let mut trackers = trackers.borrow_mut();
+        if let Some(position) = trackers.iter().rposition(|(_, t)| Arc::ptr_eq(t, tracker)) {
+            trackers.remove(position);
+        }
+    });
+}
+
+/// Sample the contexts of the given isolate which are entered on the current thread,
+/// a context which is entered more than once is sampled once.
+fn sample_entered_contexts(isolate_id: IsolateId) {
+    ENTERED_TRACKERS.with(|trackers| {
+        let trackers = trackers.borrow();
+        for (i, (id, tracker)) in trackers.iter().enumerate() {
+            if *id == isolate_id && !trackers[..i].iter().any(|(_, t)| Arc::ptr_eq(t, tracker)) {
+                tracker.lock().unwrap().sample();
+            }
+        }
+    });
+}
+
+/// Samples the contexts executing on an isolate at a fixed interval, see
+/// `V8Isolate::start_execution_sampling`. Sampling stops when the sampler is dropped.
+pub struct V8ExecutionSampler {
+    shutdown: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl V8ExecutionSampler {
+    pub(crate) fn new(isolate_id: IsolateId, interval: Duration) -> Self {
+        let shutdown = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_shutdown = Arc::clone(&shutdown);
+        let thread = thread::spawn(move || {
+            let (shutdown, cond) = &*thread_shutdown;
+            // Set while an interrupt is pending, the interrupts only run while the
+            // isolate executes JS code so they are not requested while it is idle.
+            let pending = Arc::new(AtomicBool::new(false));
+            let mut shutdown = shutdown.lock().unwrap();
+            loop {
+                shutdown = cond
+                    .wait_timeout_while(shutdown, interval, |shutdown| !*shutdown)
+                    .unwrap()
+                    .0;
+                if *shutdown {
+                    break;
+                }
+                if pending.swap(true, Ordering::AcqRel) {
+                    continue;
+                }
+                let pending = Arc::clone(&pending);
+                let requested = isolate_id.request_interrupt(move |isolate| {
+                    sample_entered_contexts(isolate.get_id());
+                    pending.store(false, Ordering::Release);
+                });
+                if !requested {
+                    break;
+                }
+            }
+        });
+        Self {
+            shutdown,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for V8ExecutionSampler {
+    fn drop(&mut self) {
+        let (shutdown, cond) = &*self.shutdown;
+        *shutdown.lock().unwrap() = true;
+        cond.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
 pub struct V8Context {
     pub(crate) inner_ctx: *mut v8_context,
+    pub(crate) isolate_id: IsolateId,
+    pub(crate) execution_tracker: Arc<Mutex<V8ContextExecutionTracker>>,
 }
 
 unsafe impl Sync for V8Context {}
@@ -23,7 +216,13 @@ impl V8Context {
             Some(g) => unsafe { v8_NewContext(isolate.inner_isolate, g.inner_obj) },
             None => unsafe { v8_NewContext(isolate.inner_isolate, ptr::null_mut()) },
         };
-        Self { inner_ctx }
+        let isolate_id = isolate.get_id();
+        isolate_id.persisted_handle_created();
+        Self {
+            inner_ctx,
+            isolate_id,
+            execution_tracker: Arc::new(Mutex::new(V8ContextExecutionTracker::default())),
+        }
     }
 
     /// Enter the context for JS code invocation.
@@ -33,10 +232,41 @@ impl V8Context {
     #[must_use]
     pub fn enter(&self) -> V8ContextScope {
         let inner_ctx_ref = unsafe { v8_ContextEnter(self.inner_ctx) };
-        V8ContextScope {
+        self.execution_tracker.lock().unwrap().enter();
+        ENTERED_TRACKERS.with(|trackers| {
+            trackers
+                .borrow_mut()
+                .push((self.isolate_id, Arc::clone(&self.execution_tracker)));
+        });
+        let ctx_scope = V8ContextScope {
             inner_ctx_ref,
             exit_on_drop: true,
+            execution_tracker: Some(Arc::clone(&self.execution_tracker)),
+        };
+        context_scope_entered(&ctx_scope);
+        ctx_scope
+    }
+
+    /// Return the execution statistics accumulated on the context so far.
+    /// The wall clock time of an execution which is currently in progress is
+    /// included, its CPU time is only accounted once the context is exited or
+    /// sampled (see `V8Isolate::start_execution_sampling`).
+    #[must_use]
+    pub fn get_execution_stats(&self) -> V8ContextExecutionStats {
+        let tracker = self.execution_tracker.lock().unwrap();
+        let mut stats = tracker.stats;
+        if let Some(wall_start) = tracker.wall_start {
+            stats.wall_time += wall_start.elapsed();
         }
+        stats
+    }
+
+    /// Enable or disable recording of the compile and run durations of the code
+    /// executed on the context (using the context scopes returned from `enter`).
+    /// The durations are reported as part of the `get_execution_stats` result.
+    /// Disabled by default.
+    pub fn set_script_timing(&self, enabled: bool) {
+        self.execution_tracker.lock().unwrap().script_timing = enabled;
     }
 
     /// Set a private data on the context that can later be retieve with `get_private_data`.
@@ -64,6 +294,8 @@ impl V8Context {
 
 impl Drop for V8Context {
     fn drop(&mut self) {
-        unsafe { v8_FreeContext(self.inner_ctx) }
+        if self.isolate_id.persisted_handle_freed() {
+            unsafe { v8_FreeContext(self.inner_ctx) }
+        }
     }
 }
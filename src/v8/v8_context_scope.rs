@@ -1,39 +1,438 @@
 use crate::v8_c_raw::bindings::{
-    v8_Compile, v8_CompileAsModule, v8_ContextRefGetGlobals, v8_ContextRefGetIsolate,
-    v8_ExitContextRef, v8_FreeContextRef, v8_GetPrivateDataFromCtxRef, v8_NewNativeFunction,
-    v8_NewObjectFromJsonString, v8_NewResolver, v8_SetPrivateDataOnCtxRef, v8_context_ref,
+    v8_Compile, v8_CompileAsModule, v8_CompileFunction, v8_CompileWithOptions,
+    v8_ContextRefAllowCodeGenerationFromStrings, v8_ContextRefGetContinuationData,
+    v8_ContextRefGetGlobals, v8_ContextRefGetIsolate, v8_ContextRefIsCurrent,
+    v8_ContextRefSetContinuationData, v8_ContextRefSetUnhandledErrorHandler,
+    v8_ErrorType_v8_ErrorType_Error, v8_ErrorType_v8_ErrorType_RangeError,
+    v8_ErrorType_v8_ErrorType_ReferenceError, v8_ErrorType_v8_ErrorType_SyntaxError,
+    v8_ErrorType_v8_ErrorType_TypeError, v8_ExitContextRef, v8_FreeContextRef,
+    v8_GetPrivateDataFromCtxRef, v8_NewError, v8_NewNativeFunction, v8_NewObjectFromJsonString,
+    v8_NewResolver, v8_NewSyntheticModule, v8_SetPrivateDataOnCtxRef, v8_UnhandledErrorType,
+    v8_UnhandledErrorType_v8_UnhandledErrorType_UnhandledRejection, v8_context_ref,
+    v8_local_string, v8_local_value,
 };
 
-use std::os::raw::c_void;
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int, c_void};
 use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
+use crate::v8::diagnostics::V8DiagnosticsEvent;
+use crate::v8::handler_scope::{check_scope_order, context_scope_exited};
 use crate::v8::isolate::V8Isolate;
-use crate::v8::v8_module::V8LocalModule;
+use crate::v8::try_catch::V8TryCatch;
+use crate::v8::v8_context::{context_tracker_exited, V8ContextExecutionTracker};
+use crate::v8::v8_error::{V8ErrorKind, V8Throwable};
+use crate::v8::v8_module::{V8LocalModule, V8ModuleError, V8ModuleErrorStage};
 use crate::v8::v8_native_function::V8LocalNativeFunction;
 use crate::v8::v8_native_function_template::free_pd;
 use crate::v8::v8_native_function_template::native_basic_function;
 use crate::v8::v8_native_function_template::V8LocalNativeFunctionArgs;
-use crate::v8::v8_object::V8LocalObject;
+use crate::v8::v8_object::{V8LocalObject, V8PropertyAttributes};
 use crate::v8::v8_resolver::V8LocalResolver;
-use crate::v8::v8_script::V8LocalScript;
+use crate::v8::v8_script::{V8CompileOptions, V8CompiledScript, V8LocalScript};
 use crate::v8::v8_string::V8LocalString;
-use crate::v8::v8_value::V8LocalValue;
+use crate::v8::v8_value::{V8LocalValue, ERROR_PAYLOAD_NAME};
+
+/// Kind of an error reported to the unhandled exception handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V8UnhandledErrorKind {
+    /// An exception that was not caught by the JS code nor by a try catch object.
+    UncaughtException,
+    /// A promise that was rejected without a rejection handler and did not get
+    /// one until the microtasks completed.
+    UnhandledRejection,
+}
+
+/// An error that was not handled, see `V8ContextScope::set_unhandled_exception_handler`.
+pub struct V8UnhandledError {
+    pub kind: V8UnhandledErrorKind,
+    /// The exception value or the promise rejection reason.
+    pub error: V8LocalValue,
+    /// The name of the script that raised the error, if known.
+    pub script_name: Option<String>,
+    /// The line number on which the error was raised, if known.
+    pub line_number: Option<usize>,
+}
+
+/// The reason `V8ContextScope::eval` or `V8ContextScope::eval_with` failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum V8EvalError {
+    /// The code raised an exception (including a `SyntaxError` on compilation),
+    /// holds the string representation of the exception.
+    Exception(String),
+    /// The execution was terminated.
+    Terminated,
+    /// The given `eval_with` binding name is not a valid JS identifier.
+    InvalidBinding(String),
+}
+
+impl fmt::Display for V8EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exception(e) => f.write_str(e),
+            Self::Terminated => f.write_str("Execution was terminated"),
+            Self::InvalidBinding(name) => write!(f, "Invalid binding name '{}'", name),
+        }
+    }
+}
+
+impl From<V8EvalError> for String {
+    fn from(e: V8EvalError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Words that can not be used as a binding name, either because JS reserves
+/// them or because the `eval_with` wrapper relies on them.
+const RESERVED_BINDING_NAMES: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Return true if the name is a JS identifier which can be used as a binding.
+fn is_binding_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first_ok = chars
+        .next()
+        .is_some_and(|c| c == '$' || c == '_' || c.is_alphabetic());
+    first_ok
+        && chars.all(|c| {
+            c == '$' || c == '_' || c == '\u{200C}' || c == '\u{200D}' || c.is_alphanumeric()
+        })
+        && !RESERVED_BINDING_NAMES.contains(&name)
+}
+
+extern "C" fn unhandled_error_callback<T: Fn(&V8Isolate, &V8ContextScope, V8UnhandledError)>(
+    ctx_ref: *mut v8_context_ref,
+    error_type: v8_UnhandledErrorType,
+    error: *mut v8_local_value,
+    script_name: *mut v8_local_string,
+    line_number: c_int,
+    pd: *mut c_void,
+) {
+    let ctx_scope = V8ContextScope {
+        inner_ctx_ref: ctx_ref,
+        exit_on_drop: false,
+        execution_tracker: None,
+    };
+    let isolate = ctx_scope.get_isolate();
+    let script_name = if script_name.is_null() {
+        None
+    } else {
+        let script_name = V8LocalString {
+            inner_string: script_name,
+        };
+        script_name
+            .to_value()
+            .to_utf8(&isolate)
+            .map(|s| s.as_str().to_string())
+    };
+    let kind = if error_type == v8_UnhandledErrorType_v8_UnhandledErrorType_UnhandledRejection {
+        V8UnhandledErrorKind::UnhandledRejection
+    } else {
+        V8UnhandledErrorKind::UncaughtException
+    };
+    let func = unsafe { &*(pd.cast::<T>()) };
+    func(
+        &isolate,
+        &ctx_scope,
+        V8UnhandledError {
+            kind,
+            error: V8LocalValue { inner_val: error },
+            script_name,
+            line_number: if line_number > 0 {
+                Some(line_number as usize)
+            } else {
+                None
+            },
+        },
+    );
+}
+
+extern "C" fn free_unhandled_error_callback<T>(pd: *mut c_void) {
+    unsafe { drop(Box::from_raw(pd.cast::<T>())) }
+}
 
 pub struct V8ContextScope {
     pub(crate) inner_ctx_ref: *mut v8_context_ref,
     pub(crate) exit_on_drop: bool,
+    pub(crate) execution_tracker: Option<Arc<Mutex<V8ContextExecutionTracker>>>,
 }
 
 impl V8ContextScope {
-    /// Compile the given code into a script object.
+    fn script_timing_tracker(&self) -> Option<&Mutex<V8ContextExecutionTracker>> {
+        self.execution_tracker
+            .as_deref()
+            .filter(|tracker| tracker.lock().unwrap().script_timing())
+    }
+
+    /// Run the given compilation, record its duration if script timing is enabled
+    /// on the context and report it to the isolate diagnostics, if any.
+    fn instrumented_compile<R, F: FnOnce() -> Option<R>>(
+        &self,
+        name: Option<&V8LocalString>,
+        compile: F,
+    ) -> Option<R> {
+        let compile = || match self.script_timing_tracker() {
+            Some(tracker) => {
+                let start = Instant::now();
+                let res = compile();
+                tracker.lock().unwrap().record_compile(start.elapsed());
+                res
+            }
+            None => compile(),
+        };
+        let isolate = self.get_isolate();
+        let diagnostics = match isolate.get_diagnostics() {
+            Some(d) => d,
+            None => return compile(),
+        };
+        let name = name
+            .and_then(|n| n.to_value().to_utf8(&isolate))
+            .map(|n| n.as_str().to_string());
+        diagnostics.on_event(
+            &isolate,
+            &V8DiagnosticsEvent::CompileStarted {
+                name: name.as_deref(),
+            },
+        );
+        let start = Instant::now();
+        let res = compile();
+        diagnostics.on_event(
+            &isolate,
+            &V8DiagnosticsEvent::CompileFinished {
+                name: name.as_deref(),
+                duration: start.elapsed(),
+                success: res.is_some(),
+            },
+        );
+        res
+    }
+
+    /// Run the given code and record its duration if script timing is enabled on the context.
+    pub(crate) fn timed_run<R, F: FnOnce() -> R>(&self, run: F) -> R {
+        let tracker = match self.script_timing_tracker() {
+            Some(tracker) => tracker,
+            None => return run(),
+        };
+        let measure = tracker.lock().unwrap().run_started();
+        let start = Instant::now();
+        let res = run();
+        let duration = if measure { Some(start.elapsed()) } else { None };
+        tracker.lock().unwrap().run_finished(duration);
+        res
+    }
+
+    /// Compile the given code into a script object, in sloppy mode like a classic script.
     #[must_use]
     pub fn compile(&self, s: &V8LocalString) -> Option<V8LocalScript> {
-        let inner_script = unsafe { v8_Compile(self.inner_ctx_ref, s.inner_string) };
-        if inner_script.is_null() {
-            None
-        } else {
-            Some(V8LocalScript { inner_script })
+        self.instrumented_compile(None, || {
+            let inner_script = unsafe { v8_Compile(self.inner_ctx_ref, s.inner_string) };
+            if inner_script.is_null() {
+                None
+            } else {
+                Some(V8LocalScript { inner_script })
+            }
+        })
+    }
+
+    /// Same as `compile` but with the given options, see `V8CompileOptions`. Note that
+    /// scripts are compiled in strict mode by default. Return None on failure.
+    #[must_use]
+    pub fn compile_with_options(
+        &self,
+        code: &V8LocalString,
+        options: &V8CompileOptions,
+    ) -> Option<V8CompiledScript> {
+        let isolate = self.get_isolate();
+        let name = options.name.map(|n| isolate.new_string(n));
+        let (cache_ptr, cache_len) = options
+            .code_cache
+            .map_or((ptr::null(), 0), |c| (c.as_ptr().cast::<c_char>(), c.len()));
+        let mut cache_rejected: c_int = 0;
+        let script = self.instrumented_compile(name.as_ref(), || {
+            let inner_script = unsafe {
+                v8_CompileWithOptions(
+                    self.inner_ctx_ref,
+                    code.inner_string,
+                    name.as_ref().map_or(ptr::null_mut(), |n| n.inner_string),
+                    options.line_offset,
+                    options.column_offset,
+                    c_int::from(options.strict),
+                    cache_ptr,
+                    cache_len,
+                    &mut cache_rejected,
+                )
+            };
+            if inner_script.is_null() {
+                None
+            } else {
+                Some(V8LocalScript { inner_script })
+            }
+        })?;
+        Some(V8CompiledScript {
+            script,
+            cache_rejected: cache_rejected != 0,
+        })
+    }
+
+    /// Compile the given code as the body of a function with the given arguments
+    /// names, the code line numbers are preserved. `name` is used as the script
+    /// name. Return the compiled function or None on failure.
+    #[must_use]
+    pub fn compile_function(
+        &self,
+        name: &V8LocalString,
+        code: &V8LocalString,
+        args: &[&str],
+    ) -> Option<V8LocalValue> {
+        let isolate = self.get_isolate();
+        let args = args
+            .iter()
+            .map(|arg| isolate.new_string(arg))
+            .collect::<Vec<V8LocalString>>();
+        let inner_args = args
+            .iter()
+            .map(|arg| arg.inner_string)
+            .collect::<Vec<*mut v8_local_string>>();
+        self.instrumented_compile(Some(name), || {
+            let inner_val = unsafe {
+                v8_CompileFunction(
+                    self.inner_ctx_ref,
+                    name.inner_string,
+                    code.inner_string,
+                    inner_args.len(),
+                    inner_args.as_ptr(),
+                )
+            };
+            if inner_val.is_null() {
+                None
+            } else {
+                Some(V8LocalValue { inner_val })
+            }
+        })
+    }
+
+    pub(crate) fn eval_error(isolate: &V8Isolate, trycatch: &V8TryCatch) -> String {
+        Self::eval_failure(isolate, trycatch).to_string()
+    }
+
+    fn eval_failure(isolate: &V8Isolate, trycatch: &V8TryCatch) -> V8EvalError {
+        if trycatch.has_terminated() {
+            return V8EvalError::Terminated;
+        }
+        V8EvalError::Exception(trycatch.get_exception().to_utf8(isolate).map_or_else(
+            || "Failed evaluating code".to_string(),
+            |e| e.as_str().to_string(),
+        ))
+    }
+
+    /// Compile and run the given code and return its result. On failure,
+    /// return the string representation of the raised exception.
+    pub fn eval(&self, code: &str) -> Result<V8LocalValue, V8EvalError> {
+        let isolate = self.get_isolate();
+        let trycatch = isolate.new_try_catch();
+        self.compile(&isolate.new_string(code))
+            .and_then(|script| script.run(self))
+            .ok_or_else(|| Self::eval_failure(&isolate, &trycatch))
+    }
+
+    /// Same as `eval` but the code can also access the given bindings as if they
+    /// were globals. The bindings only exist while the code runs, and variables
+    /// declared with `var` by the code are not added to the context globals.
+    /// Fails with `V8EvalError::InvalidBinding` if a binding name is not a JS
+    /// identifier, or is a reserved word, `eval` or `arguments`.
+    pub fn eval_with(
+        &self,
+        globals: &[(&str, &V8LocalValue)],
+        code: &str,
+    ) -> Result<V8LocalValue, V8EvalError> {
+        if let Some((name, _)) = globals.iter().find(|(name, _)| !is_binding_name(name)) {
+            return Err(V8EvalError::InvalidBinding((*name).to_string()));
         }
+        let isolate = self.get_isolate();
+        let trycatch = isolate.new_try_catch();
+        let names = globals
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<&str>>()
+            .join(", ");
+        // The code is evaluated by a direct eval call so it sees the wrapper arguments.
+        let wrapper = format!(
+            "(function({}) {{ return eval(arguments[arguments.length - 1]); }})",
+            names
+        );
+        let func = self
+            .compile(&isolate.new_string(&wrapper))
+            .and_then(|script| script.run(self))
+            .ok_or_else(|| Self::eval_failure(&isolate, &trycatch))?;
+        let code = isolate.new_string(code).to_value();
+        let mut args = globals.iter().map(|(_, val)| *val).collect::<Vec<_>>();
+        args.push(&code);
+        func.call(self, Some(&args))
+            .ok_or_else(|| Self::eval_failure(&isolate, &trycatch))
+    }
+
+    /// Create a JS `WeakRef` to the given object, the JS code can use it
+    /// to access the object without keeping it alive. Return None on failure,
+    /// for example if the given value is not an object.
+    #[must_use]
+    pub fn new_weak_ref(&self, obj: &V8LocalValue) -> Option<V8LocalValue> {
+        let isolate = self.get_isolate();
+        let weak_ref_ctor = self
+            .get_globals()
+            .get(self, &isolate.new_string("WeakRef").to_value())?;
+        weak_ref_ctor.new_instance(self, &[obj])
     }
 
     #[must_use]
@@ -42,27 +441,143 @@ impl V8ContextScope {
         V8LocalObject { inner_obj }
     }
 
-    /// Compile the given code as a module.
+    /// Allow or disallow generating code from strings (`eval`, `new Function(...)`)
+    /// on the context, allowed by default. Disallowed code generation raises an `EvalError`.
+    pub fn set_allow_code_generation_from_strings(&self, allow: bool) {
+        unsafe {
+            v8_ContextRefAllowCodeGenerationFromStrings(self.inner_ctx_ref, c_int::from(allow))
+        }
+    }
+
+    /// Add or replace a value on the globals of an already created context.
+    /// Return true on success.
+    pub fn set_global_value(
+        &self,
+        name: &str,
+        val: &V8LocalValue,
+        attributes: V8PropertyAttributes,
+    ) -> bool {
+        let isolate = self.get_isolate();
+        let name = isolate.new_string(name).to_value();
+        self.get_globals()
+            .define_property(self, &name, val, attributes)
+    }
+
+    /// Same as `set_global_value` but gets the value as a native function closure.
+    pub fn set_global_native_function<
+        T: Fn(&V8LocalNativeFunctionArgs, &V8Isolate, &V8ContextScope) -> Option<V8LocalValue>,
+    >(
+        &self,
+        name: &str,
+        func: T,
+    ) -> bool {
+        let native_func = self.new_native_function(func);
+        self.set_global_value(name, &native_func.to_value(), V8PropertyAttributes::NONE)
+    }
+
+    /// Set a handler that is called with all the errors that were not handled on the
+    /// context: uncaught exceptions (exceptions that were not caught by the JS code nor
+    /// by a try catch object) and promises that were rejected without a rejection handler.
+    /// Rejections are reported once the microtasks complete, so a promise that gets a
+    /// handler later on, while the microtasks run, is not reported.
+    /// Replaces the existing handler, if any.
+    pub fn set_unhandled_exception_handler<T: Fn(&V8Isolate, &V8ContextScope, V8UnhandledError)>(
+        &self,
+        handler: T,
+    ) {
+        unsafe {
+            v8_ContextRefSetUnhandledErrorHandler(
+                self.inner_ctx_ref,
+                Some(unhandled_error_callback::<T>),
+                Box::into_raw(Box::new(handler)).cast::<c_void>(),
+                Some(free_unhandled_error_callback::<T>),
+            )
+        };
+    }
+
+    /// Compile the given code as a module. Of the given options only `is_module`
+    /// applies, modules are always strict and the module name is given separately.
     #[must_use]
     pub fn compile_as_module(
         &self,
         name: &V8LocalString,
         code: &V8LocalString,
-        is_module: bool,
+        options: &V8CompileOptions,
     ) -> Option<V8LocalModule> {
+        self.instrumented_compile(Some(name), || {
+            let inner_module = unsafe {
+                v8_CompileAsModule(
+                    self.inner_ctx_ref,
+                    name.inner_string,
+                    code.inner_string,
+                    c_int::from(options.is_module),
+                )
+            };
+            if inner_module.is_null() {
+                return None;
+            }
+            let module = V8LocalModule { inner_module };
+            self.import_map_module_compiled(name, &module);
+            Some(module)
+        })
+    }
+
+    /// Same as `compile_as_module` but on failure, return the compilation error
+    /// along with the name of the module instead of raising it.
+    pub fn compile_as_module_checked(
+        &self,
+        name: &V8LocalString,
+        code: &V8LocalString,
+        options: &V8CompileOptions,
+    ) -> Result<V8LocalModule, V8ModuleError> {
+        let isolate = self.get_isolate();
+        let trycatch = isolate.new_try_catch();
+        self.compile_as_module(name, code, options).ok_or_else(|| {
+            let mut err =
+                V8ModuleError::from_try_catch(&isolate, V8ModuleErrorStage::Compile, &trycatch);
+            err.module = name
+                .to_value()
+                .to_utf8(&isolate)
+                .map(|n| n.as_str().to_string());
+            err
+        })
+    }
+
+    /// Create a module with the given value as its default export. Can be returned
+    /// from the module loader to expose data to JS code (`import data from "./data"`).
+    #[must_use]
+    pub fn new_synthetic_module(
+        &self,
+        name: &V8LocalString,
+        default_export: &V8LocalValue,
+    ) -> V8LocalModule {
         let inner_module = unsafe {
-            v8_CompileAsModule(
+            v8_NewSyntheticModule(
                 self.inner_ctx_ref,
                 name.inner_string,
-                code.inner_string,
-                if is_module { 1 } else { 0 },
+                default_export.inner_val,
             )
         };
-        if inner_module.is_null() {
-            None
-        } else {
-            Some(V8LocalModule { inner_module })
-        }
+        V8LocalModule { inner_module }
+    }
+
+    /// Parse the given JSON and create a module with the parsed value as its
+    /// default export. Return None if the JSON is invalid.
+    #[must_use]
+    pub fn compile_as_json_module(
+        &self,
+        name: &V8LocalString,
+        json: &V8LocalString,
+    ) -> Option<V8LocalModule> {
+        let val = self.new_object_from_json(json)?;
+        Some(self.new_synthetic_module(name, &val))
+    }
+
+    /// Create a module with an array buffer holding the given bytes as its default export.
+    #[must_use]
+    pub fn compile_as_binary_module(&self, name: &V8LocalString, data: &[u8]) -> V8LocalModule {
+        let array_buffer = self.get_isolate().new_array_buffer(data);
+        self.new_synthetic_module(name, &array_buffer.to_value())
     }
 
     pub(crate) fn get_private_data_raw<T>(&self, index: usize) -> Option<&T> {
@@ -105,6 +620,30 @@ impl V8ContextScope {
         };
     }
 
+    /// Return true if the scope context is the current context of the isolate
+    /// (it was the last context to be entered and it was not yet exited).
+    #[must_use]
+    pub fn is_current(&self) -> bool {
+        unsafe { v8_ContextRefIsCurrent(self.inner_ctx_ref) != 0 }
+    }
+
+    /// Set a value that flows across awaits and promise callbacks: the value is captured
+    /// by the continuations created from now on (like an `await` resumption or a `then`
+    /// callback) and is restored while each of them runs. Can be used to carry per
+    /// request metadata, set by a native function, into the later async callbacks of
+    /// the same logical chain (similar to Node's `AsyncLocalStorage`).
+    pub fn set_continuation_preserved_data(&self, val: &V8LocalValue) {
+        unsafe { v8_ContextRefSetContinuationData(self.inner_ctx_ref, val.inner_val) }
+    }
+
+    /// Return the value set by `set_continuation_preserved_data`, or the value captured
+    /// by the continuation that is currently running. Undefined if there is no such value.
+    #[must_use]
+    pub fn get_continuation_preserved_data(&self) -> V8LocalValue {
+        let inner_val = unsafe { v8_ContextRefGetContinuationData(self.inner_ctx_ref) };
+        V8LocalValue { inner_val }
+    }
+
     pub(crate) fn get_isolate(&self) -> V8Isolate {
         let inner_isolate = unsafe { v8_ContextRefGetIsolate(self.inner_ctx_ref) };
         V8Isolate {
@@ -117,6 +656,91 @@ impl V8ContextScope {
         self.set_private_data_raw(index + 1, pd)
     }
 
+    /// Set multiple private data slots at once (see `set_private_data`). The slots are
+    /// restored to their previous values when the returned guard is dropped, so the data
+    /// can not be used after the values are gone, for example:
+    /// `let _guard = ctx_scope.set_private_data_many(&[V8PrivateData::new(0, &request),
+    /// V8PrivateData::new(1, &user)])?;`. Fails if an index is given more than once,
+    /// in which case no slot is set.
+    pub fn set_private_data_many<'a>(
+        &'a self,
+        data: &[V8PrivateData<'a>],
+    ) -> Result<V8ContextScopeDataGuard<'a>, String> {
+        let mut indexes = data.iter().map(|pd| pd.index).collect::<Vec<_>>();
+        indexes.sort_unstable();
+        if let Some(index) = indexes.windows(2).find(|w| w[0] == w[1]) {
+            return Err(format!(
+                "Private data index {} is set more than once",
+                index[0]
+            ));
+        }
+        let previous = data
+            .iter()
+            .map(|pd| {
+                let previous =
+                    unsafe { v8_GetPrivateDataFromCtxRef(self.inner_ctx_ref, pd.index + 1) };
+                unsafe {
+                    v8_SetPrivateDataOnCtxRef(
+                        self.inner_ctx_ref,
+                        pd.index + 1,
+                        pd.pd as *mut c_void,
+                    )
+                };
+                (pd.index + 1, previous)
+            })
+            .collect();
+        Ok(V8ContextScopeDataGuard {
+            ctx_scope: self,
+            previous,
+        })
+    }
+
+    /// Create a new JS error object of the given kind with the given message.
+    #[must_use]
+    pub fn new_error(&self, kind: &V8ErrorKind, message: &str) -> V8LocalValue {
+        let isolate = self.get_isolate();
+        let error_type = match kind {
+            V8ErrorKind::TypeError => v8_ErrorType_v8_ErrorType_TypeError,
+            V8ErrorKind::RangeError => v8_ErrorType_v8_ErrorType_RangeError,
+            V8ErrorKind::ReferenceError => v8_ErrorType_v8_ErrorType_ReferenceError,
+            V8ErrorKind::SyntaxError => v8_ErrorType_v8_ErrorType_SyntaxError,
+            V8ErrorKind::Error | V8ErrorKind::Custom(_) => v8_ErrorType_v8_ErrorType_Error,
+        };
+        let message = isolate.new_string(message);
+        let error = V8LocalValue {
+            inner_val: unsafe { v8_NewError(message.inner_string, error_type) },
+        };
+        if let V8ErrorKind::Custom(name) = kind {
+            error.as_object().set(
+                self,
+                &isolate.new_string("name").to_value(),
+                &isolate.new_string(name).to_value(),
+            );
+        }
+        error
+    }
+
+    /// Raise the given error as a JS error of the matching class, see `V8Throwable`.
+    pub fn raise_error<T: V8Throwable + ?Sized>(&self, error: &T) {
+        let error = self.new_error(&error.error_kind(), &error.error_message());
+        self.get_isolate().raise_exception(error);
+    }
+
+    /// Same as `raise_error` but also attach the given Rust payload to the raised
+    /// error, the payload is not visible to JS code. The payload can be retrieved
+    /// using `V8LocalValue::get_error_payload`, also after JS code caught and rethrew
+    /// the error. The payload is dropped when the error is garbage collected, during
+    /// the garbage collection, so its `Drop` is not allowed to use the isolate.
+    pub fn raise_error_with_payload<T: V8Throwable + ?Sized, P: Any>(&self, error: &T, payload: P) {
+        let isolate = self.get_isolate();
+        let error = self.new_error(&error.error_kind(), &error.error_message());
+        let payload = V8LocalValue::new_external_data(&isolate, payload);
+        error
+            .as_object()
+            .set_private(self, ERROR_PAYLOAD_NAME, &payload);
+        isolate.raise_exception(error);
+    }
+
     /// Create a new resolver object
     #[must_use]
     pub fn new_resolver(&self) -> V8LocalResolver {
@@ -154,11 +778,59 @@ impl V8ContextScope {
     }
 }
 
+/// A private data slot set by `V8ContextScope::set_private_data_many`, the value is
+/// read back with `V8ContextScope::get_private_data` of the same type.
+pub struct V8PrivateData<'a> {
+    index: usize,
+    pd: *const c_void,
+    _data: PhantomData<&'a ()>,
+}
+
+impl<'a> V8PrivateData<'a> {
+    /// Set the given value on the private data slot of the given index.
+    #[must_use]
+    pub fn new<T>(index: usize, pd: &'a T) -> Self {
+        V8PrivateData {
+            index,
+            pd: (pd as *const T).cast::<c_void>(),
+            _data: PhantomData,
+        }
+    }
+}
+
+/// Restores the private data slots set using `V8ContextScope::set_private_data_many`
+/// to their previous values when dropped.
+pub struct V8ContextScopeDataGuard<'a> {
+    ctx_scope: &'a V8ContextScope,
+    previous: Vec<(usize, *mut c_void)>,
+}
+
+impl<'a> Drop for V8ContextScopeDataGuard<'a> {
+    fn drop(&mut self) {
+        for (index, pd) in self.previous.iter().rev() {
+            unsafe { v8_SetPrivateDataOnCtxRef(self.ctx_scope.inner_ctx_ref, *index, *pd) };
+        }
+    }
+}
+
 impl Drop for V8ContextScope {
     fn drop(&mut self) {
+        let mut error = None;
         if self.exit_on_drop {
+            // Context scopes must be dropped in the reverse order of their creation,
+            // exiting a context which is not the current one corrupts the isolate state.
+            debug_assert!(
+                self.is_current(),
+                "Context scope was dropped while another context scope is active"
+            );
             unsafe { v8_ExitContextRef(self.inner_ctx_ref) }
+            error = context_scope_exited(self);
+        }
+        if let Some(execution_tracker) = self.execution_tracker.take() {
+            context_tracker_exited(&execution_tracker);
+            execution_tracker.lock().unwrap().exit();
         }
         unsafe { v8_FreeContextRef(self.inner_ctx_ref) }
+        check_scope_order(error);
     }
 }
@@ -0,0 +1,75 @@
+use crate::v8::isolate::IsolateId;
+use crate::v8::isolate_scope::V8IsolateScope;
+use crate::v8::v8_context::V8Context;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_object_template::{V8LocalObjectTemplate, V8PersistedObjectTemplate};
+use crate::v8::v8_script::V8PersistedUnboundScript;
+
+use std::sync::Mutex;
+
+/// A recipe for creating contexts: the context globals and the setup scripts
+/// that run on each new context. The setup scripts are compiled once, on the
+/// first instantiation, and the compiled code is reused by the following ones.
+/// Useful for giving each request a fresh global state without paying the
+/// full setup cost.
+pub struct V8ContextTemplate {
+    isolate_id: IsolateId,
+    globals: Option<V8PersistedObjectTemplate>,
+    scripts: Vec<(String, Mutex<Option<V8PersistedUnboundScript>>)>,
+}
+
+impl V8ContextTemplate {
+    /// Create a new context template for the isolate of the given scope.
+    #[must_use]
+    pub fn new(isolate_scope: &V8IsolateScope, globals: Option<&V8LocalObjectTemplate>) -> Self {
+        let isolate = isolate_scope.isolate;
+        Self {
+            isolate_id: isolate.get_id(),
+            globals: globals.map(|g| g.persist(isolate)),
+            scripts: Vec::new(),
+        }
+    }
+
+    /// Add a script that will run on each new context, scripts run by the
+    /// order they were added. Like `V8ContextScope::eval`, scripts run in sloppy mode.
+    #[must_use]
+    pub fn add_script(mut self, code: &str) -> Self {
+        self.scripts.push((code.to_string(), Mutex::new(None)));
+        self
+    }
+
+    /// Create a new context and run the setup scripts on it. On failure, return
+    /// the string representation of the exception raised by the setup scripts.
+    pub fn instantiate(&self, isolate_scope: &V8IsolateScope) -> Result<V8Context, String> {
+        let isolate = isolate_scope.isolate;
+        if isolate.get_id() != self.isolate_id {
+            return Err("Context template belongs to another isolate".to_string());
+        }
+        let _h_scope = isolate.new_handlers_scope();
+        let globals = self.globals.as_ref().map(|g| g.to_local(isolate));
+        let ctx = isolate_scope.new_context(globals.as_ref());
+        {
+            let ctx_scope = ctx.enter();
+            let trycatch = isolate.new_try_catch();
+            for (code, unbound_script) in &self.scripts {
+                let script = {
+                    let mut unbound_script = unbound_script.lock().unwrap();
+                    match unbound_script.as_ref() {
+                        Some(s) => s.bind(&ctx_scope),
+                        None => {
+                            let script = ctx_scope
+                                .compile(&isolate.new_string(code))
+                                .ok_or_else(|| V8ContextScope::eval_error(isolate, &trycatch))?;
+                            *unbound_script = Some(script.to_unbound(isolate));
+                            script
+                        }
+                    }
+                };
+                script
+                    .run(&ctx_scope)
+                    .ok_or_else(|| V8ContextScope::eval_error(isolate, &trycatch))?;
+            }
+        }
+        Ok(ctx)
+    }
+}
@@ -0,0 +1,67 @@
+use std::num::{ParseFloatError, ParseIntError, TryFromIntError};
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
+
+/// The class of a JS error created from a `V8Throwable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum V8ErrorKind {
+    Error,
+    TypeError,
+    RangeError,
+    ReferenceError,
+    SyntaxError,
+    /// An `Error` with the given name, e.g. "ValidationError".
+    Custom(String),
+}
+
+/// An error that can be raised as a JS error, see `V8ContextScope::raise_error`.
+pub trait V8Throwable {
+    /// The JS error message.
+    fn error_message(&self) -> String;
+
+    /// The class of the JS error, `Error` by default.
+    fn error_kind(&self) -> V8ErrorKind {
+        V8ErrorKind::Error
+    }
+}
+
+impl V8Throwable for str {
+    fn error_message(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl V8Throwable for String {
+    fn error_message(&self) -> String {
+        self.clone()
+    }
+}
+
+macro_rules! throwable {
+    ($($t:ty => $kind:ident),*) => {
+        $(
+            impl V8Throwable for $t {
+                fn error_message(&self) -> String {
+                    self.to_string()
+                }
+
+                fn error_kind(&self) -> V8ErrorKind {
+                    V8ErrorKind::$kind
+                }
+            }
+        )*
+    };
+}
+
+// Follows the JS conventions: failing to parse raises a SyntaxError (like
+// `BigInt('foo')`), a number out of range raises a RangeError and invalid
+// data raises a TypeError.
+throwable!(
+    ParseIntError => SyntaxError,
+    ParseFloatError => SyntaxError,
+    TryFromIntError => RangeError,
+    Utf8Error => TypeError,
+    FromUtf8Error => TypeError,
+    std::io::Error => Error,
+    std::fmt::Error => Error
+);
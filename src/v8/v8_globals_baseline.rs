@@ -0,0 +1,89 @@
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_value::V8PersistValue;
+
+/// Records the global object and returns a function that restores it. The builtins
+/// used by the restore function are captured at record time and the descriptors are
+/// detached from `Object.prototype`, so polluting the builtins can not break the restore.
+const RECORD_GLOBALS: &str = r#"(() => {
+    const global = globalThis;
+    const { getOwnPropertyDescriptors, getOwnPropertyDescriptor, setPrototypeOf } = Object;
+    const { ownKeys, deleteProperty, defineProperty } = Reflect;
+    const toString = String;
+    const baseline = getOwnPropertyDescriptors(global);
+    const baselineKeys = ownKeys(baseline);
+    for (let i = 0; i < baselineKeys.length; i++) {
+        setPrototypeOf(baseline[baselineKeys[i]], null);
+    }
+    setPrototypeOf(baseline, null);
+    return () => {
+        const failed = [];
+        const keys = ownKeys(global);
+        for (let i = 0; i < keys.length; i++) {
+            const key = keys[i];
+            if (key in baseline || deleteProperty(global, key)) {
+                continue;
+            }
+            // Globals declared with `var` can not be deleted, reset their value instead.
+            const desc = getOwnPropertyDescriptor(global, key);
+            if (desc.writable) {
+                global[key] = undefined;
+                continue;
+            }
+            failed[failed.length] = toString(key);
+        }
+        for (let i = 0; i < baselineKeys.length; i++) {
+            const key = baselineKeys[i];
+            if (!defineProperty(global, key, baseline[key])) {
+                failed[failed.length] = toString(key);
+            }
+        }
+        return failed;
+    };
+})()"#;
+
+/// A recorded state of a context global object, see `V8ContextScope::record_globals`.
+pub struct V8GlobalsBaseline {
+    restore: V8PersistValue,
+}
+
+impl V8ContextScope {
+    /// Record the current properties of the context global object, so they can be
+    /// restored between executions using `restore_globals` without paying for a new
+    /// context. Only the global object own properties are recorded: changes made to
+    /// the builtins themselves (e.g. `Array.prototype.x = 1`) and the `let`, `const`
+    /// and `class` declarations of scripts (which do not live on the global object)
+    /// survive the restore, consider freezing the builtins when this matters.
+    pub fn record_globals(&self) -> Result<V8GlobalsBaseline, String> {
+        let restore = self.eval(RECORD_GLOBALS)?;
+        Ok(V8GlobalsBaseline {
+            restore: restore.persist(&self.get_isolate()),
+        })
+    }
+
+    /// Restore the global object of the context the baseline was recorded on:
+    /// properties added since then are removed (or set to `undefined` if they can
+    /// not be deleted) and the recorded properties are redefined. On failure, return
+    /// the properties that could not be restored.
+    pub fn restore_globals(&self, baseline: &V8GlobalsBaseline) -> Result<(), String> {
+        let isolate = self.get_isolate();
+        let trycatch = isolate.new_try_catch();
+        let failed = baseline
+            .restore
+            .as_local(&isolate)
+            .call(self, None)
+            .ok_or_else(|| Self::eval_error(&isolate, &trycatch))?;
+        let failed = failed.as_array().try_to_vec_with(self, |key| {
+            key.to_utf8(&isolate)
+                .map(|key| key.as_str().to_string())
+                .ok_or_else(|| "not a string".to_string())
+        })?;
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Failed restoring the globals: {}",
+                failed.join(", ")
+            ))
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::v8_c_raw::bindings::{v8_MapAsArray, v8_ValueGetIterator};
+
+use crate::v8::v8_array::V8LocalArray;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_set::V8LocalSet;
+use crate::v8::v8_value::V8LocalValue;
+
+/// A JS collection whose values can be iterated from Rust, allows native functions
+/// to accept any iterable argument (arrays, sets, maps, generators, ...) uniformly.
+pub trait V8Iterable {
+    /// Return an iterator over the collection values, or None if the value is not
+    /// iterable. Maps yield `[key, value]` entries, like `for...of` does.
+    fn values<'a>(&self, ctx_scope: &'a V8ContextScope) -> Option<V8ValuesIter<'a>>;
+}
+
+/// Iterator over the values of a `V8Iterable`. Arrays are iterated by index and maps
+/// by their entries, other values (including strings) are iterated using the JS
+/// iterator protocol (`Symbol.iterator`). If the JS
+/// iterator raises an exception the iteration stops and the exception is left for
+/// the caller try catch object.
+pub struct V8ValuesIter<'a> {
+    ctx_scope: &'a V8ContextScope,
+    state: IterState<'a>,
+}
+
+enum IterState<'a> {
+    Array { arr: V8LocalArray, index: usize },
+    Map(V8MapEntries<'a>),
+    Protocol { iterator: V8LocalValue },
+    Done,
+}
+
+/// Iterator over the `(key, value)` entries of a JS map, see `V8LocalValue::map_entries`.
+/// The entries are taken when the iterator is created, so changes to the map during
+/// the iteration are not seen.
+pub struct V8MapEntries<'a> {
+    ctx_scope: &'a V8ContextScope,
+    entries: V8LocalArray,
+    index: usize,
+}
+
+impl<'a> Iterator for V8MapEntries<'a> {
+    type Item = (V8LocalValue, V8LocalValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+        let key = self.entries.get(self.ctx_scope, self.index);
+        let val = self.entries.get(self.ctx_scope, self.index + 1);
+        self.index += 2;
+        Some((key, val))
+    }
+}
+
+impl V8LocalValue {
+    /// Return an iterator over the `(key, value)` entries of the map,
+    /// or None if the value is not a map.
+    #[must_use]
+    pub fn map_entries<'a>(&self, ctx_scope: &'a V8ContextScope) -> Option<V8MapEntries<'a>> {
+        if !self.is_map() {
+            return None;
+        }
+        Some(V8MapEntries {
+            ctx_scope,
+            entries: V8LocalArray {
+                inner_array: unsafe { v8_MapAsArray(self.inner_val) },
+            },
+            index: 0,
+        })
+    }
+}
+
+impl<'a> V8ValuesIter<'a> {
+    fn from_protocol(ctx_scope: &'a V8ContextScope, val: &V8LocalValue) -> Option<Self> {
+        let inner_val = unsafe { v8_ValueGetIterator(ctx_scope.inner_ctx_ref, val.inner_val) };
+        if inner_val.is_null() {
+            return None;
+        }
+        Some(Self {
+            ctx_scope,
+            state: IterState::Protocol {
+                iterator: V8LocalValue { inner_val },
+            },
+        })
+    }
+
+    fn protocol_next(ctx_scope: &V8ContextScope, iterator: &V8LocalValue) -> Option<V8LocalValue> {
+        let isolate = ctx_scope.get_isolate();
+        let next = iterator
+            .as_object()
+            .get(ctx_scope, &isolate.new_string("next").to_value())?;
+        if !next.is_function() {
+            return None;
+        }
+        let res = next.call_with_receiver(ctx_scope, iterator, None)?;
+        if !res.is_object() {
+            return None;
+        }
+        let res = res.as_object();
+        let done = res.get(ctx_scope, &isolate.new_string("done").to_value())?;
+        if done.is_boolean() && done.get_boolean() {
+            return None;
+        }
+        res.get(ctx_scope, &isolate.new_string("value").to_value())
+    }
+}
+
+impl<'a> Iterator for V8ValuesIter<'a> {
+    type Item = V8LocalValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = match &mut self.state {
+            IterState::Array { arr, index } => {
+                if *index >= arr.len() {
+                    None
+                } else {
+                    *index += 1;
+                    Some(arr.get(self.ctx_scope, *index - 1))
+                }
+            }
+            IterState::Map(entries) => entries.next().map(|(key, val)| {
+                let isolate = self.ctx_scope.get_isolate();
+                isolate.new_array(&[&key, &val]).to_value()
+            }),
+            IterState::Protocol { iterator } => Self::protocol_next(self.ctx_scope, iterator),
+            IterState::Done => None,
+        };
+        if res.is_none() {
+            self.state = IterState::Done;
+        }
+        res
+    }
+}
+
+impl V8Iterable for V8LocalArray {
+    fn values<'a>(&self, ctx_scope: &'a V8ContextScope) -> Option<V8ValuesIter<'a>> {
+        Some(V8ValuesIter {
+            ctx_scope,
+            state: IterState::Array {
+                arr: self.to_value().as_array(),
+                index: 0,
+            },
+        })
+    }
+}
+
+impl V8Iterable for V8LocalSet {
+    fn values<'a>(&self, ctx_scope: &'a V8ContextScope) -> Option<V8ValuesIter<'a>> {
+        V8ValuesIter::from_protocol(ctx_scope, &self.to_value())
+    }
+}
+
+impl V8Iterable for V8LocalValue {
+    fn values<'a>(&self, ctx_scope: &'a V8ContextScope) -> Option<V8ValuesIter<'a>> {
+        if self.is_array() {
+            return self.as_array().values(ctx_scope);
+        }
+        if let Some(entries) = self.map_entries(ctx_scope) {
+            return Some(V8ValuesIter {
+                ctx_scope,
+                state: IterState::Map(entries),
+            });
+        }
+        V8ValuesIter::from_protocol(ctx_scope, self)
+    }
+}
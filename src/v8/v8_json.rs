@@ -0,0 +1,119 @@
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_value::V8LocalValue;
+
+/// The name of the private value, on the context globals, that holds the
+/// function created by `STRINGIFY_STABLE` for the context.
+const STRINGIFY_STABLE_NAME: &str = "v8_rs::json_stringify_stable";
+
+/// Returns `JSON.stringify` with a replacer that copies every plain object into an
+/// object with sorted keys. The copies have no prototype so keys such as `__proto__`
+/// are kept as regular properties. Boxed primitives are left to `JSON.stringify`.
+/// The builtins are captured when the function is created, so replacing them later
+/// (e.g. `JSON.stringify = ...`) does not change the output.
+const STRINGIFY_STABLE: &str = r#"(() => {
+    const { stringify } = JSON;
+    const { isArray } = Array;
+    const { keys, create } = Object;
+    const { apply } = Reflect;
+    const { sort } = Array.prototype;
+    const NumberClass = Number;
+    const StringClass = String;
+    const BooleanClass = Boolean;
+    return (value, replacer, space) => stringify(value, function (key, val) {
+        if (typeof replacer === 'function') {
+            val = apply(replacer, this, [key, val]);
+        }
+        if (val === null || typeof val !== 'object' || isArray(val) ||
+            val instanceof NumberClass || val instanceof StringClass || val instanceof BooleanClass) {
+            return val;
+        }
+        const sorted = create(null);
+        const sortedKeys = apply(sort, keys(val), []);
+        for (let i = 0; i < sortedKeys.length; i++) {
+            sorted[sortedKeys[i]] = val[sortedKeys[i]];
+        }
+        return sorted;
+    }, space);
+})()"#;
+
+/// Options of `V8ContextScope::json_stringify_stable`, for example:
+/// `V8JsonStringifyOptions::new().pretty()`.
+#[derive(Default)]
+pub struct V8JsonStringifyOptions<'a> {
+    replacer: Option<&'a V8LocalValue>,
+    space: Option<&'a str>,
+}
+
+impl<'a> V8JsonStringifyOptions<'a> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A JS function called with each key and value, like the `JSON.stringify`
+    /// replacer. Its result is what gets serialized (and sorted).
+    #[must_use]
+    pub fn replacer(mut self, replacer: &'a V8LocalValue) -> Self {
+        self.replacer = Some(replacer);
+        self
+    }
+
+    /// Indent nested values with the given string, like the `JSON.stringify` space.
+    #[must_use]
+    pub fn space(mut self, space: &'a str) -> Self {
+        self.space = Some(space);
+        self
+    }
+
+    /// Indent nested values with two spaces.
+    #[must_use]
+    pub fn pretty(self) -> Self {
+        self.space("  ")
+    }
+}
+
+impl V8ContextScope {
+    /// Serialize the value to JSON with the object keys sorted, so equal values always
+    /// give the same output regardless of the order their properties were added in.
+    /// Note that JS orders integer-like keys numerically before the other keys, and
+    /// so does the output. Fails if the value can not be serialized (for example, a
+    /// cyclic object or a function).
+    ///
+    /// The JS builtins it relies on are captured by the first call on the context,
+    /// and kept for the following calls.
+    pub fn json_stringify_stable(
+        &self,
+        val: &V8LocalValue,
+        options: &V8JsonStringifyOptions,
+    ) -> Result<String, String> {
+        let isolate = self.get_isolate();
+        let globals = self.get_globals();
+        let stringify = match globals.get_private(self, STRINGIFY_STABLE_NAME) {
+            Some(stringify) if stringify.is_function() => stringify,
+            _ => {
+                let stringify = self.eval(STRINGIFY_STABLE)?;
+                globals.set_private(self, STRINGIFY_STABLE_NAME, &stringify);
+                stringify
+            }
+        };
+        let trycatch = isolate.new_try_catch();
+        let undefined = isolate.new_undefined();
+        let space = options.space.map(|s| isolate.new_string(s).to_value());
+        let res = stringify
+            .call(
+                self,
+                Some(&[
+                    val,
+                    options.replacer.unwrap_or(&undefined),
+                    space.as_ref().unwrap_or(&undefined),
+                ]),
+            )
+            .ok_or_else(|| Self::eval_error(&isolate, &trycatch))?;
+        if res.is_undefined() {
+            return Err("Value can not be serialized to JSON".to_string());
+        }
+        res.to_utf8(&isolate)
+            .map(|s| s.as_str().to_string())
+            .ok_or_else(|| "Value can not be serialized to JSON".to_string())
+    }
+}
@@ -1,40 +1,122 @@
 use crate::v8_c_raw::bindings::{
-    v8_EvaluateModule, v8_FreeModule, v8_FreePersistedModule, v8_InitiateModule,
-    v8_ModuleGetIdentityHash, v8_ModulePersist, v8_ModuleToLocal, v8_context_ref, v8_local_module,
-    v8_local_string, v8_persisted_module,
+    v8_EvaluateModule, v8_ExceptionGetScriptName, v8_FreeModule, v8_FreePersistedModule,
+    v8_FreeWeakModule, v8_InitiateModule, v8_ModuleEquals, v8_ModuleGetIdentityHash,
+    v8_ModuleGetNamespace, v8_ModuleGetStatus, v8_ModuleIsGraphAsync, v8_ModulePersist,
+    v8_ModuleStatus_v8_ModuleStatus_Errored, v8_ModuleStatus_v8_ModuleStatus_Evaluated,
+    v8_ModuleStatus_v8_ModuleStatus_Evaluating, v8_ModuleStatus_v8_ModuleStatus_Instantiated,
+    v8_ModuleStatus_v8_ModuleStatus_Instantiating, v8_ModuleToLocal, v8_NewWeakModule,
+    v8_WeakModuleEquals, v8_WeakModuleIsCollected, v8_context_ref, v8_local_module,
+    v8_local_string, v8_persisted_module, v8_weak_module,
 };
 
-use crate::v8::isolate::V8Isolate;
+use crate::v8::isolate::{IsolateId, V8Isolate};
+use crate::v8::try_catch::V8TryCatch;
 use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_promise::{V8LocalPromise, V8PromiseState};
 use crate::v8::v8_string::V8LocalString;
 use crate::v8::v8_value::V8LocalValue;
-use std::os::raw::c_int;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ptr;
+use std::time::{Duration, Instant};
+
+/// The longest `evaluate_and_wait` blocks before checking for tasks posted by v8 itself.
+const MAX_TASKS_WAIT: Duration = Duration::from_millis(10);
 
 /// JS script object
 pub struct V8LocalModule {
     pub(crate) inner_module: *mut v8_local_module,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum V8ModuleStatus {
+    Uninstantiated,
+    Instantiating,
+    Instantiated,
+    Evaluating,
+    Evaluated,
+    Errored,
+}
+
+/// The stage of the module graph loading in which a module failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V8ModuleErrorStage {
+    /// The module code failed to compile.
+    Compile,
+    /// The module loader failed to return an imported module.
+    Resolve,
+    /// Linking the module graph failed, for example on an import of a missing export.
+    Instantiate,
+    /// The module code raised an exception.
+    Evaluate,
+}
+
+/// A failure of loading a module graph, see `V8LocalModule::initialize_checked`
+/// and `V8LocalModule::evaluate_checked`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V8ModuleError {
+    pub stage: V8ModuleErrorStage,
+    /// The name of the module that failed, None if it is not known.
+    pub module: Option<String>,
+    /// The specifiers of the imports that lead from the root module to the module
+    /// that failed, empty if the root module failed or if the chain is not known.
+    pub import_chain: Vec<String>,
+    /// The string representation of the raised exception.
+    pub message: String,
+}
+
+/// The import chains of the modules of an initialized module graph, returned by
+/// `V8LocalModule::initialize_checked`. Modules are identified by their specifier.
+#[derive(Debug, Default)]
+pub struct V8ModuleGraph {
+    chains: HashMap<String, Vec<String>>,
+}
+
+impl V8ModuleGraph {
+    /// Return the specifiers of the imports that lead from the root module to the
+    /// module with the given specifier (including it). If the module is imported
+    /// more than once, the first chain it was resolved through is returned.
+    #[must_use]
+    pub fn import_chain(&self, specifier: &str) -> Option<&[String]> {
+        self.chains.get(specifier).map(Vec::as_slice)
+    }
+}
+
 pub struct V8PersistedModule {
     pub(crate) inner_persisted_module: *mut v8_persisted_module,
+    pub(crate) isolate_id: IsolateId,
+}
+
+/// A weak handle to a module, does not keep the module alive. Used internally
+/// and released along with the isolate, so it is not counted as a persisted handle
+/// and does not prevent disposing the isolate.
+pub(crate) struct V8WeakModule {
+    inner_weak_module: *mut v8_weak_module,
+    isolate_id: IsolateId,
 }
 
 pub(crate) extern "C" fn load_module<
-    T: Fn(&V8Isolate, &V8ContextScope, &V8LocalString, i64) -> Option<V8LocalModule>,
+    T: Fn(&V8Isolate, &V8ContextScope, &V8LocalString, &V8LocalModule) -> Option<V8LocalModule>,
 >(
     v8_ctx_ref: *mut v8_context_ref,
     name: *mut v8_local_string,
-    identity_hash: c_int,
+    referrer: *mut v8_local_module,
 ) -> *mut v8_local_module {
     let ctx_scope = V8ContextScope {
         inner_ctx_ref: v8_ctx_ref,
         exit_on_drop: false,
+        execution_tracker: None,
     };
     let isolate = ctx_scope.get_isolate();
     let name_obj = V8LocalString { inner_string: name };
+    let referrer = V8LocalModule {
+        inner_module: referrer,
+    };
+    let name_obj = ctx_scope
+        .import_map_resolve(&name_obj, &referrer)
+        .unwrap_or(name_obj);
     let load_callback: &T = ctx_scope.get_private_data_mut_raw(0).unwrap();
-    let res = load_callback(&isolate, &ctx_scope, &name_obj, identity_hash as i64);
+    let res = load_callback(&isolate, &ctx_scope, &name_obj, &referrer);
     match res {
         Some(mut r) => {
             let inner_module = r.inner_module;
@@ -45,6 +127,74 @@ pub(crate) extern "C" fn load_module<
     }
 }
 
+/// Return the name of the script that raised the given exception, if known.
+fn exception_script_name(isolate: &V8Isolate, exception: &V8LocalValue) -> Option<String> {
+    let inner_string =
+        unsafe { v8_ExceptionGetScriptName(isolate.inner_isolate, exception.inner_val) };
+    if inner_string.is_null() {
+        return None;
+    }
+    let name = V8LocalString { inner_string }
+        .to_value()
+        .to_utf8(isolate)?
+        .as_str()
+        .to_string();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+impl V8ModuleError {
+    pub(crate) fn from_exception(
+        isolate: &V8Isolate,
+        stage: V8ModuleErrorStage,
+        exception: &V8LocalValue,
+    ) -> Self {
+        Self {
+            stage,
+            module: exception_script_name(isolate, exception),
+            import_chain: Vec::new(),
+            message: exception.to_utf8(isolate).map_or_else(
+                || "Failed loading module".to_string(),
+                |e| e.as_str().to_string(),
+            ),
+        }
+    }
+
+    pub(crate) fn from_try_catch(
+        isolate: &V8Isolate,
+        stage: V8ModuleErrorStage,
+        trycatch: &V8TryCatch,
+    ) -> Self {
+        if trycatch.has_terminated() {
+            return Self {
+                stage,
+                module: None,
+                import_chain: Vec::new(),
+                message: "Execution was terminated".to_string(),
+            };
+        }
+        if !trycatch.has_caught() {
+            return Self {
+                stage,
+                module: None,
+                import_chain: Vec::new(),
+                message: String::new(),
+            };
+        }
+        Self::from_exception(isolate, stage, &trycatch.get_exception())
+    }
+
+    fn with_chain(mut self, graph: &V8ModuleGraph) -> Self {
+        if let Some(chain) = self.module.as_deref().and_then(|m| graph.import_chain(m)) {
+            self.import_chain = chain.to_vec();
+        }
+        self
+    }
+}
+
 impl V8LocalModule {
     pub fn initialize<
         T: Fn(&V8Isolate, &V8ContextScope, &V8LocalString, i64) -> Option<V8LocalModule>,
@@ -52,6 +202,20 @@ impl V8LocalModule {
         &self,
         ctx_scope: &V8ContextScope,
         load_module_callback: T,
+    ) -> bool {
+        self.initialize_with_referrer(ctx_scope, |isolate, ctx_scope, name, referrer| {
+            load_module_callback(isolate, ctx_scope, name, referrer.get_identity_hash())
+        })
+    }
+
+    /// Same as `initialize` but the module loader gets the importing module itself
+    /// instead of its identity hash.
+    fn initialize_with_referrer<
+        T: Fn(&V8Isolate, &V8ContextScope, &V8LocalString, &V8LocalModule) -> Option<V8LocalModule>,
+    >(
+        &self,
+        ctx_scope: &V8ContextScope,
+        load_module_callback: T,
     ) -> bool {
         ctx_scope.set_private_data_raw(0, Some(&load_module_callback));
         let res = unsafe {
@@ -69,8 +233,86 @@ impl V8LocalModule {
         }
     }
 
+    /// Same as `initialize` but on failure, report the stage and the module that
+    /// failed along with the import chain that lead to it. The exception is caught
+    /// and not propagated to the caller. On success, return the import chains of
+    /// the module graph, which can be given to `evaluate_checked`.
+    pub fn initialize_checked<
+        T: Fn(&V8Isolate, &V8ContextScope, &V8LocalString, i64) -> Option<V8LocalModule>,
+    >(
+        &self,
+        ctx_scope: &V8ContextScope,
+        load_module_callback: T,
+    ) -> Result<V8ModuleGraph, V8ModuleError> {
+        let isolate = ctx_scope.get_isolate();
+        // The import chains of the resolved modules, used to find the chain of the
+        // importing module. Modules are matched by identity, the identity hash only
+        // selects the bucket as different modules might share the same hash.
+        let chains_by_module = RefCell::new(HashMap::from([(
+            self.get_identity_hash(),
+            vec![(self.persist(&isolate), Vec::new())],
+        )]));
+        let chain_of = |isolate: &V8Isolate, module: &V8LocalModule| {
+            chains_by_module
+                .borrow()
+                .get(&module.get_identity_hash())?
+                .iter()
+                .find(|(m, _)| m.to_local(isolate).is_same(module))
+                .map(|(_, chain)| chain.clone())
+        };
+        let graph = RefCell::new(V8ModuleGraph::default());
+        let unresolved = RefCell::new(None);
+        let trycatch = isolate.new_try_catch();
+        let res = self.initialize_with_referrer(ctx_scope, |isolate, ctx_scope, name, referrer| {
+            let specifier = name
+                .to_value()
+                .to_utf8(isolate)
+                .map_or_else(String::new, |s| s.as_str().to_string());
+            let mut chain = chain_of(isolate, referrer).unwrap_or_default();
+            chain.push(specifier.clone());
+            let module =
+                load_module_callback(isolate, ctx_scope, name, referrer.get_identity_hash());
+            match &module {
+                Some(m) => {
+                    if chain_of(isolate, m).is_none() {
+                        chains_by_module
+                            .borrow_mut()
+                            .entry(m.get_identity_hash())
+                            .or_default()
+                            .push((m.persist(isolate), chain.clone()));
+                    }
+                    graph.borrow_mut().chains.entry(specifier).or_insert(chain);
+                }
+                None => *unresolved.borrow_mut() = Some((specifier, chain)),
+            }
+            module
+        });
+        let graph = graph.into_inner();
+        if res {
+            return Ok(graph);
+        }
+        if let Some((specifier, chain)) = unresolved.into_inner() {
+            let mut err =
+                V8ModuleError::from_try_catch(&isolate, V8ModuleErrorStage::Resolve, &trycatch);
+            if err.message.is_empty() {
+                err.message = format!("Failed resolving module '{}'", specifier);
+            }
+            err.module = Some(specifier);
+            err.import_chain = chain;
+            return Err(err);
+        }
+        let mut err =
+            V8ModuleError::from_try_catch(&isolate, V8ModuleErrorStage::Instantiate, &trycatch)
+                .with_chain(&graph);
+        if err.message.is_empty() {
+            err.message = "Failed instantiating module".to_string();
+        }
+        Err(err)
+    }
+
     pub fn evaluate(&self, ctx_scope: &V8ContextScope) -> Option<V8LocalValue> {
-        let res = unsafe { v8_EvaluateModule(self.inner_module, ctx_scope.inner_ctx_ref) };
+        let res = ctx_scope
+            .timed_run(|| unsafe { v8_EvaluateModule(self.inner_module, ctx_scope.inner_ctx_ref) });
         if res.is_null() {
             None
         } else {
@@ -78,29 +320,199 @@ impl V8LocalModule {
         }
     }
 
-    /// Convert the module into a generic JS value
+    /// Same as `evaluate` but on failure, report the module that raised the exception
+    /// along with its import chain from the given module graph. The evaluation of a
+    /// module graph that uses top level await might fail after this function returns,
+    /// in which case the returned promise is rejected.
+    pub fn evaluate_checked(
+        &self,
+        ctx_scope: &V8ContextScope,
+        graph: &V8ModuleGraph,
+    ) -> Result<V8LocalValue, V8ModuleError> {
+        let isolate = ctx_scope.get_isolate();
+        let trycatch = isolate.new_try_catch();
+        let res = self.evaluate(ctx_scope).ok_or_else(|| {
+            V8ModuleError::from_try_catch(&isolate, V8ModuleErrorStage::Evaluate, &trycatch)
+                .with_chain(graph)
+        })?;
+        if res.is_promise() {
+            let promise = res.as_promise();
+            if promise.state() == V8PromiseState::Rejected {
+                return Err(V8ModuleError::from_exception(
+                    &isolate,
+                    V8ModuleErrorStage::Evaluate,
+                    &promise.get_result(),
+                )
+                .with_chain(graph));
+            }
+        }
+        Ok(res)
+    }
+
+    /// Evaluate the module and wait for the evaluation to finish. A module that uses
+    /// top level await (directly or through its dependencies) might not finish its
+    /// evaluation when `evaluate` returns, in this case the microtasks and the isolate
+    /// foreground tasks are processed until the evaluation promise is settled or until
+    /// the given timeout passes (`Duration::MAX` waits as long as needed). While there is
+    /// no task to run, the thread blocks until a task is posted using `post_task`. Return the evaluation promise (which is still pending if
+    /// the timeout passed) or None if the evaluation failed synchronously.
+    pub fn evaluate_and_wait(
+        &self,
+        ctx_scope: &V8ContextScope,
+        timeout: Duration,
+    ) -> Option<V8LocalPromise> {
+        let res = self.evaluate(ctx_scope)?;
+        if !res.is_promise() {
+            return None;
+        }
+        let promise = res.as_promise();
+        let isolate = ctx_scope.get_isolate();
+        let task_signal = isolate.get_id().task_signal();
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            isolate.perform_microtask_checkpoint();
+            if promise.state() != V8PromiseState::Pending {
+                break;
+            }
+            let seen = task_signal.as_ref().map_or(0, |signal| signal.posted());
+            if isolate.pump_message_loop(false) {
+                continue;
+            }
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if remaining == Some(Duration::ZERO) {
+                break;
+            }
+            // Tasks posted by v8 itself are not signaled, so they are checked for periodically.
+            let wait = remaining.map_or(MAX_TASKS_WAIT, |remaining| remaining.min(MAX_TASKS_WAIT));
+            match &task_signal {
+                Some(signal) => signal.wait(seen, wait),
+                None => std::thread::sleep(wait),
+            }
+        }
+        Some(promise)
+    }
+
+    /// Return the module status.
+    #[must_use]
+    pub fn get_status(&self) -> V8ModuleStatus {
+        let inner_status = unsafe { v8_ModuleGetStatus(self.inner_module) };
+        if inner_status == v8_ModuleStatus_v8_ModuleStatus_Instantiating {
+            V8ModuleStatus::Instantiating
+        } else if inner_status == v8_ModuleStatus_v8_ModuleStatus_Instantiated {
+            V8ModuleStatus::Instantiated
+        } else if inner_status == v8_ModuleStatus_v8_ModuleStatus_Evaluating {
+            V8ModuleStatus::Evaluating
+        } else if inner_status == v8_ModuleStatus_v8_ModuleStatus_Evaluated {
+            V8ModuleStatus::Evaluated
+        } else if inner_status == v8_ModuleStatus_v8_ModuleStatus_Errored {
+            V8ModuleStatus::Errored
+        } else {
+            V8ModuleStatus::Uninstantiated
+        }
+    }
+
+    /// Return true if the module or any of its dependencies uses top level await,
+    /// in which case the evaluation promise might still be pending after `evaluate` returns.
+    /// Only applicable if the module was initialized.
+    #[must_use]
+    pub fn is_graph_async(&self) -> bool {
+        unsafe { v8_ModuleIsGraphAsync(self.inner_module) != 0 }
+    }
+
+    /// Return the module namespace object, which holds the module exports.
+    /// Return None if the module was not yet initialized.
+    #[must_use]
+    pub fn get_namespace(&self) -> Option<V8LocalValue> {
+        match self.get_status() {
+            V8ModuleStatus::Uninstantiated | V8ModuleStatus::Instantiating => None,
+            _ => Some(V8LocalValue {
+                inner_val: unsafe { v8_ModuleGetNamespace(self.inner_module) },
+            }),
+        }
+    }
+
+    /// Persist the module so it can be used outside of the current handlers scope,
+    /// for example to initialize and evaluate a module once and access its namespace
+    /// on later isolate scopes.
     #[must_use]
     pub fn persist(&self, isolate: &V8Isolate) -> V8PersistedModule {
         let inner_persisted_module =
             unsafe { v8_ModulePersist(isolate.inner_isolate, self.inner_module) };
+        let isolate_id = isolate.get_id();
+        isolate_id.persisted_handle_created();
         V8PersistedModule {
             inner_persisted_module,
+            isolate_id,
         }
     }
 
     pub fn get_identity_hash(&self) -> i64 {
         unsafe { v8_ModuleGetIdentityHash(self.inner_module) as i64 }
     }
+
+    /// Return true if both handles point to the same module.
+    pub(crate) fn is_same(&self, other: &Self) -> bool {
+        unsafe { v8_ModuleEquals(self.inner_module, other.inner_module) != 0 }
+    }
+
+    /// Create a weak handle to the module.
+    pub(crate) fn to_weak(&self, isolate: &V8Isolate) -> V8WeakModule {
+        let inner_weak_module =
+            unsafe { v8_NewWeakModule(isolate.inner_isolate, self.inner_module) };
+        V8WeakModule {
+            inner_weak_module,
+            isolate_id: isolate.get_id(),
+        }
+    }
 }
 
 impl V8PersistedModule {
+    /// Convert the persisted module back to a local module.
+    /// # Panics
+    /// If the persisted module does not belong to the given isolate or the isolate was freed.
+    #[must_use]
     pub fn to_local(&self, isolate: &V8Isolate) -> V8LocalModule {
+        self.try_to_local(isolate).unwrap()
+    }
+
+    /// Same as `to_local` but returns an error if the isolate that created the
+    /// persisted module was already freed or if the given isolate is not the one
+    /// that created it.
+    pub fn try_to_local(&self, isolate: &V8Isolate) -> Result<V8LocalModule, &'static str> {
+        if !self.isolate_id.is_alive() {
+            return Err("The isolate of the persisted module was already freed");
+        }
+        if isolate.get_id() != self.isolate_id {
+            return Err("The persisted module belongs to a different isolate");
+        }
         let inner_module =
             unsafe { v8_ModuleToLocal(isolate.inner_isolate, self.inner_persisted_module) };
-        V8LocalModule { inner_module }
+        Ok(V8LocalModule { inner_module })
+    }
+
+    /// Return the id of the isolate that created the persisted module.
+    #[must_use]
+    pub fn get_isolate_id(&self) -> IsolateId {
+        self.isolate_id
     }
 }
 
+impl V8WeakModule {
+    /// Return true if the handle points to the given module.
+    pub(crate) fn is(&self, module: &V8LocalModule) -> bool {
+        unsafe { v8_WeakModuleEquals(self.inner_weak_module, module.inner_module) != 0 }
+    }
+
+    /// Return true if the module was garbage collected.
+    pub(crate) fn is_collected(&self) -> bool {
+        unsafe { v8_WeakModuleIsCollected(self.inner_weak_module) != 0 }
+    }
+}
+
+unsafe impl Sync for V8PersistedModule {}
+unsafe impl Send for V8PersistedModule {}
+
 impl Drop for V8LocalModule {
     fn drop(&mut self) {
         if !self.inner_module.is_null() {
@@ -111,6 +523,16 @@ impl Drop for V8LocalModule {
 
 impl Drop for V8PersistedModule {
     fn drop(&mut self) {
-        unsafe { v8_FreePersistedModule(self.inner_persisted_module) }
+        if self.isolate_id.persisted_handle_freed() {
+            unsafe { v8_FreePersistedModule(self.inner_persisted_module) }
+        }
+    }
+}
+
+impl Drop for V8WeakModule {
+    fn drop(&mut self) {
+        if self.isolate_id.is_alive() {
+            unsafe { v8_FreeWeakModule(self.inner_weak_module) }
+        }
     }
 }
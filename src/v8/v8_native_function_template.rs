@@ -1,22 +1,33 @@
 use crate::v8_c_raw::bindings::{
-    v8_ArgsGet, v8_FreeNativeFunctionTemplate, v8_GetCurrentCtxRef, v8_GetCurrentIsolate,
-    v8_NativeFunctionTemplateToFunction, v8_local_native_function_template, v8_local_value,
-    v8_local_value_arr,
+    v8_ArgsGet, v8_ArgsGetSelf, v8_FreeNativeFunctionTemplate,
+    v8_FreePersistedNativeFunctionTemplate, v8_GetCurrentCtxRef, v8_GetCurrentIsolate,
+    v8_NativeFunctionTemplatePersist, v8_NativeFunctionTemplateToFunction,
+    v8_PersistedNativeFunctionTemplateToLocal, v8_local_native_function_template, v8_local_value,
+    v8_local_value_arr, v8_persisted_native_function_template,
 };
 
+use std::any::Any;
 use std::os::raw::c_void;
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
 
-use crate::v8::isolate::V8Isolate;
+use crate::v8::isolate::{IsolateId, V8Isolate};
 use crate::v8::v8_context_scope::V8ContextScope;
 use crate::v8::v8_native_function::V8LocalNativeFunction;
-use crate::v8::v8_value::V8LocalValue;
+use crate::v8::v8_object_template::verify_template_isolate;
+use crate::v8::v8_value::{V8Coerce, V8LocalValue};
 
 /// Native function template object
 pub struct V8LocalNativeFunctionTemplate {
     pub(crate) inner_func: *mut v8_local_native_function_template,
 }
 
+/// Persisted native function template, can be used outside of the handlers scope that created it.
+pub struct V8PersistedNativeFunctionTemplate {
+    pub(crate) inner_persisted_func: *mut v8_persisted_native_function_template,
+    pub(crate) isolate_id: IsolateId,
+}
+
 /// Native function args
 pub struct V8LocalNativeFunctionArgs {
     pub(crate) inner_arr: *mut v8_local_value_arr,
@@ -33,6 +44,16 @@ pub(crate) extern "C" fn free_pd<
     }
 }
 
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg
+    } else {
+        "unknown panic"
+    }
+}
+
 pub(crate) extern "C" fn native_basic_function<
     T: Fn(&V8LocalNativeFunctionArgs, &V8Isolate, &V8ContextScope) -> Option<V8LocalValue>,
 >(
@@ -56,9 +77,21 @@ pub(crate) extern "C" fn native_basic_function<
     let ctc_scope = V8ContextScope {
         inner_ctx_ref,
         exit_on_drop: false,
+        execution_tracker: None,
     };
 
-    let res = func(&args, &isolate, &ctc_scope);
+    // Unwinding into the C++ code is undefined behaviour, a panic is
+    // caught here and raised as a JS exception instead.
+    let res = match panic::catch_unwind(AssertUnwindSafe(|| func(&args, &isolate, &ctc_scope))) {
+        Ok(res) => res,
+        Err(payload) => {
+            isolate.raise_exception_str(&format!(
+                "Native function panicked: {}",
+                panic_message(payload.as_ref())
+            ));
+            None
+        }
+    };
 
     match res {
         Some(mut r) => {
@@ -79,6 +112,46 @@ impl V8LocalNativeFunctionTemplate {
             inner_func: inner_func,
         }
     }
+
+    /// Persist the native function template so it can be saved beyond the current handlers scope.
+    #[must_use]
+    pub fn persist(&self, isolate: &V8Isolate) -> V8PersistedNativeFunctionTemplate {
+        let inner_persisted_func =
+            unsafe { v8_NativeFunctionTemplatePersist(isolate.inner_isolate, self.inner_func) };
+        let isolate_id = isolate.get_id();
+        isolate_id.persisted_handle_created();
+        V8PersistedNativeFunctionTemplate {
+            inner_persisted_func,
+            isolate_id,
+        }
+    }
+}
+
+impl V8PersistedNativeFunctionTemplate {
+    /// Convert the persisted native function template back to local native function template.
+    /// # Panics
+    /// If the persisted template does not belong to the given isolate or the isolate was freed.
+    #[must_use]
+    pub fn to_local(&self, isolate: &V8Isolate) -> V8LocalNativeFunctionTemplate {
+        self.try_to_local(isolate).unwrap()
+    }
+
+    /// Same as `to_local` but returns an error if the isolate that created the
+    /// persisted template was already freed or if the given isolate is not the
+    /// one that created the persisted template.
+    pub fn try_to_local(
+        &self,
+        isolate: &V8Isolate,
+    ) -> Result<V8LocalNativeFunctionTemplate, &'static str> {
+        verify_template_isolate(self.isolate_id, isolate)?;
+        let inner_func = unsafe {
+            v8_PersistedNativeFunctionTemplateToLocal(
+                isolate.inner_isolate,
+                self.inner_persisted_func,
+            )
+        };
+        Ok(V8LocalNativeFunctionTemplate { inner_func })
+    }
 }
 
 impl V8LocalNativeFunctionArgs {
@@ -91,6 +164,13 @@ impl V8LocalNativeFunctionArgs {
         V8LocalValue { inner_val: val }
     }
 
+    /// Return the receiver (`this`) of the native function call.
+    #[must_use]
+    pub fn get_self(&self) -> V8LocalValue {
+        let val = unsafe { v8_ArgsGetSelf(self.inner_arr) };
+        V8LocalValue { inner_val: val }
+    }
+
     /// Return the amount of arguments passed to the native function
     #[must_use]
     pub const fn len(&self) -> usize {
@@ -102,6 +182,89 @@ impl V8LocalNativeFunctionArgs {
     pub const fn is_empty(&self) -> bool {
         self.len > 0
     }
+
+    /// Return an iterator over the arguments.
+    #[must_use]
+    pub const fn iter(&self) -> V8LocalNativeFunctionArgsIter<'_> {
+        V8LocalNativeFunctionArgsIter {
+            args: self,
+            index: 0,
+        }
+    }
+}
+
+/// Iterator over the native function arguments, see `V8LocalNativeFunctionArgs::iter`.
+pub struct V8LocalNativeFunctionArgsIter<'a> {
+    args: &'a V8LocalNativeFunctionArgs,
+    index: usize,
+}
+
+impl<'a> V8LocalNativeFunctionArgsIter<'a> {
+    /// Return the position (starting from 1) of the argument that will be returned
+    /// by the next call to `next`, used for reporting conversion errors.
+    #[must_use]
+    pub const fn next_position(&self) -> usize {
+        self.index + 1
+    }
+
+    /// Convert the next argument into `T`. On failure, the error includes the
+    /// argument position and the type of the given value, for example:
+    /// "argument 2: expected i64, got string 'foo'".
+    pub fn next_as<T>(&mut self) -> Result<T, String>
+    where
+        T: for<'v> TryFrom<&'v V8LocalValue, Error = String>,
+    {
+        let position = self.next_position();
+        let expected = std::any::type_name::<T>()
+            .rsplit("::")
+            .next()
+            .unwrap_or_default();
+        let val = self
+            .next()
+            .ok_or_else(|| format!("argument {}: expected {}, got nothing", position, expected))?;
+        T::try_from(&val).map_err(|_| {
+            format!(
+                "argument {}: expected {}, got {}",
+                position,
+                expected,
+                val.describe(&self.ctx_scope())
+            )
+        })
+    }
+
+    /// Same as `next_as` but converts the argument using the JS implicit conversions,
+    /// like JS builtin functions do, e.g. "42" is accepted as a number. A missing
+    /// argument is converted as `undefined`.
+    pub fn next_coerced<T: V8Coerce>(&mut self) -> Result<T, String> {
+        let position = self.next_position();
+        let ctx_scope = self.ctx_scope();
+        let val = self
+            .next()
+            .unwrap_or_else(|| ctx_scope.get_isolate().new_undefined());
+        T::coerce(&val, &ctx_scope).map_err(|e| format!("argument {}: {}", position, e))
+    }
+
+    fn ctx_scope(&self) -> V8ContextScope {
+        let inner_isolate = unsafe { v8_GetCurrentIsolate(self.args.inner_arr) };
+        V8ContextScope {
+            inner_ctx_ref: unsafe { v8_GetCurrentCtxRef(inner_isolate) },
+            exit_on_drop: false,
+            execution_tracker: None,
+        }
+    }
+}
+
+impl<'a> Iterator for V8LocalNativeFunctionArgsIter<'a> {
+    type Item = V8LocalValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.args.len {
+            return None;
+        }
+        let val = self.args.get(self.index);
+        self.index += 1;
+        Some(val)
+    }
 }
 
 impl Drop for V8LocalNativeFunctionTemplate {
@@ -109,3 +272,14 @@ impl Drop for V8LocalNativeFunctionTemplate {
         unsafe { v8_FreeNativeFunctionTemplate(self.inner_func) }
     }
 }
+
+unsafe impl Sync for V8PersistedNativeFunctionTemplate {}
+unsafe impl Send for V8PersistedNativeFunctionTemplate {}
+
+impl Drop for V8PersistedNativeFunctionTemplate {
+    fn drop(&mut self) {
+        if self.isolate_id.persisted_handle_freed() {
+            unsafe { v8_FreePersistedNativeFunctionTemplate(self.inner_persisted_func) }
+        }
+    }
+}
@@ -1,12 +1,56 @@
 use crate::v8_c_raw::bindings::{
-    v8_FreeObject, v8_ObjectFreeze, v8_ObjectGet, v8_ObjectSet, v8_ObjectToValue,
+    v8_FreeObject, v8_ObjectDefineProperty, v8_ObjectEnterCreationContext, v8_ObjectFreeze,
+    v8_ObjectGet, v8_ObjectGetConstructorName, v8_ObjectGetIdentityHash, v8_ObjectGetInternalField,
+    v8_ObjectGetPrivate, v8_ObjectHasPrivate, v8_ObjectInternalFieldCount, v8_ObjectProtoToString,
+    v8_ObjectSet, v8_ObjectSetInternalField, v8_ObjectSetPrivate, v8_ObjectToValue,
     v8_ValueGetPropertyNames, v8_local_object,
 };
 
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::ops::BitOr;
+
+use crate::v8::handler_scope::context_scope_entered;
 use crate::v8::v8_array::V8LocalArray;
 use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_string::V8LocalString;
 use crate::v8::v8_value::V8LocalValue;
 
+/// The name of the private key used to brand objects with the given type. Type names
+/// are not unique (e.g. types declared in different blocks of a function) so the key
+/// is derived from the type id, the type name is only there for debugging.
+pub(crate) fn brand_name<T: ?Sized + 'static>() -> String {
+    format!(
+        "v8_rs::brand::{}::{:?}",
+        std::any::type_name::<T>(),
+        TypeId::of::<T>()
+    )
+}
+
+/// Attributes of a property defined using `define_property`.
+/// Attributes can be combined using `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct V8PropertyAttributes(i32);
+
+impl V8PropertyAttributes {
+    /// A regular writable, enumerable and configurable property.
+    pub const NONE: Self = Self(0);
+    /// The property can not be reassigned.
+    pub const READ_ONLY: Self = Self(1);
+    /// The property will not show up on property enumeration.
+    pub const DONT_ENUM: Self = Self(2);
+    /// The property can not be deleted.
+    pub const DONT_DELETE: Self = Self(4);
+}
+
+impl BitOr for V8PropertyAttributes {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
 /// JS object
 pub struct V8LocalObject {
     pub(crate) inner_obj: *mut v8_local_object,
@@ -36,6 +80,27 @@ impl V8LocalObject {
         };
     }
 
+    /// Add or replace an own property on the object with the given attributes.
+    /// The key must be a string or a symbol. Return true on success.
+    pub fn define_property(
+        &self,
+        ctx_scope: &V8ContextScope,
+        key: &V8LocalValue,
+        val: &V8LocalValue,
+        attributes: V8PropertyAttributes,
+    ) -> bool {
+        let res = unsafe {
+            v8_ObjectDefineProperty(
+                ctx_scope.inner_ctx_ref,
+                self.inner_obj,
+                key.inner_val,
+                val.inner_val,
+                attributes.0,
+            )
+        };
+        res != 0
+    }
+
     /// Convert the object into a generic JS value
     #[must_use]
     pub fn to_value(&self) -> V8LocalValue {
@@ -43,10 +108,193 @@ impl V8LocalObject {
         V8LocalValue { inner_val }
     }
 
+    /// Walk the nested objects along the given path of keys and return the value
+    /// of the last key. Return None if any of the keys is missing or if any value
+    /// along the path (other than the last one) is not an object.
+    /// An empty path returns the object itself.
+    #[must_use]
+    pub fn get_path(&self, ctx_scope: &V8ContextScope, path: &[&str]) -> Option<V8LocalValue> {
+        let isolate = ctx_scope.get_isolate();
+        let mut curr = self.to_value();
+        for key in path {
+            if !curr.is_object() {
+                return None;
+            }
+            let key = isolate.new_string(key).to_value();
+            curr = curr.as_object().get(ctx_scope, &key)?;
+            if curr.is_undefined() {
+                return None;
+            }
+        }
+        Some(curr)
+    }
+
+    /// Set the value on the given path of keys, missing intermediate objects are
+    /// created. Return false if an existing value along the path is not an object
+    /// or if the path is empty.
+    pub fn set_path(&self, ctx_scope: &V8ContextScope, path: &[&str], val: &V8LocalValue) -> bool {
+        let (last, intermediate) = match path.split_last() {
+            Some(res) => res,
+            None => return false,
+        };
+        let isolate = ctx_scope.get_isolate();
+        let mut curr = self.to_value().as_object();
+        for key in intermediate {
+            let key = isolate.new_string(key).to_value();
+            let next = match curr.get(ctx_scope, &key) {
+                Some(next) if !next.is_undefined() => next,
+                _ => {
+                    let next = isolate.new_object().to_value();
+                    curr.set(ctx_scope, &key, &next);
+                    next
+                }
+            };
+            if !next.is_object() {
+                return false;
+            }
+            curr = next.as_object();
+        }
+        curr.set(ctx_scope, &isolate.new_string(last).to_value(), val);
+        true
+    }
+
+    /// Return the identity hash of the object. The hash is not unique but it
+    /// stays the same for the object lifetime.
+    #[must_use]
+    pub fn get_identity_hash(&self) -> i64 {
+        unsafe { v8_ObjectGetIdentityHash(self.inner_obj) as i64 }
+    }
+
+    /// Enter the context in which the object was created, the context is exited
+    /// when the returned scope is dropped. Return None if the object has no
+    /// creation context (for example, a remote object).
+    #[must_use]
+    pub fn enter_creation_context(&self) -> Option<V8ContextScope> {
+        let inner_ctx_ref = unsafe { v8_ObjectEnterCreationContext(self.inner_obj) };
+        if inner_ctx_ref.is_null() {
+            return None;
+        }
+        let ctx_scope = V8ContextScope {
+            inner_ctx_ref,
+            exit_on_drop: true,
+            execution_tracker: None,
+        };
+        context_scope_entered(&ctx_scope);
+        Some(ctx_scope)
+    }
+
     pub fn freeze(&self, ctx_scope: &V8ContextScope) {
         unsafe { v8_ObjectFreeze(ctx_scope.inner_ctx_ref, self.inner_obj) };
     }
 
+    /// Return the amount of internal fields on the object, see
+    /// `V8LocalObjectTemplate::set_internal_field_count`.
+    #[must_use]
+    pub fn internal_field_count(&self) -> usize {
+        unsafe { v8_ObjectInternalFieldCount(self.inner_obj) }
+    }
+
+    /// Set the internal field at the given index, internal fields are invisible to JS.
+    /// Return false if the index is out of range.
+    pub fn set_internal_field(&self, index: usize, val: &V8LocalValue) -> bool {
+        unsafe { v8_ObjectSetInternalField(self.inner_obj, index, val.inner_val) != 0 }
+    }
+
+    /// Return the internal field at the given index or None if the index is out of range.
+    #[must_use]
+    pub fn get_internal_field(&self, index: usize) -> Option<V8LocalValue> {
+        let inner_val = unsafe { v8_ObjectGetInternalField(self.inner_obj, index) };
+        if inner_val.is_null() {
+            None
+        } else {
+            Some(V8LocalValue { inner_val })
+        }
+    }
+
+    /// Brand the object with the given type, the brand is invisible to JS and can be
+    /// checked with `check_brand`. Objects can also be branded by their object template,
+    /// see `V8LocalObjectTemplate::set_brand`. Return true on success.
+    pub fn set_brand<T: ?Sized + 'static>(&self, ctx_scope: &V8ContextScope) -> bool {
+        let val = ctx_scope.get_isolate().new_bool(true);
+        self.set_private(ctx_scope, &brand_name::<T>(), &val)
+    }
+
+    /// Set a value on the object which is invisible to JS.
+    pub(crate) fn set_private(
+        &self,
+        ctx_scope: &V8ContextScope,
+        name: &str,
+        val: &V8LocalValue,
+    ) -> bool {
+        let name = ctx_scope.get_isolate().new_string(name);
+        unsafe {
+            v8_ObjectSetPrivate(
+                ctx_scope.inner_ctx_ref,
+                self.inner_obj,
+                name.inner_string,
+                val.inner_val,
+            ) != 0
+        }
+    }
+
+    /// Return a value that was set using `set_private`.
+    pub(crate) fn get_private(
+        &self,
+        ctx_scope: &V8ContextScope,
+        name: &str,
+    ) -> Option<V8LocalValue> {
+        let name = ctx_scope.get_isolate().new_string(name);
+        let inner_val = unsafe {
+            v8_ObjectGetPrivate(ctx_scope.inner_ctx_ref, self.inner_obj, name.inner_string)
+        };
+        if inner_val.is_null() {
+            None
+        } else {
+            Some(V8LocalValue { inner_val })
+        }
+    }
+
+    /// Return true if the object was branded with the given type, can be used by
+    /// native methods to verify that `this` was created by the expected template
+    /// before trusting its state.
+    #[must_use]
+    pub fn check_brand<T: ?Sized + 'static>(&self, ctx_scope: &V8ContextScope) -> bool {
+        let name = ctx_scope.get_isolate().new_string(&brand_name::<T>());
+        unsafe {
+            v8_ObjectHasPrivate(ctx_scope.inner_ctx_ref, self.inner_obj, name.inner_string) != 0
+        }
+    }
+
+    /// Return the name of the function that constructed the object,
+    /// e.g. "Map", "Uint8Array" or the name of a user defined class.
+    #[must_use]
+    pub fn get_constructor_name(&self, ctx_scope: &V8ContextScope) -> Option<String> {
+        let name = V8LocalString {
+            inner_string: unsafe { v8_ObjectGetConstructorName(self.inner_obj) },
+        };
+        let isolate = ctx_scope.get_isolate();
+        Some(name.to_value().to_utf8(&isolate)?.as_str().to_string())
+    }
+
+    /// Return the class of the object as reported by `Object.prototype.toString`,
+    /// e.g. "Map", "Uint8Array" or "Object" for plain objects and user defined classes
+    /// (unless the class defines `Symbol.toStringTag`).
+    #[must_use]
+    pub fn get_internal_class_name(&self, ctx_scope: &V8ContextScope) -> Option<String> {
+        let inner_string =
+            unsafe { v8_ObjectProtoToString(ctx_scope.inner_ctx_ref, self.inner_obj) };
+        if inner_string.is_null() {
+            return None;
+        }
+        let tag = V8LocalString { inner_string };
+        let isolate = ctx_scope.get_isolate();
+        let tag = tag.to_value().to_utf8(&isolate)?;
+        tag.as_str()
+            .strip_prefix("[object ")
+            .and_then(|s| s.strip_suffix(']'))
+            .map(str::to_string)
+    }
+
     /// Convert the object into a generic JS value
     #[must_use]
     pub fn get_property_names(&self, ctx_scope: &V8ContextScope) -> V8LocalArray {
@@ -54,6 +302,44 @@ impl V8LocalObject {
             unsafe { v8_ValueGetPropertyNames(ctx_scope.inner_ctx_ref, self.inner_obj) };
         V8LocalArray { inner_array }
     }
+
+    /// Convert the object properties into a map of `T`. On failure, the error
+    /// includes the name of the property that failed to convert, for example:
+    /// "property 'a': Value is not a number".
+    pub fn try_to_map<T>(&self, ctx_scope: &V8ContextScope) -> Result<HashMap<String, T>, String>
+    where
+        T: for<'v> TryFrom<&'v V8LocalValue, Error = String>,
+    {
+        self.try_to_map_with(ctx_scope, |val| T::try_from(val))
+    }
+
+    /// Same as `try_to_map` but uses the given function to convert the values,
+    /// useful for values that are themselves objects or arrays.
+    pub fn try_to_map_with<T, F>(
+        &self,
+        ctx_scope: &V8ContextScope,
+        mut f: F,
+    ) -> Result<HashMap<String, T>, String>
+    where
+        F: FnMut(&V8LocalValue) -> Result<T, String>,
+    {
+        let isolate = ctx_scope.get_isolate();
+        let names = self.get_property_names(ctx_scope);
+        let mut res = HashMap::with_capacity(names.len());
+        for i in 0..names.len() {
+            let key = names.get(ctx_scope, i);
+            let name = key
+                .to_utf8(&isolate)
+                .ok_or_else(|| "Failed converting property name to string".to_string())?;
+            let name = name.as_str().to_string();
+            let val = self
+                .get(ctx_scope, &key)
+                .ok_or_else(|| format!("property '{}': failed getting value", name))?;
+            let val = f(&val).map_err(|e| format!("property '{}': {}", name, e))?;
+            res.insert(name, val);
+        }
+        Ok(res)
+    }
 }
 
 impl Drop for V8LocalObject {
@@ -1,13 +1,17 @@
 use crate::v8_c_raw::bindings::{
-    v8_FreeObjectTemplate, v8_ObjectTemplateSetFunction, v8_ObjectTemplateSetObject,
-    v8_ObjectTemplateSetValue, v8_ObjectTemplateToValue, v8_local_object_template,
+    v8_FreeObjectTemplate, v8_FreePersistedObjectTemplate, v8_ObjectTemplatePersist,
+    v8_ObjectTemplateSetFunction, v8_ObjectTemplateSetInternalFieldCount,
+    v8_ObjectTemplateSetObject, v8_ObjectTemplateSetPrivate, v8_ObjectTemplateSetValue,
+    v8_ObjectTemplateToValue, v8_PersistedObjectTemplateToLocal, v8_local_object_template,
+    v8_persisted_object_template,
 };
 
-use crate::v8::isolate::V8Isolate;
+use crate::v8::isolate::{IsolateId, V8Isolate};
 use crate::v8::v8_context_scope::V8ContextScope;
 use crate::v8::v8_native_function_template::{
     V8LocalNativeFunctionArgs, V8LocalNativeFunctionTemplate,
 };
+use crate::v8::v8_object::{brand_name, V8LocalObject};
 use crate::v8::v8_string::V8LocalString;
 use crate::v8::v8_value::V8LocalValue;
 
@@ -16,6 +20,12 @@ pub struct V8LocalObjectTemplate {
     pub(crate) inner_obj: *mut v8_local_object_template,
 }
 
+/// JS persisted object template, can be used outside of the handlers scope that created it.
+pub struct V8PersistedObjectTemplate {
+    pub(crate) inner_persisted_obj: *mut v8_persisted_object_template,
+    pub(crate) isolate_id: IsolateId,
+}
+
 impl V8LocalObjectTemplate {
     /// Set a native function to the object template as a given key
     pub fn set_native_function(
@@ -62,6 +72,21 @@ impl V8LocalObjectTemplate {
         self.set_value(&val_name, obj);
     }
 
+    /// Brand all the objects created from the template with the given type,
+    /// see `V8LocalObject::check_brand`.
+    pub fn set_brand<T: ?Sized + 'static>(&mut self, isolate: &V8Isolate) {
+        let name = isolate.new_string(&brand_name::<T>());
+        let val = isolate.new_bool(true);
+        unsafe {
+            v8_ObjectTemplateSetPrivate(
+                isolate.inner_isolate,
+                self.inner_obj,
+                name.inner_string,
+                val.inner_val,
+            )
+        };
+    }
+
     /// Convert the object template into a generic JS value
     #[must_use]
     pub fn to_value(&self, ctx_scope: &V8ContextScope) -> V8LocalValue {
@@ -69,6 +94,89 @@ impl V8LocalObjectTemplate {
             unsafe { v8_ObjectTemplateToValue(ctx_scope.inner_ctx_ref, self.inner_obj) };
         V8LocalValue { inner_val }
     }
+
+    /// Set the amount of internal fields (invisible to JS) on the objects created
+    /// from the template, see `V8LocalObject::set_internal_field`. Must be called
+    /// before the first object is created from the template. Return false if the
+    /// count is too large, in which case the count is not changed.
+    pub fn set_internal_field_count(&mut self, count: usize) -> bool {
+        unsafe { v8_ObjectTemplateSetInternalFieldCount(self.inner_obj, count) != 0 }
+    }
+
+    /// Same as `to_value` but returns the new object, allows using the template
+    /// as a factory of host objects.
+    #[must_use]
+    pub fn new_instance(&self, ctx_scope: &V8ContextScope) -> V8LocalObject {
+        self.to_value(ctx_scope).as_object()
+    }
+
+    /// Same as `new_instance` but also set the internal fields of the new object
+    /// to the given values. Return None if the template has fewer internal fields
+    /// than the given values, see `set_internal_field_count`.
+    #[must_use]
+    pub fn new_instance_with_internal_fields(
+        &self,
+        ctx_scope: &V8ContextScope,
+        fields: &[&V8LocalValue],
+    ) -> Option<V8LocalObject> {
+        let obj = self.new_instance(ctx_scope);
+        if obj.internal_field_count() < fields.len() {
+            return None;
+        }
+        for (index, val) in fields.iter().enumerate() {
+            obj.set_internal_field(index, val);
+        }
+        Some(obj)
+    }
+
+    /// Persist the object template so it can be saved beyond the current handlers scope.
+    #[must_use]
+    pub fn persist(&self, isolate: &V8Isolate) -> V8PersistedObjectTemplate {
+        let inner_persisted_obj =
+            unsafe { v8_ObjectTemplatePersist(isolate.inner_isolate, self.inner_obj) };
+        let isolate_id = isolate.get_id();
+        isolate_id.persisted_handle_created();
+        V8PersistedObjectTemplate {
+            inner_persisted_obj,
+            isolate_id,
+        }
+    }
+}
+
+/// Check that a persisted template created on the isolate with the given id
+/// can be converted back to a local template on the given isolate.
+pub(crate) fn verify_template_isolate(
+    isolate_id: IsolateId,
+    isolate: &V8Isolate,
+) -> Result<(), &'static str> {
+    if !isolate_id.is_alive() {
+        return Err("The isolate of the persisted template was already freed");
+    }
+    if isolate.get_id() != isolate_id {
+        return Err("The persisted template belongs to a different isolate");
+    }
+    Ok(())
+}
+
+impl V8PersistedObjectTemplate {
+    /// Convert the persisted object template back to local object template.
+    /// # Panics
+    /// If the persisted template does not belong to the given isolate or the isolate was freed.
+    #[must_use]
+    pub fn to_local(&self, isolate: &V8Isolate) -> V8LocalObjectTemplate {
+        self.try_to_local(isolate).unwrap()
+    }
+
+    /// Same as `to_local` but returns an error if the isolate that created the
+    /// persisted template was already freed or if the given isolate is not the
+    /// one that created the persisted template.
+    pub fn try_to_local(&self, isolate: &V8Isolate) -> Result<V8LocalObjectTemplate, &'static str> {
+        verify_template_isolate(self.isolate_id, isolate)?;
+        let inner_obj = unsafe {
+            v8_PersistedObjectTemplateToLocal(isolate.inner_isolate, self.inner_persisted_obj)
+        };
+        Ok(V8LocalObjectTemplate { inner_obj })
+    }
 }
 
 impl Drop for V8LocalObjectTemplate {
@@ -76,3 +184,14 @@ impl Drop for V8LocalObjectTemplate {
         unsafe { v8_FreeObjectTemplate(self.inner_obj) }
     }
 }
+
+unsafe impl Sync for V8PersistedObjectTemplate {}
+unsafe impl Send for V8PersistedObjectTemplate {}
+
+impl Drop for V8PersistedObjectTemplate {
+    fn drop(&mut self) {
+        if self.isolate_id.persisted_handle_freed() {
+            unsafe { v8_FreePersistedObjectTemplate(self.inner_persisted_obj) }
+        }
+    }
+}
@@ -1,13 +1,18 @@
 use crate::v8_c_raw::bindings::{
-    v8_FreePromise, v8_PromiseGetResult, v8_PromiseGetState,
-    v8_PromiseState_v8_PromiseState_Fulfilled, v8_PromiseState_v8_PromiseState_Pending,
-    v8_PromiseState_v8_PromiseState_Rejected, v8_PromiseThen, v8_PromiseToValue, v8_local_promise,
+    v8_FreePromise, v8_PromiseGetResult, v8_PromiseGetState, v8_PromiseHookType,
+    v8_PromiseHookType_v8_PromiseHookType_Before, v8_PromiseHookType_v8_PromiseHookType_Init,
+    v8_PromiseHookType_v8_PromiseHookType_Resolve, v8_PromiseState_v8_PromiseState_Fulfilled,
+    v8_PromiseState_v8_PromiseState_Pending, v8_PromiseState_v8_PromiseState_Rejected,
+    v8_PromiseThen, v8_PromiseToValue, v8_local_promise,
 };
 
+use crate::v8::isolate::V8Isolate;
 use crate::v8::v8_context_scope::V8ContextScope;
 use crate::v8::v8_native_function::V8LocalNativeFunction;
 use crate::v8::v8_value::V8LocalValue;
 
+use std::rc::Rc;
+
 pub struct V8LocalPromise {
     pub(crate) inner_promise: *mut v8_local_promise,
 }
@@ -20,6 +25,41 @@ pub enum V8PromiseState {
     Unknown,
 }
 
+/// The reason `V8LocalPromise::result_checked` did not return a fulfilled value.
+pub enum V8PromiseResultError {
+    /// The promise is not yet settled.
+    Pending,
+    /// The promise was rejected with the given value.
+    Rejected(V8LocalValue),
+}
+
+/// The promise lifecycle event reported to a promise hook, see `V8Isolate::set_promise_hook`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V8PromiseHookType {
+    /// A promise was created.
+    Init,
+    /// A promise was resolved or rejected.
+    Resolve,
+    /// A reaction (a `then` callback or an `await` continuation) of the promise is about to run.
+    Before,
+    /// A reaction of the promise finished running.
+    After,
+}
+
+impl V8PromiseHookType {
+    pub(crate) const fn from_inner(hook_type: v8_PromiseHookType) -> Self {
+        if hook_type == v8_PromiseHookType_v8_PromiseHookType_Init {
+            Self::Init
+        } else if hook_type == v8_PromiseHookType_v8_PromiseHookType_Resolve {
+            Self::Resolve
+        } else if hook_type == v8_PromiseHookType_v8_PromiseHookType_Before {
+            Self::Before
+        } else {
+            Self::After
+        }
+    }
+}
+
 impl V8LocalPromise {
     /// Set resolve and reject callbacks
     pub fn then(
@@ -38,6 +78,37 @@ impl V8LocalPromise {
         };
     }
 
+    /// Call the given callback once the promise is settled, with `Ok` and the value
+    /// if it was fulfilled or with `Err` and the reason if it was rejected. The
+    /// callback runs as a promise reaction, so only when the microtasks are processed.
+    pub fn on_settled<F>(&self, ctx_scope: &V8ContextScope, callback: F)
+    where
+        F: Fn(&V8Isolate, &V8ContextScope, Result<&V8LocalValue, &V8LocalValue>) + 'static,
+    {
+        let callback = Rc::new(callback);
+        let on_rejected = Rc::clone(&callback);
+        let resolve = ctx_scope.new_native_function(move |args, isolate, ctx_scope| {
+            callback(isolate, ctx_scope, Ok(&args.get(0)));
+            None
+        });
+        let reject = ctx_scope.new_native_function(move |args, isolate, ctx_scope| {
+            on_rejected(isolate, ctx_scope, Err(&args.get(0)));
+            None
+        });
+        self.then(ctx_scope, &resolve, &reject);
+    }
+
+    /// Return the value the promise was fulfilled with. Unlike `get_result`, which
+    /// must only be called on a settled promise, fail if the promise is pending and
+    /// return the rejection value as an error if it was rejected.
+    pub fn result_checked(&self) -> Result<V8LocalValue, V8PromiseResultError> {
+        match self.state() {
+            V8PromiseState::Fulfilled => Ok(self.get_result()),
+            V8PromiseState::Rejected => Err(V8PromiseResultError::Rejected(self.get_result())),
+            V8PromiseState::Pending | V8PromiseState::Unknown => Err(V8PromiseResultError::Pending),
+        }
+    }
+
     /// Return the state on the promise object
     /// # Panics
     #[must_use]
@@ -1,12 +1,96 @@
 use crate::v8_c_raw::bindings::{
-    v8_FreePersistedScript, v8_FreeScript, v8_PersistedScriptToLocal, v8_Run, v8_ScriptPersist,
-    v8_local_script, v8_persisted_script,
+    v8_FreeCodeCache, v8_FreePersistedScript, v8_FreePersistedUnboundScript, v8_FreeScript,
+    v8_PersistedScriptToLocal, v8_Run, v8_ScriptCreateCodeCache, v8_ScriptGetId,
+    v8_ScriptGetUnboundScript, v8_ScriptPersist, v8_UnboundScriptBindToCurrentContext,
+    v8_UnboundScriptGetId, v8_local_script, v8_persisted_script, v8_persisted_unbound_script,
 };
 
-use crate::v8::isolate::V8Isolate;
+use crate::v8::isolate::{IsolateId, V8Isolate};
 use crate::v8::v8_context_scope::V8ContextScope;
 use crate::v8::v8_value::V8LocalValue;
 
+use std::slice;
+
+/// Options for `V8ContextScope::compile_with_options` and `V8ContextScope::compile_as_module`.
+/// Scripts are compiled in strict mode by default. For example:
+/// `V8CompileOptions::new().name("script.js").code_cache(&cache)`.
+pub struct V8CompileOptions<'a> {
+    pub(crate) name: Option<&'a str>,
+    pub(crate) line_offset: i32,
+    pub(crate) column_offset: i32,
+    pub(crate) strict: bool,
+    pub(crate) is_module: bool,
+    pub(crate) code_cache: Option<&'a [u8]>,
+}
+
+impl<'a> Default for V8CompileOptions<'a> {
+    fn default() -> Self {
+        Self {
+            name: None,
+            line_offset: 0,
+            column_offset: 0,
+            strict: true,
+            is_module: true,
+            code_cache: None,
+        }
+    }
+}
+
+impl<'a> V8CompileOptions<'a> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The script name, shown in stack traces.
+    #[must_use]
+    pub fn name(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// The line and column in which the code starts within the script, useful when
+    /// the code was extracted from a bigger file so stack traces point to the right place.
+    #[must_use]
+    pub fn offset(mut self, line_offset: i32, column_offset: i32) -> Self {
+        self.line_offset = line_offset;
+        self.column_offset = column_offset;
+        self
+    }
+
+    /// Whether to compile the script in strict mode, true by default.
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Whether `V8ContextScope::compile_as_module` marks the code as an ES module in
+    /// its origin, true by default. Scripts are never modules, `compile_with_options`
+    /// ignores it.
+    #[must_use]
+    pub fn is_module(mut self, is_module: bool) -> Self {
+        self.is_module = is_module;
+        self
+    }
+
+    /// A code cache created by `V8LocalScript::create_code_cache` for the same code
+    /// and options, used to skip the compilation.
+    #[must_use]
+    pub fn code_cache(mut self, code_cache: &'a [u8]) -> Self {
+        self.code_cache = Some(code_cache);
+        self
+    }
+}
+
+/// A script compiled by `V8ContextScope::compile_with_options`.
+pub struct V8CompiledScript {
+    pub script: V8LocalScript,
+    /// True if the given code cache did not match the code, in which case the code
+    /// was compiled as usual.
+    pub cache_rejected: bool,
+}
+
 /// JS script object
 pub struct V8LocalScript {
     pub(crate) inner_script: *mut v8_local_script,
@@ -14,13 +98,20 @@ pub struct V8LocalScript {
 
 pub struct V8PersistedScript {
     pub(crate) inner_persisted_script: *mut v8_persisted_script,
+    pub(crate) isolate_id: IsolateId,
+}
+
+/// A compiled script which is not bound to any context, see `V8LocalScript::to_unbound`.
+pub struct V8PersistedUnboundScript {
+    pub(crate) inner_persisted_script: *mut v8_persisted_unbound_script,
+    pub(crate) isolate_id: IsolateId,
 }
 
 impl V8LocalScript {
     /// Run the script
     #[must_use]
     pub fn run(&self, ctx: &V8ContextScope) -> Option<V8LocalValue> {
-        let inner_val = unsafe { v8_Run(ctx.inner_ctx_ref, self.inner_script) };
+        let inner_val = ctx.timed_run(|| unsafe { v8_Run(ctx.inner_ctx_ref, self.inner_script) });
         if inner_val.is_null() {
             None
         } else {
@@ -31,8 +122,44 @@ impl V8LocalScript {
     pub fn persist(&self, isolate: &V8Isolate) -> V8PersistedScript {
         let inner_persisted_script =
             unsafe { v8_ScriptPersist(isolate.inner_isolate, self.inner_script) };
+        let isolate_id = isolate.get_id();
+        isolate_id.persisted_handle_created();
         V8PersistedScript {
             inner_persisted_script,
+            isolate_id,
+        }
+    }
+
+    /// Return the id of the script, it is the same for all the contexts the script is
+    /// bound to and matches `V8LocalValue::get_script_id` of the functions it defines.
+    #[must_use]
+    pub fn get_id(&self) -> i64 {
+        i64::from(unsafe { v8_ScriptGetId(self.inner_script) })
+    }
+
+    /// Create a code cache for the script, it can be given to
+    /// `V8ContextScope::compile` to skip compiling the same code again,
+    /// also on other isolates and processes using the same v8 version and flags.
+    #[must_use]
+    pub fn create_code_cache(&self) -> Vec<u8> {
+        let mut len = 0;
+        let buff = unsafe { v8_ScriptCreateCodeCache(self.inner_script, &mut len) };
+        let res = unsafe { slice::from_raw_parts(buff.cast::<u8>(), len) }.to_vec();
+        unsafe { v8_FreeCodeCache(buff) };
+        res
+    }
+
+    /// Return the context independent version of the script. It can be bound to
+    /// other contexts of the same isolate without compiling the code again.
+    #[must_use]
+    pub fn to_unbound(&self, isolate: &V8Isolate) -> V8PersistedUnboundScript {
+        let inner_persisted_script =
+            unsafe { v8_ScriptGetUnboundScript(isolate.inner_isolate, self.inner_script) };
+        let isolate_id = isolate.get_id();
+        isolate_id.persisted_handle_created();
+        V8PersistedUnboundScript {
+            inner_persisted_script,
+            isolate_id,
         }
     }
 }
@@ -46,6 +173,33 @@ impl V8PersistedScript {
     }
 }
 
+impl V8PersistedUnboundScript {
+    /// Bind the script to the context of the given scope, which must be the current context.
+    #[must_use]
+    pub fn bind(&self, ctx_scope: &V8ContextScope) -> V8LocalScript {
+        debug_assert!(ctx_scope.is_current());
+        let isolate = ctx_scope.get_isolate();
+        let inner_script = unsafe {
+            v8_UnboundScriptBindToCurrentContext(isolate.inner_isolate, self.inner_persisted_script)
+        };
+        V8LocalScript { inner_script }
+    }
+
+    /// Bind the script to the context of the given scope and run it.
+    #[must_use]
+    pub fn run(&self, ctx_scope: &V8ContextScope) -> Option<V8LocalValue> {
+        self.bind(ctx_scope).run(ctx_scope)
+    }
+
+    /// Return the id of the script, see `V8LocalScript::get_id`.
+    #[must_use]
+    pub fn get_id(&self, isolate: &V8Isolate) -> i64 {
+        i64::from(unsafe {
+            v8_UnboundScriptGetId(isolate.inner_isolate, self.inner_persisted_script)
+        })
+    }
+}
+
 impl Drop for V8LocalScript {
     fn drop(&mut self) {
         unsafe { v8_FreeScript(self.inner_script) }
@@ -57,6 +211,19 @@ unsafe impl Send for V8PersistedScript {}
 
 impl Drop for V8PersistedScript {
     fn drop(&mut self) {
-        unsafe { v8_FreePersistedScript(self.inner_persisted_script) }
+        if self.isolate_id.persisted_handle_freed() {
+            unsafe { v8_FreePersistedScript(self.inner_persisted_script) }
+        }
+    }
+}
+
+unsafe impl Sync for V8PersistedUnboundScript {}
+unsafe impl Send for V8PersistedUnboundScript {}
+
+impl Drop for V8PersistedUnboundScript {
+    fn drop(&mut self) {
+        if self.isolate_id.persisted_handle_freed() {
+            unsafe { v8_FreePersistedUnboundScript(self.inner_persisted_script) }
+        }
     }
 }
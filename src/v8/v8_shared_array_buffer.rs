@@ -0,0 +1,36 @@
+use crate::v8_c_raw::bindings::{
+    v8_FreeSharedArrayBuffer, v8_SharedArrayBufferGetBackingStore, v8_SharedArrayBufferToValue,
+    v8_local_shared_array_buff,
+};
+
+use crate::v8::isolate::V8Isolate;
+use crate::v8::v8_array_buffer::V8BackingStore;
+use crate::v8::v8_value::V8LocalValue;
+
+/// JS shared array buffer, the memory of a shared array buffer can be
+/// accessed from Rust (using `V8BackingStore::as_atomics`) while JS uses it.
+pub struct V8LocalSharedArrayBuffer {
+    pub(crate) inner_shared_array_buffer: *mut v8_local_shared_array_buff,
+}
+
+impl V8LocalSharedArrayBuffer {
+    pub fn to_value(&self) -> V8LocalValue {
+        let inner_val = unsafe { v8_SharedArrayBufferToValue(self.inner_shared_array_buffer) };
+        V8LocalValue { inner_val }
+    }
+
+    /// Return the backing store of the shared array buffer. The backing store
+    /// keeps the memory alive and can be kept and accessed outside of any scope.
+    #[must_use]
+    pub fn get_backing_store(&self, isolate: &V8Isolate) -> V8BackingStore {
+        let inner_backing_store =
+            unsafe { v8_SharedArrayBufferGetBackingStore(self.inner_shared_array_buffer) };
+        V8BackingStore::new(inner_backing_store, isolate)
+    }
+}
+
+impl Drop for V8LocalSharedArrayBuffer {
+    fn drop(&mut self) {
+        unsafe { v8_FreeSharedArrayBuffer(self.inner_shared_array_buffer) }
+    }
+}
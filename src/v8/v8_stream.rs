@@ -0,0 +1,468 @@
+use crate::v8::host_value::HostValue;
+use crate::v8::isolate::{IsolateId, V8Isolate};
+use crate::v8::v8_async_iterator::set_async_iterable;
+use crate::v8::v8_builder::V8ObjectBuilder;
+use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_error::V8ErrorKind;
+use crate::v8::v8_value::{V8LocalValue, V8PersistValue};
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// How a promise returned to the JS end of a stream is settled.
+enum Settle {
+    /// Resolve with the iterator result `{value, done: false}`.
+    Item(HostValue),
+    /// Resolve with the iterator result `{value: undefined, done: true}`.
+    Done,
+    /// Resolve with `undefined`, the write was accepted.
+    Written,
+    Reject(String),
+}
+
+/// The state shared by the Rust and the JS ends of a stream. The producer end
+/// finishes (or aborts) the stream, the consumer end cancels it.
+struct StreamState {
+    isolate_id: IsolateId,
+    capacity: usize,
+    items: VecDeque<HostValue>,
+    /// The resolvers of the JS promises that wait for the Rust end: reads that wait
+    /// for an item, or writes, along with their item, that wait for room.
+    pending: VecDeque<(Option<HostValue>, V8PersistValue)>,
+    finished: bool,
+    error: Option<String>,
+    cancelled: bool,
+    /// Wakes the Rust end once the JS end made progress.
+    waker: Option<Waker>,
+}
+
+impl StreamState {
+    fn new(isolate: &V8Isolate, capacity: usize) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            isolate_id: isolate.get_id(),
+            capacity,
+            items: VecDeque::new(),
+            pending: VecDeque::new(),
+            finished: false,
+            error: None,
+            cancelled: false,
+            waker: None,
+        }))
+    }
+
+    /// Remove the pending resolvers, to settle them once the state lock is released.
+    fn take_pending(&mut self) -> Vec<V8PersistValue> {
+        self.pending
+            .drain(..)
+            .map(|(_, resolver)| resolver)
+            .collect()
+    }
+}
+
+/// Wake the other end of the stream. Like settling a promise (which runs JS), waking
+/// may run code that uses the stream, so neither is done while the state lock is held.
+fn wake(waker: Option<Waker>) {
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// Settle the promises of the given resolvers on the isolate thread. The Rust
+/// end can run on any thread so the promises are settled by an isolate task.
+fn settle_later(isolate_id: IsolateId, settles: Vec<(V8PersistValue, Settle)>) {
+    if settles.is_empty() {
+        return;
+    }
+    isolate_id.post_task(move |isolate| {
+        let _h_scope = isolate.new_handlers_scope();
+        for (resolver, settle) in settles {
+            let resolver = resolver.as_local(isolate);
+            // The task runs outside of any context, settle the promise on the context it was created in.
+            if let Some(ctx_scope) = resolver.as_object().enter_creation_context() {
+                apply_settle(&ctx_scope, &resolver, settle);
+            }
+        }
+        isolate.perform_microtask_checkpoint();
+    });
+}
+
+fn apply_settle(ctx_scope: &V8ContextScope, resolver: &V8LocalValue, settle: Settle) {
+    let isolate = ctx_scope.get_isolate();
+    let resolver = resolver.as_resolver();
+    let val = match settle {
+        Settle::Item(item) => {
+            let trycatch = isolate.new_try_catch();
+            match item.to_value(ctx_scope) {
+                Some(value) => V8ObjectBuilder::new()
+                    .prop("value", &value)
+                    .prop("done", false)
+                    .build(ctx_scope),
+                None => {
+                    if !trycatch.has_terminated() {
+                        resolver.reject(ctx_scope, &trycatch.get_exception());
+                    }
+                    return;
+                }
+            }
+        }
+        Settle::Done => V8ObjectBuilder::new()
+            .prop("value", &isolate.new_undefined())
+            .prop("done", true)
+            .build(ctx_scope),
+        Settle::Written => isolate.new_undefined(),
+        Settle::Reject(message) => {
+            let error = ctx_scope.new_error(&V8ErrorKind::Error, &message);
+            resolver.reject(ctx_scope, &error);
+            return;
+        }
+    };
+    resolver.resolve(ctx_scope, &val);
+}
+
+/// Return a promise that is already settled.
+fn settled(ctx_scope: &V8ContextScope, settle: Settle) -> V8LocalValue {
+    let resolver = ctx_scope.new_resolver();
+    apply_settle(ctx_scope, &resolver.to_value(), settle);
+    resolver.get_promise().to_value()
+}
+
+/// Return a promise that waits for the Rust end of the stream.
+fn pending(
+    ctx_scope: &V8ContextScope,
+    state: &mut StreamState,
+    item: Option<HostValue>,
+) -> V8LocalValue {
+    let resolver = ctx_scope.new_resolver();
+    state
+        .pending
+        .push_back((item, resolver.to_value().persist(&ctx_scope.get_isolate())));
+    resolver.get_promise().to_value()
+}
+
+/// The error of sending an item on a stream, gives the item back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum V8StreamSendError {
+    /// The stream buffer is full, the item can be sent once JS reads more items.
+    Full(HostValue),
+    /// JS stopped reading the stream.
+    Cancelled(HostValue),
+}
+
+/// The error of receiving an item from a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V8StreamRecvError {
+    /// There is no item to receive yet.
+    Empty,
+    /// JS closed the stream and all its items were received.
+    Closed,
+}
+
+/// The Rust end of a stream that JS reads, see `V8ContextScope::new_stream_reader`.
+/// Can be used from any thread. Dropping the sender finishes the stream, JS reads
+/// the buffered items and then gets `done`.
+pub struct V8StreamSender {
+    state: Arc<Mutex<StreamState>>,
+}
+
+impl V8StreamSender {
+    fn push(&self, item: HostValue, cx: Option<&Context>) -> Result<(), V8StreamSendError> {
+        let mut state = self.state.lock().unwrap();
+        if state.cancelled {
+            return Err(V8StreamSendError::Cancelled(item));
+        }
+        if let Some((_, resolver)) = state.pending.pop_front() {
+            let isolate_id = state.isolate_id;
+            drop(state);
+            settle_later(isolate_id, vec![(resolver, Settle::Item(item))]);
+            return Ok(());
+        }
+        if state.items.len() >= state.capacity {
+            if let Some(cx) = cx {
+                state.waker = Some(cx.waker().clone());
+            }
+            return Err(V8StreamSendError::Full(item));
+        }
+        state.items.push_back(item);
+        Ok(())
+    }
+
+    /// Send the item if JS waits for one or if there is room in the stream buffer.
+    pub fn try_send(&self, item: HostValue) -> Result<(), V8StreamSendError> {
+        self.push(item, None)
+    }
+
+    /// Send the item, waiting for room in the stream buffer if it is full. The returned
+    /// future gives the item back if JS stops reading the stream.
+    #[must_use]
+    pub fn send(&self, item: HostValue) -> V8StreamSend<'_> {
+        V8StreamSend {
+            sender: self,
+            item: Some(item),
+        }
+    }
+
+    /// Finish the stream with an error, JS reads the buffered items and then
+    /// gets a rejection with an `Error` of the given message.
+    pub fn abort(self, message: &str) {
+        self.state.lock().unwrap().error = Some(message.to_string());
+    }
+}
+
+impl Drop for V8StreamSender {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.finished = true;
+        let pending = state.take_pending();
+        let error = state.error.clone();
+        let isolate_id = state.isolate_id;
+        drop(state);
+        let settles = pending
+            .into_iter()
+            .map(|resolver| {
+                let settle = match &error {
+                    Some(error) => Settle::Reject(error.clone()),
+                    None => Settle::Done,
+                };
+                (resolver, settle)
+            })
+            .collect();
+        settle_later(isolate_id, settles);
+    }
+}
+
+/// The future returned by `V8StreamSender::send`.
+pub struct V8StreamSend<'a> {
+    sender: &'a V8StreamSender,
+    item: Option<HostValue>,
+}
+
+impl<'a> Future for V8StreamSend<'a> {
+    type Output = Result<(), HostValue>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let item = self
+            .item
+            .take()
+            .expect("Stream send polled after completion");
+        match self.sender.push(item, Some(cx)) {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(V8StreamSendError::Cancelled(item)) => Poll::Ready(Err(item)),
+            Err(V8StreamSendError::Full(item)) => {
+                self.item = Some(item);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The Rust end of a stream that JS writes, see `V8ContextScope::new_stream_writer`.
+/// Can be used from any thread. Dropping the receiver cancels the stream, the
+/// pending and the following writes are rejected.
+pub struct V8StreamReceiver {
+    state: Arc<Mutex<StreamState>>,
+}
+
+impl V8StreamReceiver {
+    fn pop(&self, cx: Option<&Context>) -> Result<HostValue, V8StreamRecvError> {
+        let mut state = self.state.lock().unwrap();
+        let mut settles = Vec::new();
+        let item = match state.items.pop_front() {
+            Some(item) => Some(item),
+            None => match state.pending.pop_front() {
+                Some((item, resolver)) => {
+                    settles.push((resolver, Settle::Written));
+                    item
+                }
+                None => None,
+            },
+        };
+        let res = match item {
+            Some(item) => {
+                // There is room for one of the writes that wait for it.
+                if state.items.len() < state.capacity {
+                    if let Some((pending_item, resolver)) = state.pending.pop_front() {
+                        state.items.extend(pending_item);
+                        settles.push((resolver, Settle::Written));
+                    }
+                }
+                Ok(item)
+            }
+            None if state.finished => Err(V8StreamRecvError::Closed),
+            None => {
+                if let Some(cx) = cx {
+                    state.waker = Some(cx.waker().clone());
+                }
+                Err(V8StreamRecvError::Empty)
+            }
+        };
+        let isolate_id = state.isolate_id;
+        drop(state);
+        settle_later(isolate_id, settles);
+        res
+    }
+
+    /// Receive an item if there is one.
+    pub fn try_recv(&self) -> Result<HostValue, V8StreamRecvError> {
+        self.pop(None)
+    }
+
+    /// Receive an item, waiting for JS to write one. The returned future
+    /// gives None once JS closed the stream and all its items were received.
+    #[must_use]
+    pub fn recv(&self) -> V8StreamRecv<'_> {
+        V8StreamRecv { receiver: self }
+    }
+}
+
+impl Drop for V8StreamReceiver {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.cancelled = true;
+        state.items.clear();
+        let pending = state.take_pending();
+        let isolate_id = state.isolate_id;
+        drop(state);
+        let settles = pending
+            .into_iter()
+            .map(|resolver| (resolver, Settle::Reject("Stream is closed".to_string())))
+            .collect();
+        settle_later(isolate_id, settles);
+    }
+}
+
+/// The future returned by `V8StreamReceiver::recv`.
+pub struct V8StreamRecv<'a> {
+    receiver: &'a V8StreamReceiver,
+}
+
+impl<'a> Future for V8StreamRecv<'a> {
+    type Output = Option<HostValue>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.receiver.pop(Some(cx)) {
+            Ok(item) => Poll::Ready(Some(item)),
+            Err(V8StreamRecvError::Closed) => Poll::Ready(None),
+            Err(V8StreamRecvError::Empty) => Poll::Pending,
+        }
+    }
+}
+
+impl V8ContextScope {
+    /// Create a stream that JS reads and Rust writes, return the JS end along with
+    /// the Rust end. The JS end is an async iterator, usable with `for await`: its `next()`
+    /// returns a promise of an iterator result (`{value, done}`) and its `return()` stops
+    /// reading the stream.
+    /// Up to `capacity` items are buffered, when the buffer is full the Rust end waits
+    /// for JS to read (with a capacity of zero each item waits for a read). The items
+    /// are converted to JS values as described in `HostValue`.
+    ///
+    /// Items sent while JS waits for them are delivered by an isolate task, so the
+    /// promises only settle while the isolate message loop is pumped (see
+    /// `V8Isolate::pump_message_loop`).
+    #[must_use]
+    pub fn new_stream_reader(&self, capacity: usize) -> (V8LocalValue, V8StreamSender) {
+        let state = StreamState::new(&self.get_isolate(), capacity);
+        let next_state = Arc::clone(&state);
+        let next = self.new_native_function(move |_args, _isolate, ctx_scope| {
+            let mut state = next_state.lock().unwrap();
+            let mut waker = None;
+            let settle = if let Some(item) = state.items.pop_front() {
+                waker = state.waker.take();
+                Settle::Item(item)
+            } else if let Some(error) = state.error.clone() {
+                Settle::Reject(error)
+            } else if state.finished || state.cancelled {
+                Settle::Done
+            } else {
+                return Some(pending(ctx_scope, &mut state, None));
+            };
+            drop(state);
+            wake(waker);
+            Some(settled(ctx_scope, settle))
+        });
+        let return_state = Arc::clone(&state);
+        let ret = self.new_native_function(move |_args, isolate, ctx_scope| {
+            let mut state = return_state.lock().unwrap();
+            state.cancelled = true;
+            state.items.clear();
+            let pending = state.take_pending();
+            let waker = state.waker.take();
+            drop(state);
+            for resolver in pending {
+                apply_settle(ctx_scope, &resolver.as_local(isolate), Settle::Done);
+            }
+            wake(waker);
+            Some(settled(ctx_scope, Settle::Done))
+        });
+        let reader = V8ObjectBuilder::new()
+            .prop("next", &next.to_value())
+            .prop("return", &ret.to_value())
+            .build(self);
+        set_async_iterable(self, &reader.as_object());
+        (reader, V8StreamSender { state })
+    }
+
+    /// Create a stream that JS writes and Rust reads, return the JS end along with
+    /// the Rust end. The JS end is an object whose `write(value)` returns a promise
+    /// that resolves once the value is accepted and whose `close()` finishes the
+    /// stream. Up to `capacity` values are buffered, when the buffer is full the
+    /// writes wait for Rust to read. The values are converted to `HostValue`, a
+    /// write of a value that can not be converted is rejected.
+    ///
+    /// Writes accepted by Rust after they waited are resolved by an isolate task,
+    /// so their promises only settle while the isolate message loop is pumped (see
+    /// `V8Isolate::pump_message_loop`).
+    #[must_use]
+    pub fn new_stream_writer(&self, capacity: usize) -> (V8LocalValue, V8StreamReceiver) {
+        let state = StreamState::new(&self.get_isolate(), capacity);
+        let write_state = Arc::clone(&state);
+        let write = self.new_native_function(move |args, _isolate, ctx_scope| {
+            let item = match args.iter().next() {
+                Some(val) => HostValue::from_value(ctx_scope, &val),
+                None => Ok(HostValue::Nil),
+            };
+            let mut state = write_state.lock().unwrap();
+            let settle = match item {
+                Err(e) => Settle::Reject(e),
+                Ok(_) if state.finished || state.cancelled => {
+                    Settle::Reject("Stream is closed".to_string())
+                }
+                Ok(item) if state.pending.is_empty() && state.items.len() < state.capacity => {
+                    state.items.push_back(item);
+                    Settle::Written
+                }
+                Ok(item) => {
+                    let promise = pending(ctx_scope, &mut state, Some(item));
+                    let waker = state.waker.take();
+                    drop(state);
+                    wake(waker);
+                    return Some(promise);
+                }
+            };
+            let waker = match settle {
+                Settle::Written => state.waker.take(),
+                _ => None,
+            };
+            drop(state);
+            wake(waker);
+            Some(settled(ctx_scope, settle))
+        });
+        let close_state = Arc::clone(&state);
+        let close = self.new_native_function(move |_args, _isolate, _ctx_scope| {
+            let mut state = close_state.lock().unwrap();
+            state.finished = true;
+            let waker = state.waker.take();
+            drop(state);
+            wake(waker);
+            None
+        });
+        let writer = V8ObjectBuilder::new()
+            .prop("write", &write.to_value())
+            .prop("close", &close.to_value())
+            .build(self);
+        (writer, V8StreamReceiver { state })
+    }
+}
@@ -1,17 +1,68 @@
 use crate::v8_c_raw::bindings::{
-    v8_FreeString, v8_StringToStringObject, v8_StringToValue, v8_local_string,
+    v8_FreeString, v8_StringIsExternal, v8_StringLength, v8_StringMaxLength,
+    v8_StringToStringObject, v8_StringToValue, v8_StringWriteUtf16, v8_local_string,
 };
 
 use crate::v8::isolate::V8Isolate;
 use crate::v8::v8_object::V8LocalObject;
 use crate::v8::v8_value::V8LocalValue;
 
+/// The encoding of the data given to `V8Isolate::new_string_from_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V8StringEncoding {
+    /// Each byte is a character (ISO-8859-1), any data is valid.
+    Latin1,
+    /// UTF-8, invalid sequences are replaced with U+FFFD.
+    Utf8Lossy,
+    /// Little endian UTF-16 code units, lone surrogates are kept as is
+    /// and a trailing odd byte is replaced with U+FFFD.
+    Utf16,
+}
+
+/// Options for `V8Isolate::try_new_string`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct V8NewStringOptions {
+    pub(crate) max_len: Option<usize>,
+    pub(crate) internalize: bool,
+}
+
+impl V8NewStringOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail creating strings that are longer than the given number of bytes
+    /// (UTF-8), the limit can not exceed `V8LocalString::max_length`.
+    #[must_use]
+    pub const fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Internalize the string, strings with the same content share the same memory.
+    /// Saves memory for strings that are created over and over again (for example
+    /// object keys), but internalizing a string costs a lookup so it is not worth
+    /// it for strings that are created once.
+    #[must_use]
+    pub const fn internalize(mut self, internalize: bool) -> Self {
+        self.internalize = internalize;
+        self
+    }
+}
+
 /// JS string object
 pub struct V8LocalString {
     pub(crate) inner_string: *mut v8_local_string,
 }
 
 impl V8LocalString {
+    /// Return the maximum length of a JS string, longer strings can not be created.
+    #[must_use]
+    pub fn max_length() -> usize {
+        unsafe { v8_StringMaxLength() }
+    }
+
     /// Convert the string object into a generic JS object.
     #[must_use]
     pub fn to_value(&self) -> V8LocalValue {
@@ -26,6 +77,87 @@ impl V8LocalString {
             unsafe { v8_StringToStringObject(isolate.inner_isolate, self.inner_string) };
         V8LocalObject { inner_obj }
     }
+
+    /// Return true if the string data is held outside of the JS heap,
+    /// for example a string created with `V8Isolate::new_external_string`.
+    #[must_use]
+    pub fn is_external(&self) -> bool {
+        unsafe { v8_StringIsExternal(self.inner_string) != 0 }
+    }
+
+    /// Return the length of the string in UTF-16 code units.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        unsafe { v8_StringLength(self.inner_string) }
+    }
+
+    /// Return true if the string is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the UTF-16 code units of the string. JS strings are not required
+    /// to be valid UTF-16 and might contain lone surrogates, which are returned as is.
+    #[must_use]
+    pub fn to_utf16(&self, isolate: &V8Isolate) -> Vec<u16> {
+        let mut res = vec![0; self.len()];
+        unsafe { v8_StringWriteUtf16(isolate.inner_isolate, self.inner_string, res.as_mut_ptr()) };
+        res
+    }
+
+    /// Convert the string to a Rust string, failing if the string contains a lone
+    /// surrogate (which has no UTF-8 representation). The error includes the index,
+    /// in UTF-16 code units, of the first lone surrogate.
+    pub fn to_string_strict(&self, isolate: &V8Isolate) -> Result<String, String> {
+        let mut res = String::new();
+        let mut index = 0;
+        for c in char::decode_utf16(self.to_utf16(isolate)) {
+            let c = c.map_err(|e| {
+                format!(
+                    "String contains a lone surrogate 0x{:X} at index {}",
+                    e.unpaired_surrogate(),
+                    index
+                )
+            })?;
+            index += c.len_utf16();
+            res.push(c);
+        }
+        Ok(res)
+    }
+
+    /// Convert the string to a Rust string, replacing lone surrogates with U+FFFD.
+    /// This is also what `V8LocalValue::to_utf8` does.
+    #[must_use]
+    pub fn to_string_lossy(&self, isolate: &V8Isolate) -> String {
+        String::from_utf16_lossy(&self.to_utf16(isolate))
+    }
+
+    /// Convert the string to WTF-8, which is UTF-8 extended to also encode lone
+    /// surrogates (as 3 bytes sequences). Unlike the other conversions it never fails
+    /// nor loses information, the result can be converted back to the exact same
+    /// JS string, but it is not valid UTF-8 if the string contains lone surrogates.
+    #[must_use]
+    pub fn to_wtf8(&self, isolate: &V8Isolate) -> Vec<u8> {
+        let mut res = Vec::with_capacity(self.len());
+        for c in char::decode_utf16(self.to_utf16(isolate)) {
+            match c {
+                Ok(c) => {
+                    let mut buff = [0; 4];
+                    res.extend_from_slice(c.encode_utf8(&mut buff).as_bytes());
+                }
+                Err(e) => {
+                    let surrogate = e.unpaired_surrogate();
+                    res.extend_from_slice(&[
+                        0xE0 | (surrogate >> 12) as u8,
+                        0x80 | ((surrogate >> 6) & 0x3F) as u8,
+                        0x80 | (surrogate & 0x3F) as u8,
+                    ]);
+                }
+            }
+        }
+        res
+    }
 }
 
 impl Drop for V8LocalString {
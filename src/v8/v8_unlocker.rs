@@ -1,11 +1,57 @@
 use crate::v8_c_raw::bindings::{v8_FreeUnlocker, v8_unlocker};
 
-pub struct V8Unlocker {
+use crate::v8::isolate::{IsolateId, V8Isolate};
+
+use std::marker::PhantomData;
+
+/// Releases the isolate lock so other threads can use the isolate while the
+/// current thread runs a long blocking operation. The valid usage is:
+///
+/// 1. The isolate is entered (and so locked) by the current thread.
+/// 2. `V8Isolate::new_unlocker` releases the lock, other threads can now enter
+///    the isolate, run code on it or terminate its execution.
+/// 3. `relock` (or dropping the unlocker) re-acquires the lock, blocking until
+///    other threads exit the isolate, and the current thread can use the isolate again.
+///
+/// The unlocker can not be sent to another thread, the lock is always re-acquired by the
+/// thread that released it. The unlocker borrows the isolate object it was created from,
+/// so that object can not be dropped while the isolate is unlocked. While an unlocker is
+/// active `V8Isolate::dispose` fails, and dropping the isolate owner while an unlocker
+/// created from another object of the isolate (see `V8Isolate::get_by_id`) is active
+/// leaks the isolate instead of freeing it (and panics on debug builds).
+pub struct V8Unlocker<'isolate> {
     pub(crate) inner_unlocker: *mut v8_unlocker,
+    pub(crate) isolate_id: IsolateId,
+    pub(crate) _isolate: PhantomData<&'isolate V8Isolate>,
+}
+
+impl<'isolate> V8Unlocker<'isolate> {
+    /// Re-acquire the isolate lock. Fails if the isolate was freed while it was
+    /// unlocked, in which case the isolate must not be used anymore.
+    pub fn relock(mut self) -> Result<(), &'static str> {
+        self.release()
+    }
+
+    fn release(&mut self) -> Result<(), &'static str> {
+        if self.inner_unlocker.is_null() {
+            return Ok(());
+        }
+        let inner_unlocker = self.inner_unlocker;
+        self.inner_unlocker = std::ptr::null_mut();
+        if !self
+            .isolate_id
+            .unlocker_relock(|| unsafe { v8_FreeUnlocker(inner_unlocker) })
+        {
+            // Re-acquiring the lock of a freed isolate is a use after free,
+            // the unlocker is intentionally leaked.
+            return Err("Isolate was freed while it was unlocked");
+        }
+        Ok(())
+    }
 }
 
-impl Drop for V8Unlocker {
+impl<'isolate> Drop for V8Unlocker<'isolate> {
     fn drop(&mut self) {
-        unsafe { v8_FreeUnlocker(self.inner_unlocker) };
+        let _ = self.release();
     }
 }
@@ -1,23 +1,36 @@
 use crate::v8_c_raw::bindings::{
-    v8_FreePersistedValue, v8_FreeValue, v8_FunctionCall, v8_GetBigInt, v8_GetBool, v8_GetNumber,
-    v8_PersistValue, v8_PersistedValueToLocal, v8_ToUtf8, v8_ValueAsArray, v8_ValueAsArrayBuffer,
-    v8_ValueAsObject, v8_ValueAsPromise, v8_ValueAsResolver, v8_ValueAsSet, v8_ValueAsString,
-    v8_ValueIsArray, v8_ValueIsArrayBuffer, v8_ValueIsAsyncFunction, v8_ValueIsBigInt,
-    v8_ValueIsBool, v8_ValueIsFunction, v8_ValueIsNull, v8_ValueIsNumber, v8_ValueIsObject,
-    v8_ValueIsPromise, v8_ValueIsSet, v8_ValueIsString, v8_ValueIsStringObject, v8_local_value,
-    v8_persisted_value,
+    v8_ExternalDataGet, v8_FreePersistedValue, v8_FreeValue, v8_FreeWeakValue, v8_FunctionCall,
+    v8_FunctionCallWithReceiver, v8_FunctionGetInferredName, v8_FunctionGetScriptColumnNumber,
+    v8_FunctionGetScriptId, v8_FunctionGetScriptLineNumber, v8_FunctionGetScriptName,
+    v8_FunctionNewInstance, v8_GetBigInt, v8_GetBigIntInt64, v8_GetBigIntUint64, v8_GetBool,
+    v8_GetNumber, v8_NewExternalData, v8_NewWeakValue, v8_PersistValue, v8_PersistedValueDuplicate,
+    v8_PersistedValueToLocal, v8_ToUtf8, v8_ValueAsArray, v8_ValueAsArrayBuffer,
+    v8_ValueAsFunction, v8_ValueAsObject, v8_ValueAsPromise, v8_ValueAsResolver, v8_ValueAsSet,
+    v8_ValueAsSharedArrayBuffer, v8_ValueAsString, v8_ValueIsArray, v8_ValueIsArrayBuffer,
+    v8_ValueIsAsyncFunction, v8_ValueIsBigInt, v8_ValueIsBool, v8_ValueIsFunction, v8_ValueIsMap,
+    v8_ValueIsNativeError, v8_ValueIsNull, v8_ValueIsNumber, v8_ValueIsObject, v8_ValueIsPromise,
+    v8_ValueIsSet, v8_ValueIsSharedArrayBuffer, v8_ValueIsString, v8_ValueIsStringObject,
+    v8_ValueIsUndefined, v8_ValueStrictEquals, v8_ValueToBoolean, v8_ValueToNumber,
+    v8_ValueToString, v8_WeakValueToLocal, v8_local_value, v8_persisted_value, v8_weak_value,
 };
 
+use std::any::{Any, TypeId};
+use std::borrow::Borrow;
+use std::num::{NonZeroI32, NonZeroI64, NonZeroU32, NonZeroU64, NonZeroUsize};
+use std::os::raw::c_void;
 use std::ptr;
+use std::sync::Arc;
 
-use crate::v8::isolate::V8Isolate;
+use crate::v8::isolate::{ExternalDataTable, IsolateId, V8Isolate};
 use crate::v8::v8_array::V8LocalArray;
 use crate::v8::v8_array_buffer::V8LocalArrayBuffer;
 use crate::v8::v8_context_scope::V8ContextScope;
+use crate::v8::v8_native_function::V8LocalNativeFunction;
 use crate::v8::v8_object::V8LocalObject;
-use crate::v8::v8_promise::V8LocalPromise;
+use crate::v8::v8_promise::{V8LocalPromise, V8PromiseState};
 use crate::v8::v8_resolver::V8LocalResolver;
 use crate::v8::v8_set::V8LocalSet;
+use crate::v8::v8_shared_array_buffer::V8LocalSharedArrayBuffer;
 use crate::v8::v8_string::V8LocalString;
 use crate::v8::v8_utf8::V8LocalUtf8;
 
@@ -29,10 +42,57 @@ pub struct V8LocalValue {
 /// JS generic persisted value
 pub struct V8PersistValue {
     pub(crate) inner_val: *mut v8_persisted_value,
+    pub(crate) isolate_id: IsolateId,
+}
+
+/// The reason `V8LocalValue::try_call` did not return a value.
+pub enum V8CallError {
+    /// The function raised the given exception, or returned a promise that
+    /// was rejected with it.
+    Exception(V8LocalValue),
+    /// The execution was terminated.
+    Terminated,
+}
+
+/// A weak handle to a JS value, unlike `V8PersistValue` it does not keep
+/// the value alive and the value can be garbage collected while the handle
+/// still exists.
+pub struct V8WeakValue {
+    pub(crate) inner_val: *mut v8_weak_value,
+    pub(crate) isolate_id: IsolateId,
+}
+
+/// The name of the private value that holds the Rust payload of an error.
+pub(crate) const ERROR_PAYLOAD_NAME: &str = "v8_rs::error_payload";
+
+extern "C" fn weak_value_collected<F: FnOnce()>(pd: *mut c_void) {
+    let finalizer = unsafe { Box::from_raw(pd.cast::<F>()) };
+    finalizer();
+}
+
+extern "C" fn weak_value_free_pd<F: FnOnce()>(pd: *mut c_void) {
+    unsafe { drop(Box::from_raw(pd.cast::<F>())) }
+}
+
+/// The Rust data owned by an external data value, with what is needed to
+/// uncount it from the isolate statistics once it is freed.
+struct ExternalData {
+    stats: Option<Arc<ExternalDataTable>>,
+    type_id: TypeId,
+    retained_size: usize,
+    data: Box<dyn Any>,
+}
+
+extern "C" fn free_external_data(pd: *mut c_void) {
+    let data = unsafe { Box::from_raw(pd.cast::<ExternalData>()) };
+    if let Some(stats) = &data.stats {
+        stats.freed(data.type_id, data.retained_size);
+    }
 }
 
 impl V8LocalValue {
-    /// Return string representation of the value or None on failure
+    /// Return string representation of the value or None on failure.
+    /// Lone surrogates are replaced with U+FFFD so the result is valid UTF-8.
     #[must_use]
     pub fn to_utf8(&self, isolate: &V8Isolate) -> Option<V8LocalUtf8> {
         let inner_val = unsafe { v8_ToUtf8(isolate.inner_isolate, self.inner_val) };
@@ -90,18 +150,46 @@ impl V8LocalValue {
         V8LocalArrayBuffer { inner_array_buffer }
     }
 
+    /// Return true if the value is a shared array buffer and false otherwise.
+    #[must_use]
+    pub fn is_shared_array_buffer(&self) -> bool {
+        (unsafe { v8_ValueIsSharedArrayBuffer(self.inner_val) } != 0)
+    }
+
+    /// Convert the object into a shared array buffer, applicable only if the value is a shared array buffer.
+    #[must_use]
+    pub fn as_shared_array_buffer(&self) -> V8LocalSharedArrayBuffer {
+        let inner_shared_array_buffer = unsafe { v8_ValueAsSharedArrayBuffer(self.inner_val) };
+        V8LocalSharedArrayBuffer {
+            inner_shared_array_buffer,
+        }
+    }
+
     /// Return true if the value is null and false otherwise.
     #[must_use]
     pub fn is_null(&self) -> bool {
         (unsafe { v8_ValueIsNull(self.inner_val) } != 0)
     }
 
+    /// Return true if the value is undefined and false otherwise.
+    #[must_use]
+    pub fn is_undefined(&self) -> bool {
+        (unsafe { v8_ValueIsUndefined(self.inner_val) } != 0)
+    }
+
     /// Return true if the value is function and false otherwise.
     #[must_use]
     pub fn is_function(&self) -> bool {
         (unsafe { v8_ValueIsFunction(self.inner_val) } != 0)
     }
 
+    /// Convert the value into a function, applicable only if the value is a function.
+    #[must_use]
+    pub(crate) fn as_native_function(&self) -> V8LocalNativeFunction {
+        let inner_func = unsafe { v8_ValueAsFunction(self.inner_val) };
+        V8LocalNativeFunction { inner_func }
+    }
+
     /// Return true if the value is async function and false otherwise.
     #[must_use]
     pub fn is_async_function(&self) -> bool {
@@ -155,6 +243,39 @@ impl V8LocalValue {
         V8LocalPromise { inner_promise }
     }
 
+    /// Return true if the value is an object with a `then` method, i.e. a value
+    /// that `await` and `Promise.resolve` treat as a promise. An exception raised
+    /// while getting the `then` property is swallowed and the value is not a thenable.
+    #[must_use]
+    pub fn is_thenable(&self, ctx_scope: &V8ContextScope) -> bool {
+        if self.is_promise() {
+            return true;
+        }
+        if !self.is_object() {
+            return false;
+        }
+        let isolate = ctx_scope.get_isolate();
+        let _trycatch = isolate.new_try_catch();
+        self.as_object()
+            .get(ctx_scope, &isolate.new_string("then").to_value())
+            .is_some_and(|then| then.is_function())
+    }
+
+    /// Convert the value into a promise like `Promise.resolve` does: a promise is
+    /// returned as is, a thenable is adopted (the returned promise settles when the
+    /// thenable does) and any other value gives a promise fulfilled with the value.
+    /// Unlike calling `Promise.resolve`, this is not affected by JS code that replaced
+    /// the `Promise` global.
+    #[must_use]
+    pub fn resolve_thenable(&self, ctx_scope: &V8ContextScope) -> V8LocalPromise {
+        if self.is_promise() {
+            return self.as_promise();
+        }
+        let resolver = ctx_scope.new_resolver();
+        resolver.resolve(ctx_scope, self);
+        resolver.get_promise()
+    }
+
     /// Convert the object into a resolver, applicable only if the object is resolver.
     #[must_use]
     pub fn as_resolver(&self) -> V8LocalResolver {
@@ -175,6 +296,18 @@ impl V8LocalValue {
         V8LocalObject { inner_obj }
     }
 
+    /// Return true if the value is a map and false otherwise.
+    #[must_use]
+    pub fn is_map(&self) -> bool {
+        (unsafe { v8_ValueIsMap(self.inner_val) } != 0)
+    }
+
+    /// Return true if the value is a native error (`Error`, `TypeError`, ...) and false otherwise.
+    #[must_use]
+    pub fn is_native_error(&self) -> bool {
+        (unsafe { v8_ValueIsNativeError(self.inner_val) } != 0)
+    }
+
     /// Return true if the value is set and false otherwise.
     #[must_use]
     pub fn is_set(&self) -> bool {
@@ -188,27 +321,209 @@ impl V8LocalValue {
         V8LocalSet { inner_set }
     }
 
+    /// Return true if the values are equal using the JS strict equality (===).
+    /// Objects are only equal to themselves.
+    #[must_use]
+    pub fn strict_equals(&self, other: &Self) -> bool {
+        (unsafe { v8_ValueStrictEquals(self.inner_val, other.inner_val) } != 0)
+    }
+
     /// Persist the local object so it can be saved beyond the current handlers scope.
     #[must_use]
     pub fn persist(&self, isolate: &V8Isolate) -> V8PersistValue {
         let inner_val = unsafe { v8_PersistValue(isolate.inner_isolate, self.inner_val) };
-        V8PersistValue { inner_val }
+        let isolate_id = isolate.get_id();
+        isolate_id.persisted_handle_created();
+        V8PersistValue {
+            inner_val,
+            isolate_id,
+        }
+    }
+
+    /// Convert the value to a number like JS does (`Number(value)`), e.g. "42" is 42
+    /// and `true` is 1. Return None if the conversion raised an exception.
+    #[must_use]
+    pub fn coerce_to_number(&self, ctx_scope: &V8ContextScope) -> Option<f64> {
+        let mut res = 0.0;
+        if unsafe { v8_ValueToNumber(ctx_scope.inner_ctx_ref, self.inner_val, &mut res) } == 0 {
+            None
+        } else {
+            Some(res)
+        }
+    }
+
+    /// Convert the value to a string like JS does (`String(value)`).
+    /// Return None if the conversion raised an exception.
+    #[must_use]
+    pub fn coerce_to_string(&self, ctx_scope: &V8ContextScope) -> Option<V8LocalString> {
+        let inner_string = unsafe { v8_ValueToString(ctx_scope.inner_ctx_ref, self.inner_val) };
+        if inner_string.is_null() {
+            None
+        } else {
+            Some(V8LocalString { inner_string })
+        }
+    }
+
+    /// Return the value truthiness like JS does (`Boolean(value)`).
+    #[must_use]
+    pub fn coerce_to_boolean(&self, isolate: &V8Isolate) -> bool {
+        unsafe { v8_ValueToBoolean(isolate.inner_isolate, self.inner_val) != 0 }
+    }
+
+    /// Create a weak handle to the value, return None if the value is not an object.
+    #[must_use]
+    pub fn to_weak(&self, isolate: &V8Isolate) -> Option<V8WeakValue> {
+        if !self.is_object() {
+            return None;
+        }
+        let inner_val = unsafe {
+            v8_NewWeakValue(
+                isolate.inner_isolate,
+                self.inner_val,
+                None,
+                ptr::null_mut(),
+                None,
+            )
+        };
+        Some(V8WeakValue::new(inner_val, isolate))
+    }
+
+    /// Same as `to_weak` but the given finalizer is called when the value is
+    /// garbage collected. The finalizer is not called if the weak handle is
+    /// dropped before the value is collected. The finalizer runs during the
+    /// garbage collection so it is not allowed to use any of the isolate APIs. The finalizer
+    /// must be `Send` since the weak handle can be moved to, and dropped on, another thread.
+    #[must_use]
+    pub fn to_weak_with_finalizer<F: FnOnce() + Send + 'static>(
+        &self,
+        isolate: &V8Isolate,
+        finalizer: F,
+    ) -> Option<V8WeakValue> {
+        if !self.is_object() {
+            return None;
+        }
+        let inner_val = unsafe {
+            v8_NewWeakValue(
+                isolate.inner_isolate,
+                self.inner_val,
+                Some(weak_value_collected::<F>),
+                Box::into_raw(Box::new(finalizer)).cast::<c_void>(),
+                Some(weak_value_free_pd::<F>),
+            )
+        };
+        Some(V8WeakValue::new(inner_val, isolate))
+    }
+
+    /// Run the value as a constructor, same as `new value(...args)`,
+    /// applicable only if the value is a function.
+    #[must_use]
+    pub fn new_instance(&self, ctx: &V8ContextScope, args: &[&Self]) -> Option<Self> {
+        let args = args
+            .iter()
+            .map(|v| v.inner_val)
+            .collect::<Vec<*mut v8_local_value>>();
+        let res = ctx.timed_run(|| unsafe {
+            v8_FunctionNewInstance(ctx.inner_ctx_ref, self.inner_val, args.len(), args.as_ptr())
+        });
+
+        if res.is_null() {
+            None
+        } else {
+            Some(Self { inner_val: res })
+        }
     }
 
     /// Run the value, applicable only if the value is a function or async function.
     #[must_use]
     pub fn call(&self, ctx: &V8ContextScope, args: Option<&[&Self]>) -> Option<Self> {
-        let res = match args {
-            Some(args) => {
-                let args = args
-                    .iter()
-                    .map(|v| v.inner_val)
-                    .collect::<Vec<*mut v8_local_value>>();
-                let ptr = args.as_ptr();
-                unsafe { v8_FunctionCall(ctx.inner_ctx_ref, self.inner_val, args.len(), ptr) }
+        self.call_args(ctx, args.unwrap_or(&[]).iter().copied())
+    }
+
+    /// Same as `call` but takes the arguments from an iterator, which can yield
+    /// owned values or references, so a dynamically built argument list does not
+    /// have to be collected into a slice of references first.
+    #[must_use]
+    pub fn call_iter<I>(&self, ctx: &V8ContextScope, args: I) -> Option<Self>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Self>,
+    {
+        // The owned arguments must be kept alive until the function returns.
+        let args = args.into_iter().collect::<Vec<_>>();
+        self.call_args(ctx, args.iter().map(Borrow::borrow))
+    }
+
+    /// Call the function with the given arguments, shared by `call` and `call_iter`.
+    pub(crate) fn call_args<'b, I: Iterator<Item = &'b Self>>(
+        &self,
+        ctx: &V8ContextScope,
+        args: I,
+    ) -> Option<Self> {
+        let inner_args = args
+            .map(|v| v.inner_val)
+            .collect::<Vec<*mut v8_local_value>>();
+        let res = ctx.timed_run(|| unsafe {
+            v8_FunctionCall(
+                ctx.inner_ctx_ref,
+                self.inner_val,
+                inner_args.len(),
+                inner_args.as_ptr(),
+            )
+        });
+
+        if res.is_null() {
+            None
+        } else {
+            Some(Self { inner_val: res })
+        }
+    }
+
+    /// Same as `call` but on failure, return the raised exception itself instead of
+    /// leaving it to the nearest `V8TryCatch`, so custom error objects can be inspected
+    /// or raised again (using `V8Isolate::raise_exception`, also on another context).
+    /// A promise that is already rejected when the function returns, for example
+    /// the result of an async function that threw, is also returned as an exception.
+    /// The exception is not propagated to the caller.
+    pub fn try_call(&self, ctx: &V8ContextScope, args: &[&Self]) -> Result<Self, V8CallError> {
+        let trycatch = ctx.get_isolate().new_try_catch();
+        let res = match self.call(ctx, Some(args)) {
+            Some(res) => res,
+            None if trycatch.has_caught() && !trycatch.has_terminated() => {
+                return Err(V8CallError::Exception(trycatch.get_exception()))
             }
-            None => unsafe { v8_FunctionCall(ctx.inner_ctx_ref, self.inner_val, 0, ptr::null()) },
+            None => return Err(V8CallError::Terminated),
         };
+        if res.is_promise() {
+            let promise = res.as_promise();
+            if promise.state() == V8PromiseState::Rejected {
+                return Err(V8CallError::Exception(promise.get_result()));
+            }
+        }
+        Ok(res)
+    }
+
+    /// Same as `call` but invoke the function with the given receiver as `this`.
+    #[must_use]
+    pub fn call_with_receiver(
+        &self,
+        ctx: &V8ContextScope,
+        receiver: &Self,
+        args: Option<&[&Self]>,
+    ) -> Option<Self> {
+        let args = args
+            .unwrap_or(&[])
+            .iter()
+            .map(|v| v.inner_val)
+            .collect::<Vec<*mut v8_local_value>>();
+        let res = ctx.timed_run(|| unsafe {
+            v8_FunctionCallWithReceiver(
+                ctx.inner_ctx_ref,
+                self.inner_val,
+                receiver.inner_val,
+                args.len(),
+                args.as_ptr(),
+            )
+        });
 
         if res.is_null() {
             None
@@ -216,20 +531,483 @@ impl V8LocalValue {
             Some(Self { inner_val: res })
         }
     }
+
+    /// Return the name of the script in which the function was defined,
+    /// applicable only if the value is a function.
+    #[must_use]
+    pub fn get_script_name(&self, isolate: &V8Isolate) -> Option<String> {
+        let name = Self {
+            inner_val: unsafe { v8_FunctionGetScriptName(self.inner_val) },
+        };
+        if !name.is_string() {
+            return None;
+        }
+        name.to_utf8(isolate).map(|s| s.as_str().to_string())
+    }
+
+    /// Return the line number (zero based) on which the function was defined,
+    /// applicable only if the value is a function.
+    #[must_use]
+    pub fn get_line_number(&self) -> Option<usize> {
+        let res = unsafe { v8_FunctionGetScriptLineNumber(self.inner_val) };
+        usize::try_from(res).ok()
+    }
+
+    /// Return the column number (zero based) on which the function was defined,
+    /// applicable only if the value is a function.
+    #[must_use]
+    pub fn get_column(&self) -> Option<usize> {
+        let res = unsafe { v8_FunctionGetScriptColumnNumber(self.inner_val) };
+        usize::try_from(res).ok()
+    }
+
+    /// Return the name V8 inferred for the function (for example, the name of the
+    /// variable an anonymous function was assigned to), applicable only if the value
+    /// is a function.
+    #[must_use]
+    pub fn get_inferred_name(&self, isolate: &V8Isolate) -> Option<String> {
+        let name = Self {
+            inner_val: unsafe { v8_FunctionGetInferredName(self.inner_val) },
+        };
+        if !name.is_string() {
+            return None;
+        }
+        let name = name.to_utf8(isolate)?.as_str().to_string();
+        if name.is_empty() {
+            None
+        } else {
+            Some(name)
+        }
+    }
+
+    /// Return the name of the function that constructed the value, e.g. "Map",
+    /// "Uint8Array" or the name of a user defined class. Applicable only if the
+    /// value is an object.
+    #[must_use]
+    pub fn get_constructor_name(&self, ctx_scope: &V8ContextScope) -> Option<String> {
+        if !self.is_object() {
+            return None;
+        }
+        self.as_object().get_constructor_name(ctx_scope)
+    }
+
+    /// Return a short description of the value type (and the value itself for
+    /// primitives), for error messages, e.g. "string 'foo'", "number 1.5" or "Map".
+    #[must_use]
+    pub fn describe(&self, ctx_scope: &V8ContextScope) -> String {
+        if self.is_undefined() {
+            "undefined".to_string()
+        } else if self.is_null() {
+            "null".to_string()
+        } else if self.is_boolean() {
+            format!("boolean {}", self.get_boolean())
+        } else if self.is_number() {
+            format!("number {}", self.get_number())
+        } else if self.is_long() {
+            format!("bigint {}", self.get_long())
+        } else if self.is_string() {
+            let isolate = ctx_scope.get_isolate();
+            let s = self
+                .to_utf8(&isolate)
+                .map(|s| s.as_str().to_string())
+                .unwrap_or_default();
+            if s.chars().count() > 32 {
+                format!("string '{}...'", s.chars().take(32).collect::<String>())
+            } else {
+                format!("string '{}'", s)
+            }
+        } else if self.is_function() {
+            "function".to_string()
+        } else if self.is_array() {
+            "array".to_string()
+        } else if self.is_object() {
+            match self.get_constructor_name(ctx_scope) {
+                Some(name) if name != "Object" => name,
+                _ => "object".to_string(),
+            }
+        } else {
+            "value".to_string()
+        }
+    }
+
+    /// Return the id of the script in which the function was defined,
+    /// applicable only if the value is a function.
+    #[must_use]
+    pub fn get_script_id(&self) -> Option<i64> {
+        match unsafe { v8_FunctionGetScriptId(self.inner_val) } {
+            0 => None,
+            id => Some(id as i64),
+        }
+    }
+}
+
+impl V8LocalValue {
+    /// Create a value, opaque to JS, that owns the given Rust data. The data is
+    /// dropped when the value is garbage collected or when the isolate is freed.
+    /// The retained size of the value is estimated as the size of `T`, see
+    /// `new_external_data_with_size`.
+    #[must_use]
+    pub fn new_external_data<T: Any>(isolate: &V8Isolate, data: T) -> Self {
+        Self::new_external_data_with_size(isolate, data, std::mem::size_of::<T>())
+    }
+
+    /// Same as `new_external_data` but declares the estimated amount of memory
+    /// retained by the data, including what it owns on the heap. The value is
+    /// counted in `V8Isolate::get_external_data_stats` under the type id of `T`
+    /// until it is garbage collected.
+    #[must_use]
+    pub fn new_external_data_with_size<T: Any>(
+        isolate: &V8Isolate,
+        data: T,
+        retained_size: usize,
+    ) -> Self {
+        let type_id = TypeId::of::<T>();
+        let stats = isolate.get_id().external_data_table();
+        if let Some(stats) = &stats {
+            stats.created(type_id, std::any::type_name::<T>(), retained_size);
+        }
+        let data = Box::new(ExternalData {
+            stats,
+            type_id,
+            retained_size,
+            data: Box::new(data),
+        });
+        let inner_val = unsafe {
+            v8_NewExternalData(
+                isolate.inner_isolate,
+                Box::into_raw(data).cast::<c_void>(),
+                Some(free_external_data),
+            )
+        };
+        Self { inner_val }
+    }
+
+    /// Return the Rust data of a value created using `new_external_data`,
+    /// None if the value is not an external data value.
+    #[must_use]
+    pub fn get_external_data(&self) -> Option<&dyn Any> {
+        let data = unsafe { v8_ExternalDataGet(self.inner_val) };
+        if data.is_null() {
+            return None;
+        }
+        Some(unsafe { &*(*data.cast::<ExternalData>()).data })
+    }
+
+    /// Return the Rust payload attached to the error using
+    /// `V8ContextScope::raise_error_with_payload`, None if the value has no
+    /// payload or the payload is not of the requested type. The payload is kept
+    /// when JS code catches and rethrows the error, so it can be used to get
+    /// back the original Rust error from the exception of a `V8TryCatch`.
+    #[must_use]
+    pub fn get_error_payload<T: Any>(&self, ctx_scope: &V8ContextScope) -> Option<&T> {
+        if !self.is_object() {
+            return None;
+        }
+        let payload = self
+            .as_object()
+            .get_private(ctx_scope, ERROR_PAYLOAD_NAME)?;
+        let payload = payload.get_external_data()?.downcast_ref::<T>()?;
+        // The payload is owned by the error object, which is kept alive by `self`.
+        Some(unsafe { &*(payload as *const T) })
+    }
+
+    /// Return the value as an integer, accepts a BigInt or a number
+    /// without a fractional part. A BigInt which does not fit a 64 bit
+    /// integer is reported as out of range for the given type.
+    fn get_integer(&self, type_name: &str) -> Result<i128, String> {
+        if self.is_long() {
+            let mut n = 0;
+            if unsafe { v8_GetBigIntInt64(self.inner_val, &mut n) } != 0 {
+                return Ok(n.into());
+            }
+            let mut n = 0;
+            if unsafe { v8_GetBigIntUint64(self.inner_val, &mut n) } != 0 {
+                return Ok(n.into());
+            }
+            return Err(format!("BigInt value is out of range for {}", type_name));
+        }
+        if self.is_number() {
+            let n = self.get_number();
+            if n.is_finite() && n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 {
+                return Ok((n as i64).into());
+            }
+            return Err(format!("Value {} is not an integer", n));
+        }
+        Err("Value is not a number".to_string())
+    }
+}
+
+macro_rules! integer_try_from {
+    ($($t:ty),*) => {
+        $(
+            impl TryFrom<&V8LocalValue> for $t {
+                type Error = String;
+
+                fn try_from(val: &V8LocalValue) -> Result<Self, Self::Error> {
+                    let n = val.get_integer(stringify!($t))?;
+                    <$t>::try_from(n).map_err(|_| {
+                        format!("Value {} is out of range for {}", n, stringify!($t))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+integer_try_from!(i64, i32, u32, u64, usize);
+
+macro_rules! non_zero_try_from {
+    ($($t:ty => $inner:ty),*) => {
+        $(
+            impl TryFrom<&V8LocalValue> for $t {
+                type Error = String;
+
+                fn try_from(val: &V8LocalValue) -> Result<Self, Self::Error> {
+                    let n = <$inner>::try_from(val)?;
+                    <$t>::new(n).ok_or_else(|| format!("Value must not be zero for {}", stringify!($t)))
+                }
+            }
+        )*
+    };
+}
+
+non_zero_try_from!(
+    NonZeroI32 => i32,
+    NonZeroI64 => i64,
+    NonZeroU32 => u32,
+    NonZeroU64 => u64,
+    NonZeroUsize => usize
+);
+
+/// Checked conversions between the generic value and the specialized wrappers,
+/// the unchecked `as_*` conversions are only valid after the matching `is_*` check.
+macro_rules! local_try_from {
+    ($($t:ty => $is:ident, $as:ident, $name:literal),*) => {
+        $(
+            impl TryFrom<&V8LocalValue> for $t {
+                type Error = String;
+
+                fn try_from(val: &V8LocalValue) -> Result<Self, Self::Error> {
+                    if val.$is() {
+                        Ok(val.$as())
+                    } else {
+                        Err(format!("Value is not {}", $name))
+                    }
+                }
+            }
+
+            impl TryFrom<V8LocalValue> for $t {
+                type Error = String;
+
+                fn try_from(val: V8LocalValue) -> Result<Self, Self::Error> {
+                    Self::try_from(&val)
+                }
+            }
+
+            impl From<$t> for V8LocalValue {
+                fn from(val: $t) -> Self {
+                    val.to_value()
+                }
+            }
+        )*
+    };
+}
+
+local_try_from!(
+    V8LocalString => is_string, as_string, "a string",
+    V8LocalArray => is_array, as_array, "an array",
+    V8LocalArrayBuffer => is_array_buffer, as_array_buffer, "an array buffer",
+    V8LocalSharedArrayBuffer => is_shared_array_buffer, as_shared_array_buffer, "a shared array buffer",
+    V8LocalObject => is_object, as_object, "an object",
+    V8LocalSet => is_set, as_set, "a set",
+    V8LocalPromise => is_promise, as_promise, "a promise",
+    V8LocalNativeFunction => is_function, as_native_function, "a function"
+);
+
+/// A type that can be created from any JS value using the JS implicit conversions,
+/// like JS builtin functions do with their arguments (for example "42" is accepted
+/// as a number), see `V8LocalNativeFunctionArgsIter::next_coerced`.
+pub trait V8Coerce: Sized {
+    fn coerce(val: &V8LocalValue, ctx_scope: &V8ContextScope) -> Result<Self, String>;
+}
+
+impl V8Coerce for f64 {
+    fn coerce(val: &V8LocalValue, ctx_scope: &V8ContextScope) -> Result<Self, String> {
+        val.coerce_to_number(ctx_scope)
+            .ok_or_else(|| "value can not be converted to a number".to_string())
+    }
+}
+
+impl V8Coerce for bool {
+    fn coerce(val: &V8LocalValue, ctx_scope: &V8ContextScope) -> Result<Self, String> {
+        Ok(val.coerce_to_boolean(&ctx_scope.get_isolate()))
+    }
+}
+
+impl V8Coerce for String {
+    fn coerce(val: &V8LocalValue, ctx_scope: &V8ContextScope) -> Result<Self, String> {
+        let s = val
+            .coerce_to_string(ctx_scope)
+            .ok_or_else(|| "value can not be converted to a string".to_string())?;
+        Ok(s.to_string_lossy(&ctx_scope.get_isolate()))
+    }
+}
+
+/// Integers are truncated towards zero and NaN is 0, like JS `ToIntegerOrInfinity`,
+/// numbers out of the type range are an error.
+macro_rules! integer_coerce {
+    ($($t:ty),*) => {
+        $(
+            impl V8Coerce for $t {
+                fn coerce(val: &V8LocalValue, ctx_scope: &V8ContextScope) -> Result<Self, String> {
+                    let n = f64::coerce(val, ctx_scope)?;
+                    let n = if n.is_nan() { 0.0 } else { n.trunc() };
+                    // `MAX as f64` rounds up to 2^63 (and 2^64) for the 64 bit types, compare
+                    // against `MAX + 1` which is exact for all the types.
+                    if n < <$t>::MIN as f64 || n >= <$t>::MAX as f64 + 1.0 {
+                        return Err(format!("{} is out of range for {}", n, stringify!($t)));
+                    }
+                    Ok(n as $t)
+                }
+            }
+        )*
+    };
+}
+
+integer_coerce!(i64, i32, u32, u64, usize);
+
+/// A value that distinguishes `undefined` (for example a missing property)
+/// from an explicit `null`, for APIs that treat the two differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum V8Nullable<T> {
+    Undefined,
+    Null,
+    Value(T),
+}
+
+impl<T> V8Nullable<T> {
+    /// Return the value, treating both `undefined` and `null` as None.
+    pub fn into_option(self) -> Option<T> {
+        match self {
+            Self::Value(v) => Some(v),
+            Self::Undefined | Self::Null => None,
+        }
+    }
+}
+
+impl<T> TryFrom<&V8LocalValue> for V8Nullable<T>
+where
+    T: for<'v> TryFrom<&'v V8LocalValue, Error = String>,
+{
+    type Error = String;
+
+    fn try_from(val: &V8LocalValue) -> Result<Self, Self::Error> {
+        if val.is_undefined() {
+            Ok(Self::Undefined)
+        } else if val.is_null() {
+            Ok(Self::Null)
+        } else {
+            T::try_from(val).map(Self::Value)
+        }
+    }
 }
 
 impl V8PersistValue {
     /// Convert the persisted value back to local value.
+    /// # Panics
+    /// If the persisted value does not belong to the given isolate or the isolate was freed.
     #[must_use]
     pub fn as_local(&self, isolate: &V8Isolate) -> V8LocalValue {
+        self.try_as_local(isolate).unwrap()
+    }
+
+    /// Same as `as_local` but returns an error if the isolate that created the
+    /// persisted value was already freed or if the given isolate is not the one
+    /// that created the persisted value.
+    pub fn try_as_local(&self, isolate: &V8Isolate) -> Result<V8LocalValue, &'static str> {
+        self.verify_isolate(isolate)?;
         let inner_val = unsafe { v8_PersistedValueToLocal(isolate.inner_isolate, self.inner_val) };
-        V8LocalValue { inner_val }
+        Ok(V8LocalValue { inner_val })
+    }
+
+    /// Create a new persisted value that refers to the same JS value.
+    /// Each of the persisted values holds its own handle and can be dropped
+    /// independently of the other.
+    /// # Panics
+    /// If the persisted value does not belong to the given isolate or the isolate was freed.
+    #[must_use]
+    pub fn duplicate(&self, isolate: &V8Isolate) -> Self {
+        self.verify_isolate(isolate).unwrap();
+        let inner_val =
+            unsafe { v8_PersistedValueDuplicate(isolate.inner_isolate, self.inner_val) };
+        self.isolate_id.persisted_handle_created();
+        Self {
+            inner_val,
+            isolate_id: self.isolate_id,
+        }
+    }
+
+    fn verify_isolate(&self, isolate: &V8Isolate) -> Result<(), &'static str> {
+        if !self.isolate_id.is_alive() {
+            return Err("The isolate of the persisted value was already freed");
+        }
+        if isolate.get_id() != self.isolate_id {
+            return Err("The persisted value belongs to a different isolate");
+        }
+        Ok(())
+    }
+
+    /// Return the id of the isolate that created the persisted value.
+    #[must_use]
+    pub fn get_isolate_id(&self) -> IsolateId {
+        self.isolate_id
     }
 }
 
 unsafe impl Sync for V8PersistValue {}
 unsafe impl Send for V8PersistValue {}
 
+impl V8WeakValue {
+    fn new(inner_val: *mut v8_weak_value, isolate: &V8Isolate) -> Self {
+        let isolate_id = isolate.get_id();
+        isolate_id.persisted_handle_created();
+        Self {
+            inner_val,
+            isolate_id,
+        }
+    }
+
+    /// Return the value the handle points to or None if it was already garbage collected.
+    /// # Panics
+    /// If the weak handle does not belong to the given isolate or the isolate was freed.
+    #[must_use]
+    pub fn to_local(&self, isolate: &V8Isolate) -> Option<V8LocalValue> {
+        assert!(
+            self.isolate_id.is_alive(),
+            "The isolate of the weak value was already freed"
+        );
+        assert!(
+            isolate.get_id() == self.isolate_id,
+            "The weak value belongs to a different isolate"
+        );
+        let inner_val = unsafe { v8_WeakValueToLocal(isolate.inner_isolate, self.inner_val) };
+        if inner_val.is_null() {
+            None
+        } else {
+            Some(V8LocalValue { inner_val })
+        }
+    }
+
+    /// Return the id of the isolate that created the weak handle.
+    #[must_use]
+    pub fn get_isolate_id(&self) -> IsolateId {
+        self.isolate_id
+    }
+}
+
+unsafe impl Sync for V8WeakValue {}
+unsafe impl Send for V8WeakValue {}
+
 impl Drop for V8LocalValue {
     fn drop(&mut self) {
         if !self.inner_val.is_null() {
@@ -240,6 +1018,18 @@ impl Drop for V8LocalValue {
 
 impl Drop for V8PersistValue {
     fn drop(&mut self) {
-        unsafe { v8_FreePersistedValue(self.inner_val) }
+        // If the isolate was already freed the handle was released with it,
+        // touching it now would be a use after free.
+        if self.isolate_id.persisted_handle_freed() {
+            unsafe { v8_FreePersistedValue(self.inner_val) }
+        }
+    }
+}
+
+impl Drop for V8WeakValue {
+    fn drop(&mut self) {
+        if self.isolate_id.persisted_handle_freed() {
+            unsafe { v8_FreeWeakValue(self.inner_val) }
+        }
     }
 }
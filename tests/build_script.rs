@@ -0,0 +1,6 @@
+//! Runs the unit tests of the build script helpers.
+
+// `main` and the helpers it alone uses are only called when running as the build script.
+#[allow(dead_code)]
+#[path = "../build.rs"]
+mod build_script;
@@ -1,5 +1,10 @@
 #include "v8include/v8.h"
 #include "v8include/libplatform/libplatform.h"
+#include <chrono>
+#include <mutex>
+#include <map>
+#include <set>
+#include <string>
 
 std::unique_ptr<v8::Platform> platform;
 
@@ -8,6 +13,20 @@ extern "C" {
 #include "v8_c_api.h"
 #include <stdlib.h>
 #include <string.h>
+#include <vector>
+#include <atomic>
+#include <time.h>
+#include <limits.h>
+#ifdef _WIN32
+#define WIN32_LEAN_AND_MEAN
+#define NOMINMAX
+#include <windows.h>
+#endif
+
+#ifdef _MSC_VER
+/* strdup is deprecated by MSVC in favour of the ISO C++ name */
+#define strdup _strdup
+#endif
 
 static v8_alloctor DefaultAllocator = {
 		.v8_Alloc = malloc,
@@ -36,13 +55,49 @@ struct v8_context {
 	v8::Persistent<v8::Context> *persistent_ctx;
 };
 
+/* Number of local wrappers which are alive on the current thread, a local wrapper
+ * is only used on the thread that created it. */
+static thread_local size_t live_locals = 0;
+
+/* Same as live_locals, broken down by the kind of the wrapper. */
+static thread_local size_t live_locals_of_kind[v8_LocalKind_Count] = {};
+
+/* Counts the local wrapper it is a member of, the v8_Free* function of the wrapper
+ * must call the wrapper destructor. Wrappers which are owned by the isolate are
+ * released right away. */
+struct v8_local_tracker {
+	v8_LocalKind kind;
+	bool tracked = true;
+	v8_local_tracker(v8_LocalKind k): kind(k) {
+		++live_locals;
+		++live_locals_of_kind[kind];
+	}
+	v8_local_tracker(const v8_local_tracker&) = delete;
+	~v8_local_tracker() { release(); }
+	void release() {
+		if (tracked) {
+			--live_locals;
+			--live_locals_of_kind[kind];
+			tracked = false;
+		}
+	}
+};
+
 struct v8_handlers_scope {
 	v8::HandleScope handle_scope;
 	v8_handlers_scope(v8::Isolate *v8_isolate): handle_scope(v8_isolate){}
 };
 
+struct v8_escapable_handlers_scope {
+	v8::EscapableHandleScope handle_scope;
+	v8_escapable_handlers_scope(v8::Isolate *v8_isolate): handle_scope(v8_isolate){}
+};
+
 struct v8_local_string {
 	v8::Local<v8::String> str;
+	v8_local_tracker tracker{v8_LocalKind_String};
+	/* cached strings are owned by the isolate and are not freed by v8_FreeString */
+	bool cached = false;
 	v8_local_string(v8::Isolate *isolate, const char *buff, size_t len) {
 		str = v8::String::NewFromUtf8(isolate, buff, v8::NewStringType::kNormal, len).ToLocalChecked();
 	}
@@ -52,6 +107,7 @@ struct v8_local_string {
 
 struct v8_local_script {
 	v8::Local<v8::Script> script;
+	v8_local_tracker tracker{v8_LocalKind_Script};
 	v8_local_script(v8::Local<v8::Context> v8_local_ctx, v8_local_string *code) {
 		v8::MaybeLocal<v8::Script> compilation_res = v8::Script::Compile(v8_local_ctx, code->str);
 		if (!compilation_res.IsEmpty()) {
@@ -63,6 +119,7 @@ struct v8_local_script {
 
 struct v8_local_module {
 	v8::Local<v8::Module> mod;
+	v8_local_tracker tracker{v8_LocalKind_Module};
 	v8_local_module(v8::Local<v8::Module> m): mod(m) {}
 	v8_local_module(v8::Isolate *isolate, v8::Persistent<v8::Module> *m) {
 		mod = v8::Local<v8::Module>::New(isolate, *m);
@@ -71,29 +128,49 @@ struct v8_local_module {
 
 struct v8_local_value {
 	v8::Local<v8::Value> val;
+	v8_local_tracker tracker{v8_LocalKind_Value};
+	/* cached values are owned by the isolate and are not freed by v8_FreeValue */
+	bool cached = false;
 	v8_local_value(v8::Local<v8::Value> value): val(value) {}
 	v8_local_value(v8::Isolate *isolate, v8::Persistent<v8::Value> *value) {
 		val = v8::Local<v8::Value>::New(isolate, *value);
 	}
 };
 
+/* Unlike v8::String::Utf8Value, lone surrogates are replaced with U+FFFD so the content is always valid UTF-8. */
 struct v8_utf8_value {
-	v8::String::Utf8Value utf8_val;
-	v8_utf8_value(v8::Isolate *isolate, v8::Local<v8::Value> val): utf8_val(isolate, val) {}
+	std::string utf8_val;
+	bool valid;
+	v8_utf8_value(v8::Isolate *isolate, v8::Local<v8::Value> val): valid(false) {
+		v8::HandleScope handle_scope(isolate);
+		/* same as v8::String::Utf8Value, a conversion failure is not reported as an exception */
+		v8::TryCatch trycatch(isolate);
+		v8::Local<v8::String> str;
+		if (!val->ToString(isolate->GetCurrentContext()).ToLocal(&str)) {
+			return;
+		}
+		utf8_val.resize(str->Utf8Length(isolate));
+		str->WriteUtf8(isolate, &utf8_val[0], utf8_val.size(), NULL,
+				v8::String::NO_NULL_TERMINATION | v8::String::REPLACE_INVALID_UTF8);
+		valid = true;
+	}
 };
 
 struct v8_local_native_function_template {
 	v8::Local<v8::FunctionTemplate> func;
+	v8_local_tracker tracker{v8_LocalKind_NativeFunctionTemplate};
 	v8_local_native_function_template(v8::Local<v8::FunctionTemplate> f): func(f) {}
 };
 
 struct v8_local_native_function {
 	v8::Local<v8::Function> func;
+	v8_local_tracker tracker{v8_LocalKind_NativeFunction};
 	v8_local_native_function(v8::Local<v8::Function> f): func(f) {}
 };
 
 struct v8_local_object_template {
 	v8::Local<v8::ObjectTemplate> obj;
+	v8_local_tracker tracker{v8_LocalKind_ObjectTemplate};
 	v8_local_object_template(v8::Local<v8::ObjectTemplate> o): obj(o) {};
 };
 
@@ -104,39 +181,57 @@ struct v8_trycatch {
 
 struct v8_context_ref {
 	v8::Local<v8::Context> context;
+	v8_local_tracker tracker{v8_LocalKind_ContextRef};
 	v8_context_ref(v8::Local<v8::Context> ctx): context(ctx){}
 };
 
 struct v8_local_promise {
 	v8::Local<v8::Promise> promise;
+	v8_local_tracker tracker{v8_LocalKind_Promise};
 	v8_local_promise(v8::Local<v8::Promise> p): promise(p) {}
 };
 
 struct v8_local_resolver {
 	v8::Local<v8::Promise::Resolver> resolver;
+	v8_local_tracker tracker{v8_LocalKind_Resolver};
 	v8_local_resolver(v8::Local<v8::Promise::Resolver> r): resolver(r) {}
 };
 
 struct v8_local_object {
 	v8::Local<v8::Object> obj;
+	v8_local_tracker tracker{v8_LocalKind_Object};
 	v8_local_object(v8::Local<v8::Object> o): obj(o) {}
 };
 
 struct v8_local_set {
 	v8::Local<v8::Set> set;
+	v8_local_tracker tracker{v8_LocalKind_Set};
 	v8_local_set(v8::Local<v8::Set> o): set(o) {}
 };
 
 struct v8_local_array {
 	v8::Local<v8::Array> arr;
+	v8_local_tracker tracker{v8_LocalKind_Array};
 	v8_local_array(v8::Local<v8::Array> a): arr(a) {}
 };
 
 struct v8_local_array_buff {
 	v8::Local<v8::ArrayBuffer> arr_buff;
+	v8_local_tracker tracker{v8_LocalKind_ArrayBuffer};
 	v8_local_array_buff(v8::Local<v8::ArrayBuffer> a): arr_buff(a) {}
 };
 
+struct v8_local_shared_array_buff {
+	v8::Local<v8::SharedArrayBuffer> arr_buff;
+	v8_local_tracker tracker{v8_LocalKind_SharedArrayBuffer};
+	v8_local_shared_array_buff(v8::Local<v8::SharedArrayBuffer> a): arr_buff(a) {}
+};
+
+struct v8_backing_store {
+	std::shared_ptr<v8::BackingStore> backing_store;
+	v8_backing_store(std::shared_ptr<v8::BackingStore> b): backing_store(b) {}
+};
+
 typedef struct v8_native_function_pd v8_native_function_pd;
 typedef struct v8_pd_node v8_pd_node;
 typedef struct v8_pd_list v8_pd_list;
@@ -165,7 +260,33 @@ struct v8_pd_node{
 };
 
 struct v8_pd_list{
+	size_t isolate_id;
+	int error_listeners_installed;
 	v8::ArrayBuffer::Allocator *allocator;
+	std::vector<v8::Global<v8::Data>> *templates;
+	/* synthetic modules that was not yet evaluated and their default export */
+	std::vector<std::pair<v8::Global<v8::Module>, v8::Global<v8::Value>>> *synthetic_modules;
+	/* promises that were rejected without a handler and their rejection reason, reported
+	 * once the microtasks complete unless a handler is added to them before that */
+	std::vector<std::pair<v8::Global<v8::Promise>, v8::Global<v8::Value>>> *pending_rejections;
+	/* Wrappers of the primitives v8 interns on the isolate roots, they do not hold
+	 * any handle scope slot and so they are valid for the entire isolate lifetime. */
+	v8_local_value *true_val;
+	v8_local_value *false_val;
+	v8_local_value *null_val;
+	v8_local_value *undefined_val;
+	v8_local_string *empty_string;
+	/* the promise hook set using v8_IsolateSetPromiseHook, if any */
+	v8_PromiseHookCallback promise_hook;
+	void *promise_hook_pd;
+	void (*promise_hook_free_pd)(void *pd);
+	/* the import.meta callback set using v8_IsolateSetImportMetaCallback, if any */
+	v8_ImportMetaCallback import_meta_callback;
+	void *import_meta_pd;
+	void (*import_meta_free_pd)(void *pd);
+	/* the last near OOM handler set using v8_IsolateSetNearOOMHandler, if any */
+	v8::NearHeapLimitCallback near_oom_callback;
+	void *near_oom_pd;
 	v8_pd_node *start;
 	v8_pd_node *end;
 };
@@ -212,21 +333,178 @@ void v8_PDListFree(v8_pd_list* pd_list) {
 	while (pd_list->end) {
 		v8_ListNodeFree(pd_list->end);
 	}
+	if (pd_list->promise_hook_free_pd) {
+		pd_list->promise_hook_free_pd(pd_list->promise_hook_pd);
+	}
+	if (pd_list->import_meta_free_pd) {
+		pd_list->import_meta_free_pd(pd_list->import_meta_pd);
+	}
+	delete pd_list->templates;
+	delete pd_list->synthetic_modules;
+	delete pd_list->pending_rejections;
+	V8_FREE(pd_list->true_val);
+	V8_FREE(pd_list->false_val);
+	V8_FREE(pd_list->null_val);
+	V8_FREE(pd_list->undefined_val);
+	V8_FREE(pd_list->empty_string);
 	V8_FREE(pd_list);
 }
 
+static std::atomic<size_t> last_isolate_id(0);
+
 v8_pd_list* v8_PDListCreate(v8::ArrayBuffer::Allocator *alloc) {
 	v8_pd_list *native_data = (v8_pd_list*)V8_ALLOC(sizeof(*native_data));
+	native_data->isolate_id = ++last_isolate_id;
+	native_data->error_listeners_installed = 0;
+	native_data->promise_hook = NULL;
+	native_data->promise_hook_pd = NULL;
+	native_data->promise_hook_free_pd = NULL;
+	native_data->import_meta_callback = NULL;
+	native_data->import_meta_pd = NULL;
+	native_data->import_meta_free_pd = NULL;
+	native_data->near_oom_callback = NULL;
+	native_data->near_oom_pd = NULL;
 	native_data->start = NULL;
 	native_data->end = NULL;
 	native_data->allocator = alloc;
+	native_data->templates = new std::vector<v8::Global<v8::Data>>();
+	native_data->synthetic_modules = new std::vector<std::pair<v8::Global<v8::Module>, v8::Global<v8::Value>>>();
+	native_data->pending_rejections = new std::vector<std::pair<v8::Global<v8::Promise>, v8::Global<v8::Value>>>();
 	return native_data;
 }
 
-void v8_Initialize(v8_alloctor *alloc) {
+struct v8_TraceCategory {
+	/* must be the first field, v8 gets a pointer to it */
+	uint8_t enabled;
+	std::string name;
+};
+
+struct v8_TraceCompleteEvent {
+	const v8_TraceCategory *category;
+	const char *name;
+	long long timestamp;
+};
+
+class v8_TracingController : public v8::TracingController {
+private:
+	std::set<std::string> enabled_categories;
+	v8_TraceEventCallback callback;
+	void *pd;
+	void(*free_pd)(void *pd);
+	std::mutex lock;
+	std::map<std::string, v8_TraceCategory*> categories;
+	std::map<uint64_t, v8_TraceCompleteEvent> complete_events;
+	uint64_t next_handle;
+
+	static long long Now() {
+		return std::chrono::duration_cast<std::chrono::microseconds>(std::chrono::steady_clock::now().time_since_epoch()).count();
+	}
+
+	bool IsEnabled(const std::string &category_group) {
+		size_t start = 0;
+		while (start <= category_group.size()) {
+			size_t end = category_group.find(',', start);
+			if (end == std::string::npos) {
+				end = category_group.size();
+			}
+			if (enabled_categories.count(category_group.substr(start, end - start))) {
+				return true;
+			}
+			start = end + 1;
+		}
+		return false;
+	}
+
+public:
+	v8_TracingController(const char *categories_str, v8_TraceEventCallback callback, void *pd, void(*free_pd)(void *pd)):
+		callback(callback), pd(pd), free_pd(free_pd), next_handle(1) {
+		std::string categories_list(categories_str ? categories_str : "");
+		size_t start = 0;
+		while (start < categories_list.size()) {
+			size_t end = categories_list.find(',', start);
+			if (end == std::string::npos) {
+				end = categories_list.size();
+			}
+			if (end > start) {
+				enabled_categories.insert(categories_list.substr(start, end - start));
+			}
+			start = end + 1;
+		}
+	}
+
+	~v8_TracingController() {
+		for (auto &it : categories) {
+			delete it.second;
+		}
+		if (free_pd) {
+			free_pd(pd);
+		}
+	}
+
+	const uint8_t* GetCategoryGroupEnabled(const char* name) override {
+		std::lock_guard<std::mutex> guard(lock);
+		auto it = categories.find(name);
+		if (it != categories.end()) {
+			return &it->second->enabled;
+		}
+		v8_TraceCategory *category = new v8_TraceCategory();
+		category->name = name;
+		/* kEnabledForRecording */
+		category->enabled = IsEnabled(category->name) ? 1 : 0;
+		categories[category->name] = category;
+		return &category->enabled;
+	}
+
+	uint64_t AddTraceEvent(
+			char phase, const uint8_t* category_enabled_flag, const char* name,
+			const char* scope, uint64_t id, uint64_t bind_id, int32_t num_args,
+			const char** arg_names, const uint8_t* arg_types,
+			const uint64_t* arg_values,
+			std::unique_ptr<v8::ConvertableToTraceFormat>* arg_convertables,
+			unsigned int flags) override {
+		return AddTraceEventWithTimestamp(phase, category_enabled_flag, name, scope, id, bind_id, num_args,
+				arg_names, arg_types, arg_values, arg_convertables, flags, Now());
+	}
+
+	uint64_t AddTraceEventWithTimestamp(
+			char phase, const uint8_t* category_enabled_flag, const char* name,
+			const char* scope, uint64_t id, uint64_t bind_id, int32_t num_args,
+			const char** arg_names, const uint8_t* arg_types,
+			const uint64_t* arg_values,
+			std::unique_ptr<v8::ConvertableToTraceFormat>* arg_convertables,
+			unsigned int flags, int64_t timestamp) override {
+		const v8_TraceCategory *category = (const v8_TraceCategory*)category_enabled_flag;
+		if (phase == 'X') {
+			/* complete event, will be reported when its duration is known */
+			std::lock_guard<std::mutex> guard(lock);
+			uint64_t handle = next_handle++;
+			complete_events[handle] = {category, name, timestamp};
+			return handle;
+		}
+		callback(phase, category->name.c_str(), name, timestamp, 0, pd);
+		return 0;
+	}
+
+	void UpdateTraceEventDuration(const uint8_t* category_enabled_flag, const char* name, uint64_t handle) override {
+		v8_TraceCompleteEvent event;
+		{
+			std::lock_guard<std::mutex> guard(lock);
+			auto it = complete_events.find(handle);
+			if (it == complete_events.end()) {
+				return;
+			}
+			event = it->second;
+			complete_events.erase(it);
+		}
+		callback('X', event.category->name.c_str(), event.name, event.timestamp, Now() - event.timestamp, pd);
+	}
+};
+
+static void v8_InitializeInternal(v8_alloctor *alloc, std::unique_ptr<v8::TracingController> tracing_controller) {
 //	v8::V8::SetFlagsFromString("--expose_gc");
 //	v8::V8::SetFlagsFromString("--log-all");
-	platform = v8::platform::NewDefaultPlatform();
+	platform = v8::platform::NewDefaultPlatform(0, v8::platform::IdleTaskSupport::kEnabled,
+			v8::platform::InProcessStackDumping::kDisabled, std::move(tracing_controller));
 	v8::V8::InitializePlatform(platform.get());
 	v8::V8::Initialize();
 	if (alloc) {
@@ -236,6 +514,43 @@ void v8_Initialize(v8_alloctor *alloc) {
 	}
 }
 
+void v8_Initialize(v8_alloctor *alloc) {
+	v8_InitializeInternal(alloc, {});
+}
+
+void v8_InitializeWithTracing(v8_alloctor *alloc, const char *categories, v8_TraceEventCallback callback, void *pd, void(*free_pd)(void *pd)) {
+	v8_InitializeInternal(alloc, std::unique_ptr<v8::TracingController>(new v8_TracingController(categories, callback, pd, free_pd)));
+}
+
+int v8_InitializeICU(const char *icu_data_file) {
+#ifdef V8_RS_I18N
+	return v8::V8::InitializeICU(icu_data_file) ? 1 : 0;
+#else
+	return 0;
+#endif
+}
+
+void v8_InitializeExternalStartupDataFromFile(const char *snapshot_blob_file) {
+	v8::V8::InitializeExternalStartupDataFromFile(snapshot_blob_file);
+}
+
+int v8_InitializeExternalStartupDataFromBytes(const char *data, size_t len) {
+	/* v8 keeps a pointer to the startup data, it must outlive v8 */
+	static v8::StartupData startup_data;
+	if (len > (size_t)INT_MAX) {
+		return 0;
+	}
+	/* the copy given by a previous call is not used once it is replaced */
+	const char *old_data = startup_data.data;
+	char *data_copy = new char[len];
+	memcpy(data_copy, data, len);
+	startup_data.data = data_copy;
+	startup_data.raw_size = (int)len;
+	v8::V8::SetSnapshotDataBlob(&startup_data);
+	delete[] old_data;
+	return 1;
+}
+
 const char* v8_Version() {
 	return v8::V8::GetVersion();
 }
@@ -248,16 +563,106 @@ static void v8_FreeAllocator(v8::ArrayBuffer::Allocator* allocator) {
 	delete allocator;
 }
 
-v8_isolate* v8_NewIsolate(size_t initial_heap_size_in_bytes, size_t maximum_heap_size_in_bytes) {
+class v8_CustomArrayBufferAllocator : public v8::ArrayBuffer::Allocator {
+private:
+	v8_ArrayBufferAllocateCallback allocate;
+	v8_ArrayBufferFreeCallback free;
+	void *pd;
+	void(*free_pd)(void *pd);
+
+public:
+	v8_CustomArrayBufferAllocator(v8_ArrayBufferAllocateCallback allocate, v8_ArrayBufferFreeCallback free, void *pd, void(*free_pd)(void *pd)):
+		allocate(allocate), free(free), pd(pd), free_pd(free_pd) {}
+
+	~v8_CustomArrayBufferAllocator() {
+		if (free_pd) {
+			free_pd(pd);
+		}
+	}
+
+	void* Allocate(size_t length) override {
+		return allocate(length, 1, pd);
+	}
+
+	void* AllocateUninitialized(size_t length) override {
+		return allocate(length, 0, pd);
+	}
+
+	void Free(void* data, size_t length) override {
+		free(data, length, pd);
+	}
+};
+
+static v8_local_value* v8_NewCachedValue(v8::Local<v8::Value> val) {
+	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
+	v8_val = new (v8_val) v8_local_value(val);
+	v8_val->cached = true;
+	v8_val->tracker.release();
+	return v8_val;
+}
+
+/* Attach the native data to the isolate, 'array_buffer_allocator' is freed with the
+ * isolate and is NULL for isolates that were not created by this API. */
+static void v8_IsolateInitNativeData(v8::Isolate *isolate, v8::ArrayBuffer::Allocator *array_buffer_allocator) {
+	v8_pd_list *native_data = v8_PDListCreate(array_buffer_allocator);
+	native_data->true_val = v8_NewCachedValue(v8::True(isolate));
+	native_data->false_val = v8_NewCachedValue(v8::False(isolate));
+	native_data->null_val = v8_NewCachedValue(v8::Null(isolate));
+	native_data->undefined_val = v8_NewCachedValue(v8::Undefined(isolate));
+	native_data->empty_string = (struct v8_local_string*)V8_ALLOC(sizeof(*native_data->empty_string));
+	native_data->empty_string = new (native_data->empty_string) v8_local_string(v8::String::Empty(isolate));
+	native_data->empty_string->cached = true;
+	native_data->empty_string->tracker.release();
+	isolate->SetData(0, native_data);
+}
+
+static v8_isolate* v8_NewIsolateInternal(size_t initial_heap_size_in_bytes, size_t maximum_heap_size_in_bytes, v8::ArrayBuffer::Allocator *array_buffer_allocator) {
 	v8::Isolate::CreateParams create_params;
-	create_params.array_buffer_allocator = v8::ArrayBuffer::Allocator::NewDefaultAllocator();
+	create_params.array_buffer_allocator = array_buffer_allocator;
 	create_params.constraints.ConfigureDefaultsFromHeapSize(initial_heap_size_in_bytes, maximum_heap_size_in_bytes);
 	v8::Isolate *isolate = v8::Isolate::New(create_params);
+	v8_IsolateInitNativeData(isolate, create_params.array_buffer_allocator);
+	return (v8_isolate*)isolate;
+}
 
-	v8_pd_list *native_data = v8_PDListCreate(create_params.array_buffer_allocator);
-	isolate->SetData(0, native_data);
+int v8_IsolateAttach(void *raw) {
+	v8::Isolate *isolate = (v8::Isolate*)raw;
+	if (isolate->GetData(0)) {
+		return 0;
+	}
+	v8_IsolateInitNativeData(isolate, NULL);
+	return 1;
+}
 
-	return (v8_isolate*)isolate;
+static void v8_UnhandledErrorMessageListener(v8::Local<v8::Message> message, v8::Local<v8::Value> error);
+static void v8_UnhandledErrorReportRejections(v8::Isolate *isolate, void *data);
+
+void v8_IsolateDetach(v8_isolate* i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	if (native_data->promise_hook) {
+		isolate->SetPromiseHook(NULL);
+	}
+	if (native_data->import_meta_callback) {
+		isolate->SetHostInitializeImportMetaObjectCallback(NULL);
+	}
+	if (native_data->error_listeners_installed) {
+		isolate->RemoveMessageListeners(v8_UnhandledErrorMessageListener);
+		isolate->SetPromiseRejectCallback(NULL);
+		isolate->RemoveMicrotasksCompletedCallback(v8_UnhandledErrorReportRejections);
+	}
+	v8_PDListFree(native_data);
+	isolate->SetData(0, NULL);
+}
+
+v8_isolate* v8_NewIsolate(size_t initial_heap_size_in_bytes, size_t maximum_heap_size_in_bytes) {
+	return v8_NewIsolateInternal(initial_heap_size_in_bytes, maximum_heap_size_in_bytes, v8::ArrayBuffer::Allocator::NewDefaultAllocator());
+}
+
+v8_isolate* v8_NewIsolateWithAllocator(size_t initial_heap_size_in_bytes, size_t maximum_heap_size_in_bytes,
+		v8_ArrayBufferAllocateCallback allocate, v8_ArrayBufferFreeCallback free, void *pd, void(*free_pd)(void *pd)) {
+	v8::ArrayBuffer::Allocator *allocator = new v8_CustomArrayBufferAllocator(allocate, free, pd, free_pd);
+	return v8_NewIsolateInternal(initial_heap_size_in_bytes, maximum_heap_size_in_bytes, allocator);
 }
 
 void v8_IsolateSetFatalErrorHandler(v8_isolate* i, void (*fatal_hanlder)(const char* location, const char* message)) {
@@ -276,6 +681,19 @@ void v8_IsolateSetNearOOMHandler(v8_isolate* i, size_t (*near_oom_callback)(void
 	v8_PDListAdd(native_data, pd, free_pd);
 	isolate->AddNearHeapLimitCallback(near_oom_callback, pd);
 	isolate->AutomaticallyRestoreInitialHeapLimit();
+	native_data->near_oom_callback = near_oom_callback;
+	native_data->near_oom_pd = pd;
+}
+
+void v8_IsolateRestoreHeapLimit(v8_isolate* i, size_t heap_limit) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	if (!native_data->near_oom_callback) {
+		return;
+	}
+	/* removing the callback is the only way to lower the heap limit, add it back right away */
+	isolate->RemoveNearHeapLimitCallback(native_data->near_oom_callback, heap_limit);
+	isolate->AddNearHeapLimitCallback(native_data->near_oom_callback, native_data->near_oom_pd);
 }
 
 void v8_TerminateCurrExecution(v8_isolate* i) {
@@ -297,11 +715,188 @@ void v8_FreeIsolate(v8_isolate* i) {
 	v8_FreeAllocator(allocator);
 }
 
+size_t v8_IsolateGetId(v8_isolate* i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	return native_data->isolate_id;
+}
+
 void v8_RequestInterrupt(v8_isolate* i, v8_InterruptCallback callback, void *data) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	isolate->RequestInterrupt((v8::InterruptCallback)callback, data);
 }
 
+void v8_IsolateClearKeptObjects(v8_isolate* i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	isolate->ClearKeptObjects();
+}
+
+void v8_IsolatePerformMicrotaskCheckpoint(v8_isolate* i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	isolate->PerformMicrotaskCheckpoint();
+}
+
+static void v8_PromiseHook(v8::PromiseHookType type, v8::Local<v8::Promise> promise, v8::Local<v8::Value> parent) {
+	v8::Isolate *isolate = promise->GetIsolate();
+	v8_pd_list *pd_list = (v8_pd_list*)isolate->GetData(0);
+	if (!pd_list->promise_hook) {
+		return;
+	}
+	v8_PromiseHookType hook_type;
+	switch(type) {
+	case v8::PromiseHookType::kInit:
+		hook_type = v8_PromiseHookType_Init;
+		break;
+	case v8::PromiseHookType::kResolve:
+		hook_type = v8_PromiseHookType_Resolve;
+		break;
+	case v8::PromiseHookType::kBefore:
+		hook_type = v8_PromiseHookType_Before;
+		break;
+	default:
+		hook_type = v8_PromiseHookType_After;
+	}
+	v8_local_promise *local_promise = (struct v8_local_promise*)V8_ALLOC(sizeof(*local_promise));
+	local_promise = new (local_promise) v8_local_promise(promise);
+	v8_local_value *local_parent = (struct v8_local_value*)V8_ALLOC(sizeof(*local_parent));
+	local_parent = new (local_parent) v8_local_value(parent.IsEmpty() ? v8::Undefined(isolate).As<v8::Value>() : parent);
+	pd_list->promise_hook((v8_isolate*)isolate, hook_type, local_promise, local_parent, pd_list->promise_hook_pd);
+}
+
+void v8_IsolateSetPromiseHook(v8_isolate* i, v8_PromiseHookCallback callback, void *pd, void(*free_pd)(void *pd)) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_pd_list *pd_list = (v8_pd_list*)isolate->GetData(0);
+	if (pd_list->promise_hook_free_pd) {
+		pd_list->promise_hook_free_pd(pd_list->promise_hook_pd);
+	}
+	pd_list->promise_hook = callback;
+	pd_list->promise_hook_pd = pd;
+	pd_list->promise_hook_free_pd = free_pd;
+	isolate->SetPromiseHook(callback ? v8_PromiseHook : NULL);
+}
+
+static void v8_InitializeImportMeta(v8::Local<v8::Context> context, v8::Local<v8::Module> module, v8::Local<v8::Object> meta) {
+	v8::Isolate *isolate = context->GetIsolate();
+	v8_pd_list *pd_list = (v8_pd_list*)isolate->GetData(0);
+	if (!pd_list->import_meta_callback) {
+		return;
+	}
+	v8_context_ref *v8_ctx_ref = (struct v8_context_ref*)V8_ALLOC(sizeof(*v8_ctx_ref));
+	v8_ctx_ref = new (v8_ctx_ref) v8_context_ref(context);
+	v8_local_module *local_module = (struct v8_local_module*)V8_ALLOC(sizeof(*local_module));
+	local_module = new (local_module) v8_local_module(module);
+	v8_local_object *local_meta = (struct v8_local_object*)V8_ALLOC(sizeof(*local_meta));
+	local_meta = new (local_meta) v8_local_object(meta);
+	pd_list->import_meta_callback(v8_ctx_ref, local_module, local_meta, pd_list->import_meta_pd);
+}
+
+void v8_IsolateSetImportMetaCallback(v8_isolate* i, v8_ImportMetaCallback callback, void *pd, void(*free_pd)(void *pd)) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_pd_list *pd_list = (v8_pd_list*)isolate->GetData(0);
+	if (pd_list->import_meta_free_pd) {
+		pd_list->import_meta_free_pd(pd_list->import_meta_pd);
+	}
+	pd_list->import_meta_callback = callback;
+	pd_list->import_meta_pd = pd;
+	pd_list->import_meta_free_pd = free_pd;
+	isolate->SetHostInitializeImportMetaObjectCallback(callback ? v8_InitializeImportMeta : NULL);
+}
+
+class v8_Task : public v8::Task {
+private:
+	v8::Isolate *isolate;
+	v8_InterruptCallback callback;
+	void *data;
+	void(*free_data)(void *data);
+
+public:
+	v8_Task(v8::Isolate *isolate, v8_InterruptCallback callback, void *data, void(*free_data)(void *data)):
+		isolate(isolate), callback(callback), data(data), free_data(free_data) {}
+
+	~v8_Task() {
+		if (data && free_data) {
+			free_data(data);
+		}
+	}
+
+	void Run() override {
+		void *task_data = data;
+		/* the callback gets the ownership on the data */
+		data = NULL;
+		callback((v8_isolate*)isolate, task_data);
+	}
+};
+
+void v8_IsolatePostTask(v8_isolate* i, v8_InterruptCallback callback, void *data, void(*free_data)(void *data), double delay_in_seconds) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	std::shared_ptr<v8::TaskRunner> runner = platform->GetForegroundTaskRunner(isolate);
+	std::unique_ptr<v8::Task> task(new v8_Task(isolate, callback, data, free_data));
+	if (delay_in_seconds > 0) {
+		runner->PostDelayedTask(std::move(task), delay_in_seconds);
+	} else {
+		runner->PostTask(std::move(task));
+	}
+}
+
+int v8_IsolatePumpMessageLoop(v8_isolate* i, int wait_for_work) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::platform::MessageLoopBehavior behavior = wait_for_work ? v8::platform::MessageLoopBehavior::kWaitForWork : v8::platform::MessageLoopBehavior::kDoNotWait;
+	return v8::platform::PumpMessageLoop(platform.get(), isolate, behavior) ? 1 : 0;
+}
+
+void v8_IsolateRunIdleTasks(v8_isolate* i, double idle_time_in_seconds) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::platform::RunIdleTasks(platform.get(), isolate, idle_time_in_seconds);
+}
+
+static void v8_IsolateSetTemplate(v8::Isolate *isolate, size_t index, v8::Local<v8::Data> t) {
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	if (native_data->templates->size() <= index) {
+		native_data->templates->resize(index + 1);
+	}
+	(*native_data->templates)[index].Reset(isolate, t);
+}
+
+static v8::Local<v8::Data> v8_IsolateGetTemplate(v8::Isolate *isolate, size_t index) {
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	if (native_data->templates->size() <= index) {
+		return v8::Local<v8::Data>();
+	}
+	return (*native_data->templates)[index].Get(isolate);
+}
+
+void v8_IsolateSetObjectTemplate(v8_isolate* i, size_t index, v8_local_object_template *obj) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_IsolateSetTemplate(isolate, index, obj->obj);
+}
+
+v8_local_object_template* v8_IsolateGetObjectTemplate(v8_isolate* i, size_t index) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Local<v8::Data> t = v8_IsolateGetTemplate(isolate, index);
+	if (t.IsEmpty() || !t->IsObjectTemplate()) {
+		return NULL;
+	}
+	v8_local_object_template *v8_obj = (struct v8_local_object_template*)V8_ALLOC(sizeof(*v8_obj));
+	v8_obj = new (v8_obj) v8_local_object_template(v8::Local<v8::ObjectTemplate>::Cast(t));
+	return v8_obj;
+}
+
+void v8_IsolateSetNativeFunctionTemplate(v8_isolate* i, size_t index, v8_local_native_function_template *func) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_IsolateSetTemplate(isolate, index, func->func);
+}
+
+v8_local_native_function_template* v8_IsolateGetNativeFunctionTemplate(v8_isolate* i, size_t index) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Local<v8::Data> t = v8_IsolateGetTemplate(isolate, index);
+	if (t.IsEmpty() || !t->IsFunctionTemplate()) {
+		return NULL;
+	}
+	v8_local_native_function_template *v8_native = (struct v8_local_native_function_template*)V8_ALLOC(sizeof(*v8_native));
+	v8_native = new (v8_native) v8_local_native_function_template(v8::Local<v8::FunctionTemplate>::Cast(t));
+	return v8_native;
+}
+
 v8_isolate_scope* v8_IsolateEnter(v8_isolate *i) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	v8_isolate_scope *v8_isolateScope = (struct v8_isolate_scope*)V8_ALLOC(sizeof(*v8_isolateScope));
@@ -321,6 +916,29 @@ void v8_IsolateRaiseException(v8_isolate *i, v8_local_value *exception) {
 	isolate->ThrowException(exception->val);
 }
 
+v8_local_value* v8_NewError(v8_local_string *message, v8_ErrorType error_type) {
+	v8::Local<v8::Value> error;
+	switch(error_type) {
+	case v8_ErrorType_TypeError:
+		error = v8::Exception::TypeError(message->str);
+		break;
+	case v8_ErrorType_RangeError:
+		error = v8::Exception::RangeError(message->str);
+		break;
+	case v8_ErrorType_ReferenceError:
+		error = v8::Exception::ReferenceError(message->str);
+		break;
+	case v8_ErrorType_SyntaxError:
+		error = v8::Exception::SyntaxError(message->str);
+		break;
+	default:
+		error = v8::Exception::Error(message->str);
+	}
+	v8_local_value *res = (v8_local_value*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_value(error);
+	return res;
+}
+
 v8_context_ref* v8_GetCurrentCtxRef(v8_isolate *i) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	v8_context_ref *ref = (v8_context_ref*) V8_ALLOC(sizeof(*ref));
@@ -328,9 +946,61 @@ v8_context_ref* v8_GetCurrentCtxRef(v8_isolate *i) {
 	return ref;
 }
 
-void v8_IdleNotificationDeadline(v8_isolate *i, double deadline_in_seconds) {
+int v8_IsolateInContext(v8_isolate *i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	return isolate->InContext() ? 1 : 0;
+}
+
+int v8_ContextRefIsCurrent(v8_context_ref *v8_ctx_ref) {
+	v8::Isolate *isolate = v8_ctx_ref->context->GetIsolate();
+	if (!isolate->InContext()) {
+		return 0;
+	}
+	v8::HandleScope handle_scope(isolate);
+	return isolate->GetCurrentContext() == v8_ctx_ref->context ? 1 : 0;
+}
+
+void v8_ContextRefSetContinuationData(v8_context_ref *v8_ctx_ref, v8_local_value *val) {
+	v8_ctx_ref->context->SetContinuationPreservedEmbedderData(val->val);
+}
+
+v8_local_value* v8_ContextRefGetContinuationData(v8_context_ref *v8_ctx_ref) {
+	v8::Local<v8::Value> val = v8_ctx_ref->context->GetContinuationPreservedEmbedderData();
+	if (val.IsEmpty()) {
+		val = v8::Undefined(v8_ctx_ref->context->GetIsolate());
+	}
+	v8_local_value *res = (struct v8_local_value*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_value(val);
+	return res;
+}
+
+int v8_IdleNotificationDeadline(v8_isolate *i, double idle_time_in_seconds) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	/* the deadline is compared with the platform monotonic clock */
+	double deadline_in_seconds = platform->MonotonicallyIncreasingTime() + idle_time_in_seconds;
+	return isolate->IdleNotificationDeadline(deadline_in_seconds) ? 1 : 0;
+}
+
+void v8_LowMemoryNotification(v8_isolate *i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	isolate->LowMemoryNotification();
+}
+
+void v8_MemoryPressureNotification(v8_isolate *i, v8_MemoryPressureLevel level) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
-	isolate->IdleNotificationDeadline(deadline_in_seconds);
+	v8::MemoryPressureLevel v8_level = v8::MemoryPressureLevel::kNone;
+	switch(level) {
+	case v8_MemoryPressureLevel_None:
+		v8_level = v8::MemoryPressureLevel::kNone;
+		break;
+	case v8_MemoryPressureLevel_Moderate:
+		v8_level = v8::MemoryPressureLevel::kModerate;
+		break;
+	case v8_MemoryPressureLevel_Critical:
+		v8_level = v8::MemoryPressureLevel::kCritical;
+		break;
+	}
+	isolate->MemoryPressureNotification(v8_level);
 }
 
 v8_trycatch* v8_NewTryCatch(v8_isolate *i) {
@@ -347,6 +1017,10 @@ v8_local_value* v8_TryCatchGetException(v8_trycatch *trycatch) {
 	return v8_val;
 }
 
+int v8_TryCatchHasCaught(v8_trycatch *trycatch) {
+	return trycatch->trycatch.HasCaught() ? 1 : 0;
+}
+
 int v8_TryCatchHasTerminated(v8_trycatch *trycatch) {
 	return trycatch->trycatch.HasTerminated() ? 1 : 0;
 }
@@ -368,6 +1042,41 @@ void v8_FreeHandlersScope(v8_handlers_scope* v8_handlersScope) {
 	V8_FREE(v8_handlersScope);
 }
 
+size_t v8_LiveLocals() {
+	return live_locals;
+}
+
+size_t v8_LiveLocalsOfKind(v8_LocalKind kind) {
+	if ((size_t)kind >= v8_LocalKind_Count) {
+		return 0;
+	}
+	return live_locals_of_kind[kind];
+}
+
+size_t v8_NumberOfHandles(v8_isolate *i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	return v8::HandleScope::NumberOfHandles(isolate);
+}
+
+v8_escapable_handlers_scope* v8_NewEscapableHandlersScope(v8_isolate *i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_escapable_handlers_scope *v8_handlersScope = (struct v8_escapable_handlers_scope*)V8_ALLOC(sizeof(*v8_handlersScope));
+	v8_handlersScope = new (v8_handlersScope) v8_escapable_handlers_scope(isolate);
+	return v8_handlersScope;
+}
+
+v8_local_value* v8_EscapableHandlersScopeEscape(v8_escapable_handlers_scope* v8_handlersScope, v8_local_value *val) {
+	v8::Local<v8::Value> escaped = v8_handlersScope->handle_scope.Escape(val->val);
+	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
+	v8_val = new (v8_val) v8_local_value(escaped);
+	return v8_val;
+}
+
+void v8_FreeEscapableHandlersScope(v8_escapable_handlers_scope* v8_handlersScope) {
+	v8_handlersScope->~v8_escapable_handlers_scope();
+	V8_FREE(v8_handlersScope);
+}
+
 static v8::Local<v8::Context> v8_NewContexInternal(v8::Isolate* v8_isolate, v8_local_object_template *globals) {
 	if (globals) {
 		return v8::Context::New(v8_isolate, nullptr, globals->obj);
@@ -411,6 +1120,17 @@ v8_context_ref* v8_ContextEnter(v8_context *v8_ctx) {
 	return ref;
 }
 
+v8_context_ref* v8_ObjectEnterCreationContext(v8_local_object *obj) {
+	v8::MaybeLocal<v8::Context> maybe_ctx = obj->obj->GetCreationContext();
+	if (maybe_ctx.IsEmpty()) {
+		return NULL;
+	}
+	v8_context_ref *ref = (v8_context_ref*) V8_ALLOC(sizeof(*ref));
+	ref = new (ref) v8_context_ref(maybe_ctx.ToLocalChecked());
+	ref->context->Enter();
+	return ref;
+}
+
 v8_isolate* v8_ContextRefGetIsolate(v8_context_ref *v8_ctx_ref) {
 	return (v8_isolate*)v8_ctx_ref->context->GetIsolate();
 }
@@ -423,11 +1143,16 @@ v8_local_object* v8_ContextRefGetGlobals(v8_context_ref *v8_ctx_ref) {
 	return v8_globals;
 }
 
+void v8_ContextRefAllowCodeGenerationFromStrings(v8_context_ref *v8_ctx_ref, int allow) {
+	v8_ctx_ref->context->AllowCodeGenerationFromStrings(allow != 0);
+}
+
 void v8_ExitContextRef(v8_context_ref *v8_ctx_ref) {
 	v8_ctx_ref->context->Exit();
 }
 
 void v8_FreeContextRef(v8_context_ref *v8_ctx_ref) {
+	v8_ctx_ref->~v8_context_ref();
 	V8_FREE(v8_ctx_ref);
 }
 
@@ -442,21 +1167,305 @@ void v8_SetPrivateDataOnCtxRef(v8_context_ref* ctx_ref, size_t index, void *pd)
 	ctx_ref->context->SetEmbedderData(index + 2, data);
 }
 
-v8_local_string* v8_NewString(v8_isolate* i, const char *str, size_t len) {
-	v8::Isolate *isolate = (v8::Isolate*)i;
-	v8_local_string *v8_str = (struct v8_local_string*)V8_ALLOC(sizeof(*v8_str));
-	v8_str = new (v8_str) v8_local_string(isolate, str, len);
-	return v8_str;
+/* Context embedder data slot that holds the unhandled error handler */
+#define UNHANDLED_ERROR_HANDLER_INDEX 0
+
+typedef struct v8_unhandled_error_handler {
+	v8_UnhandledErrorCallback callback;
+	void *pd;
+	void(*freePD)(void *pd);
+	v8_pd_node *node;
+}v8_unhandled_error_handler;
+
+static void v8_FreeUnhandledErrorHandler(void *data) {
+	v8_unhandled_error_handler *handler = (v8_unhandled_error_handler*)data;
+	if (handler->freePD) {
+		handler->freePD(handler->pd);
+	}
+	V8_FREE(handler);
 }
 
-v8_local_value* v8_StringToValue(v8_local_string *str) {
-	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
-	v8_val = new (v8_val) v8_local_value(str->str);
-	return v8_val;
+static v8_unhandled_error_handler* v8_GetUnhandledErrorHandler(v8::Local<v8::Context> context) {
+	if (context->GetNumberOfEmbedderDataFields() <= UNHANDLED_ERROR_HANDLER_INDEX) {
+		return NULL;
+	}
+	v8::Local<v8::Value> data = context->GetEmbedderData(UNHANDLED_ERROR_HANDLER_INDEX);
+	if (!data->IsExternal()) {
+		return NULL;
+	}
+	return (v8_unhandled_error_handler*)v8::Local<v8::External>::Cast(data)->Value();
 }
 
-v8_local_object* v8_StringToStringObject(v8_isolate* i, v8_local_string *str) {
-	v8::Isolate *isolate = (v8::Isolate*)i;
+static void v8_CallUnhandledErrorHandler(v8::Local<v8::Context> context, v8_UnhandledErrorType type, v8::Local<v8::Value> error,
+										 v8::Local<v8::Value> script_name, int line_number) {
+	v8_unhandled_error_handler *handler = v8_GetUnhandledErrorHandler(context);
+	if (!handler) {
+		return;
+	}
+
+	v8_context_ref *v8_ctx_ref = (struct v8_context_ref*)V8_ALLOC(sizeof(*v8_ctx_ref));
+	v8_ctx_ref = new (v8_ctx_ref) v8_context_ref(context);
+
+	v8_local_value *v8_error = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_error));
+	v8_error = new (v8_error) v8_local_value(error);
+
+	v8_local_string *v8_script_name = NULL;
+	if (!script_name.IsEmpty() && script_name->IsString()) {
+		v8_script_name = (struct v8_local_string*)V8_ALLOC(sizeof(*v8_script_name));
+		v8_script_name = new (v8_script_name) v8_local_string(v8::Local<v8::String>::Cast(script_name));
+	}
+
+	handler->callback(v8_ctx_ref, type, v8_error, v8_script_name, line_number, handler->pd);
+}
+
+static void v8_DiagnosticsGCPrologue(v8::Isolate *isolate, v8::GCType type, v8::GCCallbackFlags flags, void *data) {
+	((v8_GCCallback)data)((v8_isolate*)isolate, 1, type);
+}
+
+static void v8_DiagnosticsGCEpilogue(v8::Isolate *isolate, v8::GCType type, v8::GCCallbackFlags flags, void *data) {
+	((v8_GCCallback)data)((v8_isolate*)isolate, 0, type);
+}
+
+static void v8_DiagnosticsMessageListener(v8::Local<v8::Message> message, v8::Local<v8::Value> data) {
+	v8::Isolate *isolate = message->GetIsolate();
+	v8::HandleScope handle_scope(isolate);
+	v8_ExceptionMessageCallback callback = (v8_ExceptionMessageCallback)v8::Local<v8::External>::Cast(data)->Value();
+	v8::String::Utf8Value msg(isolate, message->Get());
+	v8::Local<v8::Value> script_name_val = message->GetScriptResourceName();
+	bool has_script_name = !script_name_val.IsEmpty() && script_name_val->IsString();
+	v8::String::Utf8Value script_name(isolate, script_name_val);
+	int line_number = -1;
+	if (isolate->InContext()) {
+		line_number = message->GetLineNumber(isolate->GetCurrentContext()).FromMaybe(-1);
+	}
+	callback((v8_isolate*)isolate, *msg ? *msg : "", *msg ? msg.length() : 0, has_script_name ? *script_name : NULL, line_number);
+}
+
+void v8_IsolateSetDiagnosticsCallbacks(v8_isolate *i, v8_GCCallback gc_callback, v8_ExceptionMessageCallback message_callback) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::HandleScope handle_scope(isolate);
+	isolate->AddGCPrologueCallback(v8_DiagnosticsGCPrologue, (void*)gc_callback);
+	isolate->AddGCEpilogueCallback(v8_DiagnosticsGCEpilogue, (void*)gc_callback);
+	isolate->AddMessageListenerWithErrorLevel(v8_DiagnosticsMessageListener, v8::Isolate::kMessageError, v8::External::New(isolate, (void*)message_callback));
+}
+
+static void v8_UnhandledErrorMessageListener(v8::Local<v8::Message> message, v8::Local<v8::Value> error) {
+	v8::Isolate *isolate = message->GetIsolate();
+	if (!isolate->InContext()) {
+		return;
+	}
+	v8::Local<v8::Context> context = isolate->GetCurrentContext();
+	int line_number = message->GetLineNumber(context).FromMaybe(0);
+	v8_CallUnhandledErrorHandler(context, v8_UnhandledErrorType_UncaughtException, error, message->GetScriptResourceName(), line_number);
+}
+
+static void v8_UnhandledErrorPromiseRejectCallback(v8::PromiseRejectMessage message) {
+	v8::Local<v8::Promise> promise = message.GetPromise();
+	v8::Isolate *isolate = promise->GetIsolate();
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	auto *pending = native_data->pending_rejections;
+	if (message.GetEvent() == v8::kPromiseRejectWithNoHandler) {
+		pending->emplace_back(v8::Global<v8::Promise>(isolate, promise), v8::Global<v8::Value>(isolate, message.GetValue()));
+	} else if (message.GetEvent() == v8::kPromiseHandlerAddedAfterReject) {
+		for (auto it = pending->begin() ; it != pending->end() ; ++it) {
+			if (it->first == promise) {
+				pending->erase(it);
+				break;
+			}
+		}
+	}
+}
+
+static void v8_UnhandledErrorReportRejections(v8::Isolate *isolate, void *data) {
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	if (native_data->pending_rejections->empty()) {
+		return;
+	}
+	/* the handlers might reject more promises, those are reported on the next completion */
+	std::vector<std::pair<v8::Global<v8::Promise>, v8::Global<v8::Value>>> rejections;
+	rejections.swap(*native_data->pending_rejections);
+	v8::HandleScope handle_scope(isolate);
+	for (auto &rejection : rejections) {
+		v8::Local<v8::Context> context;
+		if (!rejection.first.Get(isolate)->GetCreationContext().ToLocal(&context)) {
+			continue;
+		}
+		v8::Context::Scope context_scope(context);
+		v8_CallUnhandledErrorHandler(context, v8_UnhandledErrorType_UnhandledRejection, rejection.second.Get(isolate), v8::Local<v8::Value>(), 0);
+	}
+}
+
+void v8_ContextRefSetUnhandledErrorHandler(v8_context_ref *ctx_ref, v8_UnhandledErrorCallback callback, void *pd, void(*freePD)(void *pd)) {
+	v8::Isolate *isolate = ctx_ref->context->GetIsolate();
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	if (!native_data->error_listeners_installed) {
+		isolate->AddMessageListenerWithErrorLevel(v8_UnhandledErrorMessageListener, v8::Isolate::kMessageError);
+		isolate->SetPromiseRejectCallback(v8_UnhandledErrorPromiseRejectCallback);
+		isolate->AddMicrotasksCompletedCallback(v8_UnhandledErrorReportRejections);
+		native_data->error_listeners_installed = 1;
+	}
+
+	v8_unhandled_error_handler *old_handler = v8_GetUnhandledErrorHandler(ctx_ref->context);
+	if (old_handler) {
+		v8_ListNodeFree(old_handler->node);
+	}
+
+	v8_unhandled_error_handler *handler = (v8_unhandled_error_handler*)V8_ALLOC(sizeof(*handler));
+	handler->callback = callback;
+	handler->pd = pd;
+	handler->freePD = freePD;
+	handler->node = v8_PDListAdd(native_data, handler, v8_FreeUnhandledErrorHandler);
+
+	v8::Local<v8::External> data = v8::External::New(isolate, (void*)handler);
+	ctx_ref->context->SetEmbedderData(UNHANDLED_ERROR_HANDLER_INDEX, data);
+}
+
+v8_local_string* v8_NewString(v8_isolate* i, const char *str, size_t len) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_local_string *v8_str = (struct v8_local_string*)V8_ALLOC(sizeof(*v8_str));
+	v8_str = new (v8_str) v8_local_string(isolate, str, len);
+	return v8_str;
+}
+
+v8_local_string* v8_TryNewString(v8_isolate* i, const char *str, size_t len, int internalize) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	/* v8 gets the length as an int */
+	if (len > (size_t)v8::String::kMaxLength) {
+		return NULL;
+	}
+	v8::NewStringType type = internalize ? v8::NewStringType::kInternalized : v8::NewStringType::kNormal;
+	v8::MaybeLocal<v8::String> maybe_str = v8::String::NewFromUtf8(isolate, str, type, len);
+	if (maybe_str.IsEmpty()) {
+		return NULL;
+	}
+	v8_local_string *v8_str = (struct v8_local_string*)V8_ALLOC(sizeof(*v8_str));
+	v8_str = new (v8_str) v8_local_string(maybe_str.ToLocalChecked());
+	return v8_str;
+}
+
+size_t v8_StringMaxLength() {
+	return v8::String::kMaxLength;
+}
+
+v8_local_string* v8_NewStringFromOneByte(v8_isolate* i, const uint8_t *str, size_t len) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::MaybeLocal<v8::String> maybe_str = v8::String::NewFromOneByte(isolate, str, v8::NewStringType::kNormal, len);
+	if (maybe_str.IsEmpty()) {
+		return NULL;
+	}
+	v8_local_string *v8_str = (struct v8_local_string*)V8_ALLOC(sizeof(*v8_str));
+	v8_str = new (v8_str) v8_local_string(maybe_str.ToLocalChecked());
+	return v8_str;
+}
+
+v8_local_string* v8_NewStringFromTwoByte(v8_isolate* i, const uint16_t *str, size_t len) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::MaybeLocal<v8::String> maybe_str = v8::String::NewFromTwoByte(isolate, str, v8::NewStringType::kNormal, len);
+	if (maybe_str.IsEmpty()) {
+		return NULL;
+	}
+	v8_local_string *v8_str = (struct v8_local_string*)V8_ALLOC(sizeof(*v8_str));
+	v8_str = new (v8_str) v8_local_string(maybe_str.ToLocalChecked());
+	return v8_str;
+}
+
+class v8_ExternalString : public v8::String::ExternalOneByteStringResource {
+private:
+	const char *str;
+	size_t len;
+	void *pd;
+	void(*freePD)(void *pd);
+
+public:
+	v8_ExternalString(const char *str, size_t len, void *pd, void(*freePD)(void *pd)): str(str), len(len), pd(pd), freePD(freePD) {}
+	~v8_ExternalString() {
+		if (freePD) {
+			freePD(pd);
+		}
+	}
+
+	const char* data() const override {
+		return str;
+	}
+
+	size_t length() const override {
+		return len;
+	}
+};
+
+v8_local_string* v8_NewExternalString(v8_isolate* i, const char *str, size_t len, void *pd, void(*freePD)(void *pd)) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_ExternalString *resource = new v8_ExternalString(str, len, pd, freePD);
+	v8::MaybeLocal<v8::String> res = v8::String::NewExternalOneByte(isolate, resource);
+	if (res.IsEmpty()) {
+		/* v8 did not take ownership on the resource */
+		delete resource;
+		return NULL;
+	}
+	v8_local_string *v8_str = (struct v8_local_string*)V8_ALLOC(sizeof(*v8_str));
+	v8_str = new (v8_str) v8_local_string(res.ToLocalChecked());
+	return v8_str;
+}
+
+class v8_ExternalTwoByteString : public v8::String::ExternalStringResource {
+private:
+	const uint16_t *str;
+	size_t len;
+	void *pd;
+	void(*freePD)(void *pd);
+
+public:
+	v8_ExternalTwoByteString(const uint16_t *str, size_t len, void *pd, void(*freePD)(void *pd)): str(str), len(len), pd(pd), freePD(freePD) {}
+	~v8_ExternalTwoByteString() {
+		if (freePD) {
+			freePD(pd);
+		}
+	}
+
+	const uint16_t* data() const override {
+		return str;
+	}
+
+	size_t length() const override {
+		return len;
+	}
+};
+
+v8_local_string* v8_NewExternalTwoByteString(v8_isolate* i, const uint16_t *str, size_t len, void *pd, void(*freePD)(void *pd)) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_ExternalTwoByteString *resource = new v8_ExternalTwoByteString(str, len, pd, freePD);
+	v8::MaybeLocal<v8::String> res = v8::String::NewExternalTwoByte(isolate, resource);
+	if (res.IsEmpty()) {
+		/* v8 did not take ownership on the resource */
+		delete resource;
+		return NULL;
+	}
+	v8_local_string *v8_str = (struct v8_local_string*)V8_ALLOC(sizeof(*v8_str));
+	v8_str = new (v8_str) v8_local_string(res.ToLocalChecked());
+	return v8_str;
+}
+
+int v8_StringIsExternal(v8_local_string *str) {
+	return str->str->IsExternal();
+}
+
+size_t v8_StringLength(v8_local_string *str) {
+	return str->str->Length();
+}
+
+void v8_StringWriteUtf16(v8_isolate *i, v8_local_string *str, uint16_t *buff) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	str->str->Write(isolate, buff, 0, -1, v8::String::NO_NULL_TERMINATION);
+}
+
+v8_local_value* v8_StringToValue(v8_local_string *str) {
+	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
+	v8_val = new (v8_val) v8_local_value(str->str);
+	return v8_val;
+}
+
+v8_local_object* v8_StringToStringObject(v8_isolate* i, v8_local_string *str) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
 	v8::Local<v8::Value> str_obj = v8::StringObject::New(isolate, str->str);
 	v8::Local<v8::Object> obj = v8::Local<v8::Object>::Cast(str_obj);
 	v8_local_object *res = (v8_local_object*) V8_ALLOC(sizeof(*res));
@@ -465,6 +1474,10 @@ v8_local_object* v8_StringToStringObject(v8_isolate* i, v8_local_string *str) {
 }
 
 void v8_FreeString(v8_local_string *str) {
+	if (str->cached) {
+		return;
+	}
+	str->~v8_local_string();
 	V8_FREE(str);
 }
 
@@ -474,7 +1487,7 @@ static void v8_NativeBaseFunction(const v8::FunctionCallbackInfo<v8::Value>& inf
 	v8_local_value* val = nf_pd->func((v8_local_value_arr*)&info, info.Length(), nf_pd->pd);
 	if (val) {
 		info.GetReturnValue().Set(val->val);
-		V8_FREE(val);
+		v8_FreeValue(val);
 	}
 }
 
@@ -532,10 +1545,32 @@ v8_local_native_function* v8_NewNativeFunction(v8_context_ref *ctx_ref, native_f
 }
 
 void v8_FreeNativeFunctionTemplate(v8_local_native_function_template *func) {
+	func->~v8_local_native_function_template();
 	V8_FREE(func);
 }
 
+v8_persisted_native_function_template* v8_NativeFunctionTemplatePersist(v8_isolate *i, v8_local_native_function_template *func) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	return (v8_persisted_native_function_template*) new v8::Persistent<v8::FunctionTemplate>(isolate, func->func);
+}
+
+v8_local_native_function_template* v8_PersistedNativeFunctionTemplateToLocal(v8_isolate *i, v8_persisted_native_function_template *func) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Persistent<v8::FunctionTemplate> *persisted_func = (v8::Persistent<v8::FunctionTemplate>*)func;
+	v8::Local<v8::FunctionTemplate> f = v8::Local<v8::FunctionTemplate>::New(isolate, *persisted_func);
+	v8_local_native_function_template *v8_native = (struct v8_local_native_function_template*)V8_ALLOC(sizeof(*v8_native));
+	v8_native = new (v8_native) v8_local_native_function_template(f);
+	return v8_native;
+}
+
+void v8_FreePersistedNativeFunctionTemplate(v8_persisted_native_function_template *func) {
+	v8::Persistent<v8::FunctionTemplate> *persisted_func = (v8::Persistent<v8::FunctionTemplate>*)func;
+	persisted_func->Reset();
+	delete persisted_func;
+}
+
 void v8_FreeNativeFunction(v8_local_native_function *func) {
+	func->~v8_local_native_function();
 	V8_FREE(func);
 }
 
@@ -546,6 +1581,13 @@ v8_local_value* v8_NativeFunctionToValue(v8_local_native_function *func) {
 	return v8_val;
 }
 
+v8_local_native_function* v8_ValueAsFunction(v8_local_value *val) {
+	v8::Local<v8::Function> f = v8::Local<v8::Function>::Cast(val->val);
+	v8_local_native_function *res = (struct v8_local_native_function*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_native_function(f);
+	return res;
+}
+
 v8_local_value* v8_ArgsGet(v8_local_value_arr *args, size_t i) {
 	v8::FunctionCallbackInfo<v8::Value> *info = (v8::FunctionCallbackInfo<v8::Value> *)args;
 	v8::Handle<v8::Value> v = (*info)[i];
@@ -554,6 +1596,14 @@ v8_local_value* v8_ArgsGet(v8_local_value_arr *args, size_t i) {
 	return v8_val;
 }
 
+v8_local_value* v8_ArgsGetSelf(v8_local_value_arr *args) {
+	v8::FunctionCallbackInfo<v8::Value> *info = (v8::FunctionCallbackInfo<v8::Value> *)args;
+	v8::Local<v8::Value> self = info->This();
+	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
+	v8_val = new (v8_val) v8_local_value(self);
+	return v8_val;
+}
+
 v8_isolate* v8_GetCurrentIsolate(v8_local_value_arr *args) {
 	v8::FunctionCallbackInfo<v8::Value> *info = (v8::FunctionCallbackInfo<v8::Value> *)args;
 	v8::Isolate* isolate = info->GetIsolate();
@@ -569,6 +1619,7 @@ v8_local_object_template* v8_NewObjectTemplate(v8_isolate* i) {
 }
 
 void v8_FreeObjectTemplate(v8_local_object_template *obj) {
+	obj->~v8_local_object_template();
 	V8_FREE(obj);
 }
 
@@ -584,6 +1635,11 @@ void v8_ObjectTemplateSetValue(v8_local_object_template *obj, v8_local_string *n
 	obj->obj->Set(name->str, val->val);
 }
 
+void v8_ObjectTemplateSetPrivate(v8_isolate *i, v8_local_object_template *obj, v8_local_string *name, v8_local_value *val) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	obj->obj->SetPrivate(v8::Private::ForApi(isolate, name->str), val->val);
+}
+
 v8_local_value* v8_ObjectTemplateToValue(v8_context_ref *ctx_ref, v8_local_object_template *obj) {
 	v8::Local<v8::Value> v = obj->obj->NewInstance(ctx_ref->context).ToLocalChecked();
 	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
@@ -591,16 +1647,126 @@ v8_local_value* v8_ObjectTemplateToValue(v8_context_ref *ctx_ref, v8_local_objec
 	return v8_val;
 }
 
+int v8_ObjectTemplateSetInternalFieldCount(v8_local_object_template *obj, size_t count) {
+	/* v8 treats a count which is not a valid small integer as a fatal error */
+	if (count > (size_t)v8::internal::kSmiMaxValue) {
+		return 0;
+	}
+	obj->obj->SetInternalFieldCount((int)count);
+	return 1;
+}
+
+v8_persisted_object_template* v8_ObjectTemplatePersist(v8_isolate *i, v8_local_object_template *obj) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	return (v8_persisted_object_template*) new v8::Persistent<v8::ObjectTemplate>(isolate, obj->obj);
+}
+
+v8_local_object_template* v8_PersistedObjectTemplateToLocal(v8_isolate *i, v8_persisted_object_template *obj) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Persistent<v8::ObjectTemplate> *persisted_obj = (v8::Persistent<v8::ObjectTemplate>*)obj;
+	v8::Local<v8::ObjectTemplate> o = v8::Local<v8::ObjectTemplate>::New(isolate, *persisted_obj);
+	v8_local_object_template *v8_obj = (struct v8_local_object_template*)V8_ALLOC(sizeof(*v8_obj));
+	v8_obj = new (v8_obj) v8_local_object_template(o);
+	return v8_obj;
+}
+
+void v8_FreePersistedObjectTemplate(v8_persisted_object_template *obj) {
+	v8::Persistent<v8::ObjectTemplate> *persisted_obj = (v8::Persistent<v8::ObjectTemplate>*)obj;
+	persisted_obj->Reset();
+	delete persisted_obj;
+}
+
 v8_local_script* v8_Compile(v8_context_ref* v8_ctx_ref, v8_local_string* str) {
 	v8_local_script *v8_script = (struct v8_local_script*)V8_ALLOC(sizeof(*v8_script));
 	v8_script = new (v8_script) v8_local_script(v8_ctx_ref->context, str);
 	if (v8_script->script.IsEmpty()) {
+		v8_script->~v8_local_script();
 		V8_FREE(v8_script);
 		return NULL;
 	}
 	return v8_script;
 }
 
+v8_local_script* v8_CompileWithOptions(v8_context_ref* v8_ctx_ref, v8_local_string* str, v8_local_string* name, int line_offset, int column_offset,
+									   int strict, const char *cached_data, size_t cached_data_len, int *cache_rejected) {
+	v8::Isolate *isolate = v8_ctx_ref->context->GetIsolate();
+	v8::Local<v8::String> code = str->str;
+	if (strict) {
+		/* A hashbang is only allowed at the very start of the code, turn it into
+		 * a comment of the same length so the columns are unchanged. */
+		uint16_t head[2];
+		if (code->Write(isolate, head, 0, 2, v8::String::NO_NULL_TERMINATION) == 2 && head[0] == '#' && head[1] == '!') {
+			std::vector<uint16_t> buf(code->Length());
+			code->Write(isolate, buf.data(), 0, (int)buf.size(), v8::String::NO_NULL_TERMINATION);
+			buf[0] = buf[1] = '/';
+			code = v8::String::NewFromTwoByte(isolate, buf.data(), v8::NewStringType::kNormal, (int)buf.size()).ToLocalChecked();
+		}
+		/* The directive is added on its own line, which is compensated by the origin
+		 * line offset. The column offset only applies to the first line of the source,
+		 * which is now the directive, so the code first line is indented instead. */
+		std::string prefix = "'use strict';\n";
+		if (column_offset > 0) {
+			prefix.append(column_offset, ' ');
+		}
+		v8::Local<v8::String> directive = v8::String::NewFromUtf8(isolate, prefix.data(), v8::NewStringType::kNormal, (int)prefix.size()).ToLocalChecked();
+		code = v8::String::Concat(isolate, directive, code);
+		line_offset -= 1;
+		column_offset = 0;
+	}
+	v8::Local<v8::Value> resource_name = name ? name->str.As<v8::Value>() : v8::Undefined(isolate).As<v8::Value>();
+	v8::ScriptOrigin origin(isolate, resource_name, line_offset, column_offset);
+	v8::ScriptCompiler::CachedData *cache = NULL;
+	v8::ScriptCompiler::CompileOptions options = v8::ScriptCompiler::kNoCompileOptions;
+	if (cached_data) {
+		cache = new v8::ScriptCompiler::CachedData((const uint8_t*)cached_data, cached_data_len, v8::ScriptCompiler::CachedData::BufferNotOwned);
+		options = v8::ScriptCompiler::kConsumeCodeCache;
+	}
+	/* the source takes ownership on the cached data */
+	v8::ScriptCompiler::Source source(code, origin, cache);
+	v8::MaybeLocal<v8::Script> script = v8::ScriptCompiler::Compile(v8_ctx_ref->context, &source, options);
+	if (cache && cache_rejected) {
+		*cache_rejected = source.GetCachedData()->rejected ? 1 : 0;
+	}
+	if (script.IsEmpty()) {
+		return NULL;
+	}
+
+	v8_local_script *v8_script = (struct v8_local_script*)V8_ALLOC(sizeof(*v8_script));
+	v8_script = new (v8_script) v8_local_script(script.ToLocalChecked());
+	return v8_script;
+}
+
+char* v8_ScriptCreateCodeCache(v8_local_script* script, size_t *len) {
+	v8::ScriptCompiler::CachedData *cache = v8::ScriptCompiler::CreateCodeCache(script->script->GetUnboundScript());
+	char *res = (char*)V8_ALLOC(cache->length);
+	memcpy(res, cache->data, cache->length);
+	*len = cache->length;
+	delete cache;
+	return res;
+}
+
+void v8_FreeCodeCache(char *cache) {
+	V8_FREE(cache);
+}
+
+v8_local_value* v8_CompileFunction(v8_context_ref* v8_ctx_ref, v8_local_string* name, v8_local_string* code, size_t argc, v8_local_string* const* args) {
+	v8::Isolate *isolate = v8_ctx_ref->context->GetIsolate();
+	std::vector<v8::Local<v8::String>> args_arr(argc);
+	for (size_t i = 0 ; i < argc ; ++i) {
+		args_arr[i] = args[i]->str;
+	}
+	v8::ScriptOrigin origin(isolate, name->str);
+	v8::ScriptCompiler::Source source(code->str, origin);
+	v8::MaybeLocal<v8::Function> func = v8::ScriptCompiler::CompileFunction(v8_ctx_ref->context, &source, argc, args_arr.data());
+	if (func.IsEmpty()) {
+		return NULL;
+	}
+
+	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
+	v8_val = new (v8_val) v8_local_value(func.ToLocalChecked());
+	return v8_val;
+}
+
 v8_persisted_script* v8_ScriptPersist(v8_isolate *i, v8_local_script* script) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	return (v8_persisted_script*) new v8::Persistent<v8::Script>(isolate, script->script);
@@ -621,6 +1787,36 @@ void v8_FreePersistedScript(v8_persisted_script* script) {
 	delete persisted_script;
 }
 
+v8_persisted_unbound_script* v8_ScriptGetUnboundScript(v8_isolate *i, v8_local_script* script) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	return (v8_persisted_unbound_script*) new v8::Persistent<v8::UnboundScript>(isolate, script->script->GetUnboundScript());
+}
+
+v8_local_script* v8_UnboundScriptBindToCurrentContext(v8_isolate *i, v8_persisted_unbound_script* script) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Persistent<v8::UnboundScript> *persisted_script = (v8::Persistent<v8::UnboundScript>*)script;
+	v8::Local<v8::UnboundScript> unbound_script = v8::Local<v8::UnboundScript>::New(isolate, *persisted_script);
+	v8_local_script *local_script = (struct v8_local_script*)V8_ALLOC(sizeof(*local_script));
+	local_script = new (local_script) v8_local_script(unbound_script->BindToCurrentContext());
+	return local_script;
+}
+
+int v8_ScriptGetId(v8_local_script* script) {
+	return script->script->GetUnboundScript()->GetId();
+}
+
+int v8_UnboundScriptGetId(v8_isolate *i, v8_persisted_unbound_script* script) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Persistent<v8::UnboundScript> *persisted_script = (v8::Persistent<v8::UnboundScript>*)script;
+	return v8::Local<v8::UnboundScript>::New(isolate, *persisted_script)->GetId();
+}
+
+void v8_FreePersistedUnboundScript(v8_persisted_unbound_script* script) {
+	v8::Persistent<v8::UnboundScript> *persisted_script = (v8::Persistent<v8::UnboundScript>*)script;
+	persisted_script->Reset();
+	delete persisted_script;
+}
+
 static v8::MaybeLocal<v8::Module> v8_ResolveModules(v8::Local<v8::Context> context, v8::Local<v8::String> specifier,
 													v8::Local<v8::FixedArray> import_assertions, v8::Local<v8::Module> referrer) {
 	v8::Local<v8::External> external = v8::Local<v8::External>::Cast(context->GetEmbedderData(1));
@@ -630,11 +1826,12 @@ static v8::MaybeLocal<v8::Module> v8_ResolveModules(v8::Local<v8::Context> conte
 	v8_ctx_ref = new (v8_ctx_ref) v8_context_ref(context);
 
 	v8_local_string* name = (struct v8_local_string*)V8_ALLOC(sizeof(*name));
-	int identity_hash = referrer->GetIdentityHash();
-
 	name = new (name) v8_local_string(specifier);
 
-	v8_local_module* m = load_module_callback(v8_ctx_ref, name, identity_hash);
+	v8_local_module* referrer_module = (struct v8_local_module*)V8_ALLOC(sizeof(*referrer_module));
+	referrer_module = new (referrer_module) v8_local_module(referrer);
+
+	v8_local_module* m = load_module_callback(v8_ctx_ref, name, referrer_module);
 
 	v8::MaybeLocal<v8::Module> res;
 	if (m) {
@@ -661,6 +1858,37 @@ v8_local_module* v8_CompileAsModule(v8_context_ref* v8_ctx_ref, v8_local_string*
 	return ret;
 }
 
+static v8::MaybeLocal<v8::Value> v8_EvaluateSyntheticModule(v8::Local<v8::Context> context, v8::Local<v8::Module> module) {
+	v8::Isolate *isolate = context->GetIsolate();
+	v8_pd_list *pd_list = (v8_pd_list*)isolate->GetData(0);
+	for (auto it = pd_list->synthetic_modules->begin() ; it != pd_list->synthetic_modules->end() ; ++it) {
+		if (it->first.Get(isolate) != module) {
+			continue;
+		}
+		v8::Local<v8::Value> default_export = it->second.Get(isolate);
+		pd_list->synthetic_modules->erase(it);
+		v8::Maybe<bool> res = module->SetSyntheticModuleExport(isolate, v8::String::NewFromUtf8Literal(isolate, "default"), default_export);
+		if (res.IsNothing()) {
+			return v8::MaybeLocal<v8::Value>();
+		}
+		break;
+	}
+	return v8::Undefined(isolate);
+}
+
+v8_local_module* v8_NewSyntheticModule(v8_context_ref* v8_ctx_ref, v8_local_string* name, v8_local_value* default_export) {
+	v8::Isolate *isolate = v8_ctx_ref->context->GetIsolate();
+	std::vector<v8::Local<v8::String>> export_names = {v8::String::NewFromUtf8Literal(isolate, "default")};
+	v8::Local<v8::Module> mod = v8::Module::CreateSyntheticModule(isolate, name->str, export_names, v8_EvaluateSyntheticModule);
+
+	v8_pd_list *pd_list = (v8_pd_list*)isolate->GetData(0);
+	pd_list->synthetic_modules->emplace_back(v8::Global<v8::Module>(isolate, mod), v8::Global<v8::Value>(isolate, default_export->val));
+
+	v8_local_module *ret = (struct v8_local_module*)V8_ALLOC(sizeof(*ret));
+	ret = new (ret) v8_local_module(mod);
+	return ret;
+}
+
 int v8_InitiateModule(v8_local_module* m, v8_context_ref* v8_ctx_ref, V8_LoadModuleCallback load_module_callback) {
 	v8::Isolate *isolate = v8_ctx_ref->context->GetIsolate();
 	v8::Local<v8::External> data = v8::External::New(isolate, (void*)load_module_callback);
@@ -673,6 +1901,60 @@ int v8_ModuleGetIdentityHash(v8_local_module* m) {
 	return m->mod->GetIdentityHash();
 }
 
+int v8_ModuleEquals(v8_local_module* m1, v8_local_module* m2) {
+	return m1->mod == m2->mod ? 1 : 0;
+}
+
+struct v8_weak_module {
+	v8::Global<v8::Module> handle;
+};
+
+static void v8_WeakModuleCallback(const v8::WeakCallbackInfo<v8_weak_module>& info) {
+	info.GetParameter()->handle.Reset();
+}
+
+v8_weak_module* v8_NewWeakModule(v8_isolate *i, v8_local_module* m) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_weak_module *weak = new v8_weak_module{v8::Global<v8::Module>(isolate, m->mod)};
+	weak->handle.SetWeak(weak, v8_WeakModuleCallback, v8::WeakCallbackType::kParameter);
+	return weak;
+}
+
+int v8_WeakModuleEquals(v8_weak_module* weak, v8_local_module* m) {
+	return weak->handle == m->mod ? 1 : 0;
+}
+
+int v8_WeakModuleIsCollected(v8_weak_module* weak) {
+	return weak->handle.IsEmpty() ? 1 : 0;
+}
+
+void v8_FreeWeakModule(v8_weak_module* weak) {
+	weak->handle.Reset();
+	delete weak;
+}
+
+v8_ModuleStatus v8_ModuleGetStatus(v8_local_module* m) {
+	switch(m->mod->GetStatus()) {
+	case v8::Module::Status::kUninstantiated:
+		return v8_ModuleStatus_Uninstantiated;
+	case v8::Module::Status::kInstantiating:
+		return v8_ModuleStatus_Instantiating;
+	case v8::Module::Status::kInstantiated:
+		return v8_ModuleStatus_Instantiated;
+	case v8::Module::Status::kEvaluating:
+		return v8_ModuleStatus_Evaluating;
+	case v8::Module::Status::kEvaluated:
+		return v8_ModuleStatus_Evaluated;
+	case v8::Module::Status::kErrored:
+		return v8_ModuleStatus_Errored;
+	}
+	return v8_ModuleStatus_Errored;
+}
+
+int v8_ModuleIsGraphAsync(v8_local_module* m) {
+	return m->mod->IsGraphAsync() ? 1 : 0;
+}
+
 v8_local_value* v8_EvaluateModule(v8_local_module* m, v8_context_ref* v8_ctx_ref) {
 	v8::MaybeLocal<v8::Value> res = m->mod->Evaluate(v8_ctx_ref->context);
 	if (res.IsEmpty()) {
@@ -684,6 +1966,24 @@ v8_local_value* v8_EvaluateModule(v8_local_module* m, v8_context_ref* v8_ctx_ref
 	return val;
 }
 
+v8_local_value* v8_ModuleGetNamespace(v8_local_module* m) {
+	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
+	v8_val = new (v8_val) v8_local_value(m->mod->GetModuleNamespace());
+	return v8_val;
+}
+
+v8_local_string* v8_ExceptionGetScriptName(v8_isolate *i, v8_local_value *exception) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Local<v8::Message> message = v8::Exception::CreateMessage(isolate, exception->val);
+	v8::Local<v8::Value> name = message->GetScriptResourceName();
+	if (name.IsEmpty() || !name->IsString()) {
+		return NULL;
+	}
+	v8_local_string *res = (struct v8_local_string*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_string(name.As<v8::String>());
+	return res;
+}
+
 v8_persisted_module* v8_ModulePersist(v8_isolate *i, v8_local_module* m) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	return (v8_persisted_module*) new v8::Persistent<v8::Module>(isolate, m->mod);
@@ -704,10 +2004,12 @@ void v8_FreePersistedModule(v8_persisted_module* m) {
 }
 
 void v8_FreeModule(v8_local_module* m) {
+	m->~v8_local_module();
 	V8_FREE(m);
 }
 
 void v8_FreeScript(v8_local_script *script) {
+	script->~v8_local_script();
 	V8_FREE(script);
 }
 
@@ -729,12 +2031,17 @@ int v8_ValueIsFunction(v8_local_value *val){
 }
 
 v8_local_value* v8_FunctionCall(v8_context_ref *v8_ctx_ref, v8_local_value *val, size_t argc, v8_local_value* const* argv) {
-	v8::Local<v8::Value> argv_arr[argc];
+	return v8_FunctionCallWithReceiver(v8_ctx_ref, val, NULL, argc, argv);
+}
+
+v8_local_value* v8_FunctionCallWithReceiver(v8_context_ref *v8_ctx_ref, v8_local_value *val, v8_local_value *recv, size_t argc, v8_local_value* const* argv) {
+	std::vector<v8::Local<v8::Value>> argv_arr(argc);
 	for (size_t i = 0 ; i < argc ; ++i) {
 		argv_arr[i] = argv[i]->val;
 	}
+	v8::Local<v8::Value> receiver = recv ? recv->val : v8::Local<v8::Value>(v8_ctx_ref->context->Global());
 	v8::Local<v8::Function> function = v8::Local<v8::Function>::Cast(val->val);
-	v8::MaybeLocal<v8::Value> result = function->Call(v8_ctx_ref->context, v8_ctx_ref->context->Global(), argc, argv_arr);
+	v8::MaybeLocal<v8::Value> result = function->Call(v8_ctx_ref->context, receiver, argc, argv_arr.data());
 	if (result.IsEmpty()) {
 		return NULL;
 	}
@@ -746,6 +2053,54 @@ v8_local_value* v8_FunctionCall(v8_context_ref *v8_ctx_ref, v8_local_value *val,
 	return v8_val;
 }
 
+v8_local_value* v8_FunctionNewInstance(v8_context_ref *v8_ctx_ref, v8_local_value *val, size_t argc, v8_local_value* const* argv) {
+	std::vector<v8::Local<v8::Value>> argv_arr(argc);
+	for (size_t i = 0 ; i < argc ; ++i) {
+		argv_arr[i] = argv[i]->val;
+	}
+	v8::Local<v8::Function> function = v8::Local<v8::Function>::Cast(val->val);
+	v8::MaybeLocal<v8::Object> result = function->NewInstance(v8_ctx_ref->context, argc, argv_arr.data());
+	if (result.IsEmpty()) {
+		return NULL;
+	}
+
+	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
+	v8_val = new (v8_val) v8_local_value(result.ToLocalChecked());
+	return v8_val;
+}
+
+v8_local_value* v8_FunctionGetScriptName(v8_local_value *val) {
+	v8::Local<v8::Function> func = v8::Local<v8::Function>::Cast(val->val);
+	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
+	v8_val = new (v8_val) v8_local_value(func->GetScriptOrigin().ResourceName());
+	return v8_val;
+}
+
+int v8_FunctionGetScriptLineNumber(v8_local_value *val) {
+	v8::Local<v8::Function> func = v8::Local<v8::Function>::Cast(val->val);
+	int res = func->GetScriptLineNumber();
+	return res == v8::Function::kLineOffsetNotFound ? -1 : res;
+}
+
+int v8_FunctionGetScriptColumnNumber(v8_local_value *val) {
+	v8::Local<v8::Function> func = v8::Local<v8::Function>::Cast(val->val);
+	int res = func->GetScriptColumnNumber();
+	return res == v8::Function::kLineOffsetNotFound ? -1 : res;
+}
+
+v8_local_value* v8_FunctionGetInferredName(v8_local_value *val) {
+	v8::Local<v8::Function> func = v8::Local<v8::Function>::Cast(val->val);
+	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
+	v8_val = new (v8_val) v8_local_value(func->GetInferredName());
+	return v8_val;
+}
+
+int v8_FunctionGetScriptId(v8_local_value *val) {
+	v8::Local<v8::Function> func = v8::Local<v8::Function>::Cast(val->val);
+	int res = func->ScriptId();
+	return res == v8::UnboundScript::kNoScriptId ? 0 : res;
+}
+
 int v8_ValueIsAsyncFunction(v8_local_value *val) {
 	return val->val->IsAsyncFunction();
 }
@@ -789,6 +2144,27 @@ long long v8_GetBigInt(v8_local_value *val) {
 	return res;
 }
 
+int v8_GetBigIntInt64(v8_local_value *val, long long *res) {
+	if (val->val->IsInt32()) {
+		*res = v8::Local<v8::Int32>::Cast(val->val)->Value();
+		return 1;
+	}
+	bool lossless = false;
+	*res = v8::Local<v8::BigInt>::Cast(val->val)->Int64Value(&lossless);
+	return lossless ? 1 : 0;
+}
+
+int v8_GetBigIntUint64(v8_local_value *val, unsigned long long *res) {
+	if (val->val->IsInt32()) {
+		int32_t n = v8::Local<v8::Int32>::Cast(val->val)->Value();
+		*res = n;
+		return n >= 0 ? 1 : 0;
+	}
+	bool lossless = false;
+	*res = v8::Local<v8::BigInt>::Cast(val->val)->Uint64Value(&lossless);
+	return lossless ? 1 : 0;
+}
+
 int v8_ValueIsNumber(v8_local_value *val) {
 	return val->val->IsNumber();
 }
@@ -807,6 +2183,30 @@ int v8_GetBool(v8_local_value *val){
 	return boolean->Value();
 }
 
+int v8_ValueToNumber(v8_context_ref *ctx_ref, v8_local_value *val, double *res) {
+	v8::Maybe<double> maybe_res = val->val->NumberValue(ctx_ref->context);
+	if (maybe_res.IsNothing()) {
+		return 0;
+	}
+	*res = maybe_res.FromJust();
+	return 1;
+}
+
+v8_local_string* v8_ValueToString(v8_context_ref *ctx_ref, v8_local_value *val) {
+	v8::MaybeLocal<v8::String> maybe_res = val->val->ToString(ctx_ref->context);
+	if (maybe_res.IsEmpty()) {
+		return NULL;
+	}
+	v8_local_string *res = (struct v8_local_string*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_string(maybe_res.ToLocalChecked());
+	return res;
+}
+
+int v8_ValueToBoolean(v8_isolate *i, v8_local_value *val) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	return val->val->BooleanValue(isolate) ? 1 : 0;
+}
+
 
 v8_local_value* v8_ValueFromDouble(v8_isolate *i, double val) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
@@ -830,6 +2230,7 @@ v8_local_promise* v8_ValueAsPromise(v8_local_value *val) {
 }
 
 void v8_FreePromise(v8_local_promise* promise) {
+	promise->~v8_local_promise();
 	V8_FREE(promise);
 }
 v8_PromiseState v8_PromiseGetState(v8_local_promise* promise) {
@@ -870,6 +2271,7 @@ v8_local_resolver* v8_NewResolver(v8_context_ref *ctx_ref) {
 }
 
 void v8_FreeResolver(v8_local_resolver *resolver) {
+	resolver->~v8_local_resolver();
 	V8_FREE(resolver);
 }
 
@@ -914,10 +2316,18 @@ int v8_ValueIsArray(v8_local_value *val) {
 	return val->val->IsArray();
 }
 
+int v8_ValueStrictEquals(v8_local_value *val1, v8_local_value *val2) {
+	return val1->val->StrictEquals(val2->val);
+}
+
 int v8_ValueIsArrayBuffer(v8_local_value *val) {
 	return val->val->IsArrayBuffer();
 }
 
+int v8_ValueIsSharedArrayBuffer(v8_local_value *val) {
+	return val->val->IsSharedArrayBuffer();
+}
+
 v8_local_object* v8_NewObject(v8_isolate *i) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	v8::Local<v8::Object> obj = v8::Object::New(isolate);
@@ -951,6 +2361,55 @@ v8_local_resolver* v8_ValueAsResolver(v8_local_value *val) {
 	return res;
 }
 
+static v8_local_value* v8_ValueGetIteratorInternal(v8_context_ref *ctx_ref, v8_local_value *val, bool async) {
+	if (val->val->IsNullOrUndefined()) {
+		return NULL;
+	}
+	v8::Isolate *isolate = ctx_ref->context->GetIsolate();
+	/* primitives (such as strings) are iterable through their prototype */
+	v8::Local<v8::Object> obj;
+	if (!val->val->ToObject(ctx_ref->context).ToLocal(&obj)) {
+		return NULL;
+	}
+	v8::Local<v8::Value> method;
+	if (async) {
+		if (!obj->Get(ctx_ref->context, v8::Symbol::GetAsyncIterator(isolate)).ToLocal(&method)) {
+			return NULL;
+		}
+	}
+	if (!async || method->IsNullOrUndefined()) {
+		if (!obj->Get(ctx_ref->context, v8::Symbol::GetIterator(isolate)).ToLocal(&method)) {
+			return NULL;
+		}
+	}
+	if (!method->IsFunction()) {
+		return NULL;
+	}
+	v8::Local<v8::Value> iterator;
+	if (!method.As<v8::Function>()->Call(ctx_ref->context, val->val, 0, NULL).ToLocal(&iterator) || !iterator->IsObject()) {
+		return NULL;
+	}
+	v8_local_value *res = (v8_local_value*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_value(iterator);
+	return res;
+}
+
+v8_local_value* v8_ValueGetIterator(v8_context_ref *ctx_ref, v8_local_value *val) {
+	return v8_ValueGetIteratorInternal(ctx_ref, val, false);
+}
+
+v8_local_value* v8_ValueGetAsyncIterator(v8_context_ref *ctx_ref, v8_local_value *val) {
+	return v8_ValueGetIteratorInternal(ctx_ref, val, true);
+}
+
+v8_local_value* v8_SymbolAsyncIterator(v8_isolate *i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Local<v8::Value> symbol = v8::Symbol::GetAsyncIterator(isolate);
+	v8_local_value *res = (v8_local_value*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_value(symbol);
+	return res;
+}
+
 v8_local_value* v8_ObjectGet(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_value *key) {
 	v8::MaybeLocal<v8::Value> maybe_val = obj->obj->Get(ctx_ref->context, key->val);
 	if (maybe_val.IsEmpty()) {
@@ -966,11 +2425,85 @@ void v8_ObjectSet(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_value
 	v8::Maybe<bool> res = obj->obj->Set(ctx_ref->context, key->val, val->val);
 }
 
+int v8_ObjectDefineProperty(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_value *key, v8_local_value *val, int attributes) {
+	if (!key->val->IsName()) {
+		return 0;
+	}
+	v8::Local<v8::Name> name = v8::Local<v8::Name>::Cast(key->val);
+	v8::Maybe<bool> res = obj->obj->DefineOwnProperty(ctx_ref->context, name, val->val, (v8::PropertyAttribute)attributes);
+	return res.IsJust() && res.FromJust() ? 1 : 0;
+}
+
 void v8_ObjectFreeze(v8_context_ref *ctx_ref, v8_local_object *obj) {
 	obj->obj->SetIntegrityLevel(ctx_ref->context, v8::IntegrityLevel::kFrozen);
 }
 
+int v8_ObjectSetPrivate(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_string *name, v8_local_value *val) {
+	v8::Isolate *isolate = ctx_ref->context->GetIsolate();
+	v8::Maybe<bool> res = obj->obj->SetPrivate(ctx_ref->context, v8::Private::ForApi(isolate, name->str), val->val);
+	return res.FromMaybe(false) ? 1 : 0;
+}
+
+size_t v8_ObjectInternalFieldCount(v8_local_object *obj) {
+	return obj->obj->InternalFieldCount();
+}
+
+int v8_ObjectSetInternalField(v8_local_object *obj, size_t index, v8_local_value *val) {
+	if (index >= (size_t)obj->obj->InternalFieldCount()) {
+		return 0;
+	}
+	obj->obj->SetInternalField(index, val->val);
+	return 1;
+}
+
+v8_local_value* v8_ObjectGetInternalField(v8_local_object *obj, size_t index) {
+	if (index >= (size_t)obj->obj->InternalFieldCount()) {
+		return NULL;
+	}
+	v8_local_value *res = (struct v8_local_value*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_value(obj->obj->GetInternalField(index));
+	return res;
+}
+
+v8_local_value* v8_ObjectGetPrivate(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_string *name) {
+	v8::Isolate *isolate = ctx_ref->context->GetIsolate();
+	v8::Local<v8::Private> private_key = v8::Private::ForApi(isolate, name->str);
+	if (!obj->obj->HasPrivate(ctx_ref->context, private_key).FromMaybe(false)) {
+		return NULL;
+	}
+	v8::MaybeLocal<v8::Value> maybe_res = obj->obj->GetPrivate(ctx_ref->context, private_key);
+	if (maybe_res.IsEmpty()) {
+		return NULL;
+	}
+	v8_local_value *res = (struct v8_local_value*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_value(maybe_res.ToLocalChecked());
+	return res;
+}
+
+int v8_ObjectHasPrivate(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_string *name) {
+	v8::Isolate *isolate = ctx_ref->context->GetIsolate();
+	v8::Maybe<bool> res = obj->obj->HasPrivate(ctx_ref->context, v8::Private::ForApi(isolate, name->str));
+	return res.FromMaybe(false) ? 1 : 0;
+}
+
+v8_local_string* v8_ObjectGetConstructorName(v8_local_object *obj) {
+	v8_local_string *res = (struct v8_local_string*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_string(obj->obj->GetConstructorName());
+	return res;
+}
+
+v8_local_string* v8_ObjectProtoToString(v8_context_ref *ctx_ref, v8_local_object *obj) {
+	v8::MaybeLocal<v8::String> maybe_res = obj->obj->ObjectProtoToString(ctx_ref->context);
+	if (maybe_res.IsEmpty()) {
+		return NULL;
+	}
+	v8_local_string *res = (struct v8_local_string*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_string(maybe_res.ToLocalChecked());
+	return res;
+}
+
 void v8_FreeObject(v8_local_object *obj) {
+	obj->~v8_local_object();
 	V8_FREE(obj);
 }
 
@@ -981,6 +2514,10 @@ v8_local_value* v8_ObjectToValue(v8_local_object *obj) {
 	return res;
 }
 
+int v8_ObjectGetIdentityHash(v8_local_object *obj) {
+	return obj->obj->GetIdentityHash();
+}
+
 v8_local_set* v8_NewSet(v8_isolate *i) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	v8::Local<v8::Set> set = v8::Set::New(isolate);
@@ -1016,32 +2553,70 @@ int v8_ValueIsSet(v8_local_value *val) {
 }
 
 void v8_FreeSet(v8_local_set *set) {
+	set->~v8_local_set();
 	V8_FREE(set);
 }
 
-v8_local_value* v8_NewBool(v8_isolate *i, int val) {
+v8_local_value* v8_NewMap(v8_isolate *i) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
-	v8::Local<v8::Boolean> b = v8::Boolean::New(isolate, val);
-	v8::Local<v8::Value> v = v8::Local<v8::Value>::Cast(b);
+	v8::Local<v8::Value> map = v8::Map::New(isolate);
 	v8_local_value *res = (v8_local_value*) V8_ALLOC(sizeof(*res));
-	res = new (res) v8_local_value(v);
+	res = new (res) v8_local_value(map);
+	return res;
+}
+
+int v8_MapSet(v8_context_ref *ctx_ref, v8_local_value *map, v8_local_value *key, v8_local_value *val) {
+	v8::Local<v8::Map> m = v8::Local<v8::Map>::Cast(map->val);
+	return m->Set(ctx_ref->context, key->val, val->val).IsEmpty() ? 0 : 1;
+}
+
+int v8_ValueIsMap(v8_local_value *val) {
+	return val->val->IsMap();
+}
+
+v8_local_array* v8_MapAsArray(v8_local_value *map) {
+	v8::Local<v8::Array> arr = v8::Local<v8::Map>::Cast(map->val)->AsArray();
+	v8_local_array *res = (v8_local_array*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_array(arr);
 	return res;
 }
 
+int v8_ValueIsNativeError(v8_local_value *val) {
+	return val->val->IsNativeError();
+}
+
+v8_local_value* v8_NewBool(v8_isolate *i, int val) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	return val ? native_data->true_val : native_data->false_val;
+}
+
 v8_local_value* v8_NewNull(v8_isolate *i) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
-	v8::Local<v8::Primitive> n = v8::Null(isolate);
-	v8::Local<v8::Value> v = v8::Local<v8::Value>::Cast(n);
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	return native_data->null_val;
+}
 
-	v8_local_value *v8_val = (struct v8_local_value*)V8_ALLOC(sizeof(*v8_val));
-	v8_val = new (v8_val) v8_local_value(v);
-	return v8_val;
+v8_local_value* v8_NewUndefined(v8_isolate *i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	return native_data->undefined_val;
+}
+
+v8_local_string* v8_NewEmptyString(v8_isolate *i) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	return native_data->empty_string;
 }
 
 int v8_ValueIsNull(v8_local_value *val) {
 	return val->val->IsNull();
 }
 
+int v8_ValueIsUndefined(v8_local_value *val) {
+	return val->val->IsUndefined();
+}
+
 v8_local_array_buff* v8_NewArrayBuffer(v8_isolate *i, const char *data, size_t len) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	v8::Local<v8::ArrayBuffer> arr_buff = v8::ArrayBuffer::New(isolate, len);
@@ -1052,6 +2627,36 @@ v8_local_array_buff* v8_NewArrayBuffer(v8_isolate *i, const char *data, size_t l
 	return res;
 }
 
+struct v8_ExternalArrayBufferData {
+	void *pd;
+	void(*free_pd)(void *pd);
+};
+
+static void v8_ExternalArrayBufferDeleter(void *data, size_t len, void *deleter_data) {
+	v8_ExternalArrayBufferData *external_data = (v8_ExternalArrayBufferData*)deleter_data;
+	if (external_data->free_pd) {
+		external_data->free_pd(external_data->pd);
+	}
+	delete external_data;
+}
+
+v8_local_array_buff* v8_NewExternalArrayBuffer(v8_isolate *i, void *data, size_t len, void *pd, void(*free_pd)(void *pd)) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_ExternalArrayBufferData *external_data = new v8_ExternalArrayBufferData{pd, free_pd};
+	std::unique_ptr<v8::BackingStore> backing_store = v8::ArrayBuffer::NewBackingStore(data, len, v8_ExternalArrayBufferDeleter, external_data);
+	v8::Local<v8::ArrayBuffer> arr_buff = v8::ArrayBuffer::New(isolate, std::move(backing_store));
+	v8_local_array_buff *res = (v8_local_array_buff*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_array_buff(arr_buff);
+	return res;
+}
+
+v8_local_value* v8_NewUint8Array(v8_local_array_buff *arr_buffer, size_t offset, size_t len) {
+	v8::Local<v8::Uint8Array> arr = v8::Uint8Array::New(arr_buffer->arr_buff, offset, len);
+	v8_local_value *res = (v8_local_value*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_value(arr);
+	return res;
+}
+
 v8_local_value* v8_ArrayBufferToValue(v8_local_array_buff *arr_buffer) {
 	v8::Local<v8::Value> val = v8::Local<v8::Value>::Cast(arr_buffer->arr_buff);
 	v8_local_value *res = (v8_local_value*) V8_ALLOC(sizeof(*res));
@@ -1065,22 +2670,106 @@ const void* v8_ArrayBufferGetData(v8_local_array_buff *arr_buffer, size_t *len)
 }
 
 void v8_FreeArrayBuffer(v8_local_array_buff *arr_buffer) {
+	arr_buffer->~v8_local_array_buff();
+	V8_FREE(arr_buffer);
+}
+
+int v8_ArrayBufferDetach(v8_local_array_buff *arr_buffer) {
+	if (!arr_buffer->arr_buff->IsDetachable()) {
+		return 0;
+	}
+	arr_buffer->arr_buff->Detach();
+	return 1;
+}
+
+v8_backing_store* v8_ArrayBufferGetBackingStore(v8_local_array_buff *arr_buffer) {
+	v8_backing_store *res = (v8_backing_store*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_backing_store(arr_buffer->arr_buff->GetBackingStore());
+	return res;
+}
+
+const void* v8_BackingStoreGetData(v8_backing_store *backing_store, size_t *len) {
+	*len = backing_store->backing_store->ByteLength();
+	return backing_store->backing_store->Data();
+}
+
+void v8_FreeBackingStore(v8_backing_store *backing_store) {
+	backing_store->~v8_backing_store();
+	V8_FREE(backing_store);
+}
+
+v8_local_shared_array_buff* v8_NewSharedArrayBuffer(v8_isolate *i, size_t len) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Local<v8::SharedArrayBuffer> arr_buff = v8::SharedArrayBuffer::New(isolate, len);
+	v8_local_shared_array_buff *res = (v8_local_shared_array_buff*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_shared_array_buff(arr_buff);
+	return res;
+}
+
+v8_local_value* v8_SharedArrayBufferToValue(v8_local_shared_array_buff *arr_buffer) {
+	v8::Local<v8::Value> val = v8::Local<v8::Value>::Cast(arr_buffer->arr_buff);
+	v8_local_value *res = (v8_local_value*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_value(val);
+	return res;
+}
+
+v8_backing_store* v8_SharedArrayBufferGetBackingStore(v8_local_shared_array_buff *arr_buffer) {
+	v8_backing_store *res = (v8_backing_store*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_backing_store(arr_buffer->arr_buff->GetBackingStore());
+	return res;
+}
+
+void v8_FreeSharedArrayBuffer(v8_local_shared_array_buff *arr_buffer) {
+	arr_buffer->~v8_local_shared_array_buff();
 	V8_FREE(arr_buffer);
 }
 
 v8_local_array* v8_NewArray(v8_isolate *i, v8_local_value *const *vals, size_t len) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
-	v8::Local<v8::Value> vals_arr[len];
+	std::vector<v8::Local<v8::Value>> vals_arr(len);
 	for (size_t i = 0 ; i < len ; ++i) {
 		vals_arr[i] = vals[i]->val;
 	}
-	v8::Local<v8::Array> arr = v8::Array::New(isolate, vals_arr, len);
+	v8::Local<v8::Array> arr = v8::Array::New(isolate, vals_arr.data(), len);
+	v8_local_array *res = (v8_local_array*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_array(arr);
+	return res;
+}
+
+v8_local_array* v8_NewArrayFromDoubles(v8_isolate *i, const double *vals, size_t len) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::EscapableHandleScope handle_scope(isolate);
+	std::vector<v8::Local<v8::Value>> vals_arr(len);
+	for (size_t i = 0 ; i < len ; ++i) {
+		vals_arr[i] = v8::Number::New(isolate, vals[i]);
+	}
+	v8::Local<v8::Array> arr = handle_scope.Escape(v8::Array::New(isolate, vals_arr.data(), len));
+	v8_local_array *res = (v8_local_array*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_array(arr);
+	return res;
+}
+
+v8_local_array* v8_NewArrayFromStrings(v8_isolate *i, const char **strs, const size_t *lens, size_t len) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::EscapableHandleScope handle_scope(isolate);
+	std::vector<v8::Local<v8::Value>> vals_arr(len);
+	for (size_t i = 0 ; i < len ; ++i) {
+		/* v8 gets the length as an int */
+		if (lens[i] > (size_t)v8::String::kMaxLength) {
+			return NULL;
+		}
+		if (!v8::String::NewFromUtf8(isolate, strs[i], v8::NewStringType::kNormal, lens[i]).ToLocal(&vals_arr[i])) {
+			return NULL;
+		}
+	}
+	v8::Local<v8::Array> arr = handle_scope.Escape(v8::Array::New(isolate, vals_arr.data(), len));
 	v8_local_array *res = (v8_local_array*) V8_ALLOC(sizeof(*res));
 	res = new (res) v8_local_array(arr);
 	return res;
 }
 
 void v8_FreeArray(v8_local_array *arr) {
+	arr->~v8_local_array();
 	V8_FREE(arr);
 }
 
@@ -1099,6 +2788,42 @@ v8_local_value* v8_ArrayGet(v8_context_ref *ctx_ref, v8_local_array *arr, size_t
 	return res;
 }
 
+int v8_ArrayToDoubles(v8_context_ref *ctx_ref, v8_local_array *arr, double *vals) {
+	v8::Isolate *isolate = ctx_ref->context->GetIsolate();
+	uint32_t len = arr->arr->Length();
+	for (uint32_t i = 0 ; i < len ; ++i) {
+		/* release the element handle on each iteration */
+		v8::HandleScope handle_scope(isolate);
+		v8::Local<v8::Value> val;
+		if (!arr->arr->Get(ctx_ref->context, i).ToLocal(&val) || !val->IsNumber()) {
+			return 0;
+		}
+		vals[i] = val.As<v8::Number>()->Value();
+	}
+	return 1;
+}
+
+long long v8_ArrayToStrings(v8_context_ref *ctx_ref, v8_local_array *arr, v8_ArrayStringCallback callback, void *pd) {
+	v8::Isolate *isolate = ctx_ref->context->GetIsolate();
+	uint32_t len = arr->arr->Length();
+	/* reuse the same buffer for all the elements */
+	std::string buf;
+	for (uint32_t i = 0 ; i < len ; ++i) {
+		/* release the element handle on each iteration */
+		v8::HandleScope handle_scope(isolate);
+		v8::Local<v8::Value> val;
+		if (!arr->arr->Get(ctx_ref->context, i).ToLocal(&val) || !val->IsString()) {
+			return i;
+		}
+		v8::Local<v8::String> str = val.As<v8::String>();
+		buf.resize(str->Utf8Length(isolate));
+		size_t written = str->WriteUtf8(isolate, &buf[0], buf.size(), NULL,
+				v8::String::NO_NULL_TERMINATION | v8::String::REPLACE_INVALID_UTF8);
+		callback(buf.data(), written, pd);
+	}
+	return -1;
+}
+
 v8_local_value* v8_ArrayToValue(v8_local_array *arr) {
 	v8::Local<v8::Value> val = v8::Local<v8::Value>::Cast(arr->arr);
 	v8_local_value *res = (v8_local_value*) V8_ALLOC(sizeof(*res));
@@ -1120,6 +2845,13 @@ v8_local_array_buff* v8_ValueAsArrayBuffer(v8_local_value *val) {
 	return res;
 }
 
+v8_local_shared_array_buff* v8_ValueAsSharedArrayBuffer(v8_local_value *val) {
+	v8::Local<v8::SharedArrayBuffer> arr = v8::Local<v8::SharedArrayBuffer>::Cast(val->val);
+	v8_local_shared_array_buff *res = (v8_local_shared_array_buff*) V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_shared_array_buff(arr);
+	return res;
+}
+
 v8_persisted_value* v8_PersistValue(v8_isolate *i, v8_local_value *val) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	return (v8_persisted_value*) new v8::Persistent<v8::Value>(isolate, val->val);
@@ -1133,13 +2865,104 @@ v8_local_value* v8_PersistedValueToLocal(v8_isolate *i, v8_persisted_value *val)
 	return local_val;
 }
 
+v8_persisted_value* v8_PersistedValueDuplicate(v8_isolate *i, v8_persisted_value *val) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8::Persistent<v8::Value> *persisted_val = (v8::Persistent<v8::Value>*)val;
+	return (v8_persisted_value*) new v8::Persistent<v8::Value>(isolate, *persisted_val);
+}
+
 void v8_FreePersistedValue(v8_persisted_value *val) {
 	v8::Persistent<v8::Value> *persisted_val = (v8::Persistent<v8::Value>*)val;
 	persisted_val->Reset();
 	delete persisted_val;
 }
 
+struct v8_weak_value {
+	v8::Global<v8::Value> handle;
+	void (*on_collected)(void *pd);
+	void *pd;
+	void (*free_pd)(void *pd);
+};
+
+static void v8_WeakValueCallback(const v8::WeakCallbackInfo<v8_weak_value>& info) {
+	v8_weak_value *weak_val = info.GetParameter();
+	weak_val->handle.Reset();
+	if (weak_val->on_collected) {
+		weak_val->on_collected(weak_val->pd);
+		/* on_collected took the ownership on the private data */
+		weak_val->free_pd = NULL;
+	}
+}
+
+v8_weak_value* v8_NewWeakValue(v8_isolate *i, v8_local_value *val, void(*on_collected)(void *pd), void *pd, void(*free_pd)(void *pd)) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_weak_value *weak_val = new v8_weak_value{v8::Global<v8::Value>(isolate, val->val), on_collected, pd, free_pd};
+	weak_val->handle.SetWeak(weak_val, v8_WeakValueCallback, v8::WeakCallbackType::kParameter);
+	return weak_val;
+}
+
+v8_local_value* v8_WeakValueToLocal(v8_isolate *i, v8_weak_value *val) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	if (val->handle.IsEmpty()) {
+		return NULL;
+	}
+	v8_local_value *local_val = (struct v8_local_value*)V8_ALLOC(sizeof(*local_val));
+	local_val = new (local_val) v8_local_value(val->handle.Get(isolate));
+	return local_val;
+}
+
+void v8_FreeWeakValue(v8_weak_value *val) {
+	val->handle.Reset();
+	if (val->free_pd) {
+		val->free_pd(val->pd);
+	}
+	delete val;
+}
+
+struct v8_external_data {
+	void *pd;
+	void (*free_pd)(void *pd);
+	v8::Persistent<v8::External> *weak;
+};
+
+static void v8_FreeExternalData(v8_external_data *data) {
+	data->free_pd(data->pd);
+	data->weak->Reset();
+	delete data->weak;
+	V8_FREE(data);
+}
+
+v8_local_value* v8_NewExternalData(v8_isolate *i, void *pd, void(*free_pd)(void *pd)) {
+	v8::Isolate *isolate = (v8::Isolate*)i;
+	v8_external_data *data = (v8_external_data*)V8_ALLOC(sizeof(*data));
+	data->pd = pd;
+	data->free_pd = free_pd;
+
+	v8_pd_list *native_data = (v8_pd_list*)isolate->GetData(0);
+	v8_pd_node* node = v8_PDListAdd(native_data, (void*)data, (void(*)(void*))v8_FreeExternalData);
+
+	v8::Local<v8::External> external = v8::External::New(isolate, (void*)data);
+	data->weak = new v8::Persistent<v8::External>(isolate, external);
+	data->weak->SetWeak<v8_pd_node>(node, v8_FreeNativeFunctionPD, v8::WeakCallbackType::kParameter);
+
+	v8_local_value *local_val = (struct v8_local_value*)V8_ALLOC(sizeof(*local_val));
+	local_val = new (local_val) v8_local_value(external);
+	return local_val;
+}
+
+void* v8_ExternalDataGet(v8_local_value *val) {
+	if (!val->val->IsExternal()) {
+		return NULL;
+	}
+	v8_external_data *data = (v8_external_data*)val->val.As<v8::External>()->Value();
+	return data->pd;
+}
+
 void v8_FreeValue(v8_local_value *val) {
+	if (val->cached) {
+		return;
+	}
+	val->~v8_local_value();
 	V8_FREE(val);
 }
 
@@ -1147,6 +2970,10 @@ v8_utf8_value* v8_ToUtf8(v8_isolate *i, v8_local_value* val) {
 	v8::Isolate *isolate = (v8::Isolate*)i;
 	v8_utf8_value *utf8_val = (struct v8_utf8_value*)V8_ALLOC(sizeof(*utf8_val));
 	utf8_val = new (utf8_val) v8_utf8_value(isolate, val->val);
+	if (!utf8_val->valid) {
+		v8_FreeUtf8(utf8_val);
+		return NULL;
+	}
 	return utf8_val;
 }
 
@@ -1156,8 +2983,8 @@ void v8_FreeUtf8(v8_utf8_value *val) {
 }
 
 const char* v8_Utf8PtrLen(v8_utf8_value *val, size_t *len) {
-	*len = val->utf8_val.length();
-	return *val->utf8_val;
+	*len = val->utf8_val.size();
+	return val->utf8_val.data();
 }
 
 v8_unlocker* v8_NewUnlocker(v8_isolate *i) {
@@ -1171,4 +2998,72 @@ void v8_FreeUnlocker(v8_unlocker* u) {
 	delete unlocker;
 }
 
+long long v8_GetCurrentThreadCPUTime() {
+#ifdef CLOCK_THREAD_CPUTIME_ID
+	struct timespec ts;
+	if (clock_gettime(CLOCK_THREAD_CPUTIME_ID, &ts) != 0) {
+		return 0;
+	}
+	return (long long)ts.tv_sec * 1000000000LL + ts.tv_nsec;
+#elif defined(_WIN32)
+	FILETIME creation, exited, kernel, user;
+	if (!GetThreadTimes(GetCurrentThread(), &creation, &exited, &kernel, &user)) {
+		return 0;
+	}
+	ULARGE_INTEGER kernel_time, user_time;
+	kernel_time.LowPart = kernel.dwLowDateTime;
+	kernel_time.HighPart = kernel.dwHighDateTime;
+	user_time.LowPart = user.dwLowDateTime;
+	user_time.HighPart = user.dwHighDateTime;
+	/* FILETIME is in 100 nanoseconds units */
+	return (long long)(kernel_time.QuadPart + user_time.QuadPart) * 100LL;
+#else
+	return 0;
+#endif
+}
+
+static_assert(sizeof(v8::Local<v8::Value>) == sizeof(void*), "v8::Local is expected to be pointer sized");
+
+void* v8_ValueToRaw(v8_local_value *val) {
+	void *raw;
+	memcpy(&raw, &val->val, sizeof(raw));
+	return raw;
+}
+
+v8_local_value* v8_ValueFromRaw(void *raw) {
+	v8::Local<v8::Value> val;
+	memcpy((void*)&val, &raw, sizeof(raw));
+	v8_local_value *res = (struct v8_local_value*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_value(val);
+	return res;
+}
+
+void* v8_ObjectToRaw(v8_local_object *obj) {
+	void *raw;
+	memcpy(&raw, &obj->obj, sizeof(raw));
+	return raw;
+}
+
+v8_local_object* v8_ObjectFromRaw(void *raw) {
+	v8::Local<v8::Object> obj;
+	memcpy((void*)&obj, &raw, sizeof(raw));
+	v8_local_object *res = (struct v8_local_object*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_object(obj);
+	return res;
+}
+
+void* v8_StringToRaw(v8_local_string *str) {
+	void *raw;
+	memcpy(&raw, &str->str, sizeof(raw));
+	return raw;
+}
+
+v8_local_string* v8_StringFromRaw(void *raw) {
+	v8::Local<v8::String> str;
+	memcpy((void*)&str, &raw, sizeof(raw));
+	v8_local_string *res = (struct v8_local_string*)V8_ALLOC(sizeof(*res));
+	res = new (res) v8_local_string(str);
+	return res;
+}
+
 }
@@ -2,6 +2,7 @@
 #define SRC_V8_C_API_H_
 
 #include <stddef.h>
+#include <stdint.h>
 
 /* Allocator definition
  * Note: only structs memory will be allocated using the allocator,
@@ -40,18 +41,28 @@ typedef struct v8_trycatch v8_trycatch;
  * handlers that were manager by the handlers score will be freed. */
 typedef struct v8_handlers_scope v8_handlers_scope;
 
+/* Same as `v8_handlers_scope` but allows a single local handler to escape
+ * into the enclosing handlers scope. */
+typedef struct v8_escapable_handlers_scope v8_escapable_handlers_scope;
+
 /* JS String object */
 typedef struct v8_local_string v8_local_string;
 
 /* JS native function template */
 typedef struct v8_local_native_function_template v8_local_native_function_template;
 
+/* JS persisted native function template, can outlive the handlers score. */
+typedef struct v8_persisted_native_function_template v8_persisted_native_function_template;
+
 /* JS native function */
 typedef struct v8_local_native_function v8_local_native_function;
 
 /* JS native object template */
 typedef struct v8_local_object_template v8_local_object_template;
 
+/* JS persisted object template, can outlive the handlers score. */
+typedef struct v8_persisted_object_template v8_persisted_object_template;
+
 /* JS native object */
 typedef struct v8_local_object v8_local_object;
 
@@ -64,16 +75,29 @@ typedef struct v8_local_array v8_local_array;
 /* JS native array buffer*/
 typedef struct v8_local_array_buff v8_local_array_buff;
 
+/* JS native shared array buffer */
+typedef struct v8_local_shared_array_buff v8_local_shared_array_buff;
+
+/* The memory of a JS array buffer, the memory stays valid as long
+ * as the backing store is alive, even if the array buffer was detached
+ * or garbage collected. */
+typedef struct v8_backing_store v8_backing_store;
+
 /* JS script object */
 typedef struct v8_local_script v8_local_script;
 
 typedef struct v8_persisted_script v8_persisted_script;
 
+/* A compiled script which is not bound to any context */
+typedef struct v8_persisted_unbound_script v8_persisted_unbound_script;
+
 /* JS module object */
 typedef struct v8_local_module v8_local_module;
 
 /* JS persisted module object */
 typedef struct v8_persisted_module v8_persisted_module;
+/* A weak handle to a module, does not keep the module alive. */
+typedef struct v8_weak_module v8_weak_module;
 
 /* JS generic value */
 typedef struct v8_local_value v8_local_value;
@@ -93,15 +117,44 @@ typedef struct v8_utf8_value v8_utf8_value;
 /* JS persisted object, can outlive the handlers score. */
 typedef struct v8_persisted_value v8_persisted_value;
 
+/* A weak handle to a JS object, does not keep the object alive. */
+typedef struct v8_weak_value v8_weak_value;
+
 /* JS persisted object, can outlive the handlers score. */
 typedef struct v8_unlocker v8_unlocker;
 
 typedef void (*v8_InterruptCallback)(v8_isolate *isolate, void* data);
 
 /* Initialize v8, must be called before any v8 API.
- * if allocator is NULL, use default memory functions. */
+ * if allocator is NULL, use default memory functions, otherwise the
+ * allocator is kept and used until the process exits. */
 void v8_Initialize(v8_alloctor *allocator);
 
+/* Callback that gets the trace events reported by v8. The phase is the trace event phase
+ * as defined by the trace event format ('X' for complete events, 'I' for instant events, ...).
+ * Timestamps and durations are given in microseconds. For complete events the callback is
+ * called once the event ends, with its start timestamp and its duration. */
+typedef void (*v8_TraceEventCallback)(char phase, const char *category, const char *name, long long timestamp, long long duration, void *pd);
+
+/* Same as v8_Initialize but also install a tracing controller that reports
+ * the trace events of the given comma separated categories to the given callback.
+ * 'free_pd' is called with 'pd' when the platform is destroyed. */
+void v8_InitializeWithTracing(v8_alloctor *allocator, const char *categories, v8_TraceEventCallback callback, void *pd, void(*free_pd)(void *pd));
+
+/* Initialize ICU, must be called before v8_Initialize. If icu_data_file is NULL, the
+ * ICU data embedded in v8 is used. Return 1 on success and 0 on failure or if the
+ * library was compiled without i18n support. */
+int v8_InitializeICU(const char *icu_data_file);
+
+/* Load the v8 startup data (the snapshot blob) from the given file, must be called
+ * before v8_Initialize. Only has effect if v8 was built to use external startup data. */
+void v8_InitializeExternalStartupDataFromFile(const char *snapshot_blob_file);
+
+/* Same as v8_InitializeExternalStartupDataFromFile but the startup data is given
+ * as bytes, the bytes are copied and kept for the process lifetime (the copy of a
+ * previous call is freed). Return 0 if the data is larger than INT_MAX bytes. */
+int v8_InitializeExternalStartupDataFromBytes(const char *data, size_t len);
+
 const char* v8_Version();
 
 /* Dispose v8 initialization */
@@ -115,6 +168,18 @@ void v8_Dispose();
  * will abort the processes with OOM error. */
 v8_isolate* v8_NewIsolate(size_t initial_heap_size_in_bytes, size_t maximum_heap_size_in_bytes);
 
+/* Allocate memory for an array buffer, if zero_initialize is not 0 the memory must be zero initialized.
+ * Return NULL on failure (an allocation failure will be reported to JS as a RangeError). */
+typedef void* (*v8_ArrayBufferAllocateCallback)(size_t length, int zero_initialize, void *pd);
+
+/* Free memory that was allocated using v8_ArrayBufferAllocateCallback */
+typedef void (*v8_ArrayBufferFreeCallback)(void *data, size_t length, void *pd);
+
+/* Same as v8_NewIsolate but the isolate array buffers memory will be allocated using
+ * the given callbacks. 'free_pd' is called with 'pd' when the isolate is freed. */
+v8_isolate* v8_NewIsolateWithAllocator(size_t initial_heap_size_in_bytes, size_t maximum_heap_size_in_bytes,
+		v8_ArrayBufferAllocateCallback allocate, v8_ArrayBufferFreeCallback free, void *pd, void(*free_pd)(void *pd));
+
 /* Set fatal error handler, this method should write the error to some log file, when return the processes will exit */
 void v8_IsolateSetFatalErrorHandler(v8_isolate* i, void (*fatal_hanlder)(const char* location, const char* message));
 
@@ -124,6 +189,10 @@ void v8_IsolateSetOOMErrorHandler(v8_isolate* i, void (*oom_hanlder)(const char*
 /* Set near OOM handler, the callback will be called when almost reaching OOM and allow to increase the max memory to avoid OOM error. */
 void v8_IsolateSetNearOOMHandler(v8_isolate* i, size_t (*near_oom_callback)(void* data, size_t current_heap_limit, size_t initial_heap_limit), void *pd, void(*free_pd)(void*));
 
+/* Restore the heap limit raised by the near OOM handler to the given limit, or to the minimal limit
+ * possible for the current heap size if the heap is larger. Must not be called from the near OOM handler. */
+void v8_IsolateRestoreHeapLimit(v8_isolate* i, size_t heap_limit);
+
 /* Terminate the current JS code running on the given isolate */
 void v8_TerminateCurrExecution(v8_isolate* i);
 
@@ -133,8 +202,79 @@ void v8_CancelTerminateExecution(v8_isolate* i);
 /* Free the give isolate */
 void v8_FreeIsolate(v8_isolate* isolate);
 
+/* Attach the native data this API keeps on its isolates to the given v8::Isolate*,
+ * created by other components using the v8 C++ API, so it can be used as a v8_isolate.
+ * The isolate data slot 0 must not be used by the other components. Return 1 if the
+ * data was attached and 0 if the isolate already has it. */
+int v8_IsolateAttach(void *raw);
+
+/* Free the native data attached using v8_IsolateAttach without disposing the isolate,
+ * must be called before the isolate is disposed by its owner. Removes the promise hook,
+ * the import.meta callback and the unhandled errors listeners set using this API. */
+void v8_IsolateDetach(v8_isolate* isolate);
+
+/* Return a unique id of the given isolate, ids are never reused during the process lifetime. */
+size_t v8_IsolateGetId(v8_isolate* isolate);
+
 void v8_RequestInterrupt(v8_isolate* isolate, v8_InterruptCallback callback, void *data);
 
+/* Clear the objects that were kept alive by WeakRef.prototype.deref during the
+ * current job, allowing them to be collected. */
+void v8_IsolateClearKeptObjects(v8_isolate* isolate);
+
+/* Run the pending microtasks (promises callbacks) of the given isolate */
+void v8_IsolatePerformMicrotaskCheckpoint(v8_isolate* isolate);
+
+typedef enum v8_PromiseHookType{
+	v8_PromiseHookType_Init, v8_PromiseHookType_Resolve, v8_PromiseHookType_Before, v8_PromiseHookType_After
+}v8_PromiseHookType;
+
+/* Promise hook callback definition, `parent` is the promise that triggered the creation
+ * of the promise on `Init` events (undefined otherwise or if there is no such promise). */
+typedef void (*v8_PromiseHookCallback)(v8_isolate *isolate, v8_PromiseHookType type, v8_local_promise *promise, v8_local_value *parent, void *pd);
+
+/* Set the promise hook of the isolate, replacing the existing one (its private data is freed).
+ * Pass a NULL callback to remove the hook. */
+void v8_IsolateSetPromiseHook(v8_isolate* isolate, v8_PromiseHookCallback callback, void *pd, void(*free_pd)(void *pd));
+
+/* import.meta callback definition, called the first time a module accesses `import.meta`
+ * so the embedder can populate the `meta` object. */
+typedef void (*v8_ImportMetaCallback)(v8_context_ref *ctx_ref, v8_local_module *module, v8_local_object *meta, void *pd);
+
+/* Set the import.meta callback of the isolate, replacing the existing one (its private data is freed).
+ * Pass a NULL callback to remove the callback. */
+void v8_IsolateSetImportMetaCallback(v8_isolate* isolate, v8_ImportMetaCallback callback, void *pd, void(*free_pd)(void *pd));
+
+/* Post a task to the foreground task runner of the isolate, the task will run (on the thread
+ * that pumps the isolate message loop) after at least `delay_in_seconds` seconds.
+ * `free_data` is called with the data if the task is freed without running.
+ * This function is thread safe. */
+void v8_IsolatePostTask(v8_isolate* isolate, v8_InterruptCallback callback, void *data, void(*free_data)(void *data), double delay_in_seconds);
+
+/* Run a single pending foreground task of the isolate (including tasks posted by v8 itself,
+ * for example, wasm async compilation). If `wait_for_work` is not 0, block until a task is available.
+ * Return 1 if a task was executed and 0 otherwise. */
+int v8_IsolatePumpMessageLoop(v8_isolate* isolate, int wait_for_work);
+
+/* Run the pending idle tasks of the isolate for up to `idle_time_in_seconds` seconds */
+void v8_IsolateRunIdleTasks(v8_isolate* isolate, double idle_time_in_seconds);
+
+/* Store the object template on the isolate at the given index. The template will be kept
+ * alive as long as the isolate is alive (or until replaced) and can be retrieved
+ * using `v8_IsolateGetObjectTemplate` from any handlers scope. Object templates and
+ * native function templates share the same indexes. */
+void v8_IsolateSetObjectTemplate(v8_isolate* i, size_t index, v8_local_object_template *obj);
+
+/* Return the object template stored on the given index or NULL if there is no
+ * object template on this index. */
+v8_local_object_template* v8_IsolateGetObjectTemplate(v8_isolate* i, size_t index);
+
+/* Same as `v8_IsolateSetObjectTemplate` but works on native function template */
+void v8_IsolateSetNativeFunctionTemplate(v8_isolate* i, size_t index, v8_local_native_function_template *func);
+
+/* Same as `v8_IsolateGetObjectTemplate` but works on native function template */
+v8_local_native_function_template* v8_IsolateGetNativeFunctionTemplate(v8_isolate* i, size_t index);
+
 /* Enter the given isolate. This function should be called before running any JS
  * code on the isolate. */
 v8_isolate_scope* v8_IsolateEnter(v8_isolate *v8_isolate);
@@ -146,10 +286,47 @@ void v8_IsolateExit(v8_isolate_scope *v8_isolate_scope);
 /* Raise an exception, the given value will be treated as the exception value. */
 void v8_IsolateRaiseException(v8_isolate *isolate, v8_local_value *value);
 
+typedef enum v8_ErrorType{
+	v8_ErrorType_Error, v8_ErrorType_TypeError, v8_ErrorType_RangeError,
+	v8_ErrorType_ReferenceError, v8_ErrorType_SyntaxError
+}v8_ErrorType;
+
+/* Create a new JS error object of the given type with the given message. */
+v8_local_value* v8_NewError(v8_local_string *message, v8_ErrorType error_type);
+
 /* Get current run context from the of this isolate */
 v8_context_ref* v8_GetCurrentCtxRef(v8_isolate *isolate);
 
-void v8_IdleNotificationDeadline(v8_isolate *isolate, double deadline_in_seconds);
+/* Return 1 if the isolate has an entered context and 0 otherwise */
+int v8_IsolateInContext(v8_isolate *isolate);
+
+/* Return 1 if the given context is the current context of its isolate (the last
+ * context that was entered and not yet exited) and 0 otherwise */
+int v8_ContextRefIsCurrent(v8_context_ref *v8_ctx_ref);
+
+/* Set a value that is captured by the continuations (promise reactions and await
+ * resumptions) created from now on, and restored while each continuation runs. */
+void v8_ContextRefSetContinuationData(v8_context_ref *v8_ctx_ref, v8_local_value *val);
+
+/* Return the value set by `v8_ContextRefSetContinuationData` or restored by the
+ * currently running continuation, undefined if there is no such value. */
+v8_local_value* v8_ContextRefGetContinuationData(v8_context_ref *v8_ctx_ref);
+
+/* Notify the isolate that the embedder is idle for the next `idle_time_in_seconds` seconds,
+ * the isolate uses the time for garbage collection. Return 1 if the isolate has done all the
+ * cleanup it can and should not be notified again until real work has been done. */
+int v8_IdleNotificationDeadline(v8_isolate *isolate, double idle_time_in_seconds);
+
+/* Notify the isolate that the embedder is under memory pressure. */
+void v8_LowMemoryNotification(v8_isolate *isolate);
+
+typedef enum v8_MemoryPressureLevel{
+	v8_MemoryPressureLevel_None, v8_MemoryPressureLevel_Moderate, v8_MemoryPressureLevel_Critical
+}v8_MemoryPressureLevel;
+
+/* Notify the isolate about the memory pressure level, the isolate will use it as
+ * a hint for its garbage collection heuristics. */
+void v8_MemoryPressureNotification(v8_isolate *isolate, v8_MemoryPressureLevel level);
 
 /* Create a new try catch object, any exception that will be raise during the JS execution
  * will be catch by this object. */
@@ -161,6 +338,9 @@ v8_local_value* v8_TryCatchGetException(v8_trycatch *trycatch);
 /* Return true if the execution was terminated using v8_TerminateCurrExecution */
 int v8_TryCatchHasTerminated(v8_trycatch *trycatch);
 
+/* Return 1 if the try catch object caught an exception and 0 otherwise */
+int v8_TryCatchHasCaught(v8_trycatch *trycatch);
+
 /* Free the try catch object */
 void v8_FreeTryCatch(v8_trycatch *trycatch);
 
@@ -172,6 +352,36 @@ v8_handlers_scope* v8_NewHandlersScope(v8_isolate *v8_isolate);
 /* Free the given handlers crope */
 void v8_FreeHandlersScope(v8_handlers_scope* v8_handlersScope);
 
+/* Return the number of local handlers currently allocated on the isolate (on all handlers scopes). */
+size_t v8_NumberOfHandles(v8_isolate *v8_isolate);
+
+/* Return the number of local wrappers which are alive on the current thread,
+ * wrappers which are owned by the isolate are not counted. */
+size_t v8_LiveLocals();
+
+/* Kind of a local wrapper, v8_LocalKind_Count is the number of kinds */
+typedef enum v8_LocalKind{
+	v8_LocalKind_String, v8_LocalKind_Script, v8_LocalKind_Module, v8_LocalKind_Value,
+	v8_LocalKind_NativeFunctionTemplate, v8_LocalKind_NativeFunction, v8_LocalKind_ObjectTemplate,
+	v8_LocalKind_ContextRef, v8_LocalKind_Promise, v8_LocalKind_Resolver, v8_LocalKind_Object,
+	v8_LocalKind_Set, v8_LocalKind_Array, v8_LocalKind_ArrayBuffer, v8_LocalKind_SharedArrayBuffer,
+	v8_LocalKind_Count
+}v8_LocalKind;
+
+/* Same as `v8_LiveLocals` but only counts the local wrappers of the given kind. */
+size_t v8_LiveLocalsOfKind(v8_LocalKind kind);
+
+/* Create a new escapable handlers scope, same as `v8_NewHandlersScope` but allows
+ * to escape a single local value into the enclosing handlers scope. */
+v8_escapable_handlers_scope* v8_NewEscapableHandlersScope(v8_isolate *v8_isolate);
+
+/* Escape the given value into the enclosing handlers scope, the returned value will
+ * stay alive after the escapable handlers scope is freed. Can only be called once. */
+v8_local_value* v8_EscapableHandlersScopeEscape(v8_escapable_handlers_scope* v8_handlersScope, v8_local_value *val);
+
+/* Free the given escapable handlers scope */
+void v8_FreeEscapableHandlersScope(v8_escapable_handlers_scope* v8_handlersScope);
+
 /* Create a new JS context, a context is an isolate environment to run JS code.
  * A context has his own globals which are not shared with other contexts.
  * It is only possible to run a single context on a given time (per isolate). */
@@ -192,10 +402,17 @@ void* v8_GetPrivateData(v8_context* ctx, size_t index);
  * JS code on the given context. */
 v8_context_ref* v8_ContextEnter(v8_context *v8_ctx);
 
+/* Enter the context in which the given object was created,
+ * return NULL if the object has no creation context. */
+v8_context_ref* v8_ObjectEnterCreationContext(v8_local_object *obj);
+
 v8_isolate* v8_ContextRefGetIsolate(v8_context_ref *v8_ctx_ref);
 
 v8_local_object* v8_ContextRefGetGlobals(v8_context_ref *v8_ctx_ref);
 
+/* Allow or disallow `eval` and the `Function` constructor on the context, allowed by default */
+void v8_ContextRefAllowCodeGenerationFromStrings(v8_context_ref *v8_ctx_ref, int allow);
+
 /* Exit the JS context */
 void v8_ExitContextRef(v8_context_ref *v8_ctx_ref);
 
@@ -208,9 +425,71 @@ void* v8_GetPrivateDataFromCtxRef(v8_context_ref* ctx_ref, size_t index);
 /* Same as `v8_SetPrivateData` but works on `v8_context_ref` */
 void v8_SetPrivateDataOnCtxRef(v8_context_ref* ctx_ref, size_t index, void *pd);
 
+/* Kind of an error reported to the unhandled error handler */
+typedef enum v8_UnhandledErrorType{
+	v8_UnhandledErrorType_UncaughtException, v8_UnhandledErrorType_UnhandledRejection
+}v8_UnhandledErrorType;
+
+/* GC callback definition, called before (is_prologue is 1) and after (is_prologue is 0)
+ * each garbage collection. gc_type is the v8::GCType of the collection. */
+typedef void (*v8_GCCallback)(v8_isolate *isolate, int is_prologue, int gc_type);
+
+/* Uncaught exception message callback definition. `script_name` is NULL and `line_number`
+ * is -1 if the location of the exception is unknown. */
+typedef void (*v8_ExceptionMessageCallback)(v8_isolate *isolate, const char *message, size_t message_len, const char *script_name, int line_number);
+
+/* Install diagnostics callbacks on the isolate, the callbacks are called on GC and on uncaught
+ * exceptions. Should only be called once per isolate, the callbacks can not be removed. */
+void v8_IsolateSetDiagnosticsCallbacks(v8_isolate *isolate, v8_GCCallback gc_callback, v8_ExceptionMessageCallback message_callback);
+
+/* Unhandled error callback definition. `script_name` is NULL and `line_number` is 0
+ * if the location of the error is unknown (always the case for promise rejections).
+ * The callback gets ownership on `ctx_ref`, `error` and `script_name`. */
+typedef void (*v8_UnhandledErrorCallback)(v8_context_ref *ctx_ref, v8_UnhandledErrorType type, v8_local_value *error, v8_local_string *script_name, int line_number, void *pd);
+
+/* Set a handler that is called on uncaught exceptions (exceptions that are not
+ * caught by JS code nor by a try catch object) and on promises that are rejected
+ * without a rejection handler, on the given context. Rejections are reported once the
+ * microtasks complete, promises that got a handler by then are not reported.
+ * Replaces the existing handler, if any. */
+void v8_ContextRefSetUnhandledErrorHandler(v8_context_ref *ctx_ref, v8_UnhandledErrorCallback callback, void *pd, void(*freePD)(void *pd));
+
 /* Create a new JS string object */
 v8_local_string* v8_NewString(v8_isolate* v8_isolate, const char *str, size_t len);
 
+/* Same as v8_NewString but return NULL on failure (the string is too long) instead of
+ * aborting. Running out of heap memory is not caught and still aborts. If `internalize` is set, the string is internalized: strings with the same
+ * content share the same memory, which saves memory for strings that are created repeatedly. */
+v8_local_string* v8_TryNewString(v8_isolate* v8_isolate, const char *str, size_t len, int internalize);
+
+/* Return the maximum length of a JS string */
+size_t v8_StringMaxLength();
+
+/* Create a new JS string object from Latin-1 data, return NULL on failure (the string is too long). */
+v8_local_string* v8_NewStringFromOneByte(v8_isolate* v8_isolate, const uint8_t *str, size_t len);
+
+/* Create a new JS string object from UTF-16 code units, return NULL on failure (the string is too long). */
+v8_local_string* v8_NewStringFromTwoByte(v8_isolate* v8_isolate, const uint16_t *str, size_t len);
+
+/* Create a new JS string object that points to the given buffer instead of copying it.
+ * The buffer is interpreted as Latin-1 and must stay valid until `freePD` is called
+ * with the given `pd` (which happens when the string is garbage collected).
+ * Return NULL on failure (for example, if the buffer is too long). */
+v8_local_string* v8_NewExternalString(v8_isolate* v8_isolate, const char *str, size_t len, void *pd, void(*freePD)(void *pd));
+
+/* Same as `v8_NewExternalString` but the buffer holds `len` UTF-16 code units. */
+v8_local_string* v8_NewExternalTwoByteString(v8_isolate* v8_isolate, const uint16_t *str, size_t len, void *pd, void(*freePD)(void *pd));
+
+/* Return 1 if the data of the given JS string is held outside of the JS heap and 0 otherwise */
+int v8_StringIsExternal(v8_local_string *str);
+
+/* Return the length of the JS string in UTF-16 code units */
+size_t v8_StringLength(v8_local_string *str);
+
+/* Copy the UTF-16 code units of the JS string into the given buffer, which must have
+ * room for `v8_StringLength` code units. Lone surrogates are copied as is. */
+void v8_StringWriteUtf16(v8_isolate *i, v8_local_string *str, uint16_t *buff);
+
 /* Convert the JS string to JS generic value */
 v8_local_value* v8_StringToValue(v8_local_string *str);
 
@@ -234,15 +513,30 @@ v8_local_native_function* v8_NewNativeFunction(v8_context_ref *ctx_ref, native_f
 /* Free the given native function template */
 void v8_FreeNativeFunctionTemplate(v8_local_native_function_template *func);
 
+/* Persist the native function template so it can be used outside of the current handlers scope */
+v8_persisted_native_function_template* v8_NativeFunctionTemplatePersist(v8_isolate *i, v8_local_native_function_template *func);
+
+/* Turn the persisted native function template back to local native function template */
+v8_local_native_function_template* v8_PersistedNativeFunctionTemplateToLocal(v8_isolate *i, v8_persisted_native_function_template *func);
+
+/* Free the given persisted native function template */
+void v8_FreePersistedNativeFunctionTemplate(v8_persisted_native_function_template *func);
+
 /* Free the given native function */
 void v8_FreeNativeFunction(v8_local_native_function *func);
 
 /* Convert the native function into a generic JS value */
 v8_local_value* v8_NativeFunctionToValue(v8_local_native_function *func);
 
+/* Convert the generic JS value into a function, applicable only if the value is a function */
+v8_local_native_function* v8_ValueAsFunction(v8_local_value *val);
+
 /* Return the i-th index from the native function arguments */
 v8_local_value* v8_ArgsGet(v8_local_value_arr *args, size_t i);
 
+/* Return the receiver (`this`) of the native function call */
+v8_local_value* v8_ArgsGetSelf(v8_local_value_arr *args);
+
 /* Return current isolate from the native function arguments */
 v8_isolate* v8_GetCurrentIsolate(v8_local_value_arr *args);
 
@@ -261,28 +555,123 @@ void v8_ObjectTemplateSetObject(v8_local_object_template *obj, v8_local_string *
 /* Set a generic JS value on the given object template at the given key */
 void v8_ObjectTemplateSetValue(v8_local_object_template *obj, v8_local_string *name, v8_local_value *val);
 
+/* Set a private value (invisible to JS) on the given object template, the value is
+ * set on all the objects created from the template. Private values with the same
+ * name share the same key across the isolate. */
+void v8_ObjectTemplateSetPrivate(v8_isolate *i, v8_local_object_template *obj, v8_local_string *name, v8_local_value *val);
+
 /* Convert the given object template to a generic JS value */
 v8_local_value* v8_ObjectTemplateToValue(v8_context_ref *ctx_ref, v8_local_object_template *obj);
 
+/* Set the amount of internal fields (invisible to JS) on the objects created
+ * from the given object template. Must be called before the first instance is created.
+ * Return 0 if the count is too large. */
+int v8_ObjectTemplateSetInternalFieldCount(v8_local_object_template *obj, size_t count);
+
+/* Persist the object template so it can be used outside of the current handlers scope */
+v8_persisted_object_template* v8_ObjectTemplatePersist(v8_isolate *i, v8_local_object_template *obj);
+
+/* Turn the persisted object template back to local object template */
+v8_local_object_template* v8_PersistedObjectTemplateToLocal(v8_isolate *i, v8_persisted_object_template *obj);
+
+/* Free the given persisted object template */
+void v8_FreePersistedObjectTemplate(v8_persisted_object_template *obj);
+
 /* Compile the given code into a script object */
 v8_local_script* v8_Compile(v8_context_ref* v8_ctx_ref, v8_local_string* str);
 
+/* Compile the given code into a script object. `name` (can be NULL) and the offsets are
+ * the script origin, used for stack traces. If `strict` is set the code runs in strict mode
+ * (a leading hashbang is still allowed).
+ * If `cached_data` is given it is used to skip the compilation, `cache_rejected` is set to 1
+ * if the cached data does not match the code (the code is compiled as usual in this case).
+ * Return NULL on failure. */
+v8_local_script* v8_CompileWithOptions(v8_context_ref* v8_ctx_ref, v8_local_string* str, v8_local_string* name, int line_offset, int column_offset,
+									   int strict, const char *cached_data, size_t cached_data_len, int *cache_rejected);
+
+/* Create a code cache for the given script which can be given to `v8_CompileWithOptions`.
+ * The returned buffer must be freed using `v8_FreeCodeCache`. */
+char* v8_ScriptCreateCodeCache(v8_local_script* script, size_t *len);
+
+/* Free a code cache returned by `v8_ScriptCreateCodeCache` */
+void v8_FreeCodeCache(char *cache);
+
+/* Compile the given code as the body of a function with the given arguments names.
+ * 'name' is used as the script name (for stack traces). Return NULL on failure. */
+v8_local_value* v8_CompileFunction(v8_context_ref* v8_ctx_ref, v8_local_string* name, v8_local_string* code, size_t argc, v8_local_string* const* args);
+
 v8_persisted_script* v8_ScriptPersist(v8_isolate *i, v8_local_script* script);
 
 v8_local_script* v8_PersistedScriptToLocal(v8_isolate *i, v8_persisted_script* script);
 
 void v8_FreePersistedScript(v8_persisted_script* script);
 
-typedef v8_local_module* (*V8_LoadModuleCallback)(v8_context_ref* v8_ctx_ref, v8_local_string* name, int identity_hash);
+/* Return the context independent version of the script, can be bound to
+ * other contexts of the same isolate without compiling the code again. */
+v8_persisted_unbound_script* v8_ScriptGetUnboundScript(v8_isolate *i, v8_local_script* script);
+
+/* Bind the unbound script to the currently entered context */
+v8_local_script* v8_UnboundScriptBindToCurrentContext(v8_isolate *i, v8_persisted_unbound_script* script);
+
+void v8_FreePersistedUnboundScript(v8_persisted_unbound_script* script);
+
+/* Return the id of the script, the same id is reported for all the contexts the script is bound to */
+int v8_ScriptGetId(v8_local_script* script);
+
+/* Return the id of the unbound script */
+int v8_UnboundScriptGetId(v8_isolate *i, v8_persisted_unbound_script* script);
+
+/* Called to resolve the module imported by the referrer module,
+ * the callback takes the ownership on name and referrer. */
+typedef v8_local_module* (*V8_LoadModuleCallback)(v8_context_ref* v8_ctx_ref, v8_local_string* name, v8_local_module* referrer);
 
 /* Compile the given code as a module */
 v8_local_module* v8_CompileAsModule(v8_context_ref* v8_ctx_ref, v8_local_string* name, v8_local_string* code, int is_module);
 
+/* Create a synthetic module (a module that is not compiled from JS code)
+ * with the given value as its default export. */
+v8_local_module* v8_NewSyntheticModule(v8_context_ref* v8_ctx_ref, v8_local_string* name, v8_local_value* default_export);
+
 /* Initialize the module, return 1 on success and 0 on failure */
 int v8_InitiateModule(v8_local_module* m, v8_context_ref* v8_ctx_ref, V8_LoadModuleCallback load_module_callback);
 
 int v8_ModuleGetIdentityHash(v8_local_module* m);
 
+/* Return 1 if both handles point to the same module, otherwise 0 */
+int v8_ModuleEquals(v8_local_module* m1, v8_local_module* m2);
+
+/* Create a weak handle to the given module, the handle does not keep the module alive */
+v8_weak_module* v8_NewWeakModule(v8_isolate *i, v8_local_module* m);
+
+/* Return 1 if the weak handle points to the given module, otherwise 0 */
+int v8_WeakModuleEquals(v8_weak_module* weak, v8_local_module* m);
+
+/* Return 1 if the module the weak handle points to was garbage collected, otherwise 0 */
+int v8_WeakModuleIsCollected(v8_weak_module* weak);
+
+/* Free the given weak handle */
+void v8_FreeWeakModule(v8_weak_module* weak);
+
+/* Module status */
+typedef enum v8_ModuleStatus{
+	v8_ModuleStatus_Uninstantiated, v8_ModuleStatus_Instantiating, v8_ModuleStatus_Instantiated,
+	v8_ModuleStatus_Evaluating, v8_ModuleStatus_Evaluated, v8_ModuleStatus_Errored
+}v8_ModuleStatus;
+
+/* Return the status of the given module */
+v8_ModuleStatus v8_ModuleGetStatus(v8_local_module* m);
+
+/* Return 1 if the module or any of its dependencies uses top level await, otherwise 0.
+ * Only applicable when the module is instantiated. */
+int v8_ModuleIsGraphAsync(v8_local_module* m);
+
+/* Return the module namespace object, only applicable when the module is instantiated. */
+v8_local_value* v8_ModuleGetNamespace(v8_local_module* m);
+
+/* Return the name of the script that raised the given exception,
+ * NULL if the exception was not raised by a script. */
+v8_local_string* v8_ExceptionGetScriptName(v8_isolate *i, v8_local_value *exception);
+
 /* Evaluate the module code */
 v8_local_value* v8_EvaluateModule(v8_local_module* m, v8_context_ref* v8_ctx_ref);
 
@@ -307,6 +696,28 @@ int v8_ValueIsFunction(v8_local_value *val);
 /* Invoke the given function */
 v8_local_value* v8_FunctionCall(v8_context_ref *v8_ctx_ref, v8_local_value *val, size_t argc, v8_local_value* const* argv);
 
+/* Same as v8_FunctionCall but invoke the function with the given receiver as 'this' */
+v8_local_value* v8_FunctionCallWithReceiver(v8_context_ref *v8_ctx_ref, v8_local_value *val, v8_local_value *recv, size_t argc, v8_local_value* const* argv);
+
+/* Invoke the given function as a constructor, same as `new func(...)`. Return NULL on failure. */
+v8_local_value* v8_FunctionNewInstance(v8_context_ref *v8_ctx_ref, v8_local_value *val, size_t argc, v8_local_value* const* argv);
+
+/* Return the name of the script in which the given function was defined (can be undefined) */
+v8_local_value* v8_FunctionGetScriptName(v8_local_value *val);
+
+/* Return the line number (zero based) on which the given function was defined or -1 if unknown */
+int v8_FunctionGetScriptLineNumber(v8_local_value *val);
+
+/* Return the column number (zero based) on which the given function was defined or -1 if unknown */
+int v8_FunctionGetScriptColumnNumber(v8_local_value *val);
+
+/* Return the name inferred for the given function (for example, for anonymous functions
+ * assigned to a variable), the returned value can be undefined */
+v8_local_value* v8_FunctionGetInferredName(v8_local_value *val);
+
+/* Return the id of the script in which the given function was defined or 0 if unknown */
+int v8_FunctionGetScriptId(v8_local_value *val);
+
 /* Return 1 if the given JS value is an async function and 0 otherwise */
 int v8_ValueIsAsyncFunction(v8_local_value *val);
 
@@ -326,6 +737,12 @@ int v8_ValueIsBigInt(v8_local_value *val);
 
 long long v8_GetBigInt(v8_local_value *val);
 
+/* Same as `v8_GetBigInt` but return 0 if the value does not fit a signed 64 bit integer. */
+int v8_GetBigIntInt64(v8_local_value *val, long long *res);
+
+/* Same as `v8_GetBigInt` but return 0 if the value does not fit an unsigned 64 bit integer. */
+int v8_GetBigIntUint64(v8_local_value *val, unsigned long long *res);
+
 /* Return 1 if the given JS value is a number and 0 otherwise */
 int v8_ValueIsNumber(v8_local_value *val);
 
@@ -336,6 +753,16 @@ int v8_ValueIsBool(v8_local_value *val);
 
 int v8_GetBool(v8_local_value *val);
 
+/* Convert the value to a number like JS does (ToNumber), e.g. "42" -> 42.
+ * Return 1 on success and 0 if the conversion raised an exception. */
+int v8_ValueToNumber(v8_context_ref *ctx_ref, v8_local_value *val, double *res);
+
+/* Convert the value to a string like JS does (ToString), return NULL if the conversion raised an exception. */
+v8_local_string* v8_ValueToString(v8_context_ref *ctx_ref, v8_local_value *val);
+
+/* Return the value truthiness like JS does (ToBoolean) */
+int v8_ValueToBoolean(v8_isolate *i, v8_local_value *val);
+
 
 v8_local_value* v8_ValueFromDouble(v8_isolate *i, double val);
 
@@ -354,9 +781,15 @@ v8_local_array* v8_ValueGetPropertyNames(v8_context_ref *ctx_ref, v8_local_objec
 /* Return 1 if the given JS value is an array and 0 otherwise */
 int v8_ValueIsArray(v8_local_value *val);
 
+/* Return 1 if the given JS values are equal using the JS strict equality (===) and 0 otherwise */
+int v8_ValueStrictEquals(v8_local_value *val1, v8_local_value *val2);
+
 /* Return 1 if the given JS value is an array buffer and 0 otherwise */
 int v8_ValueIsArrayBuffer(v8_local_value *val);
 
+/* Return 1 if the given JS value is a shared array buffer and 0 otherwise */
+int v8_ValueIsSharedArrayBuffer(v8_local_value *val);
+
 /* Create a new JS object */
 v8_local_object* v8_NewObject(v8_isolate *i);
 
@@ -369,21 +802,67 @@ v8_local_object* v8_ValueAsObject(v8_local_value *val);
 /* Convert the generic JS value into a JS resolver*/
 v8_local_resolver* v8_ValueAsResolver(v8_local_value *val);
 
+/* Return a new iterator of the given value by calling its `Symbol.iterator` method.
+ * Return NULL if the value is not iterable or if the method raised an exception. */
+v8_local_value* v8_ValueGetIterator(v8_context_ref *ctx_ref, v8_local_value *val);
+
+/* Same as v8_ValueGetIterator but call the `Symbol.asyncIterator` method, falls back
+ * to the `Symbol.iterator` method if the value has no `Symbol.asyncIterator` method. */
+v8_local_value* v8_ValueGetAsyncIterator(v8_context_ref *ctx_ref, v8_local_value *val);
+
+/* Return the `Symbol.asyncIterator` well known symbol */
+v8_local_value* v8_SymbolAsyncIterator(v8_isolate *i);
+
 /* Return the value of a given key from the given JS object */
 v8_local_value* v8_ObjectGet(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_value *key);
 
 /* Set a value inside the object at a given key */
 void v8_ObjectSet(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_value *key, v8_local_value *val);
 
+/* Define (add or replace) an own property on the object with the given property attributes
+ * (same as v8::PropertyAttribute). The key must be a string or a symbol.
+ * Return 1 on success and 0 on failure. */
+int v8_ObjectDefineProperty(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_value *key, v8_local_value *val, int attributes);
+
 /* Freeze the object, same as Object.freeze. */
 void v8_ObjectFreeze(v8_context_ref *ctx_ref, v8_local_object *obj);
 
+/* Set a private value (invisible to JS) on the object, see `v8_ObjectTemplateSetPrivate`.
+ * Return 1 on success and 0 on failure. */
+int v8_ObjectSetPrivate(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_string *name, v8_local_value *val);
+
+/* Return the amount of internal fields on the object. */
+size_t v8_ObjectInternalFieldCount(v8_local_object *obj);
+
+/* Set the internal field at the given index, return 1 on success and 0 if the index is out of range. */
+int v8_ObjectSetInternalField(v8_local_object *obj, size_t index, v8_local_value *val);
+
+/* Return the internal field at the given index or NULL if the index is out of range. */
+v8_local_value* v8_ObjectGetInternalField(v8_local_object *obj, size_t index);
+
+/* Return the private value with the given name or NULL if the object does not have it. */
+v8_local_value* v8_ObjectGetPrivate(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_string *name);
+
+/* Return 1 if the object has a private value with the given name and 0 otherwise. */
+int v8_ObjectHasPrivate(v8_context_ref *ctx_ref, v8_local_object *obj, v8_local_string *name);
+
+/* Return the name of the function that constructed the object, e.g. "Map" or a user class name. */
+v8_local_string* v8_ObjectGetConstructorName(v8_local_object *obj);
+
+/* Return the result of Object.prototype.toString on the object, e.g. "[object Map]".
+ * Return NULL on failure. */
+v8_local_string* v8_ObjectProtoToString(v8_context_ref *ctx_ref, v8_local_object *obj);
+
 /* Free the given JS object */
 void v8_FreeObject(v8_local_object *obj);
 
 /* Convert the given JS object into JS generic value */
 v8_local_value* v8_ObjectToValue(v8_local_object *obj);
 
+/* Return the identity hash of the object. The hash is not unique but
+ * it is guaranteed to stay the same for the object lifetime. */
+int v8_ObjectGetIdentityHash(v8_local_object *obj);
+
 /* Create a new set */
 v8_local_set* v8_NewSet(v8_isolate *i);
 
@@ -402,18 +881,51 @@ int v8_ValueIsSet(v8_local_value *val);
 /* Free the given JS set */
 void v8_FreeSet(v8_local_set *set);
 
-/* Create a new boolean */
+/* Create a new JS map */
+v8_local_value* v8_NewMap(v8_isolate *i);
+
+/* Set the value of the given key on the JS map, return 1 on success and 0 on failure */
+int v8_MapSet(v8_context_ref *ctx_ref, v8_local_value *map, v8_local_value *key, v8_local_value *val);
+
+/* Return 1 if the given JS value is a map and 0 otherwise */
+int v8_ValueIsMap(v8_local_value *val);
+
+/* Return the entries of the given JS map as a flat array, [key1, value1, key2, value2, ...] */
+v8_local_array* v8_MapAsArray(v8_local_value *map);
+
+/* Return 1 if the given JS value is a native error (Error, TypeError, ...) and 0 otherwise */
+int v8_ValueIsNativeError(v8_local_value *val);
+
+/* Return a JS boolean. Booleans, null, undefined and the empty string are interned by
+ * v8, the returned objects are cached on the isolate (no allocation is performed)
+ * and freeing them is a no-op. */
 v8_local_value* v8_NewBool(v8_isolate *i, int val);
 
-/* Create a new JS null */
+/* Return a JS null */
 v8_local_value* v8_NewNull(v8_isolate *i);
 
+/* Return a JS undefined */
+v8_local_value* v8_NewUndefined(v8_isolate *i);
+
+/* Return an empty JS string */
+v8_local_string* v8_NewEmptyString(v8_isolate *i);
+
 /* Return 1 if the given JS value is null 0 otherwise */
 int v8_ValueIsNull(v8_local_value *val);
 
+/* Return 1 if the given JS value is undefined 0 otherwise */
+int v8_ValueIsUndefined(v8_local_value *val);
+
 /* Create a js ArrayBuffer */
 v8_local_array_buff* v8_NewArrayBuffer(v8_isolate *i, const char *data, size_t len);
 
+/* Create a js ArrayBuffer on top of the given memory without copying it, 'free_pd'
+ * is called with 'pd' once the memory is no longer used (possibly on another thread). */
+v8_local_array_buff* v8_NewExternalArrayBuffer(v8_isolate *i, void *data, size_t len, void *pd, void(*free_pd)(void *pd));
+
+/* Create a js Uint8Array view of the given range of the ArrayBuffer */
+v8_local_value* v8_NewUint8Array(v8_local_array_buff *arr_buffer, size_t offset, size_t len);
+
 v8_local_value* v8_ArrayBufferToValue(v8_local_array_buff *arr_buffer);
 
 /* Return the underline data of an array buffer */
@@ -422,8 +934,41 @@ const void* v8_ArrayBufferGetData(v8_local_array_buff *arr_buffer, size_t *len);
 /* Free a js ArrayBuffer */
 void v8_FreeArrayBuffer(v8_local_array_buff *arr_buffer);
 
+/* Detach the array buffer from its memory, after detached the array buffer
+ * becomes empty. Return 0 if the array buffer is not detachable. */
+int v8_ArrayBufferDetach(v8_local_array_buff *arr_buffer);
+
+/* Return the backing store of the array buffer */
+v8_backing_store* v8_ArrayBufferGetBackingStore(v8_local_array_buff *arr_buffer);
+
+/* Return the data of the backing store */
+const void* v8_BackingStoreGetData(v8_backing_store *backing_store, size_t *len);
+
+/* Free the backing store, the memory is released if no array buffer uses it */
+void v8_FreeBackingStore(v8_backing_store *backing_store);
+
+/* Create a js SharedArrayBuffer of the given length, the memory is zero initialized */
+v8_local_shared_array_buff* v8_NewSharedArrayBuffer(v8_isolate *i, size_t len);
+
+v8_local_value* v8_SharedArrayBufferToValue(v8_local_shared_array_buff *arr_buffer);
+
+/* Return the backing store of the shared array buffer */
+v8_backing_store* v8_SharedArrayBufferGetBackingStore(v8_local_shared_array_buff *arr_buffer);
+
+/* Free a js SharedArrayBuffer */
+void v8_FreeSharedArrayBuffer(v8_local_shared_array_buff *arr_buffer);
+
 v8_local_array* v8_NewArray(v8_isolate *i, v8_local_value *const *vals, size_t len);
 
+/* Create a new JS array of numbers from the given doubles, without creating
+ * a local value object per element. */
+v8_local_array* v8_NewArrayFromDoubles(v8_isolate *i, const double *vals, size_t len);
+
+/* Create a new JS array of strings from the given UTF-8 buffers and their lengths, without
+ * creating a local value object per element (v8 still creates a string per element).
+ * Return NULL if any of the strings is longer than the maximal v8 string length. */
+v8_local_array* v8_NewArrayFromStrings(v8_isolate *i, const char **strs, const size_t *lens, size_t len);
+
 /* Free the given JS array */
 void v8_FreeArray(v8_local_array *arr);
 
@@ -431,6 +976,18 @@ size_t v8_ArrayLen(v8_local_array *arr);
 
 v8_local_value* v8_ArrayGet(v8_context_ref *ctx_ref, v8_local_array *arr, size_t index);
 
+/* Copy the array elements into 'vals' which must be able to hold v8_ArrayLen elements.
+ * Return 1 on success and 0 if any of the elements is not a number. */
+int v8_ArrayToDoubles(v8_context_ref *ctx_ref, v8_local_array *arr, double *vals);
+
+/* Called with the UTF-8 content of each array element, the string is only valid during the call. */
+typedef void (*v8_ArrayStringCallback)(const char *str, size_t len, void *pd);
+
+/* Call 'callback' with each of the array elements without creating a local value object per element.
+ * Return -1 on success and the index of the first element that is not a string or whose read
+ * raised an exception otherwise. */
+long long v8_ArrayToStrings(v8_context_ref *ctx_ref, v8_local_array *arr, v8_ArrayStringCallback callback, void *pd);
+
 v8_local_value* v8_ArrayToValue(v8_local_array *obj);
 
 /* Convert the generic JS value into a JS array */
@@ -439,6 +996,9 @@ v8_local_array* v8_ValueAsArray(v8_local_value *val);
 /* Convert the generic JS value into a JS array buffer */
 v8_local_array_buff* v8_ValueAsArrayBuffer(v8_local_value *val);
 
+/* Convert the generic JS value into a JS shared array buffer */
+v8_local_shared_array_buff* v8_ValueAsSharedArrayBuffer(v8_local_value *val);
+
 /* Promise state */
 typedef enum v8_PromiseState{
 	v8_PromiseState_Unknown, v8_PromiseState_Fulfilled, v8_PromiseState_Rejected, v8_PromiseState_Pending
@@ -485,9 +1045,34 @@ v8_persisted_value* v8_PersistValue(v8_isolate *i, v8_local_value *val);
 /* Turn the persisted value back to local value */
 v8_local_value* v8_PersistedValueToLocal(v8_isolate *i, v8_persisted_value *val);
 
+/* Create a new persisted value that refer to the same JS value as the given persisted value,
+ * each of the persisted values should be freed separately. */
+v8_persisted_value* v8_PersistedValueDuplicate(v8_isolate *i, v8_persisted_value *val);
+
 /* Free the given persisted value */
 void v8_FreePersistedValue(v8_persisted_value *val);
 
+/* Create a weak handle to the given object. If the object is garbage collected before
+ * the handle is freed, `on_collected` (if given) is called with `pd` and takes ownership
+ * of it, otherwise `free_pd` is called with `pd` when the handle is freed. `on_collected`
+ * is called during the garbage collection and must not use the isolate. */
+v8_weak_value* v8_NewWeakValue(v8_isolate *i, v8_local_value *val, void(*on_collected)(void *pd), void *pd, void(*free_pd)(void *pd));
+
+/* Return the object the weak handle points to or NULL if it was garbage collected */
+v8_local_value* v8_WeakValueToLocal(v8_isolate *i, v8_weak_value *val);
+
+/* Free the given weak handle */
+void v8_FreeWeakValue(v8_weak_value *val);
+
+/* Create a JS value that holds the given private data, the value is opaque to JS code.
+ * `free_pd` is called with `pd` when the value is garbage collected (during the garbage
+ * collection, so it must not use the isolate) or when the isolate is freed. */
+v8_local_value* v8_NewExternalData(v8_isolate *i, void *pd, void(*free_pd)(void *pd));
+
+/* Return the private data of a value created using v8_NewExternalData,
+ * NULL if the value was not created using v8_NewExternalData. */
+void* v8_ExternalDataGet(v8_local_value *val);
+
 /* Free the given generic JS value */
 void v8_FreeValue(v8_local_value *val);
 
@@ -508,4 +1093,28 @@ v8_unlocker* v8_NewUnlocker(v8_isolate *i);
 /* Free the unlocker and re-aquire the lock */
 void v8_FreeUnlocker(v8_unlocker* unlocker);
 
+/* Return the CPU time consumed by the current thread in nanoseconds,
+ * or 0 if the platform does not support per thread CPU time. */
+long long v8_GetCurrentThreadCPUTime();
+
+/* Return the v8::Local handle held by the given value as an opaque pointer. The pointer
+ * has the same bits as the v8::Local<v8::Value> and is only valid while the handlers
+ * scope that created the value is alive. */
+void* v8_ValueToRaw(v8_local_value *val);
+
+/* Create a value from a pointer with the same bits as a v8::Local<v8::Value>, see `v8_ValueToRaw`. */
+v8_local_value* v8_ValueFromRaw(void *raw);
+
+/* Same as `v8_ValueToRaw` but works on objects (v8::Local<v8::Object>) */
+void* v8_ObjectToRaw(v8_local_object *obj);
+
+/* Same as `v8_ValueFromRaw` but works on objects (v8::Local<v8::Object>) */
+v8_local_object* v8_ObjectFromRaw(void *raw);
+
+/* Same as `v8_ValueToRaw` but works on strings (v8::Local<v8::String>) */
+void* v8_StringToRaw(v8_local_string *str);
+
+/* Same as `v8_ValueFromRaw` but works on strings (v8::Local<v8::String>) */
+v8_local_string* v8_StringFromRaw(void *raw);
+
 #endif /* SRC_V8_C_API_H_ */